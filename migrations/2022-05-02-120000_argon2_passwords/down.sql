@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+create table users_old (
+    id varchar primary key not null,
+    name text unique not null,
+    email text unique not null,
+
+    created_at text not null,
+
+    password varchar not null,
+    password_salt varchar not null,
+
+    is_banned boolean not null,
+    role text check(role in ('admin', 'user')) not null
+);
+
+insert into users_old
+    select id, name, email, created_at, password, coalesce(password_salt, ''), is_banned, role
+    from users;
+drop table users;
+alter table users_old rename to users;
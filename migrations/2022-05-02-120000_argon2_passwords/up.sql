@@ -0,0 +1,19 @@
+-- Argon2 hashes are stored as PHC strings that contain salt, so salt column
+-- is kept only for legacy Sha3 hashes.
+create table users_new (
+    id varchar primary key not null,
+    name text unique not null,
+    email text unique not null,
+
+    created_at text not null,
+
+    password varchar not null,
+    password_salt varchar,
+
+    is_banned boolean not null,
+    role text check(role in ('admin', 'user')) not null
+);
+
+insert into users_new select * from users;
+drop table users;
+alter table users_new rename to users;
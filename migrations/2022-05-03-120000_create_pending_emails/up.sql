@@ -0,0 +1,10 @@
+create table pending_emails (
+    token_hash varchar primary key not null,
+    user_id varchar not null,
+    email text not null,
+
+    created_at text not null,
+    expires_at text not null,
+
+    foreign key (user_id) references users(id)
+);
@@ -0,0 +1,12 @@
+create table password_reset_tokens (
+    token_hash varchar primary key not null,
+    user_id varchar not null,
+
+    created_at text not null,
+    expires_at text not null,
+
+    foreign key (user_id) references users(id)
+);
+
+-- Sessions created before this moment are considered revoked
+alter table users add column sessions_valid_after text;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table recovery_codes;
+alter table users drop column totp_secret;
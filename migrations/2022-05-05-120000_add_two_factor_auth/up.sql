@@ -0,0 +1,9 @@
+-- Base32 TOTP secret. Two-factor authentication is disabled if null
+alter table users add column totp_secret text;
+
+create table recovery_codes (
+    code_hash varchar primary key not null,
+    user_id varchar not null,
+
+    foreign key (user_id) references users(id)
+);
@@ -0,0 +1,31 @@
+-- This file should undo anything in `up.sql`
+alter table blog_posts drop column comments_locked;
+
+alter table comments drop column hidden_by;
+alter table comments drop column hidden_reason;
+alter table comments drop column is_hidden;
+
+create table users_old (
+    id varchar primary key not null,
+    name text unique not null,
+    email text unique not null,
+
+    created_at text not null,
+
+    password varchar not null,
+    password_salt varchar,
+
+    is_banned boolean not null,
+    role text check(role in ('admin', 'user')) not null,
+
+    sessions_valid_after text,
+    totp_secret text
+);
+
+insert into users_old
+    select id, name, email, created_at, password, password_salt, is_banned,
+        case role when 'moderator' then 'user' else role end,
+        sessions_valid_after, totp_secret
+    from users;
+drop table users;
+alter table users_old rename to users;
@@ -0,0 +1,28 @@
+-- Role check constraint can't be altered in sqlite, so table is recreated
+create table users_new (
+    id varchar primary key not null,
+    name text unique not null,
+    email text unique not null,
+
+    created_at text not null,
+
+    password varchar not null,
+    password_salt varchar,
+
+    is_banned boolean not null,
+    role text check(role in ('admin', 'moderator', 'user')) not null,
+
+    sessions_valid_after text,
+    totp_secret text
+);
+
+insert into users_new select * from users;
+drop table users;
+alter table users_new rename to users;
+
+-- Hidden comments keep their place in thread, but their contents are not shown
+alter table comments add column is_hidden boolean not null default false;
+alter table comments add column hidden_reason text;
+alter table comments add column hidden_by varchar references users(id);
+
+alter table blog_posts add column comments_locked boolean not null default false;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+alter table blog_posts drop column publish_at;
+alter table blog_posts drop column status;
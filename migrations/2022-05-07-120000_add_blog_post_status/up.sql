@@ -0,0 +1,5 @@
+-- Posts that existed before statuses were introduced are already live
+alter table blog_posts add column status text check(status in ('draft', 'scheduled', 'published', 'archived')) not null default 'published';
+alter table blog_posts add column publish_at text;
+
+update blog_posts set publish_at = created_at;
@@ -0,0 +1,25 @@
+create table blog_post_revisions (
+    post_id varchar not null,
+    -- Revisions of each post are numbered from 1
+    number integer not null,
+
+    author_id varchar not null,
+
+    title text not null,
+    brief text not null,
+    contents text not null,
+
+    created_at text not null,
+
+    constraint pk primary key (
+        post_id, number
+    ),
+
+    foreign key (post_id) references blog_posts(id),
+    foreign key (author_id) references users(id)
+);
+
+-- Current state of existing posts becomes their first revision
+insert into blog_post_revisions
+    select id, 1, author_id, title, brief, contents, updated_at
+    from blog_posts;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+alter table comments drop column version;
+alter table blog_posts drop column version;
@@ -0,0 +1,3 @@
+-- Incremented on every update, so that edits made from stale forms can be detected
+alter table blog_posts add column version integer not null default 1;
+alter table comments add column version integer not null default 1;
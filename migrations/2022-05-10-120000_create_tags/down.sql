@@ -0,0 +1,2 @@
+drop table blog_post_tag_junctions;
+drop table tags;
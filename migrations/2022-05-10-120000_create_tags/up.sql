@@ -0,0 +1,16 @@
+create table tags (
+    -- Names are normalized before they are saved, so they can be used as keys
+    name text primary key not null
+);
+
+create table blog_post_tag_junctions (
+    post_id varchar not null,
+    tag_name text not null,
+
+    constraint pk primary key (
+        post_id, tag_name
+    ),
+
+    foreign key (post_id) references blog_posts(id),
+    foreign key (tag_name) references tags(name)
+);
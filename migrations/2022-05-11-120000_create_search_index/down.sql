@@ -0,0 +1,12 @@
+drop trigger projects_fts_delete;
+drop trigger projects_fts_update;
+drop trigger projects_fts_insert;
+drop trigger comments_fts_delete;
+drop trigger comments_fts_update;
+drop trigger comments_fts_insert;
+drop trigger blog_posts_fts_delete;
+drop trigger blog_posts_fts_update;
+drop trigger blog_posts_fts_insert;
+drop table projects_fts;
+drop table comments_fts;
+drop table blog_posts_fts;
@@ -0,0 +1,66 @@
+-- Search indexes keep copies of searchable text, id columns are used to join them with source tables
+create virtual table blog_posts_fts using fts5(
+    id unindexed,
+    title,
+    brief,
+    contents
+);
+
+create virtual table comments_fts using fts5(
+    id unindexed,
+    contents
+);
+
+create virtual table projects_fts using fts5(
+    id unindexed,
+    title,
+    brief
+);
+
+create trigger blog_posts_fts_insert after insert on blog_posts begin
+    insert into blog_posts_fts (id, title, brief, contents)
+        values (new.id, new.title, new.brief, new.contents);
+end;
+
+create trigger blog_posts_fts_update after update of title, brief, contents on blog_posts begin
+    delete from blog_posts_fts where id = old.id;
+    insert into blog_posts_fts (id, title, brief, contents)
+        values (new.id, new.title, new.brief, new.contents);
+end;
+
+create trigger blog_posts_fts_delete after delete on blog_posts begin
+    delete from blog_posts_fts where id = old.id;
+end;
+
+create trigger comments_fts_insert after insert on comments begin
+    insert into comments_fts (id, contents) values (new.id, new.contents);
+end;
+
+create trigger comments_fts_update after update of contents on comments begin
+    delete from comments_fts where id = old.id;
+    insert into comments_fts (id, contents) values (new.id, new.contents);
+end;
+
+create trigger comments_fts_delete after delete on comments begin
+    delete from comments_fts where id = old.id;
+end;
+
+create trigger projects_fts_insert after insert on projects begin
+    insert into projects_fts (id, title, brief) values (new.id, new.title, new.brief);
+end;
+
+create trigger projects_fts_update after update of title, brief on projects begin
+    delete from projects_fts where id = old.id;
+    insert into projects_fts (id, title, brief) values (new.id, new.title, new.brief);
+end;
+
+create trigger projects_fts_delete after delete on projects begin
+    delete from projects_fts where id = old.id;
+end;
+
+insert into blog_posts_fts (id, title, brief, contents)
+    select id, title, brief, contents from blog_posts;
+insert into comments_fts (id, contents)
+    select id, contents from comments;
+insert into projects_fts (id, title, brief)
+    select id, title, brief from projects;
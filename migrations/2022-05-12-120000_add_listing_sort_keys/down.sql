@@ -0,0 +1,7 @@
+drop trigger blog_posts_comment_count_delete;
+drop trigger blog_posts_comment_count_update;
+drop trigger blog_posts_comment_count_insert;
+
+alter table projects drop column updated_at;
+alter table projects drop column created_at;
+alter table blog_posts drop column comment_count;
@@ -0,0 +1,31 @@
+-- Number of comments that are not deleted, kept up to date by triggers so posts can be sorted by it
+alter table blog_posts add column comment_count integer not null default 0;
+
+update blog_posts set comment_count = (
+    select count(*) from comments where comments.post_id = blog_posts.id and not comments.is_deleted
+);
+
+create trigger blog_posts_comment_count_insert after insert on comments begin
+    update blog_posts set comment_count = (
+        select count(*) from comments where post_id = new.post_id and not is_deleted
+    ) where id = new.post_id;
+end;
+
+create trigger blog_posts_comment_count_update after update of is_deleted on comments begin
+    update blog_posts set comment_count = (
+        select count(*) from comments where post_id = new.post_id and not is_deleted
+    ) where id = new.post_id;
+end;
+
+create trigger blog_posts_comment_count_delete after delete on comments begin
+    update blog_posts set comment_count = (
+        select count(*) from comments where post_id = old.post_id and not is_deleted
+    ) where id = old.post_id;
+end;
+
+alter table projects add column created_at text not null default '';
+alter table projects add column updated_at text not null default '';
+
+update projects set
+    created_at = strftime('%Y-%m-%d %H:%M:%S UTC', 'now'),
+    updated_at = strftime('%Y-%m-%d %H:%M:%S UTC', 'now');
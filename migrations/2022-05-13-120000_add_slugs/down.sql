@@ -0,0 +1,7 @@
+drop table project_slug_history;
+drop table blog_post_slug_history;
+
+drop index projects_slug;
+alter table projects drop column slug;
+drop index blog_posts_slug;
+alter table blog_posts drop column slug;
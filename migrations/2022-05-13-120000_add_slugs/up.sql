@@ -0,0 +1,22 @@
+-- Existing rows get their ids as slugs, which are replaced with ones
+-- made from titles when application starts
+alter table blog_posts add column slug text not null default '';
+update blog_posts set slug = id;
+create unique index blog_posts_slug on blog_posts(slug);
+
+alter table projects add column slug text not null default '';
+update projects set slug = id;
+create unique index projects_slug on projects(slug);
+
+-- Previous slugs of renamed blog posts and projects, which redirect to current ones
+create table blog_post_slug_history
+(
+    slug    text primary key not null,
+    post_id text             not null references blog_posts (id) on delete cascade
+);
+
+create table project_slug_history
+(
+    slug       text primary key not null,
+    project_id text             not null references projects (id) on delete cascade
+);
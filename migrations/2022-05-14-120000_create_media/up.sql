@@ -0,0 +1,12 @@
+-- Files uploaded by users. Contents are kept in media store under `id.extension`
+create table media
+(
+    id         text primary key not null,
+    owner_id   text             not null references users (id) on delete cascade,
+    file_name  text             not null,
+    media_type text             not null,
+    size       integer          not null,
+    created_at text             not null
+);
+
+create index media_owner on media (owner_id, created_at);
@@ -0,0 +1,22 @@
+-- Unsaved text of blog post editors, one for each user and edited post
+create table blog_post_autosaves (
+    user_id varchar not null,
+    -- Id of edited blog post, or 'new' for blog post that is being created
+    slot varchar not null,
+
+    title text not null,
+    brief text not null,
+    contents text not null,
+    tags text not null,
+
+    saved_at text not null,
+    expires_at text not null,
+
+    constraint pk primary key (
+        user_id, slot
+    ),
+
+    foreign key (user_id) references users(id)
+);
+
+create index blog_post_autosaves_expires_at on blog_post_autosaves (expires_at);
@@ -0,0 +1,2 @@
+-- Time step of the last accepted TOTP code, so that codes can't be replayed
+alter table users add column totp_last_step bigint;
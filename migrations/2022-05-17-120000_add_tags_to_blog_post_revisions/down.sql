@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table blog_post_revisions drop column tags;
@@ -0,0 +1,11 @@
+-- Tags of post at the moment of revision, comma separated in alphabetical order
+alter table blog_post_revisions add column tags text not null default '';
+
+-- Tags have not been tracked before, so all revisions get current tags of their posts
+update blog_post_revisions set tags = coalesce((
+    select group_concat(tag_name, ', ') from (
+        select tag_name from blog_post_tag_junctions
+        where blog_post_tag_junctions.post_id = blog_post_revisions.post_id
+        order by tag_name
+    )
+), '');
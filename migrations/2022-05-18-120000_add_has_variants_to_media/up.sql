@@ -0,0 +1,3 @@
+-- Whether resized copies of image are in media store. Images uploaded before copies
+-- were made on upload get them on start of application.
+alter table media add column has_variants boolean not null default 0;
@@ -1,4 +1,5 @@
 use secrecy::Secret;
+use std::net::IpAddr;
 
 /// Settings related to application
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -7,11 +8,101 @@ pub struct AppConfig {
     pub port: u16,
     /// Host path. Typically 127.0.0.1
     pub host: String,
+    /// Public URL of the site. Used to generate links sent to users
+    pub base_url: String,
     /// Secret that is used to make HMAC
     pub hmac_secret: Secret<String>,
     /// Number of worker threads.
     /// If not specified, number of CPU cores is used
     pub workers: Option<usize>,
+    /// Cost parameters of password hashing
+    pub password_hashing: PasswordHashingConfig,
+    /// Limits of failed login attempts
+    pub login_throttle: LoginThrottleConfig,
+    /// How often scheduled blog posts are checked for being due, in seconds
+    pub publish_interval_seconds: u64,
+}
+
+/// Argon2id cost parameters used when hashing user passwords.
+/// Changing them causes stored hashes to be upgraded on next successful login.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PasswordHashingConfig {
+    /// Memory size in KiB
+    pub memory_cost: u32,
+    /// Number of iterations
+    pub time_cost: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+/// Limits of failed login attempts. Once limit is reached, further logins are
+/// locked out for base duration, which doubles with every following failure.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LoginThrottleConfig {
+    /// Where failure counters are kept
+    pub storage: AttemptStorageConfig,
+    /// Number of failures per user name before lockout
+    pub attempts_per_name: u32,
+    /// Number of failures per client address before lockout
+    pub attempts_per_ip: u32,
+    /// Duration of first lockout in seconds
+    pub base_lockout_seconds: u64,
+    /// Upper bound of lockout duration in seconds
+    pub max_lockout_seconds: u64,
+    /// Failures are forgotten after this number of seconds without new ones
+    pub failure_window_seconds: u64,
+    /// Addresses of reverse proxies whose X-Forwarded-For header is trusted.
+    /// Requests from other addresses are limited by their peer address.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttemptStorageConfig {
+    /// Keep counters in Redis, shared between all workers
+    Redis,
+    /// Keep counters in memory of the process
+    Memory,
+}
+
+/// Settings related to sending emails
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EmailConfig {
+    /// Address that is put in 'From' field
+    pub sender: String,
+    /// How emails are delivered
+    pub transport: EmailTransportConfig,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum EmailTransportConfig {
+    /// Send emails through SMTP relay
+    Smtp {
+        host: String,
+        port: u16,
+        username: String,
+        password: Secret<String>,
+    },
+    /// Write emails to files in given directory. Used for local development
+    File { path: String },
+}
+
+/// Settings of uploaded files
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MediaConfig {
+    /// Largest size of uploaded file in bytes
+    pub max_upload_size: usize,
+    /// Where uploaded files are kept
+    pub storage: MediaStorageConfig,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MediaStorageConfig {
+    /// Keep files in given directory, from which they are served under `/media`
+    Local { path: String },
 }
 
 /// Settings of whole system
@@ -22,6 +113,10 @@ pub struct Config {
     pub app: AppConfig,
     /// Redis URI. Typically address of redis hosted in docker container
     pub redis_uri: Secret<String>,
+    /// Settings of email delivery
+    pub email: EmailConfig,
+    /// Settings of uploaded files
+    pub media: MediaConfig,
 }
 
 /// Environment in which application is running.
@@ -0,0 +1,175 @@
+/// Largest table of common subsequence lengths that is computed, about 4 MB
+const MAX_LCS_TABLE_CELLS: usize = 1 << 20;
+
+/// Line of text in difference between two texts
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine<'a> {
+    Unchanged(&'a str),
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+/// Computes line-based difference between old and new text using longest common subsequence.
+/// Removed lines go before added ones where lines were replaced.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Common beginning and ending don't need to take part in quadratic search
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut result: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|line| DiffLine::Unchanged(line))
+        .collect();
+    result.extend(diff_middle(old_middle, new_middle));
+    result.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| DiffLine::Unchanged(line)),
+    );
+    result
+}
+
+fn diff_middle<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let width = new.len() + 1;
+    let cells = (old.len() + 1).saturating_mul(width);
+    if cells > MAX_LCS_TABLE_CELLS {
+        // Texts changed too much to search for common lines within reasonable memory,
+        // so changed part is shown as replaced entirely
+        return old
+            .iter()
+            .map(|line| DiffLine::Removed(line))
+            .chain(new.iter().map(|line| DiffLine::Added(line)))
+            .collect();
+    }
+
+    // lcs[i * width + j] is length of longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![0u32; cells];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            result.push(DiffLine::Unchanged(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            result.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    result.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_lines, DiffLine, MAX_LCS_TABLE_CELLS};
+
+    #[test]
+    fn same_texts_have_no_changes() {
+        let text = "a\nb\nc";
+        assert_eq!(
+            diff_lines(text, text),
+            vec![
+                DiffLine::Unchanged("a"),
+                DiffLine::Unchanged("b"),
+                DiffLine::Unchanged("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn added_and_removed_lines_are_found() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            vec![
+                DiffLine::Unchanged("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Unchanged("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn replaced_line_is_removed_then_added() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nx\nc"),
+            vec![
+                DiffLine::Unchanged("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Added("x"),
+                DiffLine::Unchanged("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_with_empty_text() {
+        assert_eq!(
+            diff_lines("", "a\nb"),
+            vec![DiffLine::Added("a"), DiffLine::Added("b")]
+        );
+        assert_eq!(
+            diff_lines("a\nb", ""),
+            vec![DiffLine::Removed("a"), DiffLine::Removed("b")]
+        );
+    }
+
+    #[test]
+    fn common_lines_in_middle_are_kept() {
+        assert_eq!(
+            diff_lines("x\na\ny\nb", "a\nz\nb"),
+            vec![
+                DiffLine::Removed("x"),
+                DiffLine::Unchanged("a"),
+                DiffLine::Removed("y"),
+                DiffLine::Added("z"),
+                DiffLine::Unchanged("b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn large_change_is_shown_as_replacement() {
+        let lines = (MAX_LCS_TABLE_CELLS as f64).sqrt() as usize + 1;
+        let old: String = (0..lines).map(|i| format!("old {}\n", i)).collect();
+        let new: String = (0..lines).map(|i| format!("new {}\n", i)).collect();
+        let old = format!("first\n{}last", old);
+        let new = format!("first\n{}last", new);
+
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.len(), 2 * lines + 2);
+        assert_eq!(diff[0], DiffLine::Unchanged("first"));
+        assert_eq!(diff[1], DiffLine::Removed("old 0"));
+        assert_eq!(diff[lines + 1], DiffLine::Added("new 0"));
+        assert_eq!(diff[2 * lines + 1], DiffLine::Unchanged("last"));
+    }
+}
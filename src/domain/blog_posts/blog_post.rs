@@ -1,4 +1,5 @@
-use crate::domain::blog_posts::{BlogPostID, BlogPostVisibility};
+use crate::domain::blog_posts::{BlogPostID, BlogPostStatus, BlogPostVisibility};
+use crate::domain::slugs::Slug;
 use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 use crate::schema::blog_posts;
@@ -16,4 +17,16 @@ pub struct BlogPost {
     pub updated_at: DateTime,
 
     pub visibility: BlogPostVisibility,
+    pub comments_locked: bool,
+
+    pub status: BlogPostStatus,
+    /// Time when post was or is going to be published
+    pub publish_at: Option<DateTime>,
+
+    /// Incremented on every update
+    pub version: i32,
+    /// Number of comments that are not deleted, maintained by database
+    pub comment_count: i32,
+    /// Unique human-readable name of post in urls, made from title
+    pub slug: Slug,
 }
@@ -0,0 +1,68 @@
+use crate::domain::blog_posts::BlogPostID;
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::schema::blog_post_autosaves;
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use std::io::Write;
+
+/// Text of blog post editor that was saved automatically while user was writing,
+/// so it can be restored if page is closed before form is submitted
+#[derive(Debug, Clone, diesel::Queryable, diesel::Insertable, PartialEq)]
+pub struct BlogPostAutosave {
+    pub user_id: UserID,
+    pub slot: AutosaveSlot,
+
+    pub title: String,
+    pub brief: String,
+    pub contents: String,
+    /// Comma separated list of tags
+    pub tags: String,
+
+    pub saved_at: DateTime,
+    pub expires_at: DateTime,
+}
+
+/// Editor that autosave belongs to. Each user has one slot for new blog post
+/// and one for each blog post they edit.
+#[derive(Debug, Clone, PartialEq, diesel::AsExpression, diesel::FromSqlRow)]
+#[sql_type = "diesel::sql_types::Text"]
+pub enum AutosaveSlot {
+    NewBlogPost,
+    BlogPost(BlogPostID),
+}
+
+const NEW_BLOG_POST_SLOT: &str = "new";
+
+impl AutosaveSlot {
+    fn as_str(&self) -> &str {
+        match self {
+            AutosaveSlot::NewBlogPost => NEW_BLOG_POST_SLOT,
+            AutosaveSlot::BlogPost(id) => id.as_ref(),
+        }
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for AutosaveSlot {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        let s = <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes)?;
+        if s == NEW_BLOG_POST_SLOT {
+            Ok(AutosaveSlot::NewBlogPost)
+        } else {
+            Ok(AutosaveSlot::BlogPost(<BlogPostID as FromSql<
+                diesel::sql_types::Text,
+                Sqlite,
+            >>::from_sql(bytes)?))
+        }
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for AutosaveSlot {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <str as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(self.as_str(), out)
+    }
+}
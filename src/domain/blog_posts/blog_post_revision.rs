@@ -0,0 +1,23 @@
+use crate::domain::blog_posts::BlogPostID;
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::schema::blog_post_revisions;
+
+/// Saved version of blog post text. Revisions are never changed once created.
+#[derive(Debug, Clone, diesel::Queryable, diesel::Insertable, PartialEq)]
+pub struct BlogPostRevision {
+    pub post_id: BlogPostID,
+    pub number: i32,
+
+    /// User who saved this version
+    pub author_id: UserID,
+
+    pub title: String,
+    pub brief: String,
+    pub contents: String,
+
+    pub created_at: DateTime,
+
+    /// Tags of post, formatted the way they are entered in forms
+    pub tags: String,
+}
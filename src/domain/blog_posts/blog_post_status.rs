@@ -0,0 +1,69 @@
+use anyhow::anyhow;
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use std::io::Write;
+
+/// Stage of blog post publication. Only published posts appear in public listings.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    derive_more::Display,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sql_type = "diesel::sql_types::Text"]
+#[serde(rename_all = "lowercase")]
+pub enum BlogPostStatus {
+    /// Visible only to people who can edit the post
+    Draft,
+    /// Draft that becomes published once its `publish_at` time passes
+    Scheduled,
+    Published,
+    /// Still accessible by link, but not listed anywhere
+    Archived,
+}
+
+impl BlogPostStatus {
+    /// Whether post can be viewed by people who can't edit it
+    pub fn is_public(&self) -> bool {
+        matches!(self, BlogPostStatus::Published | BlogPostStatus::Archived)
+    }
+
+    /// Name of status as it is stored and sent in forms
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlogPostStatus::Draft => "draft",
+            BlogPostStatus::Scheduled => "scheduled",
+            BlogPostStatus::Published => "published",
+            BlogPostStatus::Archived => "archived",
+        }
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for BlogPostStatus {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes).and_then(|s| {
+            Ok(match s.as_str() {
+                "draft" => Ok(BlogPostStatus::Draft),
+                "scheduled" => Ok(BlogPostStatus::Scheduled),
+                "published" => Ok(BlogPostStatus::Published),
+                "archived" => Ok(BlogPostStatus::Archived),
+                _ => Err(anyhow!("{} is not a valid blog post status", s)),
+            }?)
+        })
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for BlogPostStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <str as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(self.as_str(), out)
+    }
+}
@@ -1,11 +1,17 @@
 mod blog_post;
+mod blog_post_autosave;
 mod blog_post_id;
+mod blog_post_revision;
+mod blog_post_status;
 mod blog_post_visibility;
 mod new_blog_post;
 mod update_blog_post;
 
 pub use blog_post::*;
+pub use blog_post_autosave::*;
 pub use blog_post_id::*;
+pub use blog_post_revision::*;
+pub use blog_post_status::*;
 pub use blog_post_visibility::*;
 pub use new_blog_post::*;
 pub use update_blog_post::*;
@@ -1,4 +1,6 @@
-use crate::domain::blog_posts::BlogPostVisibility;
+use crate::domain::blog_posts::{BlogPostStatus, BlogPostVisibility};
+use crate::domain::tags::TagName;
+use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 
 #[derive(Debug)]
@@ -8,4 +10,8 @@ pub struct NewBlogPost<'a> {
     pub brief: &'a str,
     pub contents: &'a str,
     pub visibility: BlogPostVisibility,
+    pub status: BlogPostStatus,
+    /// Required for scheduled posts. Published posts get current time if not given
+    pub publish_at: Option<DateTime>,
+    pub tags: &'a [TagName],
 }
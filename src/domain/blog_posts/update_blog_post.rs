@@ -1,4 +1,5 @@
-use crate::domain::blog_posts::{BlogPostID, BlogPostVisibility};
+use crate::domain::blog_posts::{BlogPostID, BlogPostStatus, BlogPostVisibility};
+use crate::domain::time::DateTime;
 use crate::schema::blog_posts;
 
 #[derive(diesel::AsChangeset)]
@@ -9,4 +10,6 @@ pub struct UpdateBlogPost<'a> {
     pub brief: Option<&'a str>,
     pub contents: Option<&'a str>,
     pub visibility: Option<BlogPostVisibility>,
+    pub status: Option<BlogPostStatus>,
+    pub publish_at: Option<Option<DateTime>>,
 }
@@ -17,4 +17,10 @@ pub struct Comment {
     pub updated_at: DateTime,
 
     pub is_deleted: bool,
+    pub is_hidden: bool,
+    pub hidden_reason: Option<String>,
+    pub hidden_by: Option<UserID>,
+
+    /// Incremented on every update
+    pub version: i32,
 }
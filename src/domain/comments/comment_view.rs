@@ -17,4 +17,7 @@ pub struct CommentView {
     pub updated_at: DateTime,
 
     pub is_deleted: bool,
+    pub is_hidden: bool,
+    pub hidden_reason: Option<String>,
+    pub version: i32,
 }
@@ -0,0 +1,27 @@
+use crate::domain::media::{MediaID, MediaType};
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::schema::media;
+
+/// File uploaded by user
+#[derive(Debug, diesel::Queryable, diesel::Insertable, PartialEq)]
+#[table_name = "media"]
+pub struct Media {
+    pub id: MediaID,
+    pub owner_id: UserID,
+    /// Name of file on computer of user, shown in media library
+    pub file_name: String,
+    pub media_type: MediaType,
+    /// Size in bytes
+    pub size: i32,
+    pub created_at: DateTime,
+    /// Resized copies of image are in media store, see `media::generate_variants`
+    pub has_variants: bool,
+}
+
+impl Media {
+    /// Key under which file is kept in media store
+    pub fn storage_key(&self) -> String {
+        format!("{}.{}", self.id.as_ref(), self.media_type.extension())
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::anyhow;
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use std::io::Write;
+
+/// Type of uploaded file. It is determined from contents of file, since neither its name
+/// nor content type sent by browser can be trusted. Types that can contain scripts,
+/// like svg and html, are not allowed.
+#[derive(
+    Debug, Clone, Copy, PartialEq, derive_more::Display, diesel::AsExpression, diesel::FromSqlRow,
+)]
+#[sql_type = "diesel::sql_types::Text"]
+pub enum MediaType {
+    #[display(fmt = "image/png")]
+    Png,
+    #[display(fmt = "image/jpeg")]
+    Jpeg,
+    #[display(fmt = "image/gif")]
+    Gif,
+    #[display(fmt = "image/webp")]
+    Webp,
+    #[display(fmt = "application/pdf")]
+    Pdf,
+}
+
+const ALL_TYPES: [MediaType; 5] = [
+    MediaType::Png,
+    MediaType::Jpeg,
+    MediaType::Gif,
+    MediaType::Webp,
+    MediaType::Pdf,
+];
+
+impl MediaType {
+    /// Determines type from magic bytes at start of file
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaType::Png)
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            Some(MediaType::Jpeg)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(MediaType::Gif)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            Some(MediaType::Webp)
+        } else if data.starts_with(b"%PDF-") {
+            Some(MediaType::Pdf)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaType::Png => "png",
+            MediaType::Jpeg => "jpg",
+            MediaType::Gif => "gif",
+            MediaType::Webp => "webp",
+            MediaType::Pdf => "pdf",
+        }
+    }
+
+    /// Type of file with key or name that has given extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        ALL_TYPES
+            .into_iter()
+            .find(|media_type| media_type.extension() == extension)
+    }
+
+    pub fn is_image(&self) -> bool {
+        !matches!(self, MediaType::Pdf)
+    }
+
+    /// Value for `accept` attribute of file inputs
+    pub fn accept_list() -> String {
+        ALL_TYPES
+            .iter()
+            .map(|media_type| media_type.mime())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for MediaType {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes).and_then(|s| {
+            Ok(ALL_TYPES
+                .into_iter()
+                .find(|media_type| media_type.mime() == s)
+                .ok_or_else(|| anyhow!("{} is not a valid media type", s))?)
+        })
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for MediaType {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(&self.mime(), out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_are_sniffed_from_contents() {
+        assert_eq!(
+            MediaType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(MediaType::Png)
+        );
+        assert_eq!(MediaType::sniff(b"\xff\xd8\xff\xe0"), Some(MediaType::Jpeg));
+        assert_eq!(MediaType::sniff(b"GIF89a\x01\0"), Some(MediaType::Gif));
+        assert_eq!(
+            MediaType::sniff(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some(MediaType::Webp)
+        );
+        assert_eq!(MediaType::sniff(b"%PDF-1.7\n"), Some(MediaType::Pdf));
+    }
+
+    #[test]
+    fn unknown_and_dangerous_types_are_not_sniffed() {
+        assert_eq!(MediaType::sniff(b""), None);
+        assert_eq!(MediaType::sniff(b"<svg onload=alert(1)>"), None);
+        assert_eq!(MediaType::sniff(b"<html><script>"), None);
+        assert_eq!(MediaType::sniff(b"RIFF\0\0\0\0WAVE"), None);
+    }
+}
@@ -0,0 +1,7 @@
+mod media_file;
+mod media_id;
+mod media_type;
+
+pub use media_file::*;
+pub use media_id::*;
+pub use media_type::*;
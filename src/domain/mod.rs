@@ -1,5 +1,9 @@
 pub mod blog_posts;
 pub mod comments;
+pub mod media;
 pub mod projects;
+pub mod search;
+pub mod slugs;
+pub mod tags;
 pub mod time;
 pub mod users;
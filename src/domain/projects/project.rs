@@ -1,4 +1,6 @@
 use crate::domain::projects::{ProjectID, ProjectVisibility};
+use crate::domain::slugs::Slug;
+use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 use crate::schema::projects;
 
@@ -9,4 +11,10 @@ pub struct Project {
     pub brief: String,
     pub author_id: UserID,
     pub visibility: ProjectVisibility,
+
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+
+    /// Unique human-readable name of project in urls, made from title
+    pub slug: Slug,
 }
@@ -0,0 +1,5 @@
+mod search_hit;
+mod search_query;
+
+pub use search_hit::*;
+pub use search_query::*;
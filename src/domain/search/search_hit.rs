@@ -0,0 +1,52 @@
+use crate::domain::blog_posts::BlogPostID;
+use crate::domain::comments::CommentID;
+use crate::domain::projects::ProjectID;
+use diesel::sql_types::Text;
+
+/// Blog post that matches search query. `snippet` is html with matches wrapped in `<mark>`.
+#[derive(Debug, diesel::QueryableByName)]
+pub struct BlogPostSearchHit {
+    #[sql_type = "Text"]
+    pub id: BlogPostID,
+    #[sql_type = "Text"]
+    pub title: String,
+    #[sql_type = "Text"]
+    pub snippet: String,
+}
+
+/// Comment that matches search query. `snippet` is html with matches wrapped in `<mark>`.
+#[derive(Debug, diesel::QueryableByName)]
+pub struct CommentSearchHit {
+    #[sql_type = "Text"]
+    pub id: CommentID,
+    #[sql_type = "Text"]
+    pub post_id: BlogPostID,
+    #[sql_type = "Text"]
+    pub post_title: String,
+    #[sql_type = "Text"]
+    pub snippet: String,
+}
+
+/// Project that matches search query. `snippet` is html with matches wrapped in `<mark>`.
+#[derive(Debug, diesel::QueryableByName)]
+pub struct ProjectSearchHit {
+    #[sql_type = "Text"]
+    pub id: ProjectID,
+    #[sql_type = "Text"]
+    pub title: String,
+    #[sql_type = "Text"]
+    pub snippet: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    pub blog_posts: Vec<BlogPostSearchHit>,
+    pub comments: Vec<CommentSearchHit>,
+    pub projects: Vec<ProjectSearchHit>,
+}
+
+impl SearchResults {
+    pub fn is_empty(&self) -> bool {
+        self.blog_posts.is_empty() && self.comments.is_empty() && self.projects.is_empty()
+    }
+}
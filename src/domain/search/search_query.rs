@@ -0,0 +1,63 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_QUERY_LENGTH: usize = 256;
+
+/// Text entered by user to search for. Every word of query has to be present in found text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(s: &str) -> Result<SearchQuery, anyhow::Error> {
+        if s.graphemes(true).count() > MAX_QUERY_LENGTH {
+            anyhow::bail!("Search query is too long");
+        }
+
+        let terms: Vec<String> = s.split_whitespace().map(str::to_string).collect();
+        if terms.is_empty() {
+            anyhow::bail!("Search query is empty");
+        }
+
+        Ok(Self { terms })
+    }
+
+    /// Returns query in FTS5 syntax. Each term is quoted, so operators and special
+    /// characters written by user are searched for as plain text.
+    pub fn to_fts_match(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::assert_err;
+
+    #[test]
+    fn empty_query_is_rejected() {
+        assert_err!(SearchQuery::parse(""));
+        assert_err!(SearchQuery::parse("  \t "));
+    }
+
+    #[test]
+    fn too_long_query_is_rejected() {
+        assert_err!(SearchQuery::parse(&"a".repeat(257)));
+    }
+
+    #[test]
+    fn terms_are_quoted() {
+        let query = SearchQuery::parse(" hello   world ").unwrap();
+        assert_eq!(query.to_fts_match(), r#""hello" "world""#);
+    }
+
+    #[test]
+    fn fts_syntax_is_escaped() {
+        let query = SearchQuery::parse(r#"a OR "b* NEAR(c)"#).unwrap();
+        assert_eq!(query.to_fts_match(), r#""a" "OR" """b*" "NEAR(c)""#);
+    }
+}
@@ -0,0 +1,3 @@
+mod slug;
+
+pub use slug::*;
@@ -0,0 +1,239 @@
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use std::io::Write;
+use unicode_normalization::UnicodeNormalization;
+
+const MAX_SLUG_LENGTH: usize = 64;
+/// Slug of title that has nothing that can be transliterated
+const FALLBACK_SLUG: &str = "untitled";
+
+/// Human-readable url part identifying blog post or project.
+/// Consists of lowercase ascii letters and digits separated by single `-`.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    derive_more::Display,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+    serde::Serialize,
+)]
+#[sql_type = "diesel::sql_types::Text"]
+pub struct Slug {
+    s: String,
+}
+
+impl Slug {
+    /// Parses slug as found in url
+    pub fn parse(s: &str) -> Result<Slug, anyhow::Error> {
+        let is_valid = !s.is_empty()
+            && s.len() <= MAX_SLUG_LENGTH * 2
+            && s.split('-')
+                .all(|part| !part.is_empty() && part.chars().all(is_slug_char));
+        if !is_valid {
+            anyhow::bail!("Invalid slug {}", s);
+        }
+        Ok(Self { s: s.to_string() })
+    }
+
+    /// Makes slug from title. Letters are transliterated to ascii, everything else
+    /// becomes separator. Slugs may collide, use [`Slug::with_suffix`] to make them unique.
+    pub fn from_title(title: &str) -> Slug {
+        let mut s = String::new();
+        for c in title.nfkc() {
+            match transliterate(c) {
+                Some(ascii) => {
+                    s.extend(ascii.chars().filter(char::is_ascii_alphanumeric));
+                }
+                None if c.is_alphanumeric() => {
+                    // Letters with diacritics are decomposed to base letter and marks
+                    s.extend(
+                        std::iter::once(c)
+                            .nfkd()
+                            .filter(char::is_ascii_alphanumeric)
+                            .map(|c| c.to_ascii_lowercase()),
+                    );
+                }
+                None => {
+                    if !s.is_empty() && !s.ends_with('-') {
+                        s.push('-');
+                    }
+                }
+            }
+            if s.len() > MAX_SLUG_LENGTH {
+                break;
+            }
+        }
+
+        // Long slugs are cut at word boundary if there is one
+        if s.len() > MAX_SLUG_LENGTH {
+            s.truncate(MAX_SLUG_LENGTH);
+            if let Some(boundary) = s.rfind('-') {
+                s.truncate(boundary);
+            }
+        }
+        let s = s.trim_matches('-');
+        Self {
+            s: if s.is_empty() {
+                FALLBACK_SLUG.to_string()
+            } else {
+                s.to_string()
+            },
+        }
+    }
+
+    /// Makes variant of slug used when slug is taken, `n` starts from 2
+    pub fn with_suffix(&self, n: usize) -> Slug {
+        Self {
+            s: format!("{}-{}", self.s, n),
+        }
+    }
+}
+
+fn is_slug_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit()
+}
+
+/// Transliteration of letters that are not decomposed into ascii ones
+fn transliterate(c: char) -> Option<&'static str> {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    Some(match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'þ' => "th",
+        'ı' => "i",
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' => "",
+        'ы' => "y",
+        'ь' => "",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        'і' => "i",
+        'ї' => "yi",
+        'є' => "ye",
+        'ґ' => "g",
+        _ => return None,
+    })
+}
+
+impl AsRef<String> for Slug {
+    fn as_ref(&self) -> &String {
+        &self.s
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for Slug {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes).map(|s| Slug { s })
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for Slug {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(&self.s, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    fn slug(title: &str) -> String {
+        Slug::from_title(title).as_ref().clone()
+    }
+
+    #[test]
+    fn words_are_joined_with_dashes() {
+        assert_eq!(slug("Hello, World!"), "hello-world");
+        assert_eq!(slug("  Rust   2021 -- edition "), "rust-2021-edition");
+    }
+
+    #[test]
+    fn diacritics_are_removed() {
+        assert_eq!(
+            slug("Crème brûlée à la française"),
+            "creme-brulee-a-la-francaise"
+        );
+        assert_eq!(slug("Straße in Łódź"), "strasse-in-lodz");
+    }
+
+    #[test]
+    fn cyrillic_is_transliterated() {
+        assert_eq!(slug("Привет, мир"), "privet-mir");
+        assert_eq!(slug("Щука и ёж"), "shchuka-i-yozh");
+    }
+
+    #[test]
+    fn title_without_letters_gets_fallback_slug() {
+        assert_eq!(slug("!!!"), FALLBACK_SLUG);
+        assert_eq!(slug("漢字"), FALLBACK_SLUG);
+    }
+
+    #[test]
+    fn long_title_is_cut_at_word_boundary() {
+        let s = slug(&"word ".repeat(100));
+        assert!(s.len() <= MAX_SLUG_LENGTH);
+        assert!(s.ends_with("word"));
+    }
+
+    #[test]
+    fn suffix_is_appended() {
+        assert_eq!(Slug::from_title("Post").with_suffix(2).as_ref(), "post-2");
+    }
+
+    #[test]
+    fn generated_slugs_can_be_parsed() {
+        for title in ["Hello, World!", "Привет", "!!!", "a-b"] {
+            let slug = Slug::from_title(title);
+            assert_eq!(Slug::parse(slug.as_ref()).unwrap(), slug);
+        }
+    }
+
+    #[test]
+    fn invalid_slugs_are_rejected() {
+        assert_ok!(Slug::parse("hello-world-2"));
+        assert_err!(Slug::parse(""));
+        assert_err!(Slug::parse("Hello"));
+        assert_err!(Slug::parse("hello--world"));
+        assert_err!(Slug::parse("-hello"));
+        assert_err!(Slug::parse("hello world"));
+    }
+}
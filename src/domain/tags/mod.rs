@@ -0,0 +1,3 @@
+mod tag_name;
+
+pub use tag_name::*;
@@ -0,0 +1,156 @@
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use std::io::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Normalized name of tag. Tags are case insensitive, so names are kept in lowercase,
+/// and consist of letters, digits, `-` and `_` only. Whitespace inside of tag becomes `-`.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    derive_more::Display,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+    serde::Serialize,
+)]
+#[sql_type = "diesel::sql_types::Text"]
+pub struct TagName {
+    s: String,
+}
+
+impl TagName {
+    pub fn parse(s: &str) -> Result<TagName, anyhow::Error> {
+        let s = s
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("-")
+            .to_lowercase();
+        if s.is_empty() {
+            anyhow::bail!("Tag is empty");
+        }
+
+        if s.graphemes(true).count() > MAX_TAG_LENGTH {
+            anyhow::bail!("Tag {} is longer than {} characters", s, MAX_TAG_LENGTH);
+        }
+
+        if !s
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!(
+                "Tag {} can contain only letters, digits, '-' and '_' characters",
+                s
+            );
+        }
+
+        Ok(Self { s })
+    }
+
+    /// Parses comma separated list of tags. Empty entries and duplicates are skipped.
+    pub fn parse_list(s: &str) -> Result<Vec<TagName>, anyhow::Error> {
+        let mut tags = Vec::new();
+        for tag in s.split(',').filter(|tag| !tag.trim().is_empty()) {
+            let tag = TagName::parse(tag)?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Formats tags the way they are entered in forms
+    pub fn join(tags: &[TagName]) -> String {
+        tags.iter()
+            .map(|tag| tag.s.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for TagName {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes).map(|s| TagName { s })
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for TagName {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(&self.s, out)
+    }
+}
+
+impl AsRef<String> for TagName {
+    fn as_ref(&self) -> &String {
+        &self.s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn tags_are_case_folded() {
+        let tag = TagName::parse("RuSt").unwrap();
+        assert_eq!(tag.as_ref(), "rust");
+    }
+
+    #[test]
+    fn whitespace_inside_of_tag_is_replaced_with_dash() {
+        let tag = TagName::parse("  web   development ").unwrap();
+        assert_eq!(tag.as_ref(), "web-development");
+    }
+
+    #[test]
+    fn empty_tag_is_rejected() {
+        assert_err!(TagName::parse(""));
+        assert_err!(TagName::parse("   "));
+    }
+
+    #[test]
+    fn a_32_grapheme_long_tag_is_valid() {
+        assert_ok!(TagName::parse(&"ё".repeat(32)));
+    }
+
+    #[test]
+    fn a_tag_longer_than_32_graphemes_is_rejected() {
+        assert_err!(TagName::parse(&"a".repeat(33)));
+    }
+
+    #[test]
+    fn tags_containing_invalid_characters_are_rejected() {
+        for tag in &["c++", "c#", "a/b", "<b>", "a.b", "a,b", "?"] {
+            assert_err!(TagName::parse(tag));
+        }
+    }
+
+    #[test]
+    fn tag_list_skips_empty_entries_and_duplicates() {
+        let tags = TagName::parse_list("rust, , Rust,web dev,").unwrap();
+        assert_eq!(
+            tags,
+            vec![
+                TagName::parse("rust").unwrap(),
+                TagName::parse("web-dev").unwrap()
+            ]
+        );
+        assert_eq!(TagName::join(&tags), "rust, web-dev");
+    }
+
+    #[test]
+    fn tag_list_with_invalid_tag_is_rejected() {
+        assert_err!(TagName::parse_list("rust, c++"));
+    }
+}
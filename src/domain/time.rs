@@ -1,4 +1,4 @@
-use chrono::{Duration, Utc};
+use chrono::{Duration, TimeZone, Utc};
 use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::serialize::{Output, ToSql};
@@ -9,7 +9,13 @@ use std::str::FromStr;
 type Inner = chrono::DateTime<Utc>;
 
 #[derive(
-    Debug, Clone, PartialEq, derive_more::Display, diesel::AsExpression, diesel::FromSqlRow,
+    Debug,
+    Clone,
+    PartialEq,
+    PartialOrd,
+    derive_more::Display,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
 )]
 #[sql_type = "diesel::sql_types::Text"]
 pub struct DateTime {
@@ -30,6 +36,8 @@ impl FromSql<diesel::sql_types::Text, Sqlite> for DateTime {
     }
 }
 
+/// Times are stored in text format whose lexicographic order is chronological,
+/// so they can be compared and sorted in sql
 impl ToSql<diesel::sql_types::Text, Sqlite> for DateTime {
     fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
         <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(&self.t.to_string(), out)
@@ -47,6 +55,51 @@ impl DateTime {
         Self { t: Utc::now() }
     }
 
+    pub fn plus(&self, duration: Duration) -> Self {
+        Self {
+            t: self.t + duration,
+        }
+    }
+
+    pub fn from_timestamp_nanos(nanos: i64) -> Self {
+        Self {
+            t: Utc.timestamp_nanos(nanos),
+        }
+    }
+
+    pub fn timestamp_nanos(&self) -> i64 {
+        self.t.timestamp_nanos()
+    }
+
+    /// Parses value of html `datetime-local` input, which is treated as UTC time
+    pub fn parse_html_input(s: &str) -> Option<Self> {
+        ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| chrono::NaiveDateTime::parse_from_str(s, format).ok())
+            .map(|naive| Self {
+                t: Utc.from_utc_datetime(&naive),
+            })
+    }
+
+    /// Formats time as value of html `datetime-local` input
+    pub fn to_html_input(&self) -> String {
+        self.t.format("%Y-%m-%dT%H:%M").to_string()
+    }
+
+    /// Formats time as used in Atom and JSON feeds
+    pub fn to_rfc3339(&self) -> String {
+        self.t.to_rfc3339()
+    }
+
+    /// Formats time as used in RSS feeds
+    pub fn to_rfc2822(&self) -> String {
+        self.t.to_rfc2822()
+    }
+
+    pub fn is_past(&self) -> bool {
+        self.t < Utc::now()
+    }
+
     pub fn ago(&self) -> String {
         let now = Self::now();
         self.since(&now)
@@ -170,4 +223,48 @@ mod tests {
         let duration = Duration::seconds(0);
         assert_eq!(duration_since_human_readable(duration), "just now");
     }
+
+    #[test]
+    fn html_input_roundtrip() {
+        let time = DateTime::parse_html_input("2022-05-07T13:45").unwrap();
+        assert_eq!(time.to_html_input(), "2022-05-07T13:45");
+        assert_eq!(
+            DateTime::parse_html_input("2022-05-07T13:45:10")
+                .unwrap()
+                .to_html_input(),
+            "2022-05-07T13:45"
+        );
+    }
+
+    #[test]
+    fn invalid_html_input_is_rejected() {
+        assert!(DateTime::parse_html_input("").is_none());
+        assert!(DateTime::parse_html_input("tomorrow").is_none());
+        assert!(DateTime::parse_html_input("2022-13-07T13:45").is_none());
+    }
+
+    #[test]
+    fn stored_text_sorts_chronologically() {
+        let texts: Vec<String> = [
+            0,
+            1_000_000,
+            1_000_000_000,
+            1_000_001_000,
+            1_000_001_001,
+            1_500_000_000,
+            86_400_000_000_000,
+        ]
+        .iter()
+        .map(|nanos| DateTime::from_timestamp_nanos(*nanos).to_string())
+        .collect();
+        for pair in texts.windows(2) {
+            assert!(pair[0] < pair[1], "{} >= {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn time_written_by_sqlite_can_be_read() {
+        // Format of `strftime('%Y-%m-%d %H:%M:%S UTC', 'now')` used in migrations
+        assert!(chrono::DateTime::<Utc>::from_str("2022-05-01 12:00:00 UTC").is_ok());
+    }
 }
@@ -6,8 +6,9 @@ use secrecy::Secret;
 pub struct StoredCredentials {
     pub name: UserName,
     pub password: HashedUserPassword,
-    pub salt: UserPasswordSalt,
+    pub salt: Option<UserPasswordSalt>,
     pub user_id: UserID,
+    pub is_banned: bool,
 }
 
 #[derive(Debug, Clone)]
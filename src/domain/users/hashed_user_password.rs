@@ -1,4 +1,7 @@
+use crate::config::PasswordHashingConfig;
 use crate::domain::users::{UserPassword, UserPasswordSalt};
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
 use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::serialize::{Output, ToSql};
@@ -6,6 +9,13 @@ use diesel::sqlite::Sqlite;
 use secrecy::{ExposeSecret, Secret};
 use sha3::Digest;
 use std::io::Write;
+use std::sync::OnceLock;
+use subtle::ConstantTimeEq;
+
+/// Hash that is verified against when user is not found, so that response time
+/// does not reveal whether user with given name exists. It is made with configured
+/// parameters, so that it takes as long to verify as hashes of users.
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
 
 #[derive(Debug, Clone, diesel::AsExpression)]
 #[sql_type = "diesel::sql_types::Text"]
@@ -14,7 +24,24 @@ pub struct HashedUserPassword {
 }
 
 impl HashedUserPassword {
-    pub fn parse(password: &UserPassword, salt: &UserPasswordSalt) -> Self {
+    /// Hashes password with Argon2id producing PHC string.
+    pub fn parse(
+        password: &UserPassword,
+        config: &PasswordHashingConfig,
+    ) -> Result<Self, anyhow::Error> {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let password_hash = argon2_from_config(config)?
+            .hash_password(password.as_ref().expose_secret().as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Failed to hash password: {:?}", e))?
+            .to_string();
+        Ok(Self {
+            s: Secret::new(password_hash),
+        })
+    }
+
+    /// Hashes password using legacy Sha3 scheme. Only used to verify passwords
+    /// of users that have not logged in since hashing was changed.
+    pub fn parse_legacy(password: &UserPassword, salt: &UserPasswordSalt) -> Self {
         let new_password_string =
             password.as_ref().expose_secret().to_owned() + salt.as_ref().expose_secret();
         let password_hash = sha3::Sha3_256::digest(new_password_string.as_bytes());
@@ -23,6 +50,96 @@ impl HashedUserPassword {
             s: Secret::new(password_hash),
         }
     }
+
+    /// Checks if hash was made using legacy Sha3 scheme.
+    pub fn is_legacy(&self) -> bool {
+        !self.s.expose_secret().starts_with("$argon2")
+    }
+
+    /// Checks if hash should be recomputed, either because it uses legacy scheme or
+    /// it was made with cost parameters different from current ones.
+    pub fn needs_rehash(&self, config: &PasswordHashingConfig) -> bool {
+        if self.is_legacy() {
+            return true;
+        }
+
+        PasswordHash::new(self.s.expose_secret())
+            .ok()
+            .and_then(|hash| Params::try_from(&hash).ok())
+            .map(|params| {
+                params.m_cost() != config.memory_cost
+                    || params.t_cost() != config.time_cost
+                    || params.p_cost() != config.parallelism
+            })
+            .unwrap_or(true)
+    }
+
+    /// Verifies password against hash in constant time.
+    /// Salt is only required for legacy hashes, as Argon2 ones contain salt themselves.
+    pub fn verify(
+        &self,
+        password: &UserPassword,
+        salt: Option<&UserPasswordSalt>,
+    ) -> Result<bool, anyhow::Error> {
+        if self.is_legacy() {
+            let salt = salt.ok_or_else(|| anyhow::anyhow!("Legacy password hash has no salt"))?;
+            let hashed = Self::parse_legacy(password, salt);
+            return Ok(hashed
+                .s
+                .expose_secret()
+                .as_bytes()
+                .ct_eq(self.s.expose_secret().as_bytes())
+                .into());
+        }
+
+        verify_argon2(self.s.expose_secret(), password)
+    }
+
+    /// Makes dummy hash, so that first login of unknown user doesn't take longer than others.
+    /// Called at startup.
+    pub fn prepare_dummy(config: &PasswordHashingConfig) -> Result<(), anyhow::Error> {
+        dummy_hash(config).map(|_| ())
+    }
+
+    /// Performs verification against dummy hash, taking the same time as real verification.
+    pub fn verify_dummy(password: &UserPassword, config: &PasswordHashingConfig) {
+        if let Ok(hash) = dummy_hash(config) {
+            let _ = verify_argon2(hash, password);
+        }
+    }
+}
+
+fn dummy_hash(config: &PasswordHashingConfig) -> Result<&'static str, anyhow::Error> {
+    if let Some(hash) = DUMMY_HASH.get() {
+        return Ok(hash);
+    }
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let hash = argon2_from_config(config)?
+        .hash_password(b"dummy password", &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash dummy password: {:?}", e))?
+        .to_string();
+    Ok(DUMMY_HASH.get_or_init(|| hash))
+}
+
+fn argon2_from_config(config: &PasswordHashingConfig) -> Result<Argon2<'static>, anyhow::Error> {
+    let params = Params::new(
+        config.memory_cost,
+        config.time_cost,
+        config.parallelism,
+        None,
+    )
+    .map_err(|e| anyhow::anyhow!("Invalid password hashing parameters: {:?}", e))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+fn verify_argon2(hash: &str, password: &UserPassword) -> Result<bool, anyhow::Error> {
+    let hash = PasswordHash::new(hash)
+        .map_err(|e| anyhow::anyhow!("Failed to parse password hash: {:?}", e))?;
+    match Argon2::default().verify_password(password.as_ref().expose_secret().as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(anyhow::anyhow!("Failed to verify password: {:?}", e)),
+    }
 }
 
 impl PartialEq<HashedUserPassword> for HashedUserPassword {
@@ -63,3 +180,85 @@ impl AsRef<Secret<String>> for HashedUserPassword {
         &self.s
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok_eq};
+
+    fn test_config() -> PasswordHashingConfig {
+        PasswordHashingConfig {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        }
+    }
+
+    fn password(s: &str) -> UserPassword {
+        UserPassword::parse(Secret::new(s.to_string())).unwrap()
+    }
+
+    #[test]
+    fn argon2_hash_is_verified() {
+        let hash = HashedUserPassword::parse(&password("!1Aapass"), &test_config()).unwrap();
+        assert!(!hash.is_legacy());
+        assert_ok_eq!(hash.verify(&password("!1Aapass"), None), true);
+    }
+
+    #[test]
+    fn argon2_hash_rejects_wrong_password() {
+        let hash = HashedUserPassword::parse(&password("!1Aapass"), &test_config()).unwrap();
+        assert_ok_eq!(hash.verify(&password("!1Aapasz"), None), false);
+    }
+
+    #[test]
+    fn same_password_produces_different_hashes() {
+        let first = HashedUserPassword::parse(&password("!1Aapass"), &test_config()).unwrap();
+        let second = HashedUserPassword::parse(&password("!1Aapass"), &test_config()).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn legacy_hash_is_verified_with_salt() {
+        let salt = UserPasswordSalt::generate_random();
+        let hash = HashedUserPassword::parse_legacy(&password("!1Aapass"), &salt);
+        assert!(hash.is_legacy());
+        assert_ok_eq!(hash.verify(&password("!1Aapass"), Some(&salt)), true);
+        assert_ok_eq!(hash.verify(&password("!1Aapasz"), Some(&salt)), false);
+    }
+
+    #[test]
+    fn legacy_hash_without_salt_is_error() {
+        let salt = UserPasswordSalt::generate_random();
+        let hash = HashedUserPassword::parse_legacy(&password("!1Aapass"), &salt);
+        assert_err!(hash.verify(&password("!1Aapass"), None));
+    }
+
+    #[test]
+    fn needs_rehash_when_legacy_or_params_changed() {
+        let config = test_config();
+        let salt = UserPasswordSalt::generate_random();
+        let legacy = HashedUserPassword::parse_legacy(&password("!1Aapass"), &salt);
+        assert!(legacy.needs_rehash(&config));
+
+        let hash = HashedUserPassword::parse(&password("!1Aapass"), &config).unwrap();
+        assert!(!hash.needs_rehash(&config));
+
+        let stronger = PasswordHashingConfig {
+            time_cost: 2,
+            ..config
+        };
+        assert!(hash.needs_rehash(&stronger));
+    }
+
+    #[test]
+    fn dummy_hash_uses_configured_params() {
+        let hash = dummy_hash(&test_config()).unwrap();
+        assert!(
+            hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"),
+            "{}",
+            hash
+        );
+        HashedUserPassword::verify_dummy(&password("!1Aapass"), &test_config());
+    }
+}
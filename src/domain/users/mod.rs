@@ -1,6 +1,10 @@
 mod credentials;
 mod hashed_user_password;
 mod new_user;
+mod password_reset_token;
+mod pending_email;
+mod recovery_code;
+mod totp_secret;
 mod update_user;
 mod user;
 mod user_email;
@@ -9,10 +13,15 @@ mod user_name;
 mod user_password;
 mod user_password_salt;
 mod user_role;
+mod user_token;
 
 pub use credentials::*;
 pub use hashed_user_password::*;
 pub use new_user::*;
+pub use password_reset_token::*;
+pub use pending_email::*;
+pub use recovery_code::*;
+pub use totp_secret::*;
 pub use update_user::*;
 pub use user::*;
 pub use user_email::*;
@@ -21,3 +30,4 @@ pub use user_name::*;
 pub use user_password::*;
 pub use user_password_salt::*;
 pub use user_role::*;
+pub use user_token::*;
@@ -1,7 +1,8 @@
-use crate::domain::users::{UserName, UserPassword};
+use crate::domain::users::{UserEmail, UserName, UserPassword};
 
 #[derive(Debug)]
 pub struct NewUser {
     pub name: UserName,
+    pub email: UserEmail,
     pub password: UserPassword,
 }
@@ -0,0 +1,13 @@
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::schema::password_reset_tokens;
+
+/// Request to reset password of user, identified by hash of token sent by email.
+#[derive(Debug, diesel::Queryable, diesel::Insertable, PartialEq)]
+pub struct PasswordResetToken {
+    pub token_hash: String,
+    pub user_id: UserID,
+
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+}
@@ -0,0 +1,14 @@
+use crate::domain::time::DateTime;
+use crate::domain::users::{UserEmail, UserID};
+use crate::schema::pending_emails;
+
+/// Email change that has been requested but not yet confirmed by following emailed link.
+#[derive(Debug, diesel::Queryable, diesel::Insertable, PartialEq)]
+pub struct PendingEmail {
+    pub token_hash: String,
+    pub user_id: UserID,
+    pub email: UserEmail,
+
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+}
@@ -0,0 +1,83 @@
+use rand::distributions::Uniform;
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use sha3::Digest;
+
+const CODE_LENGTH: usize = 10;
+const CODE_ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+/// Number of codes that are generated when two-factor authentication is enabled
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// One-time code that can be used instead of TOTP code if user lost access to authenticator.
+/// Only its hash is stored in database.
+#[derive(Debug, Clone)]
+pub struct RecoveryCode {
+    s: Secret<String>,
+}
+
+impl RecoveryCode {
+    pub fn generate_random() -> Self {
+        let code: String = rand::thread_rng()
+            .sample_iter(Uniform::from(0..CODE_ALPHABET.len()))
+            .take(CODE_LENGTH)
+            .map(|i| CODE_ALPHABET[i] as char)
+            .collect();
+        Self {
+            s: Secret::new(code),
+        }
+    }
+
+    /// Parses code entered by user. Case and surrounding whitespace are ignored.
+    pub fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        let s = s.trim().to_lowercase();
+        if s.len() != CODE_LENGTH || !s.bytes().all(|c| CODE_ALPHABET.contains(&c)) {
+            anyhow::bail!("Recovery code is malformed");
+        }
+        Ok(Self { s: Secret::new(s) })
+    }
+
+    /// Hash of code that is stored in database.
+    pub fn hashed(&self) -> String {
+        format!(
+            "{:x}",
+            sha3::Sha3_256::digest(self.s.expose_secret().as_bytes())
+        )
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn generated_code_is_parsed() {
+        let code = RecoveryCode::generate_random();
+        assert_ok!(RecoveryCode::parse(code.as_ref().expose_secret()));
+    }
+
+    #[test]
+    fn code_is_case_insensitive() {
+        let code = RecoveryCode::generate_random();
+        let upper = format!(" {} ", code.as_ref().expose_secret().to_uppercase());
+        assert_eq!(RecoveryCode::parse(&upper).unwrap().hashed(), code.hashed());
+    }
+
+    #[test]
+    fn code_of_wrong_length_is_rejected() {
+        assert_err!(RecoveryCode::parse(&"a".repeat(CODE_LENGTH - 1)));
+        assert_err!(RecoveryCode::parse(&"a".repeat(CODE_LENGTH + 1)));
+    }
+
+    #[test]
+    fn code_with_ambiguous_characters_is_rejected() {
+        assert_err!(RecoveryCode::parse(&"0".repeat(CODE_LENGTH)));
+        assert_err!(RecoveryCode::parse(&"l".repeat(CODE_LENGTH)));
+    }
+}
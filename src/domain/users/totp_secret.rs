@@ -0,0 +1,208 @@
+use diesel::backend::Backend;
+use diesel::deserialize::FromSql;
+use diesel::serialize::{Output, ToSql};
+use diesel::sqlite::Sqlite;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use std::io::Write;
+use subtle::ConstantTimeEq;
+
+const SECRET_LENGTH: usize = 20;
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+/// Length of time step in seconds
+const TIME_STEP: u64 = 30;
+const CODE_DIGITS: u32 = 6;
+/// Number of time steps before and after current one that are accepted,
+/// to tolerate clock drift between server and user device.
+const ALLOWED_DRIFT: u64 = 1;
+
+/// Shared secret used to generate time-based one-time passwords (RFC 6238).
+/// Stored in base32, the same way it is shown to user.
+#[derive(Debug, Clone, diesel::AsExpression, diesel::FromSqlRow)]
+#[sql_type = "diesel::sql_types::Text"]
+pub struct TotpSecret {
+    s: Secret<String>,
+}
+
+impl TotpSecret {
+    pub fn generate_random() -> Self {
+        let mut bytes = [0u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self {
+            s: Secret::new(base32::encode(BASE32, &bytes)),
+        }
+    }
+
+    pub fn parse(s: String) -> Result<Self, anyhow::Error> {
+        match base32::decode(BASE32, &s) {
+            Some(bytes) if bytes.len() == SECRET_LENGTH => Ok(Self { s: Secret::new(s) }),
+            _ => anyhow::bail!("TOTP secret is malformed"),
+        }
+    }
+
+    /// URI that authenticator apps accept to add account.
+    pub fn otpauth_uri(&self, issuer: &str, account_name: &str) -> String {
+        format!(
+            "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+            issuer = urlencoding::encode(issuer),
+            account = urlencoding::encode(account_name),
+            secret = self.s.expose_secret(),
+            digits = CODE_DIGITS,
+            period = TIME_STEP,
+        )
+    }
+
+    /// Code that authenticator app shows at the moment.
+    pub fn current_code(&self) -> String {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.code_at_step(now / TIME_STEP)
+    }
+
+    /// Checks code entered by user against codes generated for current time.
+    pub fn verify(&self, code: &str) -> bool {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.verify_at(code, now)
+    }
+
+    /// Returns time step of code entered by user if it is valid for current time.
+    /// Step can be used to reject codes that have already been accepted.
+    pub fn matching_step(&self, code: &str) -> Option<u64> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.matching_step_at(code, now)
+    }
+
+    fn verify_at(&self, code: &str, unix_time: u64) -> bool {
+        self.matching_step_at(code, unix_time).is_some()
+    }
+
+    fn matching_step_at(&self, code: &str, unix_time: u64) -> Option<u64> {
+        let code = code.trim();
+        if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let step = unix_time / TIME_STEP;
+        let mut matched = None;
+        // All steps are checked, so that time taken does not depend on which one matches
+        for s in step.saturating_sub(ALLOWED_DRIFT)..=step + ALLOWED_DRIFT {
+            if bool::from(self.code_at_step(s).as_bytes().ct_eq(code.as_bytes())) {
+                matched = Some(s);
+            }
+        }
+        matched
+    }
+
+    fn code_at_step(&self, step: u64) -> String {
+        let key = base32::decode(BASE32, self.s.expose_secret()).unwrap_or_default();
+        let mut mac =
+            Hmac::<sha1::Sha1>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        // Dynamic truncation, see RFC 4226 section 5.3
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(CODE_DIGITS),
+            width = CODE_DIGITS as usize
+        )
+    }
+}
+
+impl PartialEq<TotpSecret> for TotpSecret {
+    fn eq(&self, other: &TotpSecret) -> bool {
+        self.s.expose_secret().eq(other.s.expose_secret())
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.s
+    }
+}
+
+impl FromSql<diesel::sql_types::Text, Sqlite> for TotpSecret {
+    fn from_sql(
+        bytes: Option<&<Sqlite as Backend>::RawValue>,
+    ) -> diesel::deserialize::Result<Self> {
+        <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes)
+            .map(|s| TotpSecret { s: Secret::new(s) })
+    }
+}
+
+impl ToSql<diesel::sql_types::Text, Sqlite> for TotpSecret {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
+        <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(self.s.expose_secret(), out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    /// Secret from test vectors of RFC 6238
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::parse(base32::encode(BASE32, b"12345678901234567890")).unwrap()
+    }
+
+    #[test]
+    fn generated_secret_is_parsed() {
+        let secret = TotpSecret::generate_random();
+        assert_ok!(TotpSecret::parse(secret.as_ref().expose_secret().clone()));
+    }
+
+    #[test]
+    fn malformed_secret_is_rejected() {
+        assert_err!(TotpSecret::parse("not base32!".to_string()));
+        assert_err!(TotpSecret::parse(base32::encode(BASE32, b"short")));
+    }
+
+    #[test]
+    fn codes_match_rfc_test_vectors() {
+        let secret = rfc_secret();
+        assert!(secret.verify_at("287082", 59));
+        assert!(secret.verify_at("081804", 1111111109));
+        assert!(secret.verify_at("050471", 1111111111));
+        assert!(secret.verify_at("005924", 1234567890));
+        assert!(secret.verify_at("279037", 2000000000));
+    }
+
+    #[test]
+    fn codes_from_adjacent_steps_are_accepted() {
+        let secret = rfc_secret();
+        assert!(secret.verify_at("287082", 59 + TIME_STEP));
+        assert!(!secret.verify_at("287082", 59 + 2 * TIME_STEP));
+    }
+
+    #[test]
+    fn matching_step_of_code_is_returned() {
+        let secret = rfc_secret();
+        assert_eq!(secret.matching_step_at("287082", 59), Some(1));
+        assert_eq!(secret.matching_step_at("287082", 59 + TIME_STEP), Some(1));
+        assert_eq!(secret.matching_step_at("000000", 59), None);
+    }
+
+    #[test]
+    fn malformed_code_is_rejected() {
+        let secret = rfc_secret();
+        assert!(!secret.verify_at("28708", 59));
+        assert!(!secret.verify_at("2870822", 59));
+        assert!(!secret.verify_at("28708a", 59));
+    }
+
+    #[test]
+    fn otpauth_uri_contains_secret() {
+        let secret = rfc_secret();
+        let uri = secret.otpauth_uri("holosite", "user name");
+        assert!(uri.starts_with("otpauth://totp/holosite:user%20name?"));
+        assert!(uri.contains(secret.as_ref().expose_secret()));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::domain::users::hashed_user_password::HashedUserPassword;
-use crate::domain::users::{UserEmail, UserID, UserName};
+use crate::domain::users::{UserEmail, UserID, UserName, UserPasswordSalt, UserRole};
 use crate::schema::users;
 
 #[derive(diesel::AsChangeset)]
@@ -9,5 +9,7 @@ pub struct UpdateUser<'a> {
     pub name: Option<&'a UserName>,
     pub email: Option<&'a UserEmail>,
     pub password: Option<&'a HashedUserPassword>,
+    pub password_salt: Option<Option<&'a UserPasswordSalt>>,
     pub is_banned: Option<bool>,
+    pub role: Option<UserRole>,
 }
@@ -1,6 +1,6 @@
 use crate::domain::time::DateTime;
 use crate::domain::users::hashed_user_password::HashedUserPassword;
-use crate::domain::users::{UserEmail, UserID, UserName, UserPasswordSalt, UserRole};
+use crate::domain::users::{TotpSecret, UserEmail, UserID, UserName, UserPasswordSalt, UserRole};
 use crate::schema::users;
 
 #[derive(Debug, diesel::Queryable, diesel::Insertable, PartialEq)]
@@ -12,8 +12,13 @@ pub struct User {
     pub created_at: DateTime,
 
     pub password: HashedUserPassword,
-    pub password_salt: UserPasswordSalt,
+    pub password_salt: Option<UserPasswordSalt>,
 
     pub is_banned: bool,
     pub role: UserRole,
+
+    pub sessions_valid_after: Option<DateTime>,
+    pub totp_secret: Option<TotpSecret>,
+    /// Time step of the last accepted TOTP code
+    pub totp_last_step: Option<i64>,
 }
@@ -1,12 +1,13 @@
 use crate::middleware::Session;
+use crate::Pool;
 use actix_web::dev::Payload;
-use actix_web::error::{ErrorBadRequest, ErrorForbidden};
-use actix_web::{FromRequest, HttpRequest};
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError};
+use actix_web::{web, FromRequest, HttpRequest};
 use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::serialize::{Output, ToSql};
 use diesel::sqlite::Sqlite;
-use futures_util::future::{err, ok, Ready};
+use futures_util::future::LocalBoxFuture;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::io::Write;
 use uuid::Uuid;
@@ -58,6 +59,13 @@ impl UserID {
     }
 }
 
+/// Ids are not validated, unknown ones just don't match any user
+impl From<String> for UserID {
+    fn from(s: String) -> Self {
+        Self { s }
+    }
+}
+
 impl AsRef<String> for UserID {
     fn as_ref(&self) -> &String {
         &self.s
@@ -65,17 +73,20 @@ impl AsRef<String> for UserID {
 }
 
 impl FromRequest for UserID {
-    type Error = <actix_session::Session as FromRequest>::Error;
-    type Future = Ready<Result<UserID, Self::Error>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<UserID, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
         let session = Session::from_request_sync(req);
-        match session.get_user_id() {
-            Ok(id) => match id {
-                Some(id) => ok(id),
-                None => err(ErrorForbidden("User is not authenticated")),
-            },
-            Err(e) => err(ErrorBadRequest(e)),
-        }
+        let pool = req.app_data::<web::Data<Pool>>().cloned();
+        Box::pin(async move {
+            let pool =
+                pool.ok_or_else(|| ErrorInternalServerError("Database pool is not configured"))?;
+            match session.get_authenticated_user_id(&pool).await {
+                Ok(Some(id)) => Ok(id),
+                Ok(None) => Err(ErrorForbidden("User is not authenticated")),
+                Err(e) => Err(ErrorInternalServerError(e)),
+            }
+        })
     }
 }
@@ -17,6 +17,7 @@ use std::io::Write;
 #[sql_type = "diesel::sql_types::Text"]
 pub enum UserRole {
     Admin,
+    Moderator,
     User,
 }
 
@@ -27,6 +28,7 @@ impl FromSql<diesel::sql_types::Text, Sqlite> for UserRole {
         <String as FromSql<diesel::sql_types::Text, Sqlite>>::from_sql(bytes).and_then(|s| {
             Ok(match s.as_str() {
                 "admin" => Ok(UserRole::Admin),
+                "moderator" => Ok(UserRole::Moderator),
                 "user" => Ok(UserRole::User),
                 _ => Err(anyhow!("{} is not a valid user role", s)),
             }?)
@@ -38,6 +40,7 @@ impl ToSql<diesel::sql_types::Text, Sqlite> for UserRole {
     fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> diesel::serialize::Result {
         let s = match self {
             UserRole::Admin => "admin".to_string(),
+            UserRole::Moderator => "moderator".to_string(),
             UserRole::User => "user".to_string(),
         };
         <String as ToSql<diesel::sql_types::Text, Sqlite>>::to_sql(&s, out)
@@ -0,0 +1,77 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use sha3::Digest;
+
+const TOKEN_LENGTH: usize = 32;
+
+/// Random single-use secret that is sent to user by email.
+/// Only its hash is stored in database.
+#[derive(Debug, Clone)]
+pub struct UserToken {
+    s: Secret<String>,
+}
+
+impl UserToken {
+    pub fn generate_random() -> Self {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self {
+            s: Secret::new(token),
+        }
+    }
+
+    pub fn parse(s: String) -> Result<Self, anyhow::Error> {
+        if s.len() != TOKEN_LENGTH || !s.chars().all(|c| c.is_ascii_alphanumeric()) {
+            anyhow::bail!("Token is malformed");
+        }
+        Ok(Self { s: Secret::new(s) })
+    }
+
+    /// Hash of token that is stored in database.
+    pub fn hashed(&self) -> String {
+        format!(
+            "{:x}",
+            sha3::Sha3_256::digest(self.s.expose_secret().as_bytes())
+        )
+    }
+}
+
+impl AsRef<Secret<String>> for UserToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn generated_token_is_parsed() {
+        let token = UserToken::generate_random();
+        assert_ok!(UserToken::parse(token.as_ref().expose_secret().clone()));
+    }
+
+    #[test]
+    fn token_of_wrong_length_is_rejected() {
+        assert_err!(UserToken::parse("a".repeat(TOKEN_LENGTH - 1)));
+        assert_err!(UserToken::parse("a".repeat(TOKEN_LENGTH + 1)));
+    }
+
+    #[test]
+    fn token_with_non_alphanumeric_characters_is_rejected() {
+        assert_err!(UserToken::parse("/".repeat(TOKEN_LENGTH)));
+    }
+
+    #[test]
+    fn hash_is_stable_and_differs_from_token() {
+        let token = UserToken::generate_random();
+        assert_eq!(token.hashed(), token.hashed());
+        assert_ne!(&token.hashed(), token.as_ref().expose_secret());
+    }
+}
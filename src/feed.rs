@@ -0,0 +1,315 @@
+use crate::domain::time::DateTime;
+
+/// Feed of blog posts for feed readers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    /// Address of html page whose posts are in feed
+    pub home_url: String,
+    /// Address of feed itself
+    pub feed_url: String,
+    /// Time of latest update of any entry, or time of generation if feed is empty
+    pub updated: DateTime,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    /// Permanent id of post, which doesn't change when post is renamed
+    pub id: String,
+    /// Absolute address of post
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+    /// Post contents rendered to html
+    pub content_html: String,
+    pub author_name: String,
+    pub published: DateTime,
+    pub updated: DateTime,
+}
+
+impl Feed {
+    pub fn new(title: String, home_url: String, feed_url: String, entries: Vec<FeedEntry>) -> Self {
+        let updated = entries
+            .iter()
+            .map(|entry| &entry.updated)
+            .fold(None, |latest: Option<&DateTime>, updated| match latest {
+                Some(latest) if latest >= updated => Some(latest),
+                _ => Some(updated),
+            })
+            .cloned()
+            .unwrap_or_else(DateTime::now);
+        Self {
+            title,
+            home_url,
+            feed_url,
+            updated,
+            entries,
+        }
+    }
+}
+
+/// Format in which feed is served
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+    Json,
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Json => "application/feed+json; charset=utf-8",
+        }
+    }
+
+    /// Extension of feed file, `feed.{extension}`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "atom",
+            FeedFormat::Rss => "rss",
+            FeedFormat::Json => "json",
+        }
+    }
+
+    pub fn render(&self, feed: &Feed) -> String {
+        match self {
+            FeedFormat::Atom => render_atom(feed),
+            FeedFormat::Rss => render_rss(feed),
+            FeedFormat::Json => render_json(feed),
+        }
+    }
+}
+
+/// Escapes text to be put in xml element or attribute
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in xml 1.0 at all
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_atom(feed: &Feed) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape_xml(&feed.title)));
+    xml.push_str(&format!(
+        "  <link href=\"{}\"/>\n",
+        escape_xml(&feed.home_url)
+    ));
+    xml.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}\"/>\n",
+        escape_xml(&feed.feed_url)
+    ));
+    xml.push_str(&format!("  <id>{}</id>\n", escape_xml(&feed.feed_url)));
+    xml.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        feed.updated.to_rfc3339()
+    ));
+    for entry in &feed.entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_xml(&entry.title)
+        ));
+        xml.push_str(&format!(
+            "    <link href=\"{}\"/>\n",
+            escape_xml(&entry.url)
+        ));
+        xml.push_str(&format!("    <id>{}</id>\n", escape_xml(&entry.id)));
+        xml.push_str(&format!(
+            "    <published>{}</published>\n",
+            entry.published.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            entry.updated.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "    <author><name>{}</name></author>\n",
+            escape_xml(&entry.author_name)
+        ));
+        xml.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape_xml(&entry.summary)
+        ));
+        xml.push_str(&format!(
+            "    <content type=\"html\">{}</content>\n",
+            escape_xml(&entry.content_html)
+        ));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn render_rss(feed: &Feed) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str(concat!(
+        "<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\"",
+        " xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n"
+    ));
+    xml.push_str("  <channel>\n");
+    xml.push_str(&format!("    <title>{}</title>\n", escape_xml(&feed.title)));
+    xml.push_str(&format!(
+        "    <link>{}</link>\n",
+        escape_xml(&feed.home_url)
+    ));
+    xml.push_str(&format!(
+        "    <description>{}</description>\n",
+        escape_xml(&feed.title)
+    ));
+    xml.push_str(&format!(
+        "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape_xml(&feed.feed_url)
+    ));
+    xml.push_str(&format!(
+        "    <lastBuildDate>{}</lastBuildDate>\n",
+        feed.updated.to_rfc2822()
+    ));
+    for entry in &feed.entries {
+        xml.push_str("    <item>\n");
+        xml.push_str(&format!(
+            "      <title>{}</title>\n",
+            escape_xml(&entry.title)
+        ));
+        xml.push_str(&format!("      <link>{}</link>\n", escape_xml(&entry.url)));
+        xml.push_str(&format!(
+            "      <guid isPermaLink=\"true\">{}</guid>\n",
+            escape_xml(&entry.id)
+        ));
+        xml.push_str(&format!(
+            "      <pubDate>{}</pubDate>\n",
+            entry.published.to_rfc2822()
+        ));
+        // Rss `author` must be an email, which is not public
+        xml.push_str(&format!(
+            "      <dc:creator>{}</dc:creator>\n",
+            escape_xml(&entry.author_name)
+        ));
+        xml.push_str(&format!(
+            "      <description>{}</description>\n",
+            escape_xml(&entry.content_html)
+        ));
+        xml.push_str("    </item>\n");
+    }
+    xml.push_str("  </channel>\n");
+    xml.push_str("</rss>\n");
+    xml
+}
+
+/// Renders feed in JSON Feed 1.1 format
+fn render_json(feed: &Feed) -> String {
+    let items: Vec<serde_json::Value> = feed
+        .entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "id": entry.id,
+                "url": entry.url,
+                "title": entry.title,
+                "summary": entry.summary,
+                "content_html": entry.content_html,
+                "date_published": entry.published.to_rfc3339(),
+                "date_modified": entry.updated.to_rfc3339(),
+                "authors": [{ "name": entry.author_name }],
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title,
+        "home_page_url": feed.home_url,
+        "feed_url": feed.feed_url,
+        "items": items,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime {
+        DateTime::parse_html_input(s).unwrap()
+    }
+
+    fn entry(title: &str, updated: &str) -> FeedEntry {
+        FeedEntry {
+            id: "http://localhost/blog_posts/1/view".to_string(),
+            url: "http://localhost/posts/post".to_string(),
+            title: title.to_string(),
+            summary: "Brief".to_string(),
+            content_html: "<p>Hello & bye</p>".to_string(),
+            author_name: "author".to_string(),
+            published: time("2022-05-01T12:00"),
+            updated: time(updated),
+        }
+    }
+
+    fn feed(entries: Vec<FeedEntry>) -> Feed {
+        Feed::new(
+            "Blog".to_string(),
+            "http://localhost/blog_posts/all".to_string(),
+            "http://localhost/feed.atom".to_string(),
+            entries,
+        )
+    }
+
+    #[test]
+    fn feed_is_updated_when_latest_entry_is() {
+        let feed = feed(vec![
+            entry("first", "2022-05-03T12:00"),
+            entry("second", "2022-05-02T12:00"),
+        ]);
+        assert_eq!(feed.updated, time("2022-05-03T12:00"));
+    }
+
+    #[test]
+    fn xml_special_characters_are_escaped() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">'&'</a>\u{2}"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn atom_feed_contains_escaped_entries() {
+        let xml = FeedFormat::Atom.render(&feed(vec![entry("Fish & chips", "2022-05-02T12:00")]));
+        assert!(xml.contains("<title>Fish &amp; chips</title>"));
+        assert!(xml.contains("<updated>2022-05-02T12:00:00+00:00</updated>"));
+        assert!(xml.contains("<content type=\"html\">&lt;p&gt;Hello &amp; bye&lt;/p&gt;</content>"));
+    }
+
+    #[test]
+    fn rss_feed_uses_rfc2822_dates() {
+        let xml = FeedFormat::Rss.render(&feed(vec![entry("Post", "2022-05-02T12:00")]));
+        assert!(xml.contains("<pubDate>Sun, 01 May 2022 12:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<dc:creator>author</dc:creator>"));
+    }
+
+    #[test]
+    fn json_feed_is_valid_json() {
+        let json = FeedFormat::Json.render(&feed(vec![entry("Post", "2022-05-02T12:00")]));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(value["items"][0]["title"], "Post");
+        assert_eq!(value["items"][0]["content_html"], "<p>Hello & bye</p>");
+    }
+}
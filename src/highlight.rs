@@ -0,0 +1,705 @@
+//! Syntax highlighting of code blocks. Tokens are wrapped in spans with `hl-*` classes,
+//! so colors come from stylesheet and themes can be switched there.
+
+use pulldown_cmark::escape::escape_html;
+use std::collections::BTreeSet;
+
+/// Lexical rules of language, good enough to color code, not to parse it
+struct Language {
+    /// Names used in info strings of code blocks, first one is canonical
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// `'` starts string only if it is closed shortly after, e.g. rust lifetimes are not strings
+    char_literals: bool,
+    case_insensitive_keywords: bool,
+    /// Capitalized identifiers are types
+    capitalized_types: bool,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        char_literals: true,
+        case_insensitive_keywords: false,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        case_insensitive_keywords: false,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "of",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "type",
+            "typeof",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        literals: &["true", "false", "null", "undefined"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        char_literals: false,
+        case_insensitive_keywords: false,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["c", "cpp", "c++", "h"],
+        keywords: &[
+            "auto",
+            "break",
+            "case",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "float",
+            "for",
+            "goto",
+            "if",
+            "inline",
+            "int",
+            "long",
+            "namespace",
+            "new",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "typedef",
+            "union",
+            "unsigned",
+            "using",
+            "virtual",
+            "void",
+            "volatile",
+            "while",
+        ],
+        literals: &["true", "false", "NULL", "nullptr"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        char_literals: true,
+        case_insensitive_keywords: false,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        char_literals: true,
+        case_insensitive_keywords: false,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["shell", "sh", "bash", "console"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        case_insensitive_keywords: false,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["sql", "sqlite"],
+        keywords: &[
+            "add",
+            "alter",
+            "and",
+            "as",
+            "asc",
+            "begin",
+            "by",
+            "column",
+            "commit",
+            "create",
+            "default",
+            "delete",
+            "desc",
+            "distinct",
+            "drop",
+            "end",
+            "exists",
+            "foreign",
+            "from",
+            "group",
+            "having",
+            "if",
+            "in",
+            "index",
+            "inner",
+            "insert",
+            "integer",
+            "into",
+            "is",
+            "join",
+            "key",
+            "left",
+            "like",
+            "limit",
+            "not",
+            "null",
+            "on",
+            "or",
+            "order",
+            "primary",
+            "references",
+            "select",
+            "set",
+            "table",
+            "text",
+            "trigger",
+            "union",
+            "unique",
+            "update",
+            "values",
+            "where",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\''],
+        char_literals: false,
+        case_insensitive_keywords: true,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["json"],
+        keywords: &[],
+        literals: &["true", "false", "null"],
+        line_comments: &[],
+        block_comment: None,
+        quotes: &['"'],
+        char_literals: false,
+        case_insensitive_keywords: false,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["toml"],
+        keywords: &[],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        case_insensitive_keywords: false,
+        capitalized_types: false,
+    },
+];
+
+/// Longest char literal, like `'\u{10FFFF}'`
+const MAX_CHAR_LITERAL_LENGTH: usize = 12;
+
+fn find_language(name: &str) -> Option<&'static Language> {
+    let name = name.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.names.contains(&name.as_str()))
+}
+
+/// Attributes of code block given in its info string, e.g. ```` ```rust {3,5-7} ````
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CodeBlockInfo {
+    pub language: Option<String>,
+    /// Numbers of lines to highlight, starting from 1
+    pub highlighted_lines: BTreeSet<usize>,
+}
+
+impl CodeBlockInfo {
+    pub fn parse(info: &str) -> Self {
+        let info = info.trim();
+        let (language, rest) = match info.find(|c: char| c.is_whitespace() || c == '{') {
+            Some(i) => (&info[..i], &info[i..]),
+            None => (info, ""),
+        };
+        // Options after comma, like in `rust,ignore`, are not needed for rendering
+        let language = language.split(',').next().unwrap_or_default();
+
+        let mut highlighted_lines = BTreeSet::new();
+        let rest = rest.trim();
+        if let Some(ranges) = rest
+            .strip_prefix('{')
+            .and_then(|rest| rest.strip_suffix('}'))
+        {
+            for range in ranges.split(',').map(str::trim) {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                if let (Ok(start), Ok(end)) =
+                    (start.trim().parse::<usize>(), end.trim().parse::<usize>())
+                {
+                    highlighted_lines.extend(start.max(1)..=end);
+                }
+            }
+        }
+
+        Self {
+            language: (!language.is_empty()).then(|| language.to_string()),
+            highlighted_lines,
+        }
+    }
+}
+
+/// Renders code block with highlighted syntax and numbered lines.
+/// Code of unknown language is rendered with numbered lines only.
+pub fn highlight_code_block(code: &str, info: &CodeBlockInfo) -> String {
+    let language = info.language.as_deref().and_then(find_language);
+    let tokens = match language {
+        Some(language) => tokenize(code, language),
+        None => vec![(None, code)],
+    };
+
+    let mut html = String::new();
+    match &info.language {
+        Some(name) => {
+            let mut escaped_name = String::new();
+            escape_html(&mut escaped_name, name).expect("Writing to string can't fail");
+            html.push_str(&format!(
+                "<pre class=\"code-block\"><code class=\"language-{}\">",
+                escaped_name
+            ));
+        }
+        None => html.push_str("<pre class=\"code-block\"><code>"),
+    }
+
+    let mut line_number = 1;
+    let mut line = String::new();
+    let push_line = |html: &mut String, line: &mut String, line_number: usize| {
+        let class = if info.highlighted_lines.contains(&line_number) {
+            "line highlighted"
+        } else {
+            "line"
+        };
+        html.push_str(&format!(
+            "<span class=\"{}\" data-line=\"{}\">{}</span>\n",
+            class, line_number, line
+        ));
+        line.clear();
+    };
+    for (class, text) in tokens {
+        let mut parts = text.split('\n').peekable();
+        while let Some(part) = parts.next() {
+            if !part.is_empty() {
+                match class {
+                    Some(class) => {
+                        line.push_str(&format!("<span class=\"hl-{}\">", class));
+                        escape_html(&mut line, part).expect("Writing to string can't fail");
+                        line.push_str("</span>");
+                    }
+                    None => escape_html(&mut line, part).expect("Writing to string can't fail"),
+                }
+            }
+            if parts.peek().is_some() {
+                push_line(&mut html, &mut line, line_number);
+                line_number += 1;
+            }
+        }
+    }
+    // Code of fenced blocks ends with newline, after which there is no line
+    if !line.is_empty() {
+        push_line(&mut html, &mut line, line_number);
+    }
+
+    html.push_str("</code></pre>\n");
+    html
+}
+
+/// Splits code into tokens with their highlighting classes
+fn tokenize<'a>(code: &'a str, language: &Language) -> Vec<(Option<&'static str>, &'a str)> {
+    let mut tokens = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        let c = rest.chars().next().expect("Rest of code is not empty");
+        let token = if let Some(len) = comment_length(rest, language) {
+            Some(("comment", len))
+        } else if let Some(len) = string_length(rest, c, language) {
+            Some(("string", len))
+        } else if c.is_ascii_digit() && !is_identifier_continuation(&code[..i]) {
+            Some(("number", number_length(rest)))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if is_identifier_continuation(&code[..i]) {
+                None
+            } else {
+                word_class(word, &rest[len..], language).map(|class| (class, len))
+            }
+            .or(Some(("", len)))
+        } else {
+            None
+        };
+
+        match token {
+            Some((class, len)) => {
+                if !class.is_empty() {
+                    if plain_start < i {
+                        tokens.push((None, &code[plain_start..i]));
+                    }
+                    tokens.push((Some(class), &code[i..i + len]));
+                    plain_start = i + len;
+                }
+                i += len;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    if plain_start < code.len() {
+        tokens.push((None, &code[plain_start..]));
+    }
+    tokens
+}
+
+/// Whether text before token ends with part of identifier, e.g. `2` in `u32` is not a number
+fn is_identifier_continuation(before: &str) -> bool {
+    before
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn comment_length(rest: &str, language: &Language) -> Option<usize> {
+    if language
+        .line_comments
+        .iter()
+        .any(|start| rest.starts_with(start))
+    {
+        return Some(rest.find('\n').unwrap_or(rest.len()));
+    }
+    let (start, end) = language.block_comment?;
+    if !rest.starts_with(start) {
+        return None;
+    }
+    Some(
+        rest[start.len()..]
+            .find(end)
+            .map_or(rest.len(), |i| start.len() + i + end.len()),
+    )
+}
+
+fn string_length(rest: &str, quote: char, language: &Language) -> Option<usize> {
+    if !language.quotes.contains(&quote) {
+        return None;
+    }
+    if quote == '\'' && language.char_literals {
+        return char_literal_length(rest);
+    }
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return Some(i + c.len_utf8()),
+            _ => {}
+        }
+    }
+    Some(rest.len())
+}
+
+/// Char literal is one char or escape sequence in quotes,
+/// anything else starting with quote is rather something like lifetime
+fn char_literal_length(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    match chars.next()? {
+        (_, '\\') => chars
+            .take_while(|(i, c)| *i <= MAX_CHAR_LITERAL_LENGTH && *c != '\n')
+            .skip(1)
+            .find(|(_, c)| *c == '\'')
+            .map(|(i, _)| i + 1),
+        (_, '\n') | (_, '\'') => None,
+        _ => match chars.next()? {
+            (i, '\'') => Some(i + 1),
+            _ => None,
+        },
+    }
+}
+
+fn number_length(rest: &str) -> usize {
+    let mut len = 0;
+    let mut chars = rest.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let is_fraction = c == '.' && chars.peek().is_some_and(|(_, next)| next.is_ascii_digit());
+        if !(c.is_ascii_alphanumeric() || c == '_' || is_fraction) {
+            break;
+        }
+        len = i + c.len_utf8();
+    }
+    len
+}
+
+fn word_class(word: &str, after: &str, language: &Language) -> Option<&'static str> {
+    let is_keyword = if language.case_insensitive_keywords {
+        let lowercase = word.to_lowercase();
+        language.keywords.contains(&lowercase.as_str())
+    } else {
+        language.keywords.contains(&word)
+    };
+    if is_keyword {
+        Some("keyword")
+    } else if language.literals.contains(&word) {
+        Some("literal")
+    } else if after.starts_with('(') || (after.starts_with('!') && language.names[0] == "rust") {
+        Some("function")
+    } else if language.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+        Some("type")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(numbers: &[usize]) -> BTreeSet<usize> {
+        numbers.iter().copied().collect()
+    }
+
+    fn classes(code: &str, language: &str) -> Vec<(&'static str, String)> {
+        tokenize(code, find_language(language).unwrap())
+            .into_iter()
+            .filter_map(|(class, text)| class.map(|class| (class, text.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn info_string_is_parsed() {
+        assert_eq!(
+            CodeBlockInfo::parse("rust {3,5-7}"),
+            CodeBlockInfo {
+                language: Some("rust".to_string()),
+                highlighted_lines: lines(&[3, 5, 6, 7]),
+            }
+        );
+        assert_eq!(
+            CodeBlockInfo::parse("rust,ignore"),
+            CodeBlockInfo {
+                language: Some("rust".to_string()),
+                highlighted_lines: lines(&[]),
+            }
+        );
+        assert_eq!(CodeBlockInfo::parse(""), CodeBlockInfo::default());
+        assert_eq!(
+            CodeBlockInfo::parse("{2} "),
+            CodeBlockInfo {
+                language: None,
+                highlighted_lines: lines(&[2]),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_line_ranges_are_ignored() {
+        assert_eq!(
+            CodeBlockInfo::parse("py {x,2-,4}").highlighted_lines,
+            lines(&[4])
+        );
+    }
+
+    #[test]
+    fn rust_is_tokenized() {
+        assert_eq!(
+            classes("fn main() { let x: u32 = 42; } // done", "rust"),
+            vec![
+                ("keyword", "fn".to_string()),
+                ("function", "main".to_string()),
+                ("keyword", "let".to_string()),
+                ("number", "42".to_string()),
+                ("comment", "// done".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust_lifetimes_are_not_strings() {
+        assert_eq!(
+            classes(r"fn f<'a>(s: &'a str) -> [char; 2] { ['x', '\''] }", "rust"),
+            vec![
+                ("keyword", "fn".to_string()),
+                ("number", "2".to_string()),
+                ("string", "'x'".to_string()),
+                ("string", r"'\''".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_may_contain_escaped_quotes() {
+        assert_eq!(
+            classes(r#"print("a \" b")"#, "python"),
+            vec![
+                ("function", "print".to_string()),
+                ("string", r#""a \" b""#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sql_keywords_are_case_insensitive() {
+        assert_eq!(
+            classes("SELECT 1 from t -- all", "sql"),
+            vec![
+                ("keyword", "SELECT".to_string()),
+                ("number", "1".to_string()),
+                ("keyword", "from".to_string()),
+                ("comment", "-- all".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_are_numbered_and_highlighted() {
+        let info = CodeBlockInfo::parse("rust {2}");
+        let html = highlight_code_block("let a = 1;\nlet b = 2;\n", &info);
+        assert_eq!(
+            html,
+            concat!(
+                "<pre class=\"code-block\"><code class=\"language-rust\">",
+                "<span class=\"line\" data-line=\"1\"><span class=\"hl-keyword\">let</span> a = <span class=\"hl-number\">1</span>;</span>\n",
+                "<span class=\"line highlighted\" data-line=\"2\"><span class=\"hl-keyword\">let</span> b = <span class=\"hl-number\">2</span>;</span>\n",
+                "</code></pre>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn multiline_tokens_are_split_by_lines() {
+        let info = CodeBlockInfo::parse("c");
+        let html = highlight_code_block("/* a\nb */\n", &info);
+        assert!(html.contains(
+            "<span class=\"line\" data-line=\"1\"><span class=\"hl-comment\">/* a</span></span>\n"
+        ));
+        assert!(html.contains(
+            "<span class=\"line\" data-line=\"2\"><span class=\"hl-comment\">b */</span></span>\n"
+        ));
+    }
+
+    #[test]
+    fn unknown_language_is_escaped_only() {
+        let info = CodeBlockInfo::parse("brainfun");
+        let html = highlight_code_block("<b>&\n", &info);
+        assert_eq!(
+            html,
+            concat!(
+                "<pre class=\"code-block\"><code class=\"language-brainfun\">",
+                "<span class=\"line\" data-line=\"1\">&lt;b&gt;&amp;</span>\n",
+                "</code></pre>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn language_name_is_escaped() {
+        let info = CodeBlockInfo::parse("\"><script>");
+        assert!(!highlight_code_block("x", &info).contains("<script>"));
+    }
+}
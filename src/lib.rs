@@ -6,10 +6,17 @@ use diesel::r2d2::ConnectionManager;
 use diesel::{r2d2, SqliteConnection};
 
 pub mod config;
+pub mod diff;
 pub mod domain;
+pub mod feed;
+pub mod highlight;
+pub mod login_throttle;
+pub mod mail;
 pub mod markdown;
+pub mod media;
 pub mod middleware;
 pub mod routes;
+pub mod sanitize;
 pub mod schema;
 pub mod services;
 pub mod startup;
@@ -0,0 +1,36 @@
+use crate::config::{AttemptStorageConfig, LoginThrottleConfig};
+use crate::login_throttle::{FallbackAttemptStore, InMemoryAttemptStore, RedisAttemptStore};
+use secrecy::Secret;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Keeps counters of failed login attempts and lockouts.
+/// Implementations are blocking, so they should be called from blocking context
+/// (see `telemetry::spawn_blocking_with_tracing`).
+pub trait AttemptStore: Send + Sync {
+    /// Increments counter of failures of key, returning its new value.
+    /// Counter is forgotten when `window` passes since the last failure.
+    fn add_failure(&self, key: &str, window: Duration) -> Result<u32, anyhow::Error>;
+
+    fn clear_failures(&self, key: &str) -> Result<(), anyhow::Error>;
+
+    /// Locks key for given duration, replacing previous lock.
+    fn lock(&self, key: &str, duration: Duration) -> Result<(), anyhow::Error>;
+
+    /// Returns time left until key is unlocked, if it is locked.
+    fn lock_remaining(&self, key: &str) -> Result<Option<Duration>, anyhow::Error>;
+}
+
+/// Creates attempt store using storage specified in config.
+/// Redis store falls back to memory of the process while Redis is unavailable.
+pub fn attempt_store_from_config(
+    config: &LoginThrottleConfig,
+    redis_uri: &Secret<String>,
+) -> Result<Arc<dyn AttemptStore>, anyhow::Error> {
+    Ok(match config.storage {
+        AttemptStorageConfig::Redis => Arc::new(FallbackAttemptStore::new(RedisAttemptStore::new(
+            redis_uri,
+        )?)),
+        AttemptStorageConfig::Memory => Arc::new(InMemoryAttemptStore::new()),
+    })
+}
@@ -0,0 +1,86 @@
+use crate::login_throttle::{AttemptStore, InMemoryAttemptStore};
+use std::time::Duration;
+
+/// Attempt store that keeps counters in memory while primary store is failing,
+/// so that logins keep working and stay limited when Redis is unavailable.
+pub struct FallbackAttemptStore<S> {
+    primary: S,
+    fallback: InMemoryAttemptStore,
+}
+
+impl<S: AttemptStore> FallbackAttemptStore<S> {
+    pub fn new(primary: S) -> Self {
+        Self {
+            primary,
+            fallback: InMemoryAttemptStore::new(),
+        }
+    }
+
+    fn with_fallback<T>(
+        &self,
+        op: impl Fn(&dyn AttemptStore) -> Result<T, anyhow::Error>,
+    ) -> Result<T, anyhow::Error> {
+        op(&self.primary).or_else(|e| {
+            tracing::warn!(
+                error = ?e,
+                "Attempt store is unavailable, falling back to in-memory counters"
+            );
+            op(&self.fallback)
+        })
+    }
+}
+
+impl<S: AttemptStore> AttemptStore for FallbackAttemptStore<S> {
+    fn add_failure(&self, key: &str, window: Duration) -> Result<u32, anyhow::Error> {
+        self.with_fallback(|store| store.add_failure(key, window))
+    }
+
+    fn clear_failures(&self, key: &str) -> Result<(), anyhow::Error> {
+        self.with_fallback(|store| store.clear_failures(key))
+    }
+
+    fn lock(&self, key: &str, duration: Duration) -> Result<(), anyhow::Error> {
+        self.with_fallback(|store| store.lock(key, duration))
+    }
+
+    fn lock_remaining(&self, key: &str) -> Result<Option<Duration>, anyhow::Error> {
+        self.with_fallback(|store| store.lock_remaining(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_ok_eq, assert_some};
+
+    struct FailingAttemptStore;
+
+    impl AttemptStore for FailingAttemptStore {
+        fn add_failure(&self, _key: &str, _window: Duration) -> Result<u32, anyhow::Error> {
+            anyhow::bail!("Connection refused")
+        }
+
+        fn clear_failures(&self, _key: &str) -> Result<(), anyhow::Error> {
+            anyhow::bail!("Connection refused")
+        }
+
+        fn lock(&self, _key: &str, _duration: Duration) -> Result<(), anyhow::Error> {
+            anyhow::bail!("Connection refused")
+        }
+
+        fn lock_remaining(&self, _key: &str) -> Result<Option<Duration>, anyhow::Error> {
+            anyhow::bail!("Connection refused")
+        }
+    }
+
+    #[test]
+    fn counters_are_kept_in_memory_when_primary_fails() {
+        let store = FallbackAttemptStore::new(FailingAttemptStore);
+        let window = Duration::from_secs(60);
+        assert_ok_eq!(store.add_failure("a", window), 1);
+        assert_ok_eq!(store.add_failure("a", window), 2);
+
+        store.lock("a", window).unwrap();
+        assert_some!(store.lock_remaining("a").unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::login_throttle::AttemptStore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Attempt store that lives in memory of single process.
+/// Used in tests and for deployments without Redis.
+#[derive(Default)]
+pub struct InMemoryAttemptStore {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Number of failures and moment they expire
+    failures: HashMap<String, (u32, Instant)>,
+    locks: HashMap<String, Instant>,
+}
+
+impl InMemoryAttemptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, State>, anyhow::Error> {
+        self.state
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Attempt store lock is poisoned: {:?}", e))
+    }
+}
+
+impl AttemptStore for InMemoryAttemptStore {
+    fn add_failure(&self, key: &str, window: Duration) -> Result<u32, anyhow::Error> {
+        let now = Instant::now();
+        let mut state = self.state()?;
+        // Forget expired entries so that map does not grow indefinitely
+        state
+            .failures
+            .retain(|_, (_, expires_at)| *expires_at > now);
+        state.locks.retain(|_, until| *until > now);
+
+        let entry = state
+            .failures
+            .entry(key.to_string())
+            .or_insert((0, now + window));
+        entry.0 += 1;
+        entry.1 = now + window;
+        Ok(entry.0)
+    }
+
+    fn clear_failures(&self, key: &str) -> Result<(), anyhow::Error> {
+        self.state()?.failures.remove(key);
+        Ok(())
+    }
+
+    fn lock(&self, key: &str, duration: Duration) -> Result<(), anyhow::Error> {
+        self.state()?
+            .locks
+            .insert(key.to_string(), Instant::now() + duration);
+        Ok(())
+    }
+
+    fn lock_remaining(&self, key: &str) -> Result<Option<Duration>, anyhow::Error> {
+        let now = Instant::now();
+        Ok(self
+            .state()?
+            .locks
+            .get(key)
+            .filter(|until| **until > now)
+            .map(|until| *until - now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_none, assert_ok_eq, assert_some};
+
+    #[test]
+    fn failures_are_counted_per_key() {
+        let store = InMemoryAttemptStore::new();
+        let window = Duration::from_secs(60);
+        assert_ok_eq!(store.add_failure("a", window), 1);
+        assert_ok_eq!(store.add_failure("a", window), 2);
+        assert_ok_eq!(store.add_failure("b", window), 1);
+
+        store.clear_failures("a").unwrap();
+        assert_ok_eq!(store.add_failure("a", window), 1);
+    }
+
+    #[test]
+    fn failures_expire_after_window() {
+        let store = InMemoryAttemptStore::new();
+        store.add_failure("a", Duration::ZERO).unwrap();
+        assert_ok_eq!(store.add_failure("a", Duration::from_secs(60)), 1);
+    }
+
+    #[test]
+    fn lock_expires() {
+        let store = InMemoryAttemptStore::new();
+        store.lock("a", Duration::from_secs(60)).unwrap();
+        assert_some!(store.lock_remaining("a").unwrap());
+        assert_none!(store.lock_remaining("b").unwrap());
+
+        store.lock("a", Duration::ZERO).unwrap();
+        assert_none!(store.lock_remaining("a").unwrap());
+    }
+}
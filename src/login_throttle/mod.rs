@@ -0,0 +1,11 @@
+mod attempt_store;
+mod fallback_attempt_store;
+mod in_memory_attempt_store;
+mod redis_attempt_store;
+mod throttle;
+
+pub use attempt_store::*;
+pub use fallback_attempt_store::*;
+pub use in_memory_attempt_store::*;
+pub use redis_attempt_store::*;
+pub use throttle::*;
@@ -0,0 +1,67 @@
+use crate::login_throttle::AttemptStore;
+use redis::Commands;
+use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
+
+const FAILURES_PREFIX: &str = "login_failures:";
+const LOCK_PREFIX: &str = "login_lock:";
+/// Logins should not hang for long when Redis is unreachable
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Attempt store backed by Redis, so that counters are shared between
+/// workers and survive restarts.
+pub struct RedisAttemptStore {
+    client: redis::Client,
+}
+
+impl RedisAttemptStore {
+    pub fn new(redis_uri: &Secret<String>) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(format!("redis://{}", redis_uri.expose_secret()))?;
+        Ok(Self { client })
+    }
+}
+
+impl AttemptStore for RedisAttemptStore {
+    fn add_failure(&self, key: &str, window: Duration) -> Result<u32, anyhow::Error> {
+        let mut conn = self
+            .client
+            .get_connection_with_timeout(CONNECTION_TIMEOUT)?;
+        let key = format!("{}{}", FAILURES_PREFIX, key);
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, window.as_secs().max(1) as usize)
+            .ignore()
+            .query(&mut conn)?;
+        Ok(failures)
+    }
+
+    fn clear_failures(&self, key: &str) -> Result<(), anyhow::Error> {
+        let mut conn = self
+            .client
+            .get_connection_with_timeout(CONNECTION_TIMEOUT)?;
+        conn.del::<_, ()>(format!("{}{}", FAILURES_PREFIX, key))?;
+        Ok(())
+    }
+
+    fn lock(&self, key: &str, duration: Duration) -> Result<(), anyhow::Error> {
+        let mut conn = self
+            .client
+            .get_connection_with_timeout(CONNECTION_TIMEOUT)?;
+        conn.set_ex::<_, _, ()>(
+            format!("{}{}", LOCK_PREFIX, key),
+            1,
+            duration.as_secs().max(1) as usize,
+        )?;
+        Ok(())
+    }
+
+    fn lock_remaining(&self, key: &str) -> Result<Option<Duration>, anyhow::Error> {
+        let mut conn = self
+            .client
+            .get_connection_with_timeout(CONNECTION_TIMEOUT)?;
+        // TTL returns negative value if key does not exist or has no expiration
+        let ttl: i64 = conn.ttl(format!("{}{}", LOCK_PREFIX, key))?;
+        Ok((ttl > 0).then(|| Duration::from_secs(ttl as u64)))
+    }
+}
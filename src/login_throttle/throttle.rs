@@ -0,0 +1,281 @@
+use crate::config::LoginThrottleConfig;
+use crate::login_throttle::AttemptStore;
+use actix_web::http::header::HeaderValue;
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Limits rate of failed logins per user name and per client address,
+/// and of invalid two-factor codes per user.
+/// After number of failures exceeds configured limit, key is locked out
+/// for a period that doubles with every following failure.
+pub struct LoginThrottle {
+    store: Arc<dyn AttemptStore>,
+    config: LoginThrottleConfig,
+}
+
+impl LoginThrottle {
+    pub fn new(store: Arc<dyn AttemptStore>, config: LoginThrottleConfig) -> Self {
+        Self { store, config }
+    }
+
+    /// Address of client that requests are limited by. Forwarded address is
+    /// used only if request comes from trusted proxy, as otherwise client
+    /// could change it with every request.
+    pub fn client_address(&self, req: &HttpRequest) -> String {
+        let peer = match req.peer_addr() {
+            Some(addr) => addr.ip(),
+            None => return "unknown".to_string(),
+        };
+        client_address(
+            peer,
+            req.headers().get_all(X_FORWARDED_FOR),
+            &self.config.trusted_proxies,
+        )
+        .to_string()
+    }
+
+    /// Returns time left until login is allowed, if user name or address is locked out.
+    pub fn check(&self, name: &str, ip: &str) -> Result<Option<Duration>, anyhow::Error> {
+        let name_lock = self.store.lock_remaining(&name_key(name))?;
+        let ip_lock = self.store.lock_remaining(&ip_key(ip))?;
+        Ok(name_lock.max(ip_lock))
+    }
+
+    /// Registers failed login. Returns lockout duration if it has been applied.
+    pub fn register_failure(
+        &self,
+        name: &str,
+        ip: &str,
+    ) -> Result<Option<Duration>, anyhow::Error> {
+        let name_lockout = self.add_failure(&name_key(name), self.config.attempts_per_name)?;
+        let ip_lockout = self.add_failure(&ip_key(ip), self.config.attempts_per_ip)?;
+        Ok(name_lockout.max(ip_lockout))
+    }
+
+    /// Forgets failures of user after successful login.
+    pub fn register_success(&self, name: &str) -> Result<(), anyhow::Error> {
+        self.store.clear_failures(&name_key(name))
+    }
+
+    /// Returns time left until user is allowed to enter two-factor code again.
+    pub fn check_two_factor(&self, user_id: &str) -> Result<Option<Duration>, anyhow::Error> {
+        self.store.lock_remaining(&two_factor_key(user_id))
+    }
+
+    /// Registers invalid two-factor code. Codes are limited the same way as
+    /// passwords, but separately from them: entering password again does not
+    /// reset the counter. Returns lockout duration if it has been applied.
+    pub fn register_two_factor_failure(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<Duration>, anyhow::Error> {
+        self.add_failure(&two_factor_key(user_id), self.config.attempts_per_name)
+    }
+
+    /// Forgets invalid two-factor codes of user after successful login.
+    pub fn register_two_factor_success(&self, user_id: &str) -> Result<(), anyhow::Error> {
+        self.store.clear_failures(&two_factor_key(user_id))
+    }
+
+    fn add_failure(&self, key: &str, allowed: u32) -> Result<Option<Duration>, anyhow::Error> {
+        let window = Duration::from_secs(self.config.failure_window_seconds);
+        let failures = self.store.add_failure(key, window)?;
+        let lockout = match lockout_duration(
+            failures,
+            allowed,
+            self.config.base_lockout_seconds,
+            self.config.max_lockout_seconds,
+        ) {
+            Some(lockout) => lockout,
+            None => return Ok(None),
+        };
+
+        self.store.lock(key, lockout)?;
+        tracing::warn!(
+            key = %key,
+            failures,
+            lockout_seconds = lockout.as_secs(),
+            "Login is locked out after too many failed attempts"
+        );
+        Ok(Some(lockout))
+    }
+}
+
+/// Walks X-Forwarded-For chain from the nearest hop, skipping trusted proxies.
+/// The first address that is not a trusted proxy has been appended by one,
+/// so it can't be forged by client.
+fn client_address<'a>(
+    peer: IpAddr,
+    forwarded_for: impl DoubleEndedIterator<Item = &'a HeaderValue>,
+    trusted_proxies: &[IpAddr],
+) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    let hops: Vec<&str> = forwarded_for
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(addr) => client = addr,
+            // Malformed entry can't be trusted, so the last valid one is used
+            Err(_) => break,
+        }
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+    }
+    client
+}
+
+fn name_key(name: &str) -> String {
+    format!("name:{}", name.to_lowercase())
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+fn two_factor_key(user_id: &str) -> String {
+    format!("two_factor:{}", user_id)
+}
+
+/// Lockout starts at base duration once failures reach allowed number and doubles
+/// with each following failure, up to max duration.
+fn lockout_duration(failures: u32, allowed: u32, base: u64, max: u64) -> Option<Duration> {
+    if failures < allowed {
+        return None;
+    }
+    let exponent = (failures - allowed).min(63);
+    let seconds = base.saturating_mul(1u64 << exponent).min(max);
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AttemptStorageConfig;
+    use crate::login_throttle::InMemoryAttemptStore;
+    use claim::{assert_none, assert_some};
+
+    fn throttle() -> LoginThrottle {
+        LoginThrottle::new(
+            Arc::new(InMemoryAttemptStore::new()),
+            LoginThrottleConfig {
+                storage: AttemptStorageConfig::Memory,
+                attempts_per_name: 3,
+                attempts_per_ip: 5,
+                base_lockout_seconds: 30,
+                max_lockout_seconds: 600,
+                failure_window_seconds: 3600,
+                trusted_proxies: vec!["10.0.0.1".parse().unwrap()],
+            },
+        )
+    }
+
+    #[test]
+    fn lockout_grows_exponentially_up_to_max() {
+        assert_eq!(lockout_duration(2, 3, 30, 600), None);
+        assert_eq!(
+            lockout_duration(3, 3, 30, 600),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            lockout_duration(4, 3, 30, 600),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            lockout_duration(5, 3, 30, 600),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            lockout_duration(100, 3, 30, 600),
+            Some(Duration::from_secs(600))
+        );
+    }
+
+    #[test]
+    fn name_is_locked_after_allowed_failures() {
+        let throttle = throttle();
+        for _ in 0..2 {
+            assert_none!(throttle.register_failure("user", "1.1.1.1").unwrap());
+            assert_none!(throttle.check("user", "1.1.1.1").unwrap());
+        }
+        assert_some!(throttle.register_failure("user", "1.1.1.1").unwrap());
+        assert_some!(throttle.check("USER", "2.2.2.2").unwrap());
+        assert_none!(throttle.check("other", "2.2.2.2").unwrap());
+    }
+
+    #[test]
+    fn ip_is_locked_after_allowed_failures() {
+        let throttle = throttle();
+        for i in 0..5 {
+            throttle
+                .register_failure(&format!("user{}", i), "1.1.1.1")
+                .unwrap();
+        }
+        assert_some!(throttle.check("other", "1.1.1.1").unwrap());
+        assert_none!(throttle.check("other", "2.2.2.2").unwrap());
+    }
+
+    #[test]
+    fn two_factor_failures_are_not_reset_by_password_login() {
+        let throttle = throttle();
+        for _ in 0..2 {
+            assert_none!(throttle.register_two_factor_failure("id").unwrap());
+            throttle.register_success("user").unwrap();
+        }
+        assert_some!(throttle.register_two_factor_failure("id").unwrap());
+        assert_some!(throttle.check_two_factor("id").unwrap());
+        assert_none!(throttle.check_two_factor("other").unwrap());
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_address_is_ignored_without_trusted_proxy() {
+        let header = HeaderValue::from_static("1.1.1.1");
+        let trusted = [ip("10.0.0.1")];
+        assert_eq!(
+            client_address(ip("2.2.2.2"), [&header].into_iter(), &trusted),
+            ip("2.2.2.2")
+        );
+    }
+
+    #[test]
+    fn forwarded_address_is_taken_after_trusted_proxies() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        // Client may put anything in front of the chain
+        let header = HeaderValue::from_static("6.6.6.6, 1.1.1.1, 10.0.0.2");
+        assert_eq!(
+            client_address(ip("10.0.0.1"), [&header].into_iter(), &trusted),
+            ip("1.1.1.1")
+        );
+        assert_eq!(
+            client_address(ip("10.0.0.1"), std::iter::empty(), &trusted),
+            ip("10.0.0.1")
+        );
+        let header = HeaderValue::from_static("garbage, 10.0.0.2");
+        assert_eq!(
+            client_address(ip("10.0.0.1"), [&header].into_iter(), &trusted),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn success_resets_name_failures() {
+        let throttle = throttle();
+        throttle.register_failure("user", "1.1.1.1").unwrap();
+        throttle.register_failure("user", "1.1.1.1").unwrap();
+        throttle.register_success("user").unwrap();
+        assert_none!(throttle.register_failure("user", "1.1.1.1").unwrap());
+    }
+}
@@ -0,0 +1,8 @@
+use crate::domain::users::UserEmail;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: UserEmail,
+    pub subject: String,
+    pub body: String,
+}
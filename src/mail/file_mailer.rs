@@ -0,0 +1,38 @@
+use crate::domain::time::DateTime;
+use crate::mail::{Email, Mailer};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Mailer that writes each email to separate file instead of sending it.
+pub struct FileMailer {
+    sender: String,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(sender: &str, dir: &str) -> Result<Self, anyhow::Error> {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            sender: sender.to_string(),
+            dir,
+        })
+    }
+}
+
+impl Mailer for FileMailer {
+    #[tracing::instrument("Write email to file", skip(self, email), fields(to = %email.to))]
+    fn send(&self, email: &Email) -> Result<(), anyhow::Error> {
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        let contents = format!(
+            "Date: {}\nFrom: {}\nTo: {}\nSubject: {}\n\n{}\n",
+            DateTime::now(),
+            self.sender,
+            email.to,
+            email.subject,
+            email.body
+        );
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
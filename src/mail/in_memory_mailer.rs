@@ -0,0 +1,29 @@
+use crate::mail::{Email, Mailer};
+use std::sync::Mutex;
+
+/// Mailer that keeps all sent emails in memory. Used in tests.
+#[derive(Default)]
+pub struct InMemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all emails sent so far, in order of sending.
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().expect("Mailer lock is poisoned").clone()
+    }
+}
+
+impl Mailer for InMemoryMailer {
+    fn send(&self, email: &Email) -> Result<(), anyhow::Error> {
+        self.sent
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Mailer lock is poisoned: {:?}", e))?
+            .push(email.clone());
+        Ok(())
+    }
+}
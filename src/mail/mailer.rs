@@ -0,0 +1,28 @@
+use crate::config::{EmailConfig, EmailTransportConfig};
+use crate::mail::{Email, FileMailer, SmtpMailer};
+use std::sync::Arc;
+
+/// Delivers emails to users. Implementations are blocking, so they should be
+/// called from blocking context (see `telemetry::spawn_blocking_with_tracing`).
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), anyhow::Error>;
+}
+
+/// Creates mailer using transport specified in config.
+pub fn mailer_from_config(config: &EmailConfig) -> Result<Arc<dyn Mailer>, anyhow::Error> {
+    Ok(match &config.transport {
+        EmailTransportConfig::Smtp {
+            host,
+            port,
+            username,
+            password,
+        } => Arc::new(SmtpMailer::new(
+            &config.sender,
+            host,
+            *port,
+            username,
+            password.clone(),
+        )?),
+        EmailTransportConfig::File { path } => Arc::new(FileMailer::new(&config.sender, path)?),
+    })
+}
@@ -0,0 +1,11 @@
+mod email;
+mod file_mailer;
+mod in_memory_mailer;
+mod mailer;
+mod smtp_mailer;
+
+pub use email::*;
+pub use file_mailer::*;
+pub use in_memory_mailer::*;
+pub use mailer::*;
+pub use smtp_mailer::*;
@@ -0,0 +1,45 @@
+use crate::mail::{Email, Mailer};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use secrecy::{ExposeSecret, Secret};
+
+pub struct SmtpMailer {
+    sender: Mailbox,
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        sender: &str,
+        host: &str,
+        port: u16,
+        username: &str,
+        password: Secret<String>,
+    ) -> Result<Self, anyhow::Error> {
+        let transport = SmtpTransport::starttls_relay(host)?
+            .port(port)
+            .credentials(Credentials::new(
+                username.to_string(),
+                password.expose_secret().to_string(),
+            ))
+            .build();
+        Ok(Self {
+            sender: sender.parse()?,
+            transport,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    #[tracing::instrument("Send email over SMTP", skip(self, email), fields(to = %email.to))]
+    fn send(&self, email: &Email) -> Result<(), anyhow::Error> {
+        let message = Message::builder()
+            .from(self.sender.clone())
+            .to(email.to.as_ref().parse()?)
+            .subject(email.subject.as_str())
+            .body(email.body.clone())?;
+        self.transport.send(&message)?;
+        Ok(())
+    }
+}
@@ -1,23 +1,561 @@
-use pulldown_cmark::{html, Options, Parser};
+use crate::domain::media::MediaType;
+use crate::domain::slugs::Slug;
+use crate::highlight::{highlight_code_block, CodeBlockInfo};
+use crate::media::{
+    has_variants, variant_key, VariantFormat, MEDIA_PATH, VARIANT_SIZES, VARIANT_WIDTHS,
+};
+use crate::sanitize::{sanitize_html, SanitizePolicy, COMMENT_POLICY, POST_POLICY};
+use pulldown_cmark::escape::{escape_href, escape_html};
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag};
+use std::collections::{HashMap, HashSet};
+
+/// Paragraph that is replaced with table of contents
+const TABLE_OF_CONTENTS_MARKER: &str = "[TOC]";
+
+/// Prefix of heading ids, so headings can't take ids used by page around post
+pub const HEADING_ID_PREFIX: &str = "h-";
+/// Prefix of footnote ids, for the same reason as heading ones
+pub const FOOTNOTE_ID_PREFIX: &str = "fn-";
+
+/// Place where markdown is rendered, which determines extensions that are enabled
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownContext {
+    /// Blog posts get all extensions
+    Post,
+    /// Many comments are shown on one page, so they don't get anything that makes
+    /// ids in page, like footnotes and heading anchors
+    Comment,
+}
+
+/// Markdown extensions. Strikethrough is always enabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    pub footnotes: bool,
+    pub task_lists: bool,
+    /// Quotes, dashes and ellipses are replaced with typographic ones
+    pub smart_punctuation: bool,
+    /// Headings get ids made from their text and links to themselves
+    pub heading_anchors: bool,
+    /// Paragraph consisting of `[TOC]` is replaced with table of contents.
+    /// Requires heading anchors.
+    pub table_of_contents: bool,
+    /// Code blocks get highlighted syntax and numbered lines
+    pub syntax_highlighting: bool,
+    /// Uploaded images are loaded lazily and offer resized copies to browsers,
+    /// so long posts with many photos open quickly
+    pub lazy_media_images: bool,
+    /// Rendered html is sanitized with this policy, since markdown may contain raw html
+    pub sanitize_policy: &'static SanitizePolicy,
+}
+
+impl MarkdownContext {
+    pub fn options(&self) -> MarkdownOptions {
+        match self {
+            MarkdownContext::Post => MarkdownOptions {
+                tables: true,
+                footnotes: true,
+                task_lists: true,
+                smart_punctuation: true,
+                heading_anchors: true,
+                table_of_contents: true,
+                syntax_highlighting: true,
+                lazy_media_images: true,
+                sanitize_policy: &POST_POLICY,
+            },
+            MarkdownContext::Comment => MarkdownOptions {
+                tables: false,
+                footnotes: false,
+                task_lists: true,
+                smart_punctuation: true,
+                heading_anchors: false,
+                table_of_contents: false,
+                syntax_highlighting: true,
+                lazy_media_images: false,
+                sanitize_policy: &COMMENT_POLICY,
+            },
+        }
+    }
+}
+
+pub fn parse_markdown_to_html(markdown: &str, context: MarkdownContext) -> String {
+    render_markdown(markdown, &context.options())
+}
+
+pub fn render_markdown(markdown: &str, options: &MarkdownOptions) -> String {
+    let mut parser_options = Options::empty();
+    parser_options.insert(Options::ENABLE_STRIKETHROUGH);
+    parser_options.set(Options::ENABLE_TABLES, options.tables);
+    parser_options.set(Options::ENABLE_FOOTNOTES, options.footnotes);
+    parser_options.set(Options::ENABLE_TASKLISTS, options.task_lists);
+    parser_options.set(Options::ENABLE_SMART_PUNCTUATION, options.smart_punctuation);
+
+    let mut events: Vec<Event> = Parser::new_ext(markdown, parser_options).collect();
+    if options.footnotes {
+        events = prefix_footnote_ids(events);
+    }
+    if options.heading_anchors {
+        let headings;
+        (events, headings) = add_heading_anchors(events);
+        if options.table_of_contents {
+            events = insert_table_of_contents(events, &headings);
+        }
+    }
+    if options.syntax_highlighting {
+        events = highlight_code_blocks(events);
+    }
+    if options.lazy_media_images {
+        events = lazy_load_media_images(events);
+    }
 
-pub fn parse_markdown_to_html(markdown: &str) -> String {
     let mut output = String::new();
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    html::push_html(&mut output, Parser::new_ext(markdown, options));
-    output
+    html::push_html(&mut output, events.into_iter());
+    sanitize_html(&output, options.sanitize_policy)
+}
+
+/// Replaces footnote references and definitions with ones whose ids have footnote prefix.
+/// Footnotes are numbered in order of first appearance, like pulldown-cmark does.
+fn prefix_footnote_ids(events: Vec<Event>) -> Vec<Event> {
+    let mut numbers = HashMap::new();
+    let mut number = |name: &str| {
+        let next = numbers.len() + 1;
+        *numbers.entry(name.to_string()).or_insert(next)
+    };
+    let id = |name: &str| {
+        let mut id = String::from(FOOTNOTE_ID_PREFIX);
+        escape_html(&mut id, name).expect("Writing to string can't fail");
+        id
+    };
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::FootnoteReference(name) => Event::Html(CowStr::from(format!(
+                "<sup class=\"footnote-reference\"><a href=\"#{}\">{}</a></sup>",
+                id(&name),
+                number(&name)
+            ))),
+            Event::Start(Tag::FootnoteDefinition(name)) => Event::Html(CowStr::from(format!(
+                "<div class=\"footnote-definition\" id=\"{}\"><sup class=\"footnote-definition-label\">{}</sup>\n",
+                id(&name),
+                number(&name)
+            ))),
+            Event::End(Tag::FootnoteDefinition(_)) => Event::Html(CowStr::from("</div>\n")),
+            event => event,
+        })
+        .collect()
+}
+
+/// Replaces code blocks with highlighted ones
+fn highlight_code_blocks(events: Vec<Event>) -> Vec<Event> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+        let info = match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                CodeBlockInfo::parse(&info)
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)) => CodeBlockInfo::default(),
+            event => {
+                result.push(event);
+                continue;
+            }
+        };
+
+        let code: String = events
+            .by_ref()
+            .take_while(|event| !matches!(event, Event::End(Tag::CodeBlock(_))))
+            .filter_map(|event| match event {
+                Event::Text(text) => Some(text.into_string()),
+                _ => None,
+            })
+            .collect();
+        result.push(Event::Html(CowStr::from(highlight_code_block(
+            &code, &info,
+        ))));
+    }
+    result
+}
+
+/// Replaces images that point to uploaded media with ones that are loaded lazily.
+/// Images that have resized copies get them in `srcset`, WebP ones for browsers that support it.
+fn lazy_load_media_images(events: Vec<Event>) -> Vec<Event> {
+    let media_prefix = format!("{}/", MEDIA_PATH);
+    let mut result = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+        let (url, title) = match event {
+            Event::Start(Tag::Image(_, url, title)) if url.starts_with(&media_prefix) => {
+                (url, title)
+            }
+            event => {
+                result.push(event);
+                continue;
+            }
+        };
+
+        // Alt text is plain text of everything inside image, which may include nested images
+        let mut alt = String::new();
+        let mut depth = 0;
+        for event in events.by_ref() {
+            match event {
+                Event::Start(Tag::Image(..)) => depth += 1,
+                Event::End(Tag::Image(..)) if depth == 0 => break,
+                Event::End(Tag::Image(..)) => depth -= 1,
+                Event::Text(text) | Event::Code(text) => alt.push_str(&text),
+                Event::SoftBreak | Event::HardBreak => alt.push(' '),
+                _ => {}
+            }
+        }
+
+        let key = &url[media_prefix.len()..];
+        let variants = key
+            .rsplit_once('.')
+            .and_then(|(_, extension)| MediaType::from_extension(extension))
+            .is_some_and(has_variants);
+
+        let mut html = String::new();
+        if variants {
+            html.push_str("<picture><source type=\"");
+            html.push_str(VariantFormat::Webp.mime());
+            html.push_str("\" srcset=\"");
+            push_srcset(&mut html, key, VariantFormat::Webp);
+            html.push_str("\" sizes=\"");
+            html.push_str(VARIANT_SIZES);
+            html.push_str("\" />");
+        }
+        html.push_str("<img src=\"");
+        escape_href(&mut html, &url).expect("Writing to string can't fail");
+        if variants {
+            html.push_str("\" srcset=\"");
+            push_srcset(&mut html, key, VariantFormat::Jpeg);
+            html.push_str("\" sizes=\"");
+            html.push_str(VARIANT_SIZES);
+        }
+        html.push_str("\" alt=\"");
+        escape_html(&mut html, &alt).expect("Writing to string can't fail");
+        html.push('"');
+        if !title.is_empty() {
+            html.push_str(" title=\"");
+            escape_html(&mut html, &title).expect("Writing to string can't fail");
+            html.push('"');
+        }
+        html.push_str(" loading=\"lazy\" decoding=\"async\" />");
+        if variants {
+            html.push_str("</picture>");
+        }
+        result.push(Event::Html(CowStr::from(html)));
+    }
+    result
+}
+
+/// Appends urls of resized copies of uploaded file with their widths
+fn push_srcset(html: &mut String, key: &str, format: VariantFormat) {
+    for (i, width) in VARIANT_WIDTHS.iter().enumerate() {
+        if i > 0 {
+            html.push_str(", ");
+        }
+        let url = format!("{}/{}", MEDIA_PATH, variant_key(key, *width, format));
+        escape_href(&mut *html, &url).expect("Writing to string can't fail");
+        html.push_str(&format!(" {}w", width));
+    }
+}
+
+/// Heading that can be linked to from table of contents
+#[derive(Debug, Clone, PartialEq)]
+struct Heading {
+    level: HeadingLevel,
+    id: String,
+    text: String,
+}
+
+/// Replaces headings with ones that have ids and links to themselves
+fn add_heading_anchors(events: Vec<Event>) -> (Vec<Event>, Vec<Heading>) {
+    let mut result = Vec::with_capacity(events.len());
+    let mut headings = Vec::new();
+    let mut used_ids = HashSet::new();
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+        let level = match event {
+            Event::Start(Tag::Heading(level, _, _)) => level,
+            event => {
+                result.push(event);
+                continue;
+            }
+        };
+
+        let inner: Vec<Event> = events
+            .by_ref()
+            .take_while(|event| !matches!(event, Event::End(Tag::Heading(..))))
+            .collect();
+        let text: String = inner
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect();
+        let id = unique_heading_id(&text, &mut used_ids);
+
+        result.push(Event::Html(CowStr::from(format!(
+            "<{} id=\"{}\">",
+            level, id
+        ))));
+        result.extend(inner);
+        result.push(Event::Html(CowStr::from(format!(
+            "<a class=\"heading-anchor\" href=\"#{}\">#</a></{}>\n",
+            id, level
+        ))));
+        headings.push(Heading { level, id, text });
+    }
+    (result, headings)
+}
+
+fn unique_heading_id(text: &str, used_ids: &mut HashSet<String>) -> String {
+    let base = Slug::from_title(text);
+    let mut id = base.as_ref().clone();
+    let mut n = 2;
+    while used_ids.contains(&id) {
+        id = base.with_suffix(n).as_ref().clone();
+        n += 1;
+    }
+    used_ids.insert(id.clone());
+    format!("{}{}", HEADING_ID_PREFIX, id)
+}
+
+/// Replaces paragraphs consisting of table of contents marker with table of contents
+fn insert_table_of_contents<'a>(events: Vec<Event<'a>>, headings: &[Heading]) -> Vec<Event<'a>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut i = 0;
+    while i < events.len() {
+        if let Event::Start(Tag::Paragraph) = events[i] {
+            let end = events[i..]
+                .iter()
+                .position(|event| matches!(event, Event::End(Tag::Paragraph)))
+                .map(|position| i + position);
+            if let Some(end) = end {
+                let mut text = String::new();
+                let only_text = events[i + 1..end].iter().all(|event| match event {
+                    Event::Text(s) => {
+                        text.push_str(s);
+                        true
+                    }
+                    _ => false,
+                });
+                if only_text && text.trim() == TABLE_OF_CONTENTS_MARKER {
+                    result.push(Event::Html(CowStr::from(render_table_of_contents(
+                        headings,
+                    ))));
+                    i = end + 1;
+                    continue;
+                }
+            }
+        }
+        result.push(events[i].clone());
+        i += 1;
+    }
+    result
+}
+
+/// Renders headings as nested lists of links
+fn render_table_of_contents(headings: &[Heading]) -> String {
+    let min_level = match headings.iter().map(|heading| heading.level).min() {
+        Some(level) => level as usize,
+        None => return String::new(),
+    };
+
+    let mut html = String::from("<nav class=\"table-of-contents\">\n");
+    let mut depth = 0;
+    for heading in headings {
+        let target = heading.level as usize - min_level + 1;
+        if target > depth {
+            while depth < target {
+                html.push_str("<ul>\n");
+                depth += 1;
+            }
+        } else {
+            html.push_str("</li>\n");
+            while depth > target {
+                html.push_str("</ul>\n</li>\n");
+                depth -= 1;
+            }
+        }
+        html.push_str(&format!("<li><a href=\"#{}\">", heading.id));
+        escape_html(&mut html, &heading.text).expect("Writing to string can't fail");
+        html.push_str("</a>");
+    }
+    while depth > 0 {
+        html.push_str("</li>\n</ul>\n");
+        depth -= 1;
+    }
+    html.push_str("</nav>\n");
+    html
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_markdown_to_html;
+    use super::*;
+
+    fn post(markdown: &str) -> String {
+        parse_markdown_to_html(markdown, MarkdownContext::Post)
+    }
+
+    fn comment(markdown: &str) -> String {
+        parse_markdown_to_html(markdown, MarkdownContext::Comment)
+    }
 
     #[test]
     fn simple_test_that_markdown_parser_seems_to_work() {
         let markdown_input = "Hello world, this is a ~~complicated~~ *very simple* example.";
-        let html_output = parse_markdown_to_html(markdown_input);
+        let html_output = comment(markdown_input);
         let expected_html =
             "<p>Hello world, this is a <del>complicated</del> <em>very simple</em> example.</p>\n";
         assert_eq!(expected_html, &html_output);
     }
+
+    #[test]
+    fn tables_are_rendered_in_posts_only() {
+        let markdown = "| a | b |\n|---|---|\n| 1 | 2 |";
+        assert!(post(markdown).contains("<table>"));
+        assert!(!comment(markdown).contains("<table>"));
+    }
+
+    #[test]
+    fn footnotes_are_rendered_in_posts_only() {
+        let markdown = "Text[^1]\n\n[^1]: Note";
+        assert!(post(markdown).contains("footnote-definition"));
+        assert!(!comment(markdown).contains("footnote-definition"));
+    }
+
+    #[test]
+    fn footnote_ids_are_prefixed() {
+        let html = post("Text[^note] and more[^1][^note]\n\n[^note]: Note\n\n[^1]: Other");
+        assert!(html.contains("<a href=\"#fn-note\">1</a>"));
+        assert!(html.contains("<a href=\"#fn-1\">2</a>"));
+        assert!(html.contains(
+            "<div class=\"footnote-definition\" id=\"fn-note\"><sup class=\"footnote-definition-label\">1</sup>"
+        ));
+        assert!(html.contains("id=\"fn-1\""));
+    }
+
+    #[test]
+    fn task_lists_are_rendered() {
+        let html = comment("- [x] done\n- [ ] todo");
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\" />"));
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" />"));
+    }
+
+    #[test]
+    fn punctuation_is_smart() {
+        assert_eq!(comment("\"Quote\" -- dash..."), "<p>“Quote” – dash…</p>\n");
+    }
+
+    #[test]
+    fn headings_get_unique_anchors() {
+        let html = post("# Getting *started*\n\n## Setup\n\n## Setup");
+        assert!(html.contains(
+            "<h1 id=\"h-getting-started\">Getting <em>started</em><a class=\"heading-anchor\" href=\"#h-getting-started\">#</a></h1>"
+        ));
+        assert!(html.contains("<h2 id=\"h-setup\">"));
+        assert!(html.contains("<h2 id=\"h-setup-2\">"));
+    }
+
+    #[test]
+    fn comment_headings_have_no_anchors() {
+        assert_eq!(comment("# Title"), "<h1>Title</h1>\n");
+    }
+
+    #[test]
+    fn table_of_contents_replaces_marker() {
+        let html = post("[TOC]\n\n# One\n\n## Two & a half\n\n# Three");
+        assert!(!html.contains(TABLE_OF_CONTENTS_MARKER));
+        let expected = concat!(
+            "<nav class=\"table-of-contents\">\n",
+            "<ul>\n<li><a href=\"#h-one\">One</a>",
+            "<ul>\n<li><a href=\"#h-two-a-half\">Two &amp; a half</a></li>\n</ul>\n</li>\n",
+            "<li><a href=\"#h-three\">Three</a></li>\n</ul>\n",
+            "</nav>\n"
+        );
+        assert!(html.starts_with(expected), "{}", html);
+    }
+
+    #[test]
+    fn fenced_code_is_highlighted() {
+        let html = comment("```rust {1}\nfn main() {}\n```");
+        assert_eq!(
+            html,
+            concat!(
+                "<pre class=\"code-block\"><code class=\"language-rust\">",
+                "<span class=\"line highlighted\" data-line=\"1\">",
+                "<span class=\"hl-keyword\">fn</span> <span class=\"hl-function\">main</span>() {}",
+                "</span>\n</code></pre>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn indented_code_gets_line_numbers() {
+        let html = post("    a < b\n    c");
+        assert!(html.contains("<span class=\"line\" data-line=\"1\">a &lt; b</span>"));
+        assert!(html.contains("<span class=\"line\" data-line=\"2\">c</span>"));
+    }
+
+    #[test]
+    fn raw_html_is_sanitized() {
+        assert_eq!(
+            comment("Hi <script>alert(1)</script><img src=x onerror=alert(1)>"),
+            "<p>Hi </p>\n"
+        );
+        assert_eq!(
+            post("<div onclick=\"alert(1)\">\n\n[a](javascript:alert(1))\n\n</div>"),
+            "<div>\n<p><a>a</a></p>\n</div>"
+        );
+    }
+
+    #[test]
+    fn comment_links_are_nofollow() {
+        assert_eq!(
+            comment("[a](https://example.com)"),
+            "<p><a href=\"https://example.com\" rel=\"nofollow ugc\">a</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn uploaded_images_are_loaded_lazily() {
+        assert_eq!(
+            post("![A *cat* & `dog`](/media/1.gif \"Pets\")"),
+            concat!(
+                "<p><img src=\"/media/1.gif\" alt=\"A cat &amp; dog\" title=\"Pets\" ",
+                "loading=\"lazy\" decoding=\"async\" /></p>\n"
+            )
+        );
+        assert_eq!(
+            post("![a](https://example.com/media/1.png)"),
+            "<p><img src=\"https://example.com/media/1.png\" alt=\"a\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn uploaded_photos_offer_resized_copies() {
+        assert_eq!(
+            post("![cat](/media/1.jpg)"),
+            concat!(
+                "<p><picture><source type=\"image/webp\" ",
+                "srcset=\"/media/1-480w.webp 480w, /media/1-960w.webp 960w, /media/1-1600w.webp 1600w\" ",
+                "sizes=\"(max-width: 1127px) 100vw, 1127px\" />",
+                "<img src=\"/media/1.jpg\" ",
+                "srcset=\"/media/1-480w.jpg 480w, /media/1-960w.jpg 960w, /media/1-1600w.jpg 1600w\" ",
+                "sizes=\"(max-width: 1127px) 100vw, 1127px\" alt=\"cat\" ",
+                "loading=\"lazy\" decoding=\"async\" /></picture></p>\n"
+            )
+        );
+        assert!(!post("![paper](/media/1.pdf)").contains("srcset"));
+        assert!(!post("![cat](/media/1.jpg?v=2)").contains("srcset"));
+    }
+
+    #[test]
+    fn table_of_contents_is_not_made_without_marker() {
+        assert!(!post("# One").contains("table-of-contents"));
+        assert!(comment("[TOC]\n\n# One").contains(TABLE_OF_CONTENTS_MARKER));
+    }
 }
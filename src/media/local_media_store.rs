@@ -0,0 +1,87 @@
+use crate::media::MediaStore;
+use std::path::PathBuf;
+
+/// Media store that keeps files in local directory, which is served by `actix_files`.
+pub struct LocalMediaStore {
+    dir: PathBuf,
+    url_prefix: String,
+}
+
+impl LocalMediaStore {
+    pub fn new(dir: &str, url_prefix: &str) -> Result<Self, anyhow::Error> {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            url_prefix: url_prefix.to_string(),
+        })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, anyhow::Error> {
+        // Keys are generated by application, but they must never point outside of directory
+        if key.is_empty() || key.starts_with('.') || key.contains(['/', '\\']) {
+            return Err(anyhow::anyhow!("Invalid media key: {:?}", key));
+        }
+        Ok(self.dir.join(key))
+    }
+}
+
+impl MediaStore for LocalMediaStore {
+    #[tracing::instrument("Save media to file", skip(self, data), fields(size = data.len()))]
+    fn save(&self, key: &str, data: &[u8]) -> Result<(), anyhow::Error> {
+        std::fs::write(self.path(key)?, data)?;
+        Ok(())
+    }
+
+    #[tracing::instrument("Load media from file", skip(self))]
+    fn load(&self, key: &str) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(std::fs::read(self.path(key)?)?)
+    }
+
+    #[tracing::instrument("Delete media file", skip(self))]
+    fn delete(&self, key: &str) -> Result<(), anyhow::Error> {
+        match std::fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.url_prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn store() -> LocalMediaStore {
+        let dir = std::env::temp_dir().join(format!("holosite-media-{}", Uuid::new_v4()));
+        LocalMediaStore::new(dir.to_str().unwrap(), "/media").unwrap()
+    }
+
+    #[test]
+    fn files_are_saved_and_deleted() {
+        let store = store();
+        store.save("a.png", b"data").unwrap();
+        assert_eq!(std::fs::read(store.dir.join("a.png")).unwrap(), b"data");
+        assert_eq!(store.load("a.png").unwrap(), b"data");
+        assert_eq!(store.url("a.png"), "/media/a.png");
+
+        store.delete("a.png").unwrap();
+        assert!(!store.dir.join("a.png").exists());
+        // Deleting missing file is not an error, so that deletion can be retried
+        store.delete("a.png").unwrap();
+        std::fs::remove_dir(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn keys_cant_escape_directory() {
+        let store = store();
+        for key in ["", "../a.png", "a/b.png", "..", ".hidden", "a\\b"] {
+            assert!(store.save(key, b"data").is_err(), "{}", key);
+        }
+        std::fs::remove_dir(&store.dir).unwrap();
+    }
+}
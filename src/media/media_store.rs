@@ -0,0 +1,26 @@
+use crate::config::{MediaConfig, MediaStorageConfig};
+use crate::media::LocalMediaStore;
+use std::sync::Arc;
+
+/// Path under which uploaded files are served
+pub const MEDIA_PATH: &str = "/media";
+
+/// Keeps contents of uploaded files. Implementations are blocking, so they should be
+/// called from blocking context (see `telemetry::spawn_blocking_with_tracing`).
+pub trait MediaStore: Send + Sync {
+    fn save(&self, key: &str, data: &[u8]) -> Result<(), anyhow::Error>;
+
+    fn load(&self, key: &str) -> Result<Vec<u8>, anyhow::Error>;
+
+    fn delete(&self, key: &str) -> Result<(), anyhow::Error>;
+
+    /// Url at which file is served
+    fn url(&self, key: &str) -> String;
+}
+
+/// Creates media store using storage specified in config.
+pub fn media_store_from_config(config: &MediaConfig) -> Result<Arc<dyn MediaStore>, anyhow::Error> {
+    Ok(match &config.storage {
+        MediaStorageConfig::Local { path } => Arc::new(LocalMediaStore::new(path, MEDIA_PATH)?),
+    })
+}
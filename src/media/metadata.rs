@@ -0,0 +1,437 @@
+//! Removal of metadata, like EXIF with camera details and GPS location, from uploaded images.
+//! Images are not decoded, only containers are rewritten without metadata blocks.
+//! Parts of file that can't be parsed are kept as is. Orientation of JPEG photos is kept
+//! in minimal EXIF of its own, since without it photos taken sideways would be shown sideways.
+
+use crate::domain::media::MediaType;
+
+/// Returns contents of file without metadata. Only JPEG, PNG and WebP images are changed,
+/// since other types can't contain EXIF.
+pub fn strip_metadata(media_type: MediaType, data: &[u8]) -> Vec<u8> {
+    match media_type {
+        MediaType::Jpeg => strip_jpeg(data),
+        MediaType::Png => strip_png(data),
+        MediaType::Webp => strip_webp(data),
+        MediaType::Gif | MediaType::Pdf => data.to_vec(),
+    }
+}
+
+/// JPEG markers of segments that are kept: JFIF header, ICC color profile and Adobe
+/// color transform. Other application segments, like EXIF and XMP in APP1 and IPTC in APP13,
+/// and comments are removed.
+const KEPT_JPEG_APP_MARKERS: [u8; 3] = [0xe0, 0xe2, 0xee];
+const JPEG_START_OF_SCAN: u8 = 0xda;
+const JPEG_COMMENT: u8 = 0xfe;
+const JPEG_APP1: u8 = 0xe1;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const EXIF_ORIENTATION_TAG: u16 = 0x0112;
+const EXIF_SHORT_TYPE: u16 = 3;
+/// Orientation of image that is stored as it should be shown, which needs no EXIF
+const DEFAULT_ORIENTATION: u16 = 1;
+
+/// Markers that are not followed by segment length: temporary marker, restart markers,
+/// start and end of image
+fn is_standalone_jpeg_marker(marker: u8) -> bool {
+    marker == 0x01 || (0xd0..=0xd9).contains(&marker)
+}
+
+fn strip_jpeg(data: &[u8]) -> Vec<u8> {
+    let mut has_orientation = false;
+    let mut result = Vec::with_capacity(data.len());
+    result.extend_from_slice(&data[..2.min(data.len())]);
+    let mut rest = &data[2.min(data.len())..];
+
+    // Segments with metadata are before image data, which starts after start of scan
+    while rest.first() == Some(&0xff) {
+        // Marker may be preceded by any number of fill bytes
+        let fill = rest.iter().take_while(|byte| **byte == 0xff).count();
+        let marker = match rest.get(fill) {
+            Some(marker) => *marker,
+            None => break,
+        };
+        if is_standalone_jpeg_marker(marker) {
+            result.extend_from_slice(&rest[..fill + 1]);
+            rest = &rest[fill + 1..];
+            continue;
+        }
+        if marker == JPEG_START_OF_SCAN || rest.len() < fill + 3 {
+            break;
+        }
+        let length = u16::from_be_bytes([rest[fill + 1], rest[fill + 2]]) as usize;
+        let segment_length = fill + 1 + length;
+        if length < 2 || rest.len() < segment_length {
+            break;
+        }
+        let is_metadata = (0xe0..=0xef).contains(&marker)
+            && !KEPT_JPEG_APP_MARKERS.contains(&marker)
+            || marker == JPEG_COMMENT;
+        if !is_metadata {
+            result.extend_from_slice(&rest[..segment_length]);
+        } else if marker == JPEG_APP1 && !has_orientation {
+            let orientation = exif_orientation(&rest[fill + 3..segment_length])
+                .filter(|orientation| *orientation != DEFAULT_ORIENTATION);
+            if let Some(orientation) = orientation {
+                result.extend_from_slice(&orientation_exif_segment(orientation));
+                has_orientation = true;
+            }
+        }
+        rest = &rest[segment_length..];
+    }
+    result.extend_from_slice(rest);
+    result
+}
+
+/// Reads orientation from first IFD of EXIF segment data, if it is there
+fn exif_orientation(segment: &[u8]) -> Option<u16> {
+    let tiff = segment.strip_prefix(EXIF_HEADER)?;
+    let is_big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if is_big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| {
+        let bytes = tiff.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+        Some(if is_big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    if u16_at(2)? != 42 {
+        return None;
+    }
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|entry| u16_at(*entry) == Some(EXIF_ORIENTATION_TAG))
+        .filter(|entry| u16_at(entry + 2) == Some(EXIF_SHORT_TYPE))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// APP1 segment with EXIF that has nothing but orientation
+fn orientation_exif_segment(orientation: u16) -> Vec<u8> {
+    let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend_from_slice(&EXIF_ORIENTATION_TAG.to_be_bytes());
+    tiff.extend_from_slice(&EXIF_SHORT_TYPE.to_be_bytes());
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    // There is no next IFD
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+
+    let length = 2 + EXIF_HEADER.len() + tiff.len();
+    let mut segment = vec![0xff, JPEG_APP1];
+    segment.extend_from_slice(&(length as u16).to_be_bytes());
+    segment.extend_from_slice(EXIF_HEADER);
+    segment.extend_from_slice(&tiff);
+    segment
+}
+
+const PNG_SIGNATURE_LENGTH: usize = 8;
+/// Chunks with EXIF, text like author and comments, and modification time
+const PNG_METADATA_CHUNKS: [&[u8]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+fn strip_png(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    result.extend_from_slice(&data[..PNG_SIGNATURE_LENGTH.min(data.len())]);
+    let mut rest = &data[PNG_SIGNATURE_LENGTH.min(data.len())..];
+
+    // Chunk is length of data, type, data and checksum
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let chunk_length = match length.checked_add(12).filter(|l| *l <= rest.len()) {
+            Some(chunk_length) => chunk_length,
+            None => break,
+        };
+        let chunk_type = &rest[4..8];
+        if !PNG_METADATA_CHUNKS.contains(&chunk_type) {
+            result.extend_from_slice(&rest[..chunk_length]);
+        }
+        rest = &rest[chunk_length..];
+        if chunk_type == b"IEND" {
+            return result;
+        }
+    }
+    result.extend_from_slice(rest);
+    result
+}
+
+const WEBP_HEADER_LENGTH: usize = 12;
+const WEBP_METADATA_CHUNKS: [&[u8]; 2] = [b"EXIF", b"XMP "];
+/// Flags of extended format header that tell that file has EXIF and XMP chunks
+const WEBP_METADATA_FLAGS: u8 = 0x08 | 0x04;
+
+fn strip_webp(data: &[u8]) -> Vec<u8> {
+    if data.len() < WEBP_HEADER_LENGTH {
+        return data.to_vec();
+    }
+    let mut result = Vec::with_capacity(data.len());
+    result.extend_from_slice(&data[..WEBP_HEADER_LENGTH]);
+    let mut rest = &data[WEBP_HEADER_LENGTH..];
+
+    // Chunk is type, length of data and data padded to even length
+    while rest.len() >= 8 {
+        let length = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let chunk_length = match length
+            .checked_add(8 + length % 2)
+            .filter(|l| *l <= rest.len())
+        {
+            Some(chunk_length) => chunk_length,
+            None => break,
+        };
+        let chunk_type = &rest[..4];
+        if !WEBP_METADATA_CHUNKS.contains(&chunk_type) {
+            let start = result.len();
+            result.extend_from_slice(&rest[..chunk_length]);
+            if chunk_type == b"VP8X" && length > 0 {
+                result[start + 8] &= !WEBP_METADATA_FLAGS;
+            }
+        }
+        rest = &rest[chunk_length..];
+    }
+    result.extend_from_slice(rest);
+
+    // Size in RIFF header doesn't include tag and size itself
+    let riff_size = (result.len() - 8) as u32;
+    result[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn png_chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(b"CRC!");
+        chunk
+    }
+
+    fn webp_chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = chunk_type.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+        file.extend_from_slice(b"WEBP");
+        file.extend_from_slice(&body);
+        file
+    }
+
+    #[test]
+    fn exif_and_comments_are_removed_from_jpeg() {
+        let jfif = jpeg_segment(0xe0, b"JFIF\0");
+        let icc = jpeg_segment(0xe2, b"ICC_PROFILE\0");
+        let tables = jpeg_segment(0xdb, b"quantization");
+        let scan = [
+            jpeg_segment(0xda, b"scan"),
+            b"\x12\xff\0\x34\xff\xd9".to_vec(),
+        ]
+        .concat();
+        let image = [
+            b"\xff\xd8".to_vec(),
+            jfif.clone(),
+            jpeg_segment(0xe1, b"Exif\0\0GPS 51.5N"),
+            jpeg_segment(0xe1, b"http://ns.adobe.com/xap/1.0/\0<xmp>"),
+            icc.clone(),
+            jpeg_segment(0xed, b"Photoshop 3.0\0"),
+            jpeg_segment(0xfe, b"comment"),
+            tables.clone(),
+            scan.clone(),
+        ]
+        .concat();
+
+        assert_eq!(
+            strip_metadata(MediaType::Jpeg, &image),
+            [b"\xff\xd8".to_vec(), jfif, icc, tables, scan].concat()
+        );
+    }
+
+    #[test]
+    fn jpeg_fill_bytes_and_markers_without_length_are_skipped() {
+        let tables = jpeg_segment(0xdb, b"quantization");
+        let scan = [jpeg_segment(0xda, b"scan"), b"\x12\xff\xd9".to_vec()].concat();
+        let image = [
+            b"\xff\xd8".to_vec(),
+            b"\xff\xff".to_vec(),
+            jpeg_segment(0xe1, b"Exif\0\0GPS 51.5N"),
+            b"\xff\x01".to_vec(),
+            b"\xff\xd0".to_vec(),
+            jpeg_segment(0xfe, b"comment"),
+            tables.clone(),
+            scan.clone(),
+        ]
+        .concat();
+
+        assert_eq!(
+            strip_metadata(MediaType::Jpeg, &image),
+            [
+                b"\xff\xd8".to_vec(),
+                b"\xff\x01\xff\xd0".to_vec(),
+                tables,
+                scan
+            ]
+            .concat()
+        );
+    }
+
+    /// Little endian EXIF with camera model and given orientation in its first IFD
+    fn exif_with_orientation(orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0II\x2a\0\x08\0\0\0".to_vec();
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        // Model, ASCII string at offset 38
+        exif.extend_from_slice(&[0x10, 0x01, 2, 0, 6, 0, 0, 0, 38, 0, 0, 0]);
+        exif.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0]);
+        exif.extend_from_slice(&orientation.to_le_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        exif.extend_from_slice(b"Phone\0");
+        exif
+    }
+
+    #[test]
+    fn orientation_of_jpeg_is_kept_without_other_exif() {
+        let jfif = jpeg_segment(0xe0, b"JFIF\0");
+        let scan = [jpeg_segment(0xda, b"scan"), b"\x12\xff\xd9".to_vec()].concat();
+        let image = [
+            b"\xff\xd8".to_vec(),
+            jfif.clone(),
+            jpeg_segment(0xe1, &exif_with_orientation(6)),
+            scan.clone(),
+        ]
+        .concat();
+
+        let stripped = strip_metadata(MediaType::Jpeg, &image);
+        assert_eq!(
+            stripped,
+            [
+                b"\xff\xd8".to_vec(),
+                jfif.clone(),
+                orientation_exif_segment(6),
+                scan.clone()
+            ]
+            .concat()
+        );
+        assert!(!stripped.windows(5).any(|w| w == b"Phone"));
+
+        let upright = [
+            b"\xff\xd8".to_vec(),
+            jpeg_segment(0xe1, &exif_with_orientation(1)),
+            scan.clone(),
+        ]
+        .concat();
+        assert_eq!(
+            strip_metadata(MediaType::Jpeg, &upright),
+            [b"\xff\xd8".to_vec(), scan].concat()
+        );
+    }
+
+    #[test]
+    fn metadata_chunks_are_removed_from_png() {
+        let header = png_chunk(b"IHDR", b"header");
+        let data = png_chunk(b"IDAT", b"pixels");
+        let end = png_chunk(b"IEND", b"");
+        let image = [
+            b"\x89PNG\r\n\x1a\n".to_vec(),
+            header.clone(),
+            png_chunk(b"eXIf", b"MM\0*GPS"),
+            png_chunk(b"tEXt", b"Author\0me"),
+            png_chunk(b"tIME", b"time"),
+            data.clone(),
+            png_chunk(b"iTXt", b"XML:com.adobe.xmp\0"),
+            end.clone(),
+        ]
+        .concat();
+
+        assert_eq!(
+            strip_metadata(MediaType::Png, &image),
+            [b"\x89PNG\r\n\x1a\n".to_vec(), header, data, end].concat()
+        );
+    }
+
+    #[test]
+    fn metadata_chunks_and_flags_are_removed_from_webp() {
+        let image = webp(&[
+            webp_chunk(b"VP8X", &[0x08 | 0x04 | 0x10, 0, 0, 0, 1, 0, 0, 1, 0, 0]),
+            webp_chunk(b"VP8L", b"pixels"),
+            webp_chunk(b"EXIF", b"MM\0*GPS"),
+            webp_chunk(b"XMP ", b"<xmp>"),
+        ]);
+
+        assert_eq!(
+            strip_metadata(MediaType::Webp, &image),
+            webp(&[
+                webp_chunk(b"VP8X", &[0x10, 0, 0, 0, 1, 0, 0, 1, 0, 0]),
+                webp_chunk(b"VP8L", b"pixels"),
+            ])
+        );
+    }
+
+    #[test]
+    fn unparsable_data_is_kept() {
+        let truncated_png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(strip_metadata(MediaType::Png, truncated_png), truncated_png);
+        assert_eq!(
+            strip_metadata(MediaType::Jpeg, b"\xff\xd8\xff"),
+            b"\xff\xd8\xff"
+        );
+        assert_eq!(strip_metadata(MediaType::Gif, b"GIF89a"), b"GIF89a");
+    }
+
+    #[test]
+    fn kept_orientation_is_read_by_decoders() {
+        use image::{ImageDecoder, ImageReader};
+
+        let mut encoded = Vec::new();
+        image::RgbImage::new(4, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut encoded),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let photo = [
+            encoded[..2].to_vec(),
+            jpeg_segment(0xe1, &exif_with_orientation(6)),
+            encoded[2..].to_vec(),
+        ]
+        .concat();
+
+        let stripped = strip_metadata(MediaType::Jpeg, &photo);
+        let mut decoder = ImageReader::new(std::io::Cursor::new(&stripped))
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        assert_eq!(
+            decoder.orientation().unwrap(),
+            image::metadata::Orientation::Rotate90
+        );
+    }
+}
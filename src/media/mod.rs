@@ -0,0 +1,9 @@
+mod local_media_store;
+mod media_store;
+mod metadata;
+mod variants;
+
+pub use local_media_store::*;
+pub use media_store::*;
+pub use metadata::*;
+pub use variants::*;
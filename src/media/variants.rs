@@ -0,0 +1,192 @@
+//! Resized copies of uploaded images. Posts offer them to browsers with `srcset`,
+//! so that pages don't load full-size photos on small screens.
+
+use crate::domain::media::MediaType;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits, Rgb, RgbImage, RgbaImage};
+use std::io::Cursor;
+
+/// Widths of resized copies. Images that are narrower get copies of their own width,
+/// so that every copy exists for every image.
+pub const VARIANT_WIDTHS: [u32; 3] = [480, 960, 1600];
+/// Width at which images in posts are shown, which is width of page container
+pub const VARIANT_SIZES: &str = "(max-width: 1127px) 100vw, 1127px";
+/// Larger images are not decoded, since they would take too much memory
+const MAX_DECODED_DIMENSION: u32 = 12_000;
+const WEBP_QUALITY: f32 = 80.0;
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariantFormat {
+    /// Smaller, used by browsers that support it
+    Webp,
+    /// Fallback for browsers without WebP
+    Jpeg,
+}
+
+impl VariantFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            VariantFormat::Webp => "image/webp",
+            VariantFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VariantFormat::Webp => "webp",
+            VariantFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+const ALL_FORMATS: [VariantFormat; 2] = [VariantFormat::Webp, VariantFormat::Jpeg];
+
+/// Whether files of type get resized copies. GIFs don't, since they would lose animation.
+pub fn has_variants(media_type: MediaType) -> bool {
+    matches!(
+        media_type,
+        MediaType::Jpeg | MediaType::Png | MediaType::Webp
+    )
+}
+
+/// Key under which resized copy of file with given key is kept in media store
+pub fn variant_key(key: &str, width: u32, format: VariantFormat) -> String {
+    let stem = key.rsplit_once('.').map_or(key, |(stem, _)| stem);
+    format!("{}-{}w.{}", stem, width, format.extension())
+}
+
+/// Keys of all resized copies of file
+pub fn variant_keys(key: &str) -> Vec<String> {
+    VARIANT_WIDTHS
+        .iter()
+        .flat_map(|width| {
+            ALL_FORMATS
+                .iter()
+                .map(move |format| variant_key(key, *width, *format))
+        })
+        .collect()
+}
+
+/// Makes resized copies of image in all formats, returning them with their keys.
+/// Copies have no metadata, so orientation from EXIF is applied to their pixels.
+pub fn generate_variants(key: &str, data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, anyhow::Error> {
+    let image = decode_image(data)?;
+    let mut variants = Vec::new();
+    for width in VARIANT_WIDTHS {
+        let resized = if image.width() > width {
+            image.resize(width, u32::MAX, FilterType::CatmullRom)
+        } else {
+            image.clone()
+        };
+        let pixels = resized.to_rgba8();
+        for format in ALL_FORMATS {
+            let encoded = match format {
+                VariantFormat::Webp => encode_webp(&pixels)?,
+                VariantFormat::Jpeg => encode_jpeg(&pixels)?,
+            };
+            variants.push((variant_key(key, width, format), encoded));
+        }
+    }
+    Ok(variants)
+}
+
+fn decode_image(data: &[u8]) -> Result<DynamicImage, anyhow::Error> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODED_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODED_DIMENSION);
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode_webp(pixels: &RgbaImage) -> Result<Vec<u8>, anyhow::Error> {
+    let encoded = webp::Encoder::from_rgba(pixels.as_raw(), pixels.width(), pixels.height())
+        .encode_simple(false, WEBP_QUALITY)
+        .map_err(|e| anyhow::anyhow!("Failed to encode WebP: {:?}", e))?;
+    Ok(encoded.to_vec())
+}
+
+fn encode_jpeg(pixels: &RgbaImage) -> Result<Vec<u8>, anyhow::Error> {
+    // JPEG has no transparency, so transparent parts are put on white background
+    let flattened = RgbImage::from_fn(pixels.width(), pixels.height(), |x, y| {
+        let [r, g, b, a] = pixels.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    });
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY).encode_image(&flattened)?;
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([200, 10, 10, 0]));
+        let mut data = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn variant_keys_are_made_from_key_of_file() {
+        assert_eq!(
+            variant_key("abc.png", 480, VariantFormat::Webp),
+            "abc-480w.webp"
+        );
+        assert_eq!(
+            variant_key("abc.jpg", 1600, VariantFormat::Jpeg),
+            "abc-1600w.jpg"
+        );
+        assert_eq!(variant_keys("abc.png").len(), 6);
+    }
+
+    #[test]
+    fn images_are_resized_but_not_enlarged() {
+        let variants = generate_variants("abc.png", &png(600, 300)).unwrap();
+        let keys: Vec<&str> = variants.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, variant_keys("abc.png"));
+
+        let size = |key: &str| {
+            let (_, data) = variants.iter().find(|(k, _)| k == key).unwrap();
+            let image = image::load_from_memory(data).unwrap();
+            (image.width(), image.height())
+        };
+        assert_eq!(size("abc-480w.webp"), (480, 240));
+        assert_eq!(size("abc-480w.jpg"), (480, 240));
+        assert_eq!(size("abc-960w.webp"), (600, 300));
+        assert_eq!(size("abc-1600w.jpg"), (600, 300));
+    }
+
+    #[test]
+    fn transparent_parts_of_jpeg_copies_are_white() {
+        let variants = generate_variants("abc.png", &png(8, 8)).unwrap();
+        let (_, jpeg) = variants
+            .iter()
+            .find(|(key, _)| key.ends_with(".jpg"))
+            .unwrap();
+        let pixel = *image::load_from_memory(jpeg)
+            .unwrap()
+            .to_rgb8()
+            .get_pixel(4, 4);
+        assert!(pixel.0.iter().all(|c| *c > 245), "{:?}", pixel);
+    }
+
+    #[test]
+    fn files_that_are_not_images_are_error() {
+        assert!(generate_variants("abc.png", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").is_err());
+        assert!(has_variants(MediaType::Jpeg));
+        assert!(!has_variants(MediaType::Gif));
+        assert!(!has_variants(MediaType::Pdf));
+    }
+}
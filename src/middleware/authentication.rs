@@ -1,9 +1,13 @@
+use crate::domain::users::{UserID, UserRole};
 use crate::middleware::Session;
+use crate::services::get_user_by_id;
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::utils::{e500, see_other};
+use crate::Pool;
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
-use actix_web::error::InternalError;
-use actix_web::{FromRequest, HttpMessage};
+use actix_web::error::{ErrorForbidden, InternalError};
+use actix_web::{web, FromRequest, HttpMessage};
 
 pub async fn require_login(
     mut req: ServiceRequest,
@@ -14,7 +18,15 @@ pub async fn require_login(
         Session::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
+    let pool = req
+        .app_data::<web::Data<Pool>>()
+        .cloned()
+        .ok_or_else(|| e500(anyhow::anyhow!("Database pool is not configured")))?;
+    match session
+        .get_authenticated_user_id(&pool)
+        .await
+        .map_err(e500)?
+    {
         Some(id) => {
             req.extensions_mut().insert(id);
             next.call(req).await
@@ -36,7 +48,15 @@ pub async fn require_non_logged(
         Session::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
+    let pool = req
+        .app_data::<web::Data<Pool>>()
+        .cloned()
+        .ok_or_else(|| e500(anyhow::anyhow!("Database pool is not configured")))?;
+    match session
+        .get_authenticated_user_id(&pool)
+        .await
+        .map_err(e500)?
+    {
         Some(_) => {
             let response = see_other("/account/home");
             let e = anyhow::anyhow!("The user has already logged in");
@@ -45,3 +65,29 @@ pub async fn require_non_logged(
         None => next.call(req).await,
     }
 }
+
+/// Allows only administrators. Must be wrapped in `require_login`,
+/// which stores id of logged in user in request extensions.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: actix_web_lab::middleware::Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let user_id = req
+        .extensions()
+        .get::<UserID>()
+        .cloned()
+        .ok_or_else(|| e500(anyhow::anyhow!("User id is not set by require_login")))?;
+    let pool = req
+        .app_data::<web::Data<Pool>>()
+        .cloned()
+        .ok_or_else(|| e500(anyhow::anyhow!("Database pool is not configured")))?;
+
+    let user = spawn_blocking_with_tracing(move || get_user_by_id(&pool, &user_id))
+        .await
+        .map_err(e500)?
+        .map_err(e500)?;
+    match user {
+        Some(user) if user.role == UserRole::Admin => next.call(req).await,
+        _ => Err(ErrorForbidden("User is not an administrator")),
+    }
+}
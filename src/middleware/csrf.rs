@@ -0,0 +1,148 @@
+use crate::middleware::Session;
+use crate::utils::e500;
+use actix_multipart::Multipart;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::{ErrorForbidden, PayloadError};
+use actix_web::http::Method;
+use actix_web::web::Bytes;
+use actix_web::{FromRequest, HttpMessage};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use secrecy::ExposeSecret;
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Poll;
+use subtle::ConstantTimeEq;
+
+/// Header that scripts can use to pass CSRF token
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+const MULTIPART_CONTENT_TYPE: &str = "multipart/form-data";
+/// How much of multipart form is read looking for token
+const MULTIPART_TOKEN_SEARCH_LIMIT: usize = 16 * 1024;
+
+#[derive(serde::Deserialize)]
+struct CsrfTokenForm {
+    csrf_token: Option<String>,
+}
+
+/// Rejects requests with unsafe methods that do not carry CSRF token of current session.
+/// Token is taken from `X-CSRF-Token` header or, for url-encoded and multipart forms,
+/// from `csrf_token` field.
+pub async fn require_csrf_token(
+    mut req: ServiceRequest,
+    next: actix_web_lab::middleware::Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if is_safe_method(req.method()) {
+        return next.call(req).await;
+    }
+
+    // Without session token nothing can match, so body is not worth reading
+    let session = Session::from_request_sync(req.parts_mut().0);
+    let expected = match session.get_existing_csrf_token().map_err(e500)? {
+        Some(expected) => expected,
+        None => return Err(reject()),
+    };
+
+    let provided = match req.headers().get(CSRF_TOKEN_HEADER) {
+        Some(header) => header.to_str().ok().map(|token| token.to_string()),
+        None if req.content_type() == FORM_CONTENT_TYPE => {
+            let body = {
+                let (http_request, payload) = req.parts_mut();
+                Bytes::from_request(http_request, payload).await
+            }?;
+            let token = serde_urlencoded::from_bytes::<CsrfTokenForm>(&body)
+                .ok()
+                .and_then(|form| form.csrf_token);
+            // Body has been consumed, so it is given back for handler to extract form
+            req.set_payload(payload_from_stream(futures_util::stream::once(
+                async move { Ok(body) },
+            )));
+            token
+        }
+        None if req.content_type() == MULTIPART_CONTENT_TYPE => {
+            multipart_csrf_token(&mut req).await
+        }
+        None => None,
+    };
+
+    let is_valid = provided.is_some_and(|provided| {
+        bool::from(
+            provided
+                .as_bytes()
+                .ct_eq(expected.expose_secret().as_bytes()),
+        )
+    });
+    if !is_valid {
+        return Err(reject());
+    }
+
+    next.call(req).await
+}
+
+fn reject() -> actix_web::Error {
+    tracing::warn!("Rejected request with invalid CSRF token");
+    ErrorForbidden("Invalid CSRF token")
+}
+
+/// Reads `csrf_token` field of multipart form. Forms may carry large files, so only fields
+/// before the first file are looked at, and only up to `MULTIPART_TOKEN_SEARCH_LIMIT` bytes
+/// of body are read. Read part is given back to handler together with the rest of body.
+async fn multipart_csrf_token(req: &mut ServiceRequest) -> Option<String> {
+    let payload = Rc::new(RefCell::new(req.take_payload()));
+    let read = Rc::new(RefCell::new(Vec::<Bytes>::new()));
+    let recording = {
+        let (payload, read) = (payload.clone(), read.clone());
+        let mut read_size = 0;
+        futures_util::stream::poll_fn(move |cx| {
+            if read_size > MULTIPART_TOKEN_SEARCH_LIMIT {
+                return Poll::Ready(None);
+            }
+            let poll = Pin::new(&mut *payload.borrow_mut()).poll_next(cx);
+            if let Poll::Ready(Some(Ok(chunk))) = &poll {
+                read_size += chunk.len();
+                read.borrow_mut().push(chunk.clone());
+            }
+            poll
+        })
+    };
+
+    let mut token = None;
+    let mut multipart = Multipart::new(req.headers(), recording);
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        if field.content_disposition().get_filename().is_some() {
+            break;
+        }
+        let mut value = Vec::new();
+        while let Ok(Some(chunk)) = field.try_next().await {
+            value.extend_from_slice(&chunk);
+        }
+        if field.name() == "csrf_token" {
+            token = String::from_utf8(value).ok();
+            break;
+        }
+    }
+    drop(multipart);
+
+    let rest = payload.replace(Payload::None);
+    let read = read.take();
+    req.set_payload(payload_from_stream(
+        futures_util::stream::iter(read.into_iter().map(Ok)).chain(rest),
+    ));
+    token
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn payload_from_stream(
+    stream: impl Stream<Item = Result<Bytes, PayloadError>> + 'static,
+) -> Payload {
+    let stream: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(stream);
+    Payload::from(stream)
+}
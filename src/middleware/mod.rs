@@ -1,7 +1,9 @@
 mod authentication;
+mod csrf;
 mod messages;
 mod session;
 
 pub use authentication::*;
+pub use csrf::*;
 pub use messages::*;
 pub use session::*;
@@ -1,8 +1,12 @@
-use crate::domain::users::UserID;
+use crate::domain::time::DateTime;
+use crate::domain::users::{TotpSecret, UserID};
+use crate::services::is_session_valid;
+use crate::telemetry::spawn_blocking_with_tracing;
+use crate::Pool;
 use actix_session::SessionExt;
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::de::DeserializeOwned;
 use std::future::{ready, Ready};
 use uuid::Uuid;
@@ -12,8 +16,14 @@ pub struct Session(actix_session::Session);
 impl Session {
     const USER_ID_KEY: &'static str = "user_id";
     const CSRF_TOKEN_KEY: &'static str = "csrf";
+    const LOGGED_IN_AT_KEY: &'static str = "logged_in_at";
+    const TWO_FACTOR_USER_ID_KEY: &'static str = "two_factor_user_id";
+    const TWO_FACTOR_ATTEMPTS_KEY: &'static str = "two_factor_attempts";
+    const TOTP_ENROLLMENT_KEY: &'static str = "totp_enrollment";
 
+    /// Changes session key and CSRF token, should be called when user logs in.
     pub fn renew(&self) {
+        self.0.remove(Self::CSRF_TOKEN_KEY);
         self.0.renew();
     }
 
@@ -29,10 +39,19 @@ impl Session {
         Ok(token)
     }
 
+    /// Gets CSRF token without generating new one if session does not have it yet.
+    pub fn get_existing_csrf_token(&self) -> Result<Option<Secret<String>>, anyhow::Error> {
+        let r = self.0.get::<String>(Self::CSRF_TOKEN_KEY)?;
+        Ok(r.map(Secret::new))
+    }
+
     pub fn insert_user_id(&self, user_id: UserID) -> Result<(), anyhow::Error> {
         self.0
             .insert(Self::USER_ID_KEY, user_id)
-            .map_err(|e| anyhow::anyhow!("Failed to insert user id: {:?}", e))
+            .map_err(|e| anyhow::anyhow!("Failed to insert user id: {:?}", e))?;
+        self.0
+            .insert(Self::LOGGED_IN_AT_KEY, DateTime::now().timestamp_nanos())
+            .map_err(|e| anyhow::anyhow!("Failed to insert login time: {:?}", e))
     }
 
     pub fn get_user_id(&self) -> Result<Option<UserID>, anyhow::Error> {
@@ -40,6 +59,88 @@ impl Session {
         Ok(r)
     }
 
+    pub fn get_logged_in_at(&self) -> Result<Option<DateTime>, anyhow::Error> {
+        let r = self.0.get::<i64>(Self::LOGGED_IN_AT_KEY)?;
+        Ok(r.map(DateTime::from_timestamp_nanos))
+    }
+
+    /// Gets id of logged in user, checking that session has not been revoked
+    /// (for example by password reset). Revoked sessions are purged.
+    pub async fn get_authenticated_user_id(
+        &self,
+        pool: &Pool,
+    ) -> Result<Option<UserID>, anyhow::Error> {
+        let user_id = match self.get_user_id()? {
+            Some(user_id) => user_id,
+            None => return Ok(None),
+        };
+        let logged_in_at = self.get_logged_in_at()?;
+
+        let pool = pool.clone();
+        let id = user_id.clone();
+        let is_valid = spawn_blocking_with_tracing(move || {
+            is_session_valid(&pool, &id, logged_in_at.as_ref())
+        })
+        .await??;
+        if is_valid {
+            Ok(Some(user_id))
+        } else {
+            self.0.purge();
+            Ok(None)
+        }
+    }
+
+    /// Stores id of user that has entered correct password,
+    /// but has not yet passed second authentication step.
+    pub fn insert_two_factor_user_id(&self, user_id: UserID) -> Result<(), anyhow::Error> {
+        self.0.remove(Self::TWO_FACTOR_ATTEMPTS_KEY);
+        self.0
+            .insert(Self::TWO_FACTOR_USER_ID_KEY, user_id)
+            .map_err(|e| anyhow::anyhow!("Failed to insert two-factor user id: {:?}", e))
+    }
+
+    pub fn get_two_factor_user_id(&self) -> Result<Option<UserID>, anyhow::Error> {
+        let r = self.0.get(Self::TWO_FACTOR_USER_ID_KEY)?;
+        Ok(r)
+    }
+
+    pub fn remove_two_factor_user_id(&self) {
+        self.0.remove(Self::TWO_FACTOR_USER_ID_KEY);
+        self.0.remove(Self::TWO_FACTOR_ATTEMPTS_KEY);
+    }
+
+    /// Registers failed attempt to pass second authentication step.
+    /// Returns total number of failed attempts.
+    pub fn add_two_factor_attempt(&self) -> Result<u32, anyhow::Error> {
+        let attempts = self
+            .0
+            .get::<u32>(Self::TWO_FACTOR_ATTEMPTS_KEY)?
+            .unwrap_or(0)
+            + 1;
+        self.0
+            .insert(Self::TWO_FACTOR_ATTEMPTS_KEY, attempts)
+            .map_err(|e| anyhow::anyhow!("Failed to insert two-factor attempts: {:?}", e))?;
+        Ok(attempts)
+    }
+
+    /// Stores secret that is being enrolled until user confirms it with valid code.
+    pub fn insert_totp_enrollment_secret(&self, secret: &TotpSecret) -> Result<(), anyhow::Error> {
+        self.0
+            .insert(Self::TOTP_ENROLLMENT_KEY, secret.as_ref().expose_secret())
+            .map_err(|e| anyhow::anyhow!("Failed to insert TOTP secret: {:?}", e))
+    }
+
+    pub fn get_totp_enrollment_secret(&self) -> Result<Option<TotpSecret>, anyhow::Error> {
+        self.0
+            .get::<String>(Self::TOTP_ENROLLMENT_KEY)?
+            .map(TotpSecret::parse)
+            .transpose()
+    }
+
+    pub fn remove_totp_enrollment_secret(&self) {
+        self.0.remove(Self::TOTP_ENROLLMENT_KEY);
+    }
+
     pub fn insert_form_data<D>(&self, key: &str, form_data: D) -> Result<(), anyhow::Error>
     where
         D: serde::Serialize,
@@ -1,8 +1,17 @@
+use crate::config::PasswordHashingConfig;
 use crate::domain::users::{
-    Credentials, HashedUserPassword, PasswordError, UpdateUser, UserID, UserName, UserPassword,
+    Credentials, HashedUserPassword, PasswordError, UpdateUser, UserEmail, UserID, UserName,
+    UserPassword, UserRole, UserToken,
 };
+use crate::mail::{Email, Mailer};
 use crate::middleware::{Messages, Session};
-use crate::services::{get_user_by_id, update_user, validate_credentials, AuthError, UserError};
+use crate::services::{
+    confirm_pending_email, count_recovery_codes, get_user_by_email, get_user_by_id,
+    insert_pending_email, update_user, validate_credentials, AuthError, ConfirmEmailError,
+    UserError, EMAIL_CONFIRMATION_LIFETIME_HOURS,
+};
+use crate::startup::ApplicationBaseUrl;
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::utils::{e500, redirect_with_error, render_template, see_other};
 use crate::Pool;
 use actix_web::error::InternalError;
@@ -18,6 +27,9 @@ struct AccountPage<'a> {
     messages: Messages,
     name: &'a str,
     email: &'a str,
+    two_factor_enabled: bool,
+    recovery_codes_left: i64,
+    is_admin: bool,
     csrf_token: &'a str,
 }
 
@@ -31,19 +43,21 @@ pub async fn account_settings(
     let user = get_user_by_id(&pool, &user_id)
         .map_err(e500)?
         .ok_or_else(|| e500("Failed to get user"))?;
+    let recovery_codes_left = count_recovery_codes(&pool, &user_id).map_err(e500)?;
 
     render_template(AccountPage {
         messages: messages.into(),
         name: user.name.as_ref(),
         email: user.email.as_ref(),
+        two_factor_enabled: user.totp_secret.is_some(),
+        recovery_codes_left,
+        is_admin: user.role == UserRole::Admin,
         csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
     })
 }
 
 #[derive(thiserror::Error)]
 pub enum ChangeNameError {
-    #[error("Invalid CSRF token")]
-    CSRFError,
     #[error("Taken name")]
     TakenName,
     #[error("Invalid name")]
@@ -63,30 +77,14 @@ impl std::fmt::Debug for ChangeNameError {
 #[derive(serde::Deserialize)]
 pub struct ChangeNameForm {
     new_name: String,
-    csrf_token: Secret<String>,
 }
 
-#[tracing::instrument(skip(form, pool, session))]
+#[tracing::instrument(skip(form, pool))]
 pub async fn change_name(
     form: web::Form<ChangeNameForm>,
     pool: web::Data<Pool>,
     user_id: UserID,
-    session: Session,
 ) -> Result<HttpResponse, InternalError<ChangeNameError>> {
-    if form.csrf_token.expose_secret()
-        != session
-            .get_csrf_token()
-            .map_err(|e| {
-                redirect_with_error_to_account(ChangeNameError::UnexpectedError(anyhow::anyhow!(
-                    "Failed to fetch CSRF token from session: {:?}",
-                    e
-                )))
-            })?
-            .expose_secret()
-    {
-        return Err(redirect_with_error_to_account(ChangeNameError::CSRFError));
-    }
-
     let user_name = UserName::parse(&form.0.new_name)
         .map_err(|e| redirect_with_error("/account/settings", ChangeNameError::InvalidName(e)))?;
 
@@ -95,7 +93,9 @@ pub async fn change_name(
         name: Some(&user_name),
         email: None,
         password: None,
+        password_salt: None,
         is_banned: None,
+        role: None,
     };
 
     update_user(&pool, &changeset).map_err(|e| {
@@ -111,9 +111,89 @@ pub async fn change_name(
     Ok(see_other("/account/settings"))
 }
 
-#[tracing::instrument("Change email")]
-pub async fn change_email() -> actix_web::Result<HttpResponse> {
-    todo!()
+#[derive(thiserror::Error)]
+pub enum ChangeEmailError {
+    #[error("Invalid email")]
+    InvalidEmail(#[source] anyhow::Error),
+    #[error("Email is already taken")]
+    TakenEmail,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ChangeEmailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ChangeEmailForm {
+    new_email: String,
+}
+
+#[tracing::instrument("Change email", skip(form, pool, mailer, base_url))]
+pub async fn change_email(
+    form: web::Form<ChangeEmailForm>,
+    pool: web::Data<Pool>,
+    mailer: web::Data<dyn Mailer>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: UserID,
+) -> Result<HttpResponse, InternalError<ChangeEmailError>> {
+    let new_email = UserEmail::parse(form.0.new_email)
+        .map_err(|e| redirect_with_error_to_account(ChangeEmailError::InvalidEmail(e)))?;
+
+    if get_user_by_email(&pool, &new_email)
+        .map_err(|e| redirect_with_error_to_account(ChangeEmailError::UnexpectedError(e)))?
+        .is_some()
+    {
+        return Err(redirect_with_error_to_account(ChangeEmailError::TakenEmail));
+    }
+
+    let token = insert_pending_email(&pool, &user_id, &new_email)
+        .map_err(|e| redirect_with_error_to_account(ChangeEmailError::UnexpectedError(e)))?;
+
+    let email = Email {
+        to: new_email,
+        subject: "Confirm your new email".to_string(),
+        body: format!(
+            "To confirm changing email of your account follow the link:\n\
+            {}/confirm_email?token={}\n\n\
+            The link expires in {} hours. If you did not request this change, ignore this email.",
+            base_url.0,
+            token.as_ref().expose_secret(),
+            EMAIL_CONFIRMATION_LIFETIME_HOURS
+        ),
+    };
+    let mailer = mailer.into_inner();
+    spawn_blocking_with_tracing(move || mailer.send(&email))
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|r| r)
+        .map_err(|e| redirect_with_error_to_account(ChangeEmailError::UnexpectedError(e)))?;
+
+    FlashMessage::info("Confirmation link has been sent to your new email").send();
+    Ok(see_other("/account/settings"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConfirmEmailQuery {
+    token: String,
+}
+
+#[tracing::instrument("Confirm email", skip(query, pool))]
+pub async fn confirm_email(
+    query: web::Query<ConfirmEmailQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, InternalError<ConfirmEmailError>> {
+    let token = UserToken::parse(query.0.token)
+        .map_err(|_| redirect_with_error_to_account(ConfirmEmailError::InvalidToken))?;
+    confirm_pending_email(&pool, &token).map_err(redirect_with_error_to_account)?;
+
+    FlashMessage::info("Your email has been changed").send();
+    Ok(see_other("/account/settings"))
 }
 
 #[derive(serde::Deserialize)]
@@ -121,13 +201,10 @@ pub struct ChangePasswordForm {
     current_password: Secret<String>,
     new_password: Secret<String>,
     repeat_new_password: Secret<String>,
-    csrf_token: Secret<String>,
 }
 
 #[derive(thiserror::Error)]
 pub enum ChangePasswordError {
-    #[error("Invalid CSRF token")]
-    CSRFError,
     #[error("Repeat password does not match new password")]
     RepeatPasswordDoesntMatch,
     #[error("Current password is incorrect")]
@@ -146,28 +223,13 @@ impl std::fmt::Debug for ChangePasswordError {
     }
 }
 
-#[tracing::instrument("Change password", skip(form, pool, session))]
+#[tracing::instrument("Change password", skip(form, pool, hashing))]
 pub async fn change_password(
     form: web::Form<ChangePasswordForm>,
     pool: web::Data<Pool>,
+    hashing: web::Data<PasswordHashingConfig>,
     user_id: UserID,
-    session: Session,
 ) -> Result<HttpResponse, InternalError<ChangePasswordError>> {
-    if form.csrf_token.expose_secret()
-        != session
-            .get_csrf_token()
-            .map_err(|e| {
-                redirect_with_error_to_account(ChangePasswordError::UnexpectedError(
-                    anyhow::anyhow!("Failed to fetch CSRF token from session: {:?}", e),
-                ))
-            })?
-            .expose_secret()
-    {
-        return Err(redirect_with_error_to_account(
-            ChangePasswordError::CSRFError,
-        ));
-    }
-
     if form.new_password.expose_secret() != form.repeat_new_password.expose_secret() {
         return Err(redirect_with_error_to_account(
             ChangePasswordError::RepeatPasswordDoesntMatch,
@@ -191,26 +253,48 @@ pub async fn change_password(
         password: old_password,
     };
 
-    if let Err(e) = validate_credentials(credentials, &pool) {
+    let validation_result = {
+        let pool = pool.get_ref().clone();
+        let hashing = hashing.get_ref().clone();
+        spawn_blocking_with_tracing(move || validate_credentials(credentials, &pool, &hashing))
+            .await
+            .map_err(|e| {
+                redirect_with_error_to_account(ChangePasswordError::UnexpectedError(
+                    anyhow::Error::new(e),
+                ))
+            })?
+    };
+    if let Err(e) = validation_result {
         let e = match e {
             AuthError::InvalidCredentials(_) => {
                 ChangePasswordError::InvalidCurrentPassword(e.into())
             }
-            AuthError::UnexpectedError(_) => ChangePasswordError::UnexpectedError(e.into()),
+            AuthError::Banned | AuthError::UnexpectedError(_) => {
+                ChangePasswordError::UnexpectedError(e.into())
+            }
         };
         return Err(redirect_with_error_to_account(e));
     }
 
     let new_password = UserPassword::parse(form.new_password.clone())
         .map_err(|e| redirect_with_error_to_account(ChangePasswordError::InvalidNewPassword(e)))?;
-    let hashed_new_password = HashedUserPassword::parse(&new_password, &user.password_salt);
+    let hashed_new_password = {
+        let hashing = hashing.get_ref().clone();
+        spawn_blocking_with_tracing(move || HashedUserPassword::parse(&new_password, &hashing))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r)
+            .map_err(|e| redirect_with_error_to_account(ChangePasswordError::UnexpectedError(e)))?
+    };
 
     let changeset = UpdateUser {
         id: &user_id,
         name: None,
         email: None,
         password: Some(&hashed_new_password),
+        password_salt: Some(None),
         is_banned: None,
+        role: None,
     };
     update_user(&pool, &changeset).map_err(|e| {
         redirect_with_error_to_account(ChangePasswordError::UnexpectedError(e.into()))
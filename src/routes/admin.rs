@@ -0,0 +1,224 @@
+use crate::domain::users::{UpdateUser, UserID, UserRole};
+use crate::middleware::{Messages, Session};
+use crate::services::{
+    ban_user, get_user_by_id, revoke_user_sessions, search_users, unban_user, update_user,
+    USERS_PER_PAGE,
+};
+use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_web::error::InternalError;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama::Template;
+use secrecy::ExposeSecret;
+use std::fmt::Formatter;
+
+struct UserRow {
+    id: String,
+    name: String,
+    email: String,
+    registered_when: String,
+    role: UserRole,
+    is_banned: bool,
+    is_current: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin_users.html")]
+struct AdminUsersTemplate<'a> {
+    messages: Messages,
+    users: Vec<UserRow>,
+    query: &'a str,
+    page: i64,
+    total_pages: i64,
+    prev_page_link: Option<String>,
+    next_page_link: Option<String>,
+    csrf_token: &'a str,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AdminUsersQuery {
+    page: Option<i64>,
+    query: Option<String>,
+}
+
+#[tracing::instrument("Admin users", skip(pool, messages, session))]
+pub async fn admin_users(
+    pool: web::Data<Pool>,
+    query: web::Query<AdminUsersQuery>,
+    current_user_id: UserID,
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let page = query.page.unwrap_or(1).max(1);
+    let name_query = query.query.as_deref().unwrap_or("").trim();
+    let (users, total) =
+        search_users(&pool, Some(name_query).filter(|q| !q.is_empty()), page).map_err(e500)?;
+    let total_pages = ((total + USERS_PER_PAGE - 1) / USERS_PER_PAGE).max(1);
+
+    let page_link = |page: i64| {
+        format!(
+            "/admin/users?page={}&query={}",
+            page,
+            urlencoding::encode(name_query)
+        )
+    };
+
+    render_template(AdminUsersTemplate {
+        messages: messages.into(),
+        users: users
+            .into_iter()
+            .map(|user| UserRow {
+                is_current: user.id == current_user_id,
+                id: user.id.as_ref().clone(),
+                name: user.name.as_ref().clone(),
+                email: user.email.as_ref().to_string(),
+                registered_when: user.created_at.ago(),
+                role: user.role,
+                is_banned: user.is_banned,
+            })
+            .collect(),
+        query: name_query,
+        page,
+        total_pages,
+        prev_page_link: (page > 1).then(|| page_link(page - 1)),
+        next_page_link: (page < total_pages).then(|| page_link(page + 1)),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum AdminError {
+    #[error("You can't change your own account")]
+    OwnAccount,
+    #[error("No user with such id")]
+    NoSuchUser,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for AdminError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Action that administrator can perform on account of other user
+enum UserAction {
+    Ban,
+    Unban,
+    Promote,
+    MakeModerator,
+    Demote,
+    ForceLogout,
+}
+
+fn perform_user_action(
+    pool: &Pool,
+    current_user_id: &UserID,
+    user_id: &UserID,
+    action: UserAction,
+) -> Result<HttpResponse, AdminError> {
+    if user_id == current_user_id {
+        return Err(AdminError::OwnAccount);
+    }
+    let user = get_user_by_id(pool, user_id)?.ok_or(AdminError::NoSuchUser)?;
+
+    let set_role = |role| {
+        update_user(
+            pool,
+            &UpdateUser {
+                id: user_id,
+                name: None,
+                email: None,
+                password: None,
+                password_salt: None,
+                is_banned: None,
+                role: Some(role),
+            },
+        )
+        .map_err(anyhow::Error::new)
+    };
+    let (result, message) = match action {
+        UserAction::Ban => (ban_user(pool, user_id), "has been banned"),
+        UserAction::Unban => (unban_user(pool, user_id), "has been unbanned"),
+        UserAction::Promote => (set_role(UserRole::Admin), "is now an administrator"),
+        UserAction::MakeModerator => (set_role(UserRole::Moderator), "is now a moderator"),
+        UserAction::Demote => (set_role(UserRole::User), "is now a regular user"),
+        UserAction::ForceLogout => (
+            revoke_user_sessions(pool, user_id),
+            "has been logged out everywhere",
+        ),
+    };
+    result?;
+
+    tracing::info!("User {} {}", user.name.as_ref(), message);
+    FlashMessage::info(format!("User {} {}", user.name.as_ref(), message)).send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument("Ban user", skip(pool))]
+pub async fn ban(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::Ban)
+        .map_err(redirect_with_error_to_admin)
+}
+
+#[tracing::instrument("Unban user", skip(pool))]
+pub async fn unban(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::Unban)
+        .map_err(redirect_with_error_to_admin)
+}
+
+#[tracing::instrument("Promote user", skip(pool))]
+pub async fn promote(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::Promote)
+        .map_err(redirect_with_error_to_admin)
+}
+
+#[tracing::instrument("Make user moderator", skip(pool))]
+pub async fn make_moderator(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::MakeModerator)
+        .map_err(redirect_with_error_to_admin)
+}
+
+#[tracing::instrument("Demote user", skip(pool))]
+pub async fn demote(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::Demote)
+        .map_err(redirect_with_error_to_admin)
+}
+
+#[tracing::instrument("Force logout of user", skip(pool))]
+pub async fn force_logout(
+    pool: web::Data<Pool>,
+    path: web::Path<UserID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<AdminError>> {
+    perform_user_action(&pool, &current_user_id, &path, UserAction::ForceLogout)
+        .map_err(redirect_with_error_to_admin)
+}
+
+fn redirect_with_error_to_admin<E: std::fmt::Display>(e: E) -> InternalError<E> {
+    redirect_with_error("/admin/users", e)
+}
@@ -0,0 +1,86 @@
+use crate::domain::blog_posts::{AutosaveSlot, BlogPostID};
+use crate::domain::users::UserID;
+use crate::routes::blog_post_history::get_editable_blog_post;
+use crate::services::{delete_autosave, save_autosave, AutosaveContents};
+use crate::utils::{e500, see_other};
+use crate::Pool;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+
+/// Fields of blog post editor that are sent by its script every few seconds
+#[derive(serde::Deserialize)]
+pub struct AutosaveFormData {
+    title: String,
+    brief: String,
+    contents: String,
+    tags: String,
+}
+
+impl AutosaveFormData {
+    fn contents(&self) -> AutosaveContents<'_> {
+        AutosaveContents {
+            title: &self.title,
+            brief: &self.brief,
+            contents: &self.contents,
+            tags: &self.tags,
+        }
+    }
+}
+
+#[tracing::instrument("Autosave new blog post", skip(pool, form))]
+pub async fn autosave_new_blog_post(
+    pool: web::Data<Pool>,
+    form: web::Form<AutosaveFormData>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    save_autosave(&pool, &user_id, AutosaveSlot::NewBlogPost, &form.contents()).map_err(e500)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[tracing::instrument("Autosave blog post", skip(pool, form))]
+pub async fn autosave_blog_post(
+    pool: web::Data<Pool>,
+    path: web::Path<BlogPostID>,
+    form: web::Form<AutosaveFormData>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let blog_post = get_editable_blog_post(&pool, &path, &user_id)?;
+    save_autosave(
+        &pool,
+        &user_id,
+        AutosaveSlot::BlogPost(blog_post.id),
+        &form.contents(),
+    )
+    .map_err(e500)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[tracing::instrument("Discard autosave of new blog post", skip(pool))]
+pub async fn discard_new_blog_post_autosave(
+    pool: web::Data<Pool>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    delete_autosave(&pool, &user_id, &AutosaveSlot::NewBlogPost).map_err(e500)?;
+    FlashMessage::info("Unsaved draft has been discarded").send();
+    Ok(see_other("/blog_posts/create"))
+}
+
+#[tracing::instrument("Discard autosave of blog post", skip(pool))]
+pub async fn discard_blog_post_autosave(
+    pool: web::Data<Pool>,
+    path: web::Path<BlogPostID>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let blog_post_id = path.into_inner();
+    delete_autosave(
+        &pool,
+        &user_id,
+        &AutosaveSlot::BlogPost(blog_post_id.clone()),
+    )
+    .map_err(e500)?;
+    FlashMessage::info("Unsaved draft has been discarded").send();
+    Ok(see_other(&format!(
+        "/blog_posts/{}/edit",
+        blog_post_id.as_ref()
+    )))
+}
@@ -0,0 +1,196 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostID};
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::middleware::{Messages, Session};
+use crate::routes::internal::edit_conflict::{diff_rows, DiffRow};
+use crate::services::{
+    can_edit_blog_post, get_blog_post_by_id, get_blog_post_revision, get_blog_post_revisions,
+    restore_blog_post_revision, BlogPostError,
+};
+use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama::Template;
+use secrecy::ExposeSecret;
+use std::fmt::Formatter;
+
+/// Returns blog post if user is allowed to see its history, which is the same as editing it
+pub(crate) fn get_editable_blog_post(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+    user_id: &UserID,
+) -> actix_web::Result<BlogPost> {
+    let blog_post = get_blog_post_by_id(pool, blog_post_id)
+        .map_err(e500)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("No blog post with such id"))?;
+    if !can_edit_blog_post(pool, &blog_post, user_id).map_err(e500)? {
+        return Err(actix_web::error::ErrorForbidden(
+            "User is not allowed to edit blog post",
+        ));
+    }
+    Ok(blog_post)
+}
+
+struct RevisionRow {
+    number: i32,
+    title: String,
+    author_id: String,
+    author_name: String,
+    saved_when: String,
+    is_latest: bool,
+}
+
+#[derive(Template)]
+#[template(path = "blog_post_history.html")]
+struct BlogPostHistoryTemplate<'a> {
+    messages: Messages,
+    blog_post_id: &'a str,
+    blog_post_title: &'a str,
+    revisions: Vec<RevisionRow>,
+    csrf_token: &'a str,
+}
+
+#[tracing::instrument("Blog post history", skip(pool, messages, session))]
+pub async fn history(
+    pool: web::Data<Pool>,
+    path: web::Path<BlogPostID>,
+    user_id: UserID,
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let blog_post = get_editable_blog_post(&pool, &path, &user_id)?;
+    let revisions = get_blog_post_revisions(&pool, &blog_post.id).map_err(e500)?;
+
+    let now = DateTime::now();
+    render_template(BlogPostHistoryTemplate {
+        messages: messages.into(),
+        blog_post_id: blog_post.id.as_ref(),
+        blog_post_title: &blog_post.title,
+        revisions: revisions
+            .into_iter()
+            .enumerate()
+            .map(|(i, (revision, author_name))| RevisionRow {
+                number: revision.number,
+                title: revision.title,
+                author_id: revision.author_id.as_ref().clone(),
+                author_name: author_name.as_ref().clone(),
+                saved_when: revision.created_at.since(&now),
+                is_latest: i == 0,
+            })
+            .collect(),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(Template)]
+#[template(path = "blog_post_diff.html")]
+struct BlogPostDiffTemplate<'a> {
+    messages: Messages,
+    blog_post_id: &'a str,
+    blog_post_title: &'a str,
+    from: i32,
+    to: i32,
+    title_diff: Vec<DiffRow>,
+    brief_diff: Vec<DiffRow>,
+    contents_diff: Vec<DiffRow>,
+    tags_diff: Vec<DiffRow>,
+}
+
+/// Revisions to compare. By default latest revision is compared with previous one
+#[derive(Debug, serde::Deserialize)]
+pub struct DiffQuery {
+    from: Option<i32>,
+    to: Option<i32>,
+}
+
+#[tracing::instrument("Blog post diff", skip(pool, messages))]
+pub async fn diff(
+    pool: web::Data<Pool>,
+    path: web::Path<BlogPostID>,
+    query: web::Query<DiffQuery>,
+    user_id: UserID,
+    messages: IncomingFlashMessages,
+) -> actix_web::Result<HttpResponse> {
+    let blog_post = get_editable_blog_post(&pool, &path, &user_id)?;
+    let get_revision = |number| {
+        get_blog_post_revision(&pool, &blog_post.id, number)
+            .map_err(e500)?
+            .ok_or_else(|| actix_web::error::ErrorNotFound("No revision with such number"))
+    };
+    let to = match query.to {
+        Some(number) => get_revision(number)?,
+        None => get_blog_post_revisions(&pool, &blog_post.id)
+            .map_err(e500)?
+            .into_iter()
+            .next()
+            .map(|(revision, _)| revision)
+            .ok_or_else(|| actix_web::error::ErrorNotFound("Blog post has no revisions"))?,
+    };
+    let from = get_revision(query.from.unwrap_or(to.number - 1).max(1))?;
+
+    render_template(BlogPostDiffTemplate {
+        messages: messages.into(),
+        blog_post_id: blog_post.id.as_ref(),
+        blog_post_title: &blog_post.title,
+        from: from.number,
+        to: to.number,
+        title_diff: diff_rows(&from.title, &to.title),
+        brief_diff: diff_rows(&from.brief, &to.brief),
+        contents_diff: diff_rows(&from.contents, &to.contents),
+        tags_diff: diff_rows(&from.tags, &to.tags),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum RestoreRevisionError {
+    #[error("No revision with such number")]
+    NoSuchRevision,
+    #[error("Title is already taken")]
+    TakenTitle,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for RestoreRevisionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+impl From<BlogPostError> for RestoreRevisionError {
+    fn from(e: BlogPostError) -> Self {
+        match e {
+            BlogPostError::TakenTitle => Self::TakenTitle,
+            // Revisions are restored without version check
+            BlogPostError::Conflict => {
+                Self::UnexpectedError(anyhow::anyhow!("Unexpected edit conflict"))
+            }
+            BlogPostError::UnexpectedError(e) => Self::UnexpectedError(e),
+        }
+    }
+}
+
+#[tracing::instrument("Restore blog post revision", skip(pool))]
+pub async fn restore(
+    pool: web::Data<Pool>,
+    path: web::Path<(BlogPostID, i32)>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let (blog_post_id, number) = path.into_inner();
+    let blog_post = get_editable_blog_post(&pool, &blog_post_id, &user_id)?;
+    let history_url = format!("/blog_posts/{}/history", blog_post.id);
+    let redirect = |e: RestoreRevisionError| -> actix_web::Error {
+        redirect_with_error(&history_url, e).into()
+    };
+
+    let revision = get_blog_post_revision(&pool, &blog_post.id, number)
+        .map_err(|e| redirect(e.into()))?
+        .ok_or_else(|| redirect(RestoreRevisionError::NoSuchRevision))?;
+    restore_blog_post_revision(&pool, &revision, &user_id).map_err(|e| redirect(e.into()))?;
+
+    FlashMessage::info(format!("Revision {} has been restored", number)).send();
+    Ok(see_other(&history_url))
+}
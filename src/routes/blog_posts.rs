@@ -1,22 +1,30 @@
 use crate::domain::blog_posts::{
-    BlogPost, BlogPostID, BlogPostVisibility, NewBlogPost, UpdateBlogPost,
+    AutosaveSlot, BlogPost, BlogPostID, BlogPostStatus, BlogPostVisibility, NewBlogPost,
+    UpdateBlogPost,
 };
+use crate::domain::slugs::Slug;
+use crate::domain::tags::TagName;
+use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
-use crate::markdown::parse_markdown_to_html;
+use crate::markdown::{parse_markdown_to_html, MarkdownContext};
 use crate::middleware::{Messages, Session};
 use crate::routes::error_handlers::ErrorPageTemplate;
 use crate::routes::internal::comments::render_regular_comments;
+use crate::routes::internal::edit_conflict::{render_edit_conflict, ConflictField, EditConflict};
+use crate::routes::internal::pagination::{PageQuery, Pagination};
 use crate::services::{
-    get_all_blog_posts, get_blog_post_by_id, get_comment_views_for_blog_post, insert_new_blog_post,
-    update_blog_post,
+    can_edit_blog_post, can_moderate_comments, can_view_blog_post, delete_autosave,
+    get_all_blog_posts, get_autosave, get_blog_post_by_id, get_blog_post_by_slug,
+    get_blog_post_tags, get_comment_views_for_blog_post, insert_new_blog_post, update_blog_post,
+    BlogPostError, SlugMatch, SortOrder,
 };
-use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::utils::{e500, moved_permanently, redirect_with_error, render_template, see_other};
 use crate::Pool;
 use actix_web::error::InternalError;
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use askama::Template;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 
 const EDIT_BLOG_POST_CACHE: &str = "edit_blog_post_form";
 
@@ -25,32 +33,48 @@ const EDIT_BLOG_POST_CACHE: &str = "edit_blog_post_form";
 struct BlogPostsTemplate {
     messages: Messages,
     blog_posts: Vec<BlogPost>,
+    pagination: Pagination,
 }
 
+/// Orders in which blog post listings can be sorted
+pub(crate) const BLOG_POST_ORDERS: &[SortOrder] = &[
+    SortOrder::Newest,
+    SortOrder::Oldest,
+    SortOrder::Updated,
+    SortOrder::Commented,
+];
+
 #[tracing::instrument("All blog posts", skip(pool, messages))]
 pub async fn all_blog_posts(
     pool: web::Data<Pool>,
+    query: web::Query<PageQuery>,
     messages: IncomingFlashMessages,
 ) -> actix_web::Result<HttpResponse> {
-    let blog_posts = get_all_blog_posts(&pool).map_err(e500)?;
+    let request = query.to_request(BLOG_POST_ORDERS)?;
+    let page = get_all_blog_posts(&pool, &request).map_err(e500)?;
 
     render_template(BlogPostsTemplate {
         messages: messages.into(),
-        blog_posts,
+        pagination: Pagination::new("/blog_posts/all", &request, &page, BLOG_POST_ORDERS),
+        blog_posts: page.items,
     })
 }
 
 #[derive(Template)]
-#[template(path = "blog_post.html", escape = "none")]
+#[template(path = "blog_post.html")]
 struct BlogPostTemplate<'a> {
     messages: Messages,
     blog_post_id: &'a str,
     blog_post_title: &'a str,
     blog_post_brief: &'a str,
     blog_post_contents: &'a str,
+    status_label: Option<String>,
+    tags: Vec<TagName>,
     rendered_comments: String,
     csrf_token: &'a str,
-    is_authenticated: bool,
+    can_edit: bool,
+    can_moderate: bool,
+    comments_locked: bool,
 }
 
 #[tracing::instrument("Blog post", skip(pool, messages, session))]
@@ -65,6 +89,54 @@ pub async fn blog_post(
     let blog_post = get_blog_post_by_id(&pool, &blog_post_id)
         .map_err(e500)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("No blog post with such id"))?;
+    render_blog_post(&pool, blog_post, messages, current_user_id, &session)
+}
+
+#[tracing::instrument("Blog post by slug", skip(pool, messages, session))]
+pub async fn blog_post_by_slug(
+    pool: web::Data<Pool>,
+    params: web::Path<String>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let not_found = || actix_web::error::ErrorNotFound("No blog post with such slug");
+    let slug = Slug::parse(&params).map_err(|_| not_found())?;
+    match get_blog_post_by_slug(&pool, &slug)
+        .map_err(e500)?
+        .ok_or_else(not_found)?
+    {
+        SlugMatch::Current(blog_post) => {
+            render_blog_post(&pool, blog_post, messages, current_user_id, &session)
+        }
+        // Redirect would tell current slug to those who can't see the post
+        SlugMatch::Previous(blog_post) => {
+            let is_visible = can_view_blog_post(&pool, &blog_post, current_user_id.as_ref())
+                .map_err(e500)?
+                && (blog_post.visibility == BlogPostVisibility::All || current_user_id.is_some());
+            if !is_visible {
+                return Err(not_found());
+            }
+            Ok(moved_permanently(&format!(
+                "/posts/{}",
+                blog_post.slug.as_ref()
+            )))
+        }
+    }
+}
+
+fn render_blog_post(
+    pool: &Pool,
+    blog_post: BlogPost,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+    session: &Session,
+) -> actix_web::Result<HttpResponse> {
+    let blog_post_id = blog_post.id.clone();
+    // Unpublished posts are not shown to be existing to those who can't see them
+    if !can_view_blog_post(pool, &blog_post, current_user_id.as_ref()).map_err(e500)? {
+        return Err(actix_web::error::ErrorNotFound("No blog post with such id"));
+    }
 
     if blog_post.visibility == BlogPostVisibility::Authenticated && current_user_id.is_none() {
         return render_template(ErrorPageTemplate {
@@ -74,27 +146,65 @@ pub async fn blog_post(
         });
     }
 
-    let comments = get_comment_views_for_blog_post(&pool, &blog_post_id).map_err(e500)?;
-    let rendered_comments =
-        render_regular_comments(comments, current_user_id.as_ref()).map_err(e500)?;
+    let (can_edit, can_moderate) = match current_user_id.as_ref() {
+        Some(user_id) => (
+            can_edit_blog_post(pool, &blog_post, user_id).map_err(e500)?,
+            can_moderate_comments(pool, user_id).map_err(e500)?,
+        ),
+        None => (false, false),
+    };
+
+    let tags = get_blog_post_tags(pool, &blog_post_id).map_err(e500)?;
+    let comments = get_comment_views_for_blog_post(pool, &blog_post_id).map_err(e500)?;
+    let rendered_comments = render_regular_comments(
+        comments,
+        current_user_id.as_ref(),
+        can_moderate,
+        blog_post.comments_locked,
+    )
+    .map_err(e500)?;
 
     render_template(BlogPostTemplate {
         messages: messages.into(),
         blog_post_id: blog_post.id.as_ref().as_str(),
         blog_post_title: &blog_post.title,
         blog_post_brief: &blog_post.brief,
-        blog_post_contents: &parse_markdown_to_html(&blog_post.contents),
+        blog_post_contents: &parse_markdown_to_html(&blog_post.contents, MarkdownContext::Post),
+        status_label: status_label(&blog_post),
+        tags,
         rendered_comments,
         csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
-        is_authenticated: current_user_id.is_some(),
+        can_edit,
+        can_moderate,
+        comments_locked: blog_post.comments_locked,
     })
 }
 
+/// Label shown to editors of blog post that is not published
+fn status_label(blog_post: &BlogPost) -> Option<String> {
+    match blog_post.status {
+        BlogPostStatus::Draft => Some("Draft".to_string()),
+        BlogPostStatus::Scheduled => Some(match &blog_post.publish_at {
+            Some(time) => format!("Scheduled for {}", time),
+            None => "Scheduled".to_string(),
+        }),
+        BlogPostStatus::Published => None,
+        BlogPostStatus::Archived => Some("Archived".to_string()),
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct BlogPostDisplay {
     title: String,
     brief: String,
     contents: String,
+    status: BlogPostStatus,
+    /// Value of `datetime-local` input
+    publish_at: String,
+    /// Comma separated list of tags
+    tags: String,
+    /// Version of blog post that is being edited, absent for new blog posts
+    version: Option<i32>,
 }
 
 impl Default for BlogPostDisplay {
@@ -103,16 +213,70 @@ impl Default for BlogPostDisplay {
             title: "Untitled".to_string(),
             brief: "".to_string(),
             contents: "".to_string(),
+            status: BlogPostStatus::Published,
+            publish_at: "".to_string(),
+            tags: "".to_string(),
+            version: None,
         }
     }
 }
 
+/// Unsaved text of editor, which user can restore or discard
+struct AutosaveDisplay {
+    title: String,
+    brief: String,
+    contents: String,
+    tags: String,
+    saved_when: String,
+}
+
+/// Returns autosave of editor if it differs from what editor shows
+fn get_autosave_display(
+    pool: &Pool,
+    user_id: &UserID,
+    slot: &AutosaveSlot,
+    blog_post: &BlogPostDisplay,
+) -> Result<Option<AutosaveDisplay>, anyhow::Error> {
+    Ok(get_autosave(pool, user_id, slot)?
+        .filter(|autosave| {
+            (
+                &autosave.title,
+                &autosave.brief,
+                &autosave.contents,
+                &autosave.tags,
+            ) != (
+                &blog_post.title,
+                &blog_post.brief,
+                &blog_post.contents,
+                &blog_post.tags,
+            )
+        })
+        .map(|autosave| AutosaveDisplay {
+            saved_when: autosave.saved_at.ago(),
+            title: autosave.title,
+            brief: autosave.brief,
+            contents: autosave.contents,
+            tags: autosave.tags,
+        }))
+}
+
+/// Removes autosave of editor after its form has been submitted. Blog post is already saved,
+/// so failure is only logged.
+fn clear_autosave(pool: &Pool, user_id: &UserID, slot: &AutosaveSlot) {
+    if let Err(e) = delete_autosave(pool, user_id, slot) {
+        tracing::error!("Failed to delete autosave: {:?}", e);
+    }
+}
+
 #[derive(Template)]
 #[template(path = "edit_blog_post.html")]
 struct EditBlogPostTemplate<'a> {
     messages: Messages,
     blog_post: BlogPostDisplay,
     action: &'a str,
+    /// Address that editor text is periodically sent to
+    autosave_action: &'a str,
+    autosave: Option<AutosaveDisplay>,
     csrf_token: &'a str,
 }
 
@@ -121,56 +285,155 @@ pub async fn edit_blog_post_form(
     pool: web::Data<Pool>,
     params: web::Path<BlogPostID>,
     messages: IncomingFlashMessages,
+    user_id: UserID,
     session: Session,
 ) -> actix_web::Result<HttpResponse> {
     let blog_post_id = params.into_inner();
     let blog_post = get_blog_post_by_id(&pool, &blog_post_id)
         .map_err(e500)?
         .ok_or_else(|| actix_web::error::ErrorNotFound("No blog post with such id"))?;
+    if !can_edit_blog_post(&pool, &blog_post, &user_id).map_err(e500)? {
+        return Err(actix_web::error::ErrorForbidden(
+            "User is not allowed to edit blog post",
+        ));
+    }
 
+    let tags = get_blog_post_tags(&pool, &blog_post_id).map_err(e500)?;
+    let blog_post = BlogPostDisplay {
+        title: blog_post.title,
+        brief: blog_post.brief,
+        contents: blog_post.contents,
+        status: blog_post.status,
+        publish_at: blog_post
+            .publish_at
+            .map(|time| time.to_html_input())
+            .unwrap_or_default(),
+        tags: TagName::join(&tags),
+        version: Some(blog_post.version),
+    };
+    let slot = AutosaveSlot::BlogPost(blog_post_id.clone());
     render_template(EditBlogPostTemplate {
         messages: messages.into(),
-        blog_post: BlogPostDisplay {
-            title: blog_post.title,
-            brief: blog_post.brief,
-            contents: blog_post.contents,
-        },
+        autosave: get_autosave_display(&pool, &user_id, &slot, &blog_post).map_err(e500)?,
+        blog_post,
         action: format!("/blog_posts/{}/edit", blog_post_id.as_ref()).as_str(),
+        autosave_action: format!("/blog_posts/{}/autosave", blog_post_id.as_ref()).as_str(),
         csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
     })
 }
 
+#[derive(serde::Deserialize)]
+pub struct BlogPostForm {
+    title: String,
+    brief: String,
+    contents: String,
+    visible_to_all: Option<String>,
+    /// Posts are published right away if not specified
+    status: Option<BlogPostStatus>,
+    publish_at: Option<String>,
+    /// Comma separated list of tags
+    tags: Option<String>,
+}
+
+/// Form of existing blog post, which can't be saved without version it was filled from
 #[derive(serde::Deserialize)]
 pub struct EditBlogPostForm {
     title: String,
     brief: String,
     contents: String,
     visible_to_all: Option<String>,
-    csrf_token: Secret<String>,
+    status: Option<BlogPostStatus>,
+    publish_at: Option<String>,
+    tags: Option<String>,
+    /// Version of blog post the form was filled from
+    version: i32,
+}
+
+impl EditBlogPostForm {
+    fn split(self) -> (BlogPostForm, i32) {
+        let form = BlogPostForm {
+            title: self.title,
+            brief: self.brief,
+            contents: self.contents,
+            visible_to_all: self.visible_to_all,
+            status: self.status,
+            publish_at: self.publish_at,
+            tags: self.tags,
+        };
+        (form, self.version)
+    }
+}
+
+impl BlogPostForm {
+    /// Returns status of post and its publication time given in form.
+    /// Scheduled posts with publication time in the past are published right away.
+    fn publication(&self) -> Result<(BlogPostStatus, Option<DateTime>), anyhow::Error> {
+        let publish_at = self
+            .publish_at
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                DateTime::parse_html_input(s)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid publication time"))
+            })
+            .transpose()?;
+        match self.status.unwrap_or(BlogPostStatus::Published) {
+            BlogPostStatus::Scheduled => match publish_at {
+                Some(time) if time.is_past() => Ok((BlogPostStatus::Published, Some(time))),
+                Some(time) => Ok((BlogPostStatus::Scheduled, Some(time))),
+                None => Err(anyhow::anyhow!(
+                    "Publication time must be set for scheduled blog post"
+                )),
+            },
+            other => Ok((other, publish_at)),
+        }
+    }
+
+    fn tags(&self) -> Result<Vec<TagName>, anyhow::Error> {
+        TagName::parse_list(self.tags.as_deref().unwrap_or(""))
+    }
 }
 
-#[tracing::instrument("Edit blog post form", skip(pool, form, session))]
+#[tracing::instrument("Edit blog post", skip(pool, form, session))]
 pub async fn edit_blog_post(
     pool: web::Data<Pool>,
     form: web::Form<EditBlogPostForm>,
     blog_post_id: web::Path<BlogPostID>,
+    user_id: UserID,
     session: Session,
-) -> Result<HttpResponse, InternalError<anyhow::Error>> {
+) -> actix_web::Result<HttpResponse> {
     let blog_post_id = blog_post_id.into_inner();
+    let (form, expected_version) = form.into_inner().split();
+    let blog_post = get_blog_post_by_id(&pool, &blog_post_id)
+        .map_err(e500)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("No blog post with such id"))?;
+    if !can_edit_blog_post(&pool, &blog_post, &user_id).map_err(e500)? {
+        return Err(actix_web::error::ErrorForbidden(
+            "User is not allowed to edit blog post",
+        ));
+    }
 
-    let redirect = |e| {
+    let redirect = |e: anyhow::Error| {
         redirect_with_error(
             format!("/blog_posts/{}/edit", blog_post_id.as_ref()).as_str(),
             e,
         )
     };
 
-    if form.csrf_token.expose_secret()
-        != session.get_csrf_token().map_err(redirect)?.expose_secret()
-    {
-        return Err(redirect(anyhow::anyhow!("Invalid CSRF token")));
-    }
-
+    let (status, publish_at) = form.publication().map_err(redirect)?;
+    let tags = form.tags().map_err(redirect)?;
+    let publish_at = match status {
+        BlogPostStatus::Draft => Some(None),
+        BlogPostStatus::Scheduled => Some(publish_at),
+        // Publication time of post that was published before is kept
+        BlogPostStatus::Published => Some(
+            publish_at
+                .or(blog_post.publish_at)
+                .or_else(|| Some(DateTime::now())),
+        ),
+        BlogPostStatus::Archived => None,
+    };
     let changeset = UpdateBlogPost {
         id: &blog_post_id,
         title: Some(&form.title),
@@ -181,16 +444,87 @@ pub async fn edit_blog_post(
         } else {
             BlogPostVisibility::Authenticated
         }),
+        status: Some(status),
+        publish_at,
     };
-    update_blog_post(&pool, &changeset).map_err(|e| redirect(e.into()))?;
+    match update_blog_post(
+        &pool,
+        &changeset,
+        Some(&tags),
+        &user_id,
+        Some(expected_version),
+    ) {
+        Ok(()) => {}
+        Err(BlogPostError::Conflict) => {
+            return render_blog_post_conflict(&pool, &blog_post_id, &form, &session)
+        }
+        Err(e) => return Err(redirect(e.into()).into()),
+    }
+    clear_autosave(
+        &pool,
+        &user_id,
+        &AutosaveSlot::BlogPost(blog_post_id.clone()),
+    );
     Ok(see_other(
         format!("/blog_posts/{}/view", blog_post_id.as_ref()).as_str(),
     ))
 }
 
-#[tracing::instrument("Create blog post form", skip(messages, session))]
+/// Shows how submitted form differs from blog post that was saved while it was edited
+fn render_blog_post_conflict(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+    form: &BlogPostForm,
+    session: &Session,
+) -> actix_web::Result<HttpResponse> {
+    let current = get_blog_post_by_id(pool, blog_post_id)
+        .map_err(e500)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("No blog post with such id"))?;
+    let status = form.status.unwrap_or(BlogPostStatus::Published);
+    let mut form_fields = vec![
+        ("title", form.title.as_str()),
+        ("brief", form.brief.as_str()),
+        ("contents", form.contents.as_str()),
+        ("status", status.as_str()),
+        ("publish_at", form.publish_at.as_deref().unwrap_or("")),
+        ("tags", form.tags.as_deref().unwrap_or("")),
+    ];
+    if form.visible_to_all.is_some() {
+        form_fields.push(("visible_to_all", "on"));
+    }
+
+    render_edit_conflict(EditConflict {
+        title: "Blog post",
+        back_link: &format!("/blog_posts/{}/edit", blog_post_id),
+        action: &format!("/blog_posts/{}/edit", blog_post_id),
+        fields: vec![
+            ConflictField {
+                label: "Title",
+                current: &current.title,
+                submitted: &form.title,
+            },
+            ConflictField {
+                label: "Brief",
+                current: &current.brief,
+                submitted: &form.brief,
+            },
+            ConflictField {
+                label: "Contents",
+                current: &current.contents,
+                submitted: &form.contents,
+            },
+        ],
+        form_fields,
+        current_version: current.version,
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[tracing::instrument("Create blog post form", skip(pool, messages, session))]
 pub async fn create_blog_post_form(
+    pool: web::Data<Pool>,
     messages: IncomingFlashMessages,
+    user_id: UserID,
     session: Session,
 ) -> actix_web::Result<HttpResponse> {
     let blog_post = session
@@ -199,16 +533,18 @@ pub async fn create_blog_post_form(
         .unwrap_or_default();
     render_template(EditBlogPostTemplate {
         messages: messages.into(),
+        autosave: get_autosave_display(&pool, &user_id, &AutosaveSlot::NewBlogPost, &blog_post)
+            .map_err(e500)?,
         blog_post,
         action: "/blog_posts/create",
+        autosave_action: "/blog_posts/autosave",
         csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
     })
 }
 
 #[tracing::instrument("Create blog post", skip(form, pool, session))]
 pub async fn create_blog_post(
-    // NOTE: Duplicate usage here
-    form: web::Form<EditBlogPostForm>,
+    form: web::Form<BlogPostForm>,
     pool: web::Data<Pool>,
     user_id: UserID,
     session: Session,
@@ -220,6 +556,10 @@ pub async fn create_blog_post(
                 title: form.title.clone(),
                 brief: form.brief.clone(),
                 contents: form.contents.clone(),
+                status: form.status.unwrap_or(BlogPostStatus::Published),
+                publish_at: form.publish_at.clone().unwrap_or_default(),
+                tags: form.tags.clone().unwrap_or_default(),
+                version: None,
             },
         ) {
             anyhow::anyhow!(
@@ -233,6 +573,8 @@ pub async fn create_blog_post(
         redirect_with_error("/blog_posts/create", e)
     };
 
+    let (status, publish_at) = form.publication().map_err(create_blog_post_redirect)?;
+    let tags = form.tags().map_err(create_blog_post_redirect)?;
     let new_blog_post = NewBlogPost {
         author_id: &user_id,
         title: &form.title,
@@ -243,10 +585,14 @@ pub async fn create_blog_post(
             .as_ref()
             .map(|_| BlogPostVisibility::All)
             .unwrap_or(BlogPostVisibility::Authenticated),
+        status,
+        publish_at,
+        tags: &tags,
     };
     let blog_post = insert_new_blog_post(&pool, &new_blog_post)
         .map_err(anyhow::Error::new)
         .map_err(create_blog_post_redirect)?;
+    clear_autosave(&pool, &user_id, &AutosaveSlot::NewBlogPost);
 
     Ok(see_other(
         format!("/blog_posts/{}/view", blog_post.id.as_ref()).as_str(),
@@ -3,13 +3,18 @@ use crate::domain::comments::NewComment;
 use crate::domain::comments::{CommentID, UpdateComment};
 use crate::domain::users::UserID;
 use crate::middleware::Session;
-use crate::services::insert_new_comment;
-use crate::services::{get_comment_by_id, update_comment};
+use crate::routes::internal::edit_conflict::{render_edit_conflict, ConflictField, EditConflict};
+use crate::services::{
+    can_moderate_comments, can_view_blog_post, get_blog_post_by_id, get_comment_by_id,
+    hide_comment, insert_new_comment, restore_comment, set_blog_post_comments_locked,
+    update_comment, CommentError,
+};
 use crate::utils::{e500, redirect_with_error, see_other};
 use crate::Pool;
 use actix_web::error::InternalError;
 use actix_web::{web, HttpResponse};
-use secrecy::{ExposeSecret, Secret};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::ExposeSecret;
 
 #[derive(serde::Deserialize)]
 pub struct CreateCommentFormData {
@@ -17,21 +22,56 @@ pub struct CreateCommentFormData {
     reply_to_id: Option<CommentID>,
 }
 
+#[derive(thiserror::Error)]
+pub enum CreateCommentError {
+    #[error("No blog post with such id")]
+    NoSuchBlogPost,
+    #[error("Comments on this blog post are locked")]
+    CommentsLocked,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for CreateCommentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+        error_chain_fmt(self, f)
+    }
+}
+
 #[tracing::instrument("Create comment", skip(pool, form))]
 pub async fn create_comment(
     pool: web::Data<Pool>,
     user_id: UserID,
     post_id: web::Path<BlogPostID>,
     form: web::Form<CreateCommentFormData>,
-) -> actix_web::Result<HttpResponse> {
+) -> Result<HttpResponse, InternalError<CreateCommentError>> {
     let post_id = post_id.into_inner();
+    let redirect = |e| redirect_with_error(&format!("/blog_posts/{}/view", post_id), e);
+
+    let blog_post = get_blog_post_by_id(&pool, &post_id)
+        .map_err(CreateCommentError::UnexpectedError)
+        .map_err(redirect)?
+        .ok_or_else(|| redirect(CreateCommentError::NoSuchBlogPost))?;
+    let can_view = can_view_blog_post(&pool, &blog_post, Some(&user_id))
+        .map_err(CreateCommentError::UnexpectedError)
+        .map_err(redirect)?;
+    if !can_view {
+        return Err(redirect(CreateCommentError::NoSuchBlogPost));
+    }
+    if blog_post.comments_locked {
+        return Err(redirect(CreateCommentError::CommentsLocked));
+    }
+
     let new_comment = NewComment {
         author_id: &user_id,
         post_id: &post_id,
         parent_id: form.0.reply_to_id.as_ref(),
         contents: &form.0.contents,
     };
-    let new_comment = insert_new_comment(&pool, &new_comment).map_err(e500)?;
+    let new_comment = insert_new_comment(&pool, &new_comment)
+        .map_err(CreateCommentError::UnexpectedError)
+        .map_err(redirect)?;
     Ok(see_other(&format!(
         "/blog_posts/{}/view#comment-{}",
         post_id, new_comment.id
@@ -40,10 +80,10 @@ pub async fn create_comment(
 
 #[derive(thiserror::Error)]
 pub enum EditCommentError {
-    #[error("Invalid CSRF token")]
-    CSRFError,
     #[error("Can't change others comment")]
     CantChangeOthersComment,
+    #[error("Can't change comment removed by moderator")]
+    CommentIsHidden,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -58,7 +98,8 @@ impl std::fmt::Debug for EditCommentError {
 #[derive(serde::Deserialize)]
 pub struct EditCommentForm {
     contents: String,
-    csrf_token: Secret<String>,
+    /// Version of comment that was edited
+    version: i32,
 }
 
 #[tracing::instrument("Edit comment", skip(pool, form, session))]
@@ -68,25 +109,16 @@ pub async fn edit_comment(
     form: web::Form<EditCommentForm>,
     current_user_id: UserID,
     session: Session,
-) -> Result<HttpResponse, InternalError<EditCommentError>> {
+) -> actix_web::Result<HttpResponse> {
     let (post_id, comment_id) = path.into_inner();
-    let redirect = |e| {
+    let redirect = |e: EditCommentError| -> actix_web::Error {
         redirect_with_error(
             &format!("/blog_posts/{}/view#comment-{}", post_id, comment_id),
             e,
         )
+        .into()
     };
 
-    if form.csrf_token.expose_secret()
-        != session
-            .get_csrf_token()
-            .map_err(EditCommentError::UnexpectedError)
-            .map_err(redirect)?
-            .expose_secret()
-    {
-        return Err(redirect(EditCommentError::CSRFError));
-    }
-
     if let Some(comment) = get_comment_by_id(&pool, &comment_id)
         .map_err(EditCommentError::UnexpectedError)
         .map_err(redirect)?
@@ -94,15 +126,40 @@ pub async fn edit_comment(
         if comment.author_id != current_user_id {
             return Err(redirect(EditCommentError::CantChangeOthersComment));
         }
+        if comment.is_hidden {
+            return Err(redirect(EditCommentError::CommentIsHidden));
+        }
     }
     let changeset = UpdateComment {
         id: &comment_id,
-        contents: Some(form.0.contents.as_str()),
+        contents: Some(form.contents.as_str()),
         is_deleted: None,
     };
-    update_comment(&pool, &changeset)
-        .map_err(EditCommentError::UnexpectedError)
-        .map_err(redirect)?;
+    match update_comment(&pool, &changeset, Some(form.version)) {
+        Ok(()) => {}
+        Err(CommentError::Conflict) => {
+            let current = get_comment_by_id(&pool, &comment_id)
+                .map_err(e500)?
+                .ok_or_else(|| e500("Failed to get comment"))?;
+            let back_link = format!("/blog_posts/{}/view#comment-{}", post_id, comment_id);
+            return render_edit_conflict(EditConflict {
+                title: "Comment",
+                back_link: &back_link,
+                action: &format!("/blog_posts/{}/comments/{}/edit", post_id, comment_id),
+                fields: vec![ConflictField {
+                    label: "Contents",
+                    current: &current.contents,
+                    submitted: &form.contents,
+                }],
+                form_fields: vec![("contents", form.contents.as_str())],
+                current_version: current.version,
+                csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+            });
+        }
+        Err(CommentError::UnexpectedError(e)) => {
+            return Err(redirect(EditCommentError::UnexpectedError(e)))
+        }
+    }
 
     Ok(see_other(&format!(
         "/blog_posts/{}/view#comment-{}",
@@ -124,8 +181,6 @@ pub async fn delete_comment(
         )
     };
 
-    // TODO: CSRF
-
     if let Some(comment) = get_comment_by_id(&pool, &comment_id)
         .map_err(EditCommentError::UnexpectedError)
         .map_err(redirect)?
@@ -133,17 +188,144 @@ pub async fn delete_comment(
         if comment.author_id != current_user_id {
             return Err(redirect(EditCommentError::CantChangeOthersComment));
         }
+        if comment.is_hidden {
+            return Err(redirect(EditCommentError::CommentIsHidden));
+        }
     }
     let changeset = UpdateComment {
         id: &comment_id,
         contents: None,
         is_deleted: Some(true),
     };
-    update_comment(&pool, &changeset)
-        .map_err(EditCommentError::UnexpectedError)
+    update_comment(&pool, &changeset, None)
+        .map_err(|e| EditCommentError::UnexpectedError(e.into()))
         .map_err(redirect)?;
     Ok(see_other(&format!(
         "/blog_posts/{}/view#comment-{}",
         post_id, comment_id
     )))
 }
+
+#[derive(thiserror::Error)]
+pub enum ModerationError {
+    #[error("You are not allowed to moderate comments")]
+    NotModerator,
+    #[error("Reason for hiding comment must be given")]
+    EmptyReason,
+    #[error("No comment with such id")]
+    NoSuchComment,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ModerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+        error_chain_fmt(self, f)
+    }
+}
+
+fn ensure_moderator(pool: &Pool, user_id: &UserID) -> Result<(), ModerationError> {
+    if can_moderate_comments(pool, user_id)? {
+        Ok(())
+    } else {
+        Err(ModerationError::NotModerator)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct HideCommentForm {
+    reason: String,
+}
+
+#[tracing::instrument("Hide comment", skip(pool, form))]
+pub async fn hide(
+    pool: web::Data<Pool>,
+    path: web::Path<(BlogPostID, CommentID)>,
+    form: web::Form<HideCommentForm>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let (post_id, comment_id) = path.into_inner();
+    let comment_url = format!("/blog_posts/{}/view#comment-{}", post_id, comment_id);
+    let redirect = |e| redirect_with_error(&comment_url, e);
+
+    ensure_moderator(&pool, &current_user_id).map_err(redirect)?;
+    let reason = form.0.reason.trim();
+    if reason.is_empty() {
+        return Err(redirect(ModerationError::EmptyReason));
+    }
+    get_comment_by_id(&pool, &comment_id)
+        .map_err(ModerationError::UnexpectedError)
+        .map_err(redirect)?
+        .filter(|comment| comment.post_id == post_id)
+        .ok_or_else(|| redirect(ModerationError::NoSuchComment))?;
+
+    hide_comment(&pool, &comment_id, &current_user_id, reason)
+        .map_err(ModerationError::UnexpectedError)
+        .map_err(redirect)?;
+    tracing::info!("Comment {} hidden: {}", comment_id, reason);
+    FlashMessage::info("Comment has been hidden").send();
+    Ok(see_other(&comment_url))
+}
+
+#[tracing::instrument("Restore comment", skip(pool))]
+pub async fn restore(
+    pool: web::Data<Pool>,
+    path: web::Path<(BlogPostID, CommentID)>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    let (post_id, comment_id) = path.into_inner();
+    let comment_url = format!("/blog_posts/{}/view#comment-{}", post_id, comment_id);
+    let redirect = |e| redirect_with_error(&comment_url, e);
+
+    ensure_moderator(&pool, &current_user_id).map_err(redirect)?;
+    get_comment_by_id(&pool, &comment_id)
+        .map_err(ModerationError::UnexpectedError)
+        .map_err(redirect)?
+        .filter(|comment| comment.post_id == post_id)
+        .ok_or_else(|| redirect(ModerationError::NoSuchComment))?;
+
+    restore_comment(&pool, &comment_id)
+        .map_err(ModerationError::UnexpectedError)
+        .map_err(redirect)?;
+    FlashMessage::info("Comment has been restored").send();
+    Ok(see_other(&comment_url))
+}
+
+fn set_comments_locked(
+    pool: &Pool,
+    post_id: &BlogPostID,
+    current_user_id: &UserID,
+    locked: bool,
+) -> Result<HttpResponse, ModerationError> {
+    ensure_moderator(pool, current_user_id)?;
+    set_blog_post_comments_locked(pool, post_id, locked)?;
+
+    FlashMessage::info(if locked {
+        "Comments have been locked"
+    } else {
+        "Comments have been unlocked"
+    })
+    .send();
+    Ok(see_other(&format!("/blog_posts/{}/view", post_id)))
+}
+
+#[tracing::instrument("Lock comments", skip(pool))]
+pub async fn lock(
+    pool: web::Data<Pool>,
+    post_id: web::Path<BlogPostID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    set_comments_locked(&pool, &post_id, &current_user_id, true)
+        .map_err(|e| redirect_with_error(&format!("/blog_posts/{}/view", post_id), e))
+}
+
+#[tracing::instrument("Unlock comments", skip(pool))]
+pub async fn unlock(
+    pool: web::Data<Pool>,
+    post_id: web::Path<BlogPostID>,
+    current_user_id: UserID,
+) -> Result<HttpResponse, InternalError<ModerationError>> {
+    set_comments_locked(&pool, &post_id, &current_user_id, false)
+        .map_err(|e| redirect_with_error(&format!("/blog_posts/{}/view", post_id), e))
+}
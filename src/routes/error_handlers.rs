@@ -1,6 +1,7 @@
 use crate::middleware::Messages;
 use crate::utils::render_template;
 use actix_web::dev::ServiceResponse;
+use actix_web::http::StatusCode;
 use actix_web::middleware::ErrorHandlerResponse;
 use askama::Template;
 
@@ -14,11 +15,27 @@ pub struct ErrorPageTemplate<'a> {
 
 pub fn not_found_handler<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
     let (req, _) = res.into_parts();
-    let res = render_template(ErrorPageTemplate {
+    let mut res = render_template(ErrorPageTemplate {
         error_title: "Not found",
         error_message: "Page with requested URL is nonexistent",
         messages: Messages::empty(),
     })?;
+    *res.status_mut() = StatusCode::NOT_FOUND;
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, res)
+            .map_into_boxed_body()
+            .map_into_right_body(),
+    ))
+}
+
+pub fn forbidden_handler<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let (req, _) = res.into_parts();
+    let mut res = render_template(ErrorPageTemplate {
+        error_title: "Insufficient permissions",
+        error_message: "You are not allowed to access this page",
+        messages: Messages::empty(),
+    })?;
+    *res.status_mut() = StatusCode::FORBIDDEN;
     Ok(ErrorHandlerResponse::Response(
         ServiceResponse::new(req, res)
             .map_into_boxed_body()
@@ -0,0 +1,120 @@
+use crate::domain::blog_posts::BlogPost;
+use crate::domain::projects::{ProjectID, ProjectVisibility};
+use crate::domain::users::UserName;
+use crate::feed::{Feed, FeedEntry, FeedFormat};
+use crate::markdown::{parse_markdown_to_html, MarkdownContext};
+use crate::routes::users::resolve_user;
+use crate::sanitize::make_urls_absolute;
+use crate::services::{get_feed_blog_posts, get_project_by_id, FeedSource};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::e500;
+use crate::Pool;
+use actix_web::error::ErrorNotFound;
+use actix_web::{web, HttpResponse};
+
+fn feed_entry(base_url: &str, (blog_post, author_name): (BlogPost, UserName)) -> FeedEntry {
+    let url = format!("{}/posts/{}", base_url, blog_post.slug.as_ref());
+    // Feed readers don't know where html comes from, so links and images must be absolute
+    let content_html = make_urls_absolute(
+        &parse_markdown_to_html(&blog_post.contents, MarkdownContext::Post),
+        base_url,
+        &url,
+    );
+    FeedEntry {
+        id: format!("{}/blog_posts/{}/view", base_url, blog_post.id.as_ref()),
+        url,
+        content_html,
+        title: blog_post.title,
+        summary: blog_post.brief,
+        author_name: author_name.as_ref().to_string(),
+        published: blog_post.publish_at.unwrap_or(blog_post.created_at),
+        updated: blog_post.updated_at,
+    }
+}
+
+/// Renders feed of source. Feed is served at `{feed_prefix}/feed.{extension}`.
+fn render_feed(
+    pool: &Pool,
+    base_url: &str,
+    source: FeedSource,
+    title: String,
+    home_path: &str,
+    feed_prefix: &str,
+    format: FeedFormat,
+) -> actix_web::Result<HttpResponse> {
+    let entries = get_feed_blog_posts(pool, source)
+        .map_err(e500)?
+        .into_iter()
+        .map(|row| feed_entry(base_url, row))
+        .collect();
+    let feed = Feed::new(
+        title,
+        format!("{}{}", base_url, home_path),
+        format!("{}{}/feed.{}", base_url, feed_prefix, format.extension()),
+        entries,
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(format.render(&feed)))
+}
+
+#[tracing::instrument("Blog feed", skip(pool, base_url))]
+pub async fn blog_feed(
+    pool: web::Data<Pool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    path: web::Path<FeedFormat>,
+) -> actix_web::Result<HttpResponse> {
+    render_feed(
+        &pool,
+        &base_url.0,
+        FeedSource::All,
+        "Holodome".to_string(),
+        "/blog_posts/all",
+        "",
+        path.into_inner(),
+    )
+}
+
+#[tracing::instrument("User feed", skip(pool, base_url))]
+pub async fn user_feed(
+    pool: web::Data<Pool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    path: web::Path<(String, FeedFormat)>,
+) -> actix_web::Result<HttpResponse> {
+    let (id_or_name, format) = path.into_inner();
+    let user = resolve_user(&pool, &id_or_name)?;
+
+    render_feed(
+        &pool,
+        &base_url.0,
+        FeedSource::Author(&user.id),
+        format!("Holodome: {}", user.name.as_ref()),
+        &format!("/users/{}", user.id.as_ref()),
+        &format!("/users/{}", user.id.as_ref()),
+        format,
+    )
+}
+
+#[tracing::instrument("Project feed", skip(pool, base_url))]
+pub async fn project_feed(
+    pool: web::Data<Pool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    path: web::Path<(ProjectID, FeedFormat)>,
+) -> actix_web::Result<HttpResponse> {
+    let (project_id, format) = path.into_inner();
+    // Projects hidden from anonymous users don't have public feeds
+    let project = get_project_by_id(&pool, &project_id)
+        .map_err(e500)?
+        .filter(|project| project.visibility == ProjectVisibility::All)
+        .ok_or_else(|| ErrorNotFound("No project with such id"))?;
+
+    render_feed(
+        &pool,
+        &base_url.0,
+        FeedSource::Project(&project.id),
+        format!("Holodome: {}", project.title),
+        &format!("/projects/{}/view", project.id.as_ref()),
+        &format!("/projects/{}", project.id.as_ref()),
+        format,
+    )
+}
@@ -1,12 +1,12 @@
 use crate::domain::comments::CommentView;
 use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
-use crate::markdown::parse_markdown_to_html;
+use crate::markdown::{parse_markdown_to_html, MarkdownContext};
 use askama::Template;
 use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Template)]
-#[template(path = "comment.html", escape = "none")]
+#[template(path = "comment.html")]
 struct CommentTemplate<'a> {
     pub author: &'a str,
     pub date: &'a str,
@@ -15,8 +15,13 @@ struct CommentTemplate<'a> {
     pub id: &'a str,
     pub is_comment_author: bool,
     pub is_deleted: bool,
+    pub is_hidden: bool,
+    pub hidden_reason: &'a str,
+    pub can_moderate: bool,
+    pub can_reply: bool,
     pub author_id: &'a str,
     pub contents_raw: &'a str,
+    pub version: i32,
 }
 
 pub struct RenderCommentData<'a> {
@@ -27,32 +32,50 @@ pub struct RenderCommentData<'a> {
     rendered_children: Vec<String>,
     is_comment_author: bool,
     is_deleted: bool,
+    is_hidden: bool,
+    hidden_reason: Option<&'a str>,
     author_id: &'a str,
+    version: i32,
 }
 
+/// Renders comment tree. Comments hidden by moderators are replaced with placeholder,
+/// but their replies are still shown.
 pub fn render_regular_comments(
     comments: Vec<CommentView>,
     current_user: Option<&UserID>,
+    can_moderate: bool,
+    comments_locked: bool,
 ) -> Result<String, anyhow::Error> {
     render_comments(
         comments,
         current_user,
         |a, b| a.contents.cmp(&b.contents),
-        render_comment,
+        |data| render_comment(data, can_moderate, !comments_locked),
     )
 }
 
-fn render_comment(data: RenderCommentData) -> Result<String, anyhow::Error> {
+fn render_comment(
+    data: RenderCommentData,
+    can_moderate: bool,
+    can_reply: bool,
+) -> Result<String, anyhow::Error> {
+    // Contents of hidden comments never leave the server
+    let contents = if data.is_hidden { "" } else { data.contents };
     CommentTemplate {
         author: data.author,
         date: data.date,
-        contents: &parse_markdown_to_html(data.contents),
+        contents: &parse_markdown_to_html(contents, MarkdownContext::Comment),
         rendered_children: data.rendered_children,
         id: data.id,
         is_comment_author: data.is_comment_author,
         is_deleted: data.is_deleted,
+        is_hidden: data.is_hidden,
+        hidden_reason: data.hidden_reason.unwrap_or(""),
+        can_moderate,
+        can_reply,
         author_id: data.author_id,
-        contents_raw: data.contents,
+        contents_raw: contents,
+        version: data.version,
     }
     .render()
     .map_err(|e| anyhow::anyhow!("Failed to render comment: {:?}", e))
@@ -111,7 +134,10 @@ where
                     .map(|it| &current.author_id == it)
                     .unwrap_or(false),
                 is_deleted: current.is_deleted,
+                is_hidden: current.is_hidden,
+                hidden_reason: current.hidden_reason.as_deref(),
                 author_id: current.author_id.as_ref(),
+                version: current.version,
             })?;
             rendered.insert(current_id, s);
 
@@ -207,6 +233,9 @@ mod tests {
             created_at: time.clone(),
             updated_at: time,
             is_deleted: false,
+            is_hidden: false,
+            hidden_reason: None,
+            version: 1,
         }
     }
 
@@ -293,4 +322,41 @@ mod tests {
         let rendered_without_spaces = remove_spaces(&rendered);
         assert_eq!(rendered_without_spaces, expected_without_spaces);
     }
+
+    #[test]
+    fn hidden_comment_is_replaced_with_placeholder_and_keeps_replies() {
+        let id0 = CommentID::generate_random();
+        let mut hidden = generate_comment("hidden contents".to_string(), Some(id0.clone()), None);
+        hidden.is_hidden = true;
+        hidden.hidden_reason = Some("spam".to_string());
+        let comments = vec![
+            hidden,
+            generate_comment("reply contents".to_string(), None, Some(id0)),
+        ];
+
+        let rendered = render_regular_comments(comments, None, false, false).unwrap();
+        assert!(rendered.contains("Removed by moderator"));
+        assert!(!rendered.contains("hidden contents"));
+        assert!(!rendered.contains("spam"));
+        assert!(rendered.contains("reply contents"));
+    }
+
+    #[test]
+    fn hidden_comment_reason_is_shown_to_moderators() {
+        let mut hidden = generate_comment("hidden contents".to_string(), None, None);
+        hidden.is_hidden = true;
+        hidden.hidden_reason = Some("<b>spam</b>".to_string());
+
+        let rendered = render_regular_comments(vec![hidden], None, true, false).unwrap();
+        assert!(!rendered.contains("hidden contents"));
+        assert!(rendered.contains("&lt;b&gt;spam&lt;/b&gt;"));
+        assert!(rendered.contains("Restore"));
+    }
+
+    #[test]
+    fn replies_are_not_offered_when_comments_are_locked() {
+        let comments = vec![generate_comment("hello".to_string(), None, None)];
+        let rendered = render_regular_comments(comments, None, false, true).unwrap();
+        assert!(!rendered.contains("comment-reply-button"));
+    }
 }
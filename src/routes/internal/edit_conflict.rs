@@ -0,0 +1,93 @@
+use crate::diff::{diff_lines, DiffLine};
+use crate::middleware::Messages;
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use askama::Template;
+
+/// Line of diff as it is shown on page
+pub struct DiffRow {
+    pub class: &'static str,
+    pub sign: &'static str,
+    pub line: String,
+}
+
+impl From<DiffLine<'_>> for DiffRow {
+    fn from(line: DiffLine) -> Self {
+        let (class, sign, line) = match line {
+            DiffLine::Unchanged(line) => ("", " ", line),
+            DiffLine::Added(line) => ("positive", "+", line),
+            DiffLine::Removed(line) => ("negative", "-", line),
+        };
+        Self {
+            class,
+            sign,
+            line: line.to_string(),
+        }
+    }
+}
+
+pub fn diff_rows(old: &str, new: &str) -> Vec<DiffRow> {
+    diff_lines(old, new)
+        .into_iter()
+        .map(DiffRow::from)
+        .collect()
+}
+
+/// Field of edited entity which was changed concurrently
+pub struct ConflictField<'a> {
+    pub label: &'a str,
+    pub current: &'a str,
+    pub submitted: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "edit_conflict.html")]
+struct EditConflictTemplate<'a> {
+    messages: Messages,
+    title: &'a str,
+    back_link: &'a str,
+    action: &'a str,
+    fields: Vec<(&'a str, Vec<DiffRow>)>,
+    form_fields: Vec<(&'a str, &'a str)>,
+    version: i32,
+    csrf_token: &'a str,
+}
+
+/// Data needed to show conflict between saved and submitted versions of edited entity
+pub struct EditConflict<'a> {
+    pub title: &'a str,
+    /// Page where user can start editing over
+    pub back_link: &'a str,
+    /// Action where submitted version can be saved again, overwriting current one
+    pub action: &'a str,
+    pub fields: Vec<ConflictField<'a>>,
+    /// Submitted form fields that are sent again when user chooses to overwrite
+    pub form_fields: Vec<(&'a str, &'a str)>,
+    /// Version of entity that is currently saved
+    pub current_version: i32,
+    pub csrf_token: &'a str,
+}
+
+/// Renders page with `409 Conflict` status that shows how submitted version
+/// differs from the one that was saved in the meantime.
+pub fn render_edit_conflict(conflict: EditConflict) -> actix_web::Result<HttpResponse> {
+    let body = EditConflictTemplate {
+        messages: Messages::empty(),
+        title: conflict.title,
+        back_link: conflict.back_link,
+        action: conflict.action,
+        fields: conflict
+            .fields
+            .iter()
+            .map(|field| (field.label, diff_rows(field.current, field.submitted)))
+            .collect(),
+        form_fields: conflict.form_fields,
+        version: conflict.current_version,
+        csrf_token: conflict.csrf_token,
+    }
+    .render()
+    .map_err(crate::utils::e500)?;
+    Ok(HttpResponse::Conflict()
+        .content_type(ContentType::html())
+        .body(body))
+}
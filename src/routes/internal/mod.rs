@@ -1 +1,3 @@
 pub mod comments;
+pub mod edit_conflict;
+pub mod pagination;
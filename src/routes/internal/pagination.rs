@@ -0,0 +1,151 @@
+use crate::services::{Cursor, Page, PagePosition, PageRequest, SortOrder};
+use actix_web::error::ErrorBadRequest;
+
+/// Query parameters of paginated listing
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct PageQuery {
+    pub sort: Option<SortOrder>,
+    pub size: Option<i64>,
+    pub after: Option<String>,
+    pub before: Option<String>,
+}
+
+impl PageQuery {
+    /// Makes page request. Orders not in `orders` fall back to the first one of them.
+    pub fn to_request(&self, orders: &[SortOrder]) -> actix_web::Result<PageRequest> {
+        let order = self
+            .sort
+            .filter(|order| orders.contains(order))
+            .unwrap_or(orders[0]);
+        let mut request = PageRequest::first(order, self.size.unwrap_or(PageRequest::DEFAULT_SIZE));
+        request.position = match (&self.after, &self.before) {
+            (Some(_), Some(_)) => {
+                return Err(ErrorBadRequest("Only one of page cursors can be set"))
+            }
+            (Some(after), None) => Some(PagePosition::After(
+                Cursor::parse(after).map_err(ErrorBadRequest)?,
+            )),
+            (None, Some(before)) => Some(PagePosition::Before(
+                Cursor::parse(before).map_err(ErrorBadRequest)?,
+            )),
+            (None, None) => None,
+        };
+        if let Some(cursor) = request.cursor() {
+            cursor.check_key(order).map_err(ErrorBadRequest)?;
+        }
+        Ok(request)
+    }
+}
+
+pub struct SortLink {
+    pub label: &'static str,
+    pub href: String,
+    pub is_active: bool,
+}
+
+/// Links to neighbour pages and to listing in other orders
+pub struct Pagination {
+    pub prev_link: Option<String>,
+    pub next_link: Option<String>,
+    pub sort_links: Vec<SortLink>,
+}
+
+impl Pagination {
+    pub fn new<T>(path: &str, request: &PageRequest, page: &Page<T>, orders: &[SortOrder]) -> Self {
+        let link = |order: SortOrder, position: Option<(&str, &Cursor)>| {
+            let mut href = format!("{}?sort={}&size={}", path, order.as_str(), request.size);
+            if let Some((name, cursor)) = position {
+                href.push_str(&format!(
+                    "&{}={}",
+                    name,
+                    urlencoding::encode(&cursor.to_string())
+                ));
+            }
+            href
+        };
+        Self {
+            prev_link: page
+                .prev
+                .as_ref()
+                .map(|cursor| link(request.order, Some(("before", cursor)))),
+            next_link: page
+                .next
+                .as_ref()
+                .map(|cursor| link(request.order, Some(("after", cursor)))),
+            sort_links: orders
+                .iter()
+                .map(|&order| SortLink {
+                    label: order.label(),
+                    href: link(order, None),
+                    is_active: order == request.order,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: &[SortOrder] = &[SortOrder::Newest, SortOrder::Oldest];
+
+    #[test]
+    fn unsupported_order_falls_back_to_first_one() {
+        let query = PageQuery {
+            sort: Some(SortOrder::Commented),
+            ..Default::default()
+        };
+        let request = query.to_request(ORDERS).unwrap();
+        assert_eq!(request.order, SortOrder::Newest);
+        assert_eq!(request.size, PageRequest::DEFAULT_SIZE);
+    }
+
+    #[test]
+    fn page_size_is_clamped() {
+        let query = PageQuery {
+            size: Some(100_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.to_request(ORDERS).unwrap().size,
+            PageRequest::MAX_SIZE
+        );
+    }
+
+    #[test]
+    fn invalid_cursor_is_rejected() {
+        let query = PageQuery {
+            after: Some("no separator".into()),
+            ..Default::default()
+        };
+        assert!(query.to_request(ORDERS).is_err());
+    }
+
+    #[test]
+    fn cursor_with_key_of_other_order_is_rejected() {
+        let query = PageQuery {
+            sort: Some(SortOrder::Commented),
+            after: Some("abc|x".into()),
+            ..Default::default()
+        };
+        assert!(query.to_request(&[SortOrder::Commented]).is_err());
+    }
+
+    #[test]
+    fn links_keep_order_and_size() {
+        let request = PageRequest::first(SortOrder::Oldest, 5);
+        let page = Page {
+            items: vec![1],
+            prev: None,
+            next: Some(Cursor::new("2022-05-01 12:00:00 UTC", "id")),
+        };
+        let pagination = Pagination::new("/blog_posts/all", &request, &page, ORDERS);
+        assert_eq!(pagination.prev_link, None);
+        assert_eq!(
+            pagination.next_link.as_deref(),
+            Some("/blog_posts/all?sort=oldest&size=5&after=2022-05-01%2012%3A00%3A00%20UTC%7Cid")
+        );
+        assert!(pagination.sort_links[1].is_active);
+    }
+}
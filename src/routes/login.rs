@@ -1,14 +1,19 @@
-use crate::domain::users::{Credentials, PasswordError, UserName, UserPassword};
+use crate::config::PasswordHashingConfig;
+use crate::domain::users::{Credentials, PasswordError, RecoveryCode, UserName, UserPassword};
+use crate::login_throttle::LoginThrottle;
 use crate::middleware::{Messages, Session};
-use crate::services::{validate_credentials, AuthError};
+use crate::services::{
+    get_user_by_id, use_recovery_code, use_totp_step, validate_credentials, AuthError,
+};
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::utils::{e500, see_other};
 use crate::utils::{redirect_with_error, render_template};
 use crate::Pool;
 use actix_web::error::InternalError;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use askama::Template;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 
 const LOGIN_FORM_SESSION_KEY: &str = "login_form";
 
@@ -22,6 +27,7 @@ struct LoginCache {
 struct LoginTemplate<'a> {
     messages: Messages,
     name: Option<&'a str>,
+    csrf_token: &'a str,
 }
 
 #[tracing::instrument(skip(messages, session))]
@@ -38,6 +44,7 @@ pub async fn login_form(
     render_template(LoginTemplate {
         messages: messages.into(),
         name,
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
     })
 }
 
@@ -49,6 +56,10 @@ pub enum LoginError {
     InvalidPassword(#[source] PasswordError),
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error("Too many failed login attempts, try again in {0} seconds")]
+    TooManyAttempts(u64),
+    #[error("Your account has been banned")]
+    Banned,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -66,11 +77,14 @@ pub struct LoginFormData {
     password: Secret<String>,
 }
 
-#[tracing::instrument("Login", skip(form, pool, session))]
+#[tracing::instrument("Login", skip(form, pool, hashing, throttle, session, req))]
 pub async fn login(
     form: web::Form<LoginFormData>,
     pool: web::Data<Pool>,
+    hashing: web::Data<PasswordHashingConfig>,
+    throttle: web::Data<LoginThrottle>,
     session: Session,
+    req: HttpRequest,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let login_redirect = |e| {
         let e = if let Err(new_e) = session.insert_form_data(
@@ -99,21 +113,216 @@ pub async fn login(
             .map_err(login_redirect)?,
     };
 
-    match validate_credentials(credentials, &pool) {
+    let ip = throttle.client_address(&req);
+    let name = credentials.name.as_ref().to_string();
+
+    let lockout = {
+        let throttle = throttle.clone();
+        let (name, ip) = (name.clone(), ip.clone());
+        spawn_blocking_with_tracing(move || throttle.check(&name, &ip))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r)
+            .map_err(LoginError::UnexpectedError)
+            .map_err(login_redirect)?
+    };
+    if let Some(remaining) = lockout {
+        return Err(login_redirect(LoginError::TooManyAttempts(
+            remaining.as_secs().max(1),
+        )));
+    }
+
+    let validation_result = {
+        let pool = pool.get_ref().clone();
+        let hashing = hashing.get_ref().clone();
+        spawn_blocking_with_tracing(move || validate_credentials(credentials, &pool, &hashing))
+            .await
+            .map_err(|e| LoginError::UnexpectedError(anyhow::Error::new(e)))
+            .map_err(login_redirect)?
+    };
+
+    match validation_result {
         Ok(user_id) => {
+            spawn_blocking_with_tracing(move || throttle.register_success(&name))
+                .await
+                .map_err(anyhow::Error::new)
+                .and_then(|r| r)
+                .map_err(LoginError::UnexpectedError)
+                .map_err(login_redirect)?;
+
+            let user = get_user_by_id(&pool, &user_id)
+                .map_err(LoginError::UnexpectedError)
+                .map_err(login_redirect)?
+                .ok_or_else(|| anyhow::anyhow!("Failed to get user by id"))
+                .map_err(LoginError::UnexpectedError)
+                .map_err(login_redirect)?;
+
             session.renew();
+            if user.totp_secret.is_some() {
+                session
+                    .insert_two_factor_user_id(user_id)
+                    .map_err(LoginError::UnexpectedError)
+                    .map_err(login_redirect)?;
+                return Ok(see_other("/login/two_factor"));
+            }
             session
                 .insert_user_id(user_id)
                 .map_err(LoginError::UnexpectedError)
                 .map_err(login_redirect)?;
             Ok(see_other("/blog_posts/all"))
         }
-        Err(e) => {
-            let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
-                AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
-            };
-            Err(login_redirect(e))
+        Err(AuthError::InvalidCredentials(e)) => {
+            let lockout =
+                spawn_blocking_with_tracing(move || throttle.register_failure(&name, &ip))
+                    .await
+                    .map_err(anyhow::Error::new)
+                    .and_then(|r| r)
+                    .map_err(LoginError::UnexpectedError)
+                    .map_err(login_redirect)?;
+            Err(login_redirect(match lockout {
+                Some(lockout) => LoginError::TooManyAttempts(lockout.as_secs()),
+                None => LoginError::AuthError(e),
+            }))
+        }
+        Err(AuthError::Banned) => Err(login_redirect(LoginError::Banned)),
+        Err(e) => Err(login_redirect(LoginError::UnexpectedError(e.into()))),
+    }
+}
+
+/// Number of wrong codes after which user has to enter password again
+const MAX_TWO_FACTOR_ATTEMPTS: u32 = 5;
+
+#[derive(Template)]
+#[template(path = "login_two_factor.html")]
+struct TwoFactorTemplate<'a> {
+    messages: Messages,
+    csrf_token: &'a str,
+}
+
+#[tracing::instrument(skip(messages, session))]
+pub async fn two_factor_form(
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    if session.get_two_factor_user_id().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+
+    render_template(TwoFactorTemplate {
+        messages: messages.into(),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum TwoFactorError {
+    #[error("Password has not been entered")]
+    NotAuthenticated,
+    #[error("Invalid code")]
+    InvalidCode,
+    #[error("Too many invalid codes, log in again")]
+    TooManyAttempts,
+    #[error("Too many invalid codes, try again in {0} seconds")]
+    LockedOut(u64),
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for TwoFactorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct TwoFactorFormData {
+    code: Secret<String>,
+}
+
+#[tracing::instrument("Two-factor login", skip(form, pool, throttle, session))]
+pub async fn two_factor_login(
+    form: web::Form<TwoFactorFormData>,
+    pool: web::Data<Pool>,
+    throttle: web::Data<LoginThrottle>,
+    session: Session,
+) -> Result<HttpResponse, InternalError<TwoFactorError>> {
+    let login_redirect = |e| redirect_with_error("/login", TwoFactorError::UnexpectedError(e));
+    let user_id = session
+        .get_two_factor_user_id()
+        .map_err(login_redirect)?
+        .ok_or_else(|| redirect_with_error("/login", TwoFactorError::NotAuthenticated))?;
+    let user = get_user_by_id(&pool, &user_id)
+        .map_err(login_redirect)?
+        .ok_or_else(|| redirect_with_error("/login", TwoFactorError::NotAuthenticated))?;
+    let throttle_key = user_id.as_ref().to_string();
+
+    let lockout = {
+        let throttle = throttle.clone();
+        let key = throttle_key.clone();
+        spawn_blocking_with_tracing(move || throttle.check_two_factor(&key))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r)
+            .map_err(login_redirect)?
+    };
+    if let Some(remaining) = lockout {
+        return Err(redirect_with_error(
+            "/login/two_factor",
+            TwoFactorError::LockedOut(remaining.as_secs().max(1)),
+        ));
+    }
+
+    let code = form.code.expose_secret();
+    let is_valid = match user.totp_secret {
+        Some(secret) => match secret.matching_step(code) {
+            // Code that has already been accepted once is treated as invalid
+            Some(step) => use_totp_step(&pool, &user_id, step).map_err(login_redirect)?,
+            None => match RecoveryCode::parse(code) {
+                Ok(recovery_code) => {
+                    use_recovery_code(&pool, &user_id, &recovery_code).map_err(login_redirect)?
+                }
+                Err(_) => false,
+            },
+        },
+        // Two-factor authentication has been disabled in the meantime
+        None => true,
+    };
+
+    if !is_valid {
+        let lockout = {
+            let throttle = throttle.clone();
+            let key = throttle_key.clone();
+            spawn_blocking_with_tracing(move || throttle.register_two_factor_failure(&key))
+                .await
+                .map_err(anyhow::Error::new)
+                .and_then(|r| r)
+                .map_err(login_redirect)?
+        };
+        let attempts = session.add_two_factor_attempt().map_err(login_redirect)?;
+        if attempts >= MAX_TWO_FACTOR_ATTEMPTS {
+            session.remove_two_factor_user_id();
+            return Err(redirect_with_error(
+                "/login",
+                TwoFactorError::TooManyAttempts,
+            ));
         }
+        return Err(redirect_with_error(
+            "/login/two_factor",
+            match lockout {
+                Some(lockout) => TwoFactorError::LockedOut(lockout.as_secs()),
+                None => TwoFactorError::InvalidCode,
+            },
+        ));
     }
+
+    spawn_blocking_with_tracing(move || throttle.register_two_factor_success(&throttle_key))
+        .await
+        .map_err(anyhow::Error::new)
+        .and_then(|r| r)
+        .map_err(login_redirect)?;
+    session.remove_two_factor_user_id();
+    session.renew();
+    session.insert_user_id(user_id).map_err(login_redirect)?;
+    Ok(see_other("/blog_posts/all"))
 }
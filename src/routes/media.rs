@@ -0,0 +1,246 @@
+use crate::config::MediaConfig;
+use crate::domain::media::{Media, MediaID, MediaType};
+use crate::domain::users::UserID;
+use crate::media::MediaStore;
+use crate::middleware::{Messages, Session};
+use crate::routes::internal::pagination::{PageQuery, Pagination};
+use crate::services::{
+    delete_media, format_file_size, get_media_by_id, get_media_of_owner, save_media, MediaError,
+    SortOrder,
+};
+use crate::telemetry::spawn_blocking_with_tracing;
+use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_multipart::Multipart;
+use actix_web::error::{ErrorForbidden, ErrorNotFound, InternalError};
+use actix_web::http::header::ACCEPT;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama::Template;
+use futures_util::TryStreamExt;
+use secrecy::ExposeSecret;
+
+const MEDIA_LIBRARY_PATH: &str = "/account/media";
+const MEDIA_ORDERS: &[SortOrder] = &[SortOrder::Newest, SortOrder::Oldest];
+
+struct MediaInfo {
+    id: String,
+    url: String,
+    file_name: String,
+    media_type: String,
+    size: String,
+    uploaded: String,
+    is_image: bool,
+    markdown: String,
+}
+
+#[derive(Template)]
+#[template(path = "media_library.html")]
+struct MediaLibraryTemplate<'a> {
+    messages: Messages,
+    csrf_token: &'a str,
+    items: Vec<MediaInfo>,
+    pagination: Pagination,
+    accept: String,
+    max_upload_size: String,
+}
+
+/// Markdown that shows image or links to document
+fn markdown_reference(item: &Media, url: &str) -> String {
+    let text: String = item
+        .file_name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | '\\'))
+        .collect();
+    if item.media_type.is_image() {
+        format!("![{}]({})", text, url)
+    } else {
+        format!("[{}]({})", text, url)
+    }
+}
+
+#[tracing::instrument("Media library", skip(pool, store, config, messages, session))]
+pub async fn media_library(
+    pool: web::Data<Pool>,
+    store: web::Data<dyn MediaStore>,
+    config: web::Data<MediaConfig>,
+    query: web::Query<PageQuery>,
+    messages: IncomingFlashMessages,
+    session: Session,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let request = query.to_request(MEDIA_ORDERS)?;
+    let page = get_media_of_owner(&pool, &user_id, &request).map_err(e500)?;
+
+    render_template(MediaLibraryTemplate {
+        messages: messages.into(),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+        pagination: Pagination::new(MEDIA_LIBRARY_PATH, &request, &page, MEDIA_ORDERS),
+        items: page
+            .items
+            .iter()
+            .map(|item| {
+                let url = store.url(&item.storage_key());
+                MediaInfo {
+                    id: item.id.as_ref().clone(),
+                    markdown: markdown_reference(item, &url),
+                    url,
+                    file_name: item.file_name.clone(),
+                    media_type: item.media_type.mime(),
+                    size: format_file_size(item.size as usize),
+                    uploaded: item.created_at.ago(),
+                    is_image: item.media_type.is_image(),
+                }
+            })
+            .collect(),
+        accept: MediaType::accept_list(),
+        max_upload_size: format_file_size(config.max_upload_size),
+    })
+}
+
+#[derive(serde::Serialize)]
+struct UploadedMedia {
+    url: String,
+    markdown: String,
+}
+
+/// Saves file from `file` field of multipart form. Scripts that ask for JSON get url
+/// and markdown of uploaded file, forms are redirected back to media library.
+#[tracing::instrument("Upload media", skip(request, body, pool, store, config))]
+pub async fn upload_media(
+    request: HttpRequest,
+    body: web::Bytes,
+    pool: web::Data<Pool>,
+    store: web::Data<dyn MediaStore>,
+    config: web::Data<MediaConfig>,
+    user_id: UserID,
+) -> Result<HttpResponse, InternalError<MediaError>> {
+    let wants_json = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+    let fail = |e: MediaError| {
+        if wants_json {
+            let response =
+                HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }));
+            InternalError::from_response(e, response)
+        } else {
+            redirect_with_error(MEDIA_LIBRARY_PATH, e)
+        }
+    };
+
+    let (file_name, data) = read_uploaded_file(&request, body)
+        .await
+        .map_err(|_| fail(MediaError::NoFile))?
+        .ok_or_else(|| fail(MediaError::NoFile))?;
+
+    let saved = {
+        let pool = pool.get_ref().clone();
+        let store = store.into_inner();
+        let max_size = config.max_upload_size;
+        spawn_blocking_with_tracing(move || {
+            let saved = save_media(&pool, &*store, &user_id, &file_name, &data, max_size)?;
+            let url = store.url(&saved.storage_key());
+            Ok::<_, MediaError>((saved, url))
+        })
+        .await
+        .map_err(|e| fail(MediaError::UnexpectedError(e.into())))?
+    };
+    let (saved, url) = saved.map_err(fail)?;
+
+    if wants_json {
+        Ok(HttpResponse::Ok().json(UploadedMedia {
+            markdown: markdown_reference(&saved, &url),
+            url,
+        }))
+    } else {
+        FlashMessage::success(format!("{} has been uploaded", saved.file_name)).send();
+        Ok(see_other(MEDIA_LIBRARY_PATH))
+    }
+}
+
+/// Name and contents of file from `file` field of multipart form
+async fn read_uploaded_file(
+    request: &HttpRequest,
+    body: web::Bytes,
+) -> Result<Option<(String, Vec<u8>)>, actix_multipart::MultipartError> {
+    let body = futures_util::stream::once(async move { Ok(body) });
+    let mut multipart = Multipart::new(request.headers(), body);
+    while let Some(mut field) = multipart.try_next().await? {
+        let file_name = match field.content_disposition().get_filename() {
+            Some(file_name) if field.name() == "file" => file_name.to_string(),
+            _ => continue,
+        };
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await? {
+            data.extend_from_slice(&chunk);
+        }
+        return Ok(Some((file_name, data)));
+    }
+    Ok(None)
+}
+
+#[tracing::instrument("Delete media", skip(pool, store))]
+pub async fn delete_uploaded_media(
+    path: web::Path<MediaID>,
+    pool: web::Data<Pool>,
+    store: web::Data<dyn MediaStore>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let item = get_media_by_id(&pool, &path)
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("File does not exist"))?;
+    if item.owner_id != user_id {
+        return Err(ErrorForbidden("Only owner can delete file"));
+    }
+
+    let pool = pool.get_ref().clone();
+    let store = store.into_inner();
+    let file_name = item.file_name.clone();
+    spawn_blocking_with_tracing(move || delete_media(&pool, &*store, &item))
+        .await
+        .map_err(e500)?
+        .map_err(e500)?;
+
+    FlashMessage::info(format!("{} has been deleted", file_name)).send();
+    Ok(see_other(MEDIA_LIBRARY_PATH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::time::DateTime;
+
+    fn media(file_name: &str, media_type: MediaType) -> Media {
+        Media {
+            id: MediaID::generate_random(),
+            owner_id: UserID::from("user".to_string()),
+            file_name: file_name.to_string(),
+            media_type,
+            size: 1,
+            created_at: DateTime::now(),
+            has_variants: false,
+        }
+    }
+
+    #[test]
+    fn images_are_embedded_and_documents_are_linked() {
+        assert_eq!(
+            markdown_reference(&media("cat.png", MediaType::Png), "/media/1.png"),
+            "![cat.png](/media/1.png)"
+        );
+        assert_eq!(
+            markdown_reference(&media("paper.pdf", MediaType::Pdf), "/media/2.pdf"),
+            "[paper.pdf](/media/2.pdf)"
+        );
+    }
+
+    #[test]
+    fn brackets_are_removed_from_link_text() {
+        assert_eq!(
+            markdown_reference(&media("a]b[c\\.gif", MediaType::Gif), "/media/3.gif"),
+            "![abc.gif](/media/3.gif)"
+        );
+    }
+}
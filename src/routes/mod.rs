@@ -1,33 +1,45 @@
-use crate::middleware::{require_login, require_non_logged};
+use crate::middleware::{require_admin, require_login, require_non_logged};
 use crate::routes::users::user_page;
 use crate::utils::see_other;
 use actix_web::{web, HttpResponse};
 use actix_web_lab::middleware::from_fn;
 
 mod account;
+mod admin;
+mod blog_post_autosaves;
+mod blog_post_history;
 mod blog_posts;
 mod comments;
 pub(crate) mod error_handlers;
+mod feeds;
 mod health_check;
 mod internal;
 mod login;
 mod logout;
+mod media;
+mod password_reset;
+mod preview;
 mod projects;
 mod registration;
+mod search;
+mod tags;
+mod two_factor;
 mod users;
 
 async fn redirect_to_blog_posts() -> HttpResponse {
     see_other("/blog_posts/all")
 }
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
+/// Registers all routes. Uploads get their own payload config, since they are much
+/// larger than any other request.
+pub fn configure(cfg: &mut web::ServiceConfig, upload_payload_config: web::PayloadConfig) {
     cfg.route("/health_check", web::get().to(health_check::health_check))
         .route("", web::get().to(redirect_to_blog_posts))
         .route("/", web::get().to(redirect_to_blog_posts))
         .service(
             web::resource("/logout")
                 .wrap(from_fn(require_login))
-                .route(web::get().to(logout::logout)),
+                .route(web::post().to(logout::logout)),
         )
         .service(
             web::resource("/registration")
@@ -42,15 +54,124 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .route("/settings", web::get().to(account::account_settings))
                 .route("/change_name", web::post().to(account::change_name))
                 .route("/change_password", web::post().to(account::change_password))
-                .route("/change_email", web::post().to(account::change_email)),
+                .route("/change_email", web::post().to(account::change_email))
+                .route("/media", web::get().to(media::media_library))
+                .service(
+                    web::resource("/media/upload")
+                        .app_data(upload_payload_config)
+                        .route(web::post().to(media::upload_media)),
+                )
+                .route(
+                    "/media/{media_id}/delete",
+                    web::post().to(media::delete_uploaded_media),
+                )
+                .route(
+                    "/two_factor/enable",
+                    web::get().to(two_factor::enable_two_factor_form),
+                )
+                .route(
+                    "/two_factor/enable",
+                    web::post().to(two_factor::enable_two_factor_confirm),
+                )
+                .route(
+                    "/two_factor/disable",
+                    web::post().to(two_factor::disable_two_factor_confirm),
+                ),
+        )
+        .service(
+            web::scope("/admin")
+                .wrap(from_fn(require_admin))
+                .wrap(from_fn(require_login))
+                .route("/users", web::get().to(admin::admin_users))
+                .route("/users/{user_id}/ban", web::post().to(admin::ban))
+                .route("/users/{user_id}/unban", web::post().to(admin::unban))
+                .route("/users/{user_id}/promote", web::post().to(admin::promote))
+                .route(
+                    "/users/{user_id}/make_moderator",
+                    web::post().to(admin::make_moderator),
+                )
+                .route("/users/{user_id}/demote", web::post().to(admin::demote))
+                .route(
+                    "/users/{user_id}/logout",
+                    web::post().to(admin::force_logout),
+                ),
+        )
+        .service(
+            web::resource("/preview")
+                .wrap(from_fn(require_login))
+                .route(web::post().to(preview::preview)),
         )
+        .route("/confirm_email", web::get().to(account::confirm_email))
         .service(
             web::resource("/login")
                 .wrap(from_fn(require_non_logged))
                 .route(web::get().to(login::login_form))
                 .route(web::post().to(login::login)),
         )
+        .service(
+            web::resource("/login/two_factor")
+                .wrap(from_fn(require_non_logged))
+                .route(web::get().to(login::two_factor_form))
+                .route(web::post().to(login::two_factor_login)),
+        )
+        .service(
+            web::scope("/password_reset")
+                .wrap(from_fn(require_non_logged))
+                .route("", web::get().to(password_reset::password_reset_form))
+                .route("", web::post().to(password_reset::request_password_reset))
+                .route(
+                    "/confirm",
+                    web::get().to(password_reset::password_reset_confirm_form),
+                )
+                .route(
+                    "/confirm",
+                    web::post().to(password_reset::password_reset_confirm),
+                ),
+        )
+        .route("/feed.{format}", web::get().to(feeds::blog_feed))
         .route("/users/{user_id}", web::get().to(user_page))
+        .route(
+            "/users/{user_id}/feed.{format}",
+            web::get().to(feeds::user_feed),
+        )
+        .route(
+            "/posts/{slug}",
+            web::get().to(blog_posts::blog_post_by_slug),
+        )
+        .route(
+            "/users/{user_id}/blog_posts",
+            web::get().to(users::user_blog_posts),
+        )
+        .route(
+            "/users/{user_id}/comments",
+            web::get().to(users::user_comments),
+        )
+        .route("/search", web::get().to(search::search_page))
+        .route("/tags", web::get().to(tags::all_tags))
+        .route("/tags/{tag}", web::get().to(tags::tag))
+        .service(
+            web::scope("/projects")
+                .route("/all", web::get().to(projects::all_projects))
+                .service(
+                    web::resource("/create")
+                        .wrap(from_fn(require_login))
+                        .route(web::get().to(projects::create_project_form))
+                        .route(web::post().to(projects::create_project)),
+                )
+                .route("/{project_id}/view", web::get().to(projects::project))
+                .service(
+                    web::resource("/{project_id}/edit")
+                        .wrap(from_fn(require_login))
+                        .route(web::get().to(projects::edit_project_form))
+                        .route(web::post().to(projects::edit_project)),
+                )
+                .route(
+                    "/{project_id}/feed.{format}",
+                    web::get().to(feeds::project_feed),
+                )
+                // Slugs go last, since project pages like `/projects/all` look like them
+                .route("/{slug}", web::get().to(projects::project_by_slug)),
+        )
         .service(
             web::scope("/blog_posts")
                 .route("/all", web::get().to(blog_posts::all_blog_posts))
@@ -61,12 +182,47 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                         .route(web::get().to(blog_posts::create_blog_post_form))
                         .route(web::post().to(blog_posts::create_blog_post)),
                 )
+                .service(
+                    web::resource("/autosave")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(blog_post_autosaves::autosave_new_blog_post)),
+                )
+                .service(
+                    web::resource("/autosave/discard")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(blog_post_autosaves::discard_new_blog_post_autosave)),
+                )
                 .service(
                     web::resource("/{post_id}/edit")
                         .wrap(from_fn(require_login))
                         .route(web::get().to(blog_posts::edit_blog_post_form))
                         .route(web::post().to(blog_posts::edit_blog_post)),
                 )
+                .service(
+                    web::resource("/{post_id}/autosave")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(blog_post_autosaves::autosave_blog_post)),
+                )
+                .service(
+                    web::resource("/{post_id}/autosave/discard")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(blog_post_autosaves::discard_blog_post_autosave)),
+                )
+                .service(
+                    web::resource("/{post_id}/history")
+                        .wrap(from_fn(require_login))
+                        .route(web::get().to(blog_post_history::history)),
+                )
+                .service(
+                    web::resource("/{post_id}/diff")
+                        .wrap(from_fn(require_login))
+                        .route(web::get().to(blog_post_history::diff)),
+                )
+                .service(
+                    web::resource("/{post_id}/revisions/{number}/restore")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(blog_post_history::restore)),
+                )
                 .service(
                     web::resource("/{post_id}/comments/create")
                         .wrap(from_fn(require_login))
@@ -80,7 +236,27 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .service(
                     web::resource("/{post_id}/comments/{comment_id}/delete")
                         .wrap(from_fn(require_login))
-                        .route(web::get().to(comments::delete_comment)),
+                        .route(web::post().to(comments::delete_comment)),
+                )
+                .service(
+                    web::resource("/{post_id}/comments/{comment_id}/hide")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(comments::hide)),
+                )
+                .service(
+                    web::resource("/{post_id}/comments/{comment_id}/restore")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(comments::restore)),
+                )
+                .service(
+                    web::resource("/{post_id}/lock_comments")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(comments::lock)),
+                )
+                .service(
+                    web::resource("/{post_id}/unlock_comments")
+                        .wrap(from_fn(require_login))
+                        .route(web::post().to(comments::unlock)),
                 ),
         );
 }
@@ -0,0 +1,230 @@
+use crate::config::PasswordHashingConfig;
+use crate::domain::users::{HashedUserPassword, PasswordError, UserEmail, UserPassword, UserToken};
+use crate::mail::{Email, Mailer};
+use crate::middleware::{Messages, Session};
+use crate::services::{
+    check_password_reset_token, get_user_by_email, insert_password_reset_token, reset_password,
+    PasswordResetError, PASSWORD_RESET_LIFETIME_HOURS,
+};
+use crate::startup::ApplicationBaseUrl;
+use crate::telemetry::spawn_blocking_with_tracing;
+use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_web::error::InternalError;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama::Template;
+use secrecy::{ExposeSecret, Secret};
+use std::fmt::Formatter;
+
+#[derive(Template)]
+#[template(path = "password_reset.html")]
+struct PasswordResetTemplate<'a> {
+    messages: Messages,
+    csrf_token: &'a str,
+}
+
+#[tracing::instrument(skip(messages, session))]
+pub async fn password_reset_form(
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    render_template(PasswordResetTemplate {
+        messages: messages.into(),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum RequestPasswordResetError {
+    #[error("Invalid email")]
+    InvalidEmail(#[source] anyhow::Error),
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for RequestPasswordResetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct PasswordResetForm {
+    email: String,
+}
+
+#[tracing::instrument("Request password reset", skip(form, pool, mailer, base_url))]
+pub async fn request_password_reset(
+    form: web::Form<PasswordResetForm>,
+    pool: web::Data<Pool>,
+    mailer: web::Data<dyn Mailer>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, InternalError<RequestPasswordResetError>> {
+    let email = UserEmail::parse(form.0.email).map_err(|e| {
+        redirect_with_error(
+            "/password_reset",
+            RequestPasswordResetError::InvalidEmail(e),
+        )
+    })?;
+
+    // Response does not depend on whether account exists, so that this form
+    // can't be used to find out emails of users.
+    let user = get_user_by_email(&pool, &email).map_err(|e| {
+        redirect_with_error(
+            "/password_reset",
+            RequestPasswordResetError::UnexpectedError(e),
+        )
+    })?;
+    if let Some(user) = user {
+        let token = insert_password_reset_token(&pool, &user.id).map_err(|e| {
+            redirect_with_error(
+                "/password_reset",
+                RequestPasswordResetError::UnexpectedError(e),
+            )
+        })?;
+
+        let email = Email {
+            to: email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "To set new password for account {} follow the link:\n\
+                {}/password_reset/confirm?token={}\n\n\
+                The link expires in {} hours. If you did not request password reset, ignore this email.",
+                user.name.as_ref(),
+                base_url.0,
+                token.as_ref().expose_secret(),
+                PASSWORD_RESET_LIFETIME_HOURS
+            ),
+        };
+        let mailer = mailer.into_inner();
+        spawn_blocking_with_tracing(move || mailer.send(&email))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r)
+            .map_err(|e| {
+                redirect_with_error(
+                    "/password_reset",
+                    RequestPasswordResetError::UnexpectedError(e),
+                )
+            })?;
+    }
+
+    FlashMessage::info(
+        "If account with such email exists, password reset link has been sent to it",
+    )
+    .send();
+    Ok(see_other("/login"))
+}
+
+#[derive(Template)]
+#[template(path = "password_reset_confirm.html")]
+struct PasswordResetConfirmTemplate<'a> {
+    messages: Messages,
+    token: &'a str,
+    csrf_token: &'a str,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PasswordResetQuery {
+    token: String,
+}
+
+#[tracing::instrument(skip(query, pool, messages, session))]
+pub async fn password_reset_confirm_form(
+    query: web::Query<PasswordResetQuery>,
+    pool: web::Data<Pool>,
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let token = UserToken::parse(query.0.token)
+        .map_err(|_| redirect_with_error("/password_reset", PasswordResetError::InvalidToken))?;
+    check_password_reset_token(&pool, &token)
+        .map_err(|e| redirect_with_error("/password_reset", e))?;
+
+    render_template(PasswordResetConfirmTemplate {
+        messages: messages.into(),
+        token: token.as_ref().expose_secret(),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfirmPasswordResetError {
+    #[error("Repeat password does not match new password")]
+    RepeatPasswordDoesntMatch,
+    #[error("New password is invalid")]
+    InvalidNewPassword(#[source] PasswordError),
+    #[error(transparent)]
+    ResetError(#[from] PasswordResetError),
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ConfirmPasswordResetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct PasswordResetConfirmForm {
+    token: String,
+    new_password: Secret<String>,
+    repeat_new_password: Secret<String>,
+}
+
+#[tracing::instrument("Confirm password reset", skip(form, pool, hashing))]
+pub async fn password_reset_confirm(
+    form: web::Form<PasswordResetConfirmForm>,
+    pool: web::Data<Pool>,
+    hashing: web::Data<PasswordHashingConfig>,
+) -> Result<HttpResponse, InternalError<ConfirmPasswordResetError>> {
+    let token = UserToken::parse(form.token.clone()).map_err(|_| {
+        redirect_with_error(
+            "/password_reset",
+            ConfirmPasswordResetError::ResetError(PasswordResetError::InvalidToken),
+        )
+    })?;
+    let confirm_location = format!(
+        "/password_reset/confirm?token={}",
+        token.as_ref().expose_secret()
+    );
+
+    if form.new_password.expose_secret() != form.repeat_new_password.expose_secret() {
+        return Err(redirect_with_error(
+            &confirm_location,
+            ConfirmPasswordResetError::RepeatPasswordDoesntMatch,
+        ));
+    }
+
+    let new_password = UserPassword::parse(form.new_password.clone()).map_err(|e| {
+        redirect_with_error(
+            &confirm_location,
+            ConfirmPasswordResetError::InvalidNewPassword(e),
+        )
+    })?;
+    let hashed_new_password = {
+        let hashing = hashing.get_ref().clone();
+        spawn_blocking_with_tracing(move || HashedUserPassword::parse(&new_password, &hashing))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r)
+            .map_err(|e| {
+                redirect_with_error(
+                    &confirm_location,
+                    ConfirmPasswordResetError::UnexpectedError(e),
+                )
+            })?
+    };
+
+    reset_password(&pool, &token, &hashed_new_password)
+        .map_err(|e| redirect_with_error("/password_reset", e.into()))?;
+
+    FlashMessage::info("Your password has been reset, you can now log in").send();
+    Ok(see_other("/login"))
+}
@@ -0,0 +1,17 @@
+use crate::markdown::{parse_markdown_to_html, MarkdownContext};
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+
+#[derive(serde::Deserialize)]
+pub struct PreviewFormData {
+    contents: String,
+    context: MarkdownContext,
+}
+
+/// Renders markdown the same way as saved post or comment, so editors can show preview
+#[tracing::instrument("Preview markdown", skip(form), fields(context = ?form.context))]
+pub async fn preview(form: web::Form<PreviewFormData>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(parse_markdown_to_html(&form.contents, form.context))
+}
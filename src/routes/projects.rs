@@ -1,21 +1,285 @@
-// use crate::domain::projects::Project;
-// use actix_web_flash_messages::IncomingFlashMessages;
-// use askama::Template;
-
-// #[derive(Template)]
-// #[template(path = "projects.html")]
-// struct ProjectsTemplate {
-//     messages: IncomingFlashMessages,
-//     projects: Vec<Project>,
-// }
-
-// #[tracing::instrument("All projects", skip(pool, messages))]
-// pub async fn all_projects(
-//     pool: web::Data<Pool>,
-//     messages: IncomingFlashMessages
-// ) -> actix_web::Result<HttpResponse> {
-//     let projects = get_all_projects(&pool).map_err(e500);
-//     render_template(ProjectsTemplate{
-//
-//     })
-// }
+use crate::domain::blog_posts::BlogPost;
+use crate::domain::projects::{NewProject, Project, ProjectID, ProjectVisibility, UpdateProject};
+use crate::domain::slugs::Slug;
+use crate::domain::users::UserID;
+use crate::middleware::{Messages, Session};
+use crate::routes::error_handlers::ErrorPageTemplate;
+use crate::routes::internal::pagination::{PageQuery, Pagination};
+use crate::services::{
+    can_edit_project, can_view_blog_post, can_view_project, get_blog_post_by_id,
+    get_listed_projects, get_project_blog_post_ids, get_project_by_id, get_project_by_slug,
+    get_project_editor_ids, get_user_by_id, insert_new_project, update_project, ProjectError,
+    SlugMatch, SortOrder,
+};
+use crate::utils::{e500, moved_permanently, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_web::error::{ErrorForbidden, ErrorNotFound, InternalError};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use secrecy::ExposeSecret;
+
+/// Orders in which project listings can be sorted
+const PROJECT_ORDERS: &[SortOrder] = &[SortOrder::Newest, SortOrder::Oldest, SortOrder::Updated];
+
+#[derive(Template)]
+#[template(path = "projects.html")]
+struct ProjectsTemplate {
+    messages: Messages,
+    projects: Vec<Project>,
+    pagination: Pagination,
+    can_create: bool,
+}
+
+#[tracing::instrument("All projects", skip(pool, messages))]
+pub async fn all_projects(
+    pool: web::Data<Pool>,
+    query: web::Query<PageQuery>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> actix_web::Result<HttpResponse> {
+    let request = query.to_request(PROJECT_ORDERS)?;
+    let page = get_listed_projects(&pool, current_user_id.is_some(), &request).map_err(e500)?;
+
+    render_template(ProjectsTemplate {
+        messages: messages.into(),
+        pagination: Pagination::new("/projects/all", &request, &page, PROJECT_ORDERS),
+        projects: page.items,
+        can_create: current_user_id.is_some(),
+    })
+}
+
+struct UserInfo {
+    id: String,
+    name: String,
+}
+
+fn user_info(pool: &Pool, user_id: &UserID) -> actix_web::Result<UserInfo> {
+    let user = get_user_by_id(pool, user_id)
+        .map_err(e500)?
+        .ok_or_else(|| e500("Failed to get user"))?;
+    Ok(UserInfo {
+        id: user.id.as_ref().clone(),
+        name: user.name.as_ref().to_string(),
+    })
+}
+
+#[derive(Template)]
+#[template(path = "project.html")]
+struct ProjectTemplate<'a> {
+    messages: Messages,
+    project: &'a Project,
+    author: UserInfo,
+    editors: Vec<UserInfo>,
+    blog_posts: Vec<BlogPost>,
+    has_feed: bool,
+    can_edit: bool,
+}
+
+#[tracing::instrument("Project", skip(pool, messages))]
+pub async fn project(
+    pool: web::Data<Pool>,
+    path: web::Path<ProjectID>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> actix_web::Result<HttpResponse> {
+    let project = get_project_by_id(&pool, &path)
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("No project with such id"))?;
+    render_project(&pool, project, messages, current_user_id)
+}
+
+fn render_project(
+    pool: &Pool,
+    project: Project,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> actix_web::Result<HttpResponse> {
+    if !can_view_project(&project, current_user_id.as_ref()) {
+        return render_template(ErrorPageTemplate {
+            error_title: "Insufficient permissions",
+            error_message: "You have to be authenticated to view this project",
+            messages: messages.into(),
+        });
+    }
+
+    let can_edit = match current_user_id.as_ref() {
+        Some(user_id) => can_edit_project(pool, &project, user_id).map_err(e500)?,
+        None => false,
+    };
+    let editors = get_project_editor_ids(pool, &project.id)
+        .map_err(e500)?
+        .iter()
+        .filter(|editor_id| **editor_id != project.author_id)
+        .map(|editor_id| user_info(pool, editor_id))
+        .collect::<actix_web::Result<Vec<_>>>()?;
+    // Posts that are not published are listed only to those who can see them
+    let mut blog_posts = Vec::new();
+    for post_id in get_project_blog_post_ids(pool, &project.id).map_err(e500)? {
+        if let Some(blog_post) = get_blog_post_by_id(pool, &post_id).map_err(e500)? {
+            if can_view_blog_post(pool, &blog_post, current_user_id.as_ref()).map_err(e500)? {
+                blog_posts.push(blog_post);
+            }
+        }
+    }
+    blog_posts.sort_by(|a, b| b.created_at.as_ref().cmp(a.created_at.as_ref()));
+
+    render_template(ProjectTemplate {
+        messages: messages.into(),
+        author: user_info(pool, &project.author_id)?,
+        editors,
+        blog_posts,
+        has_feed: project.visibility == ProjectVisibility::All,
+        can_edit,
+        project: &project,
+    })
+}
+
+#[tracing::instrument("Project by slug", skip(pool, messages))]
+pub async fn project_by_slug(
+    pool: web::Data<Pool>,
+    params: web::Path<String>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> actix_web::Result<HttpResponse> {
+    let not_found = || ErrorNotFound("No project with such slug");
+    let slug = Slug::parse(&params).map_err(|_| not_found())?;
+    match get_project_by_slug(&pool, &slug)
+        .map_err(e500)?
+        .ok_or_else(not_found)?
+    {
+        SlugMatch::Current(project) => render_project(&pool, project, messages, current_user_id),
+        // Redirect would tell current slug to those who can't see the project
+        SlugMatch::Previous(project) => {
+            if !can_view_project(&project, current_user_id.as_ref()) {
+                return Err(not_found());
+            }
+            Ok(moved_permanently(&format!(
+                "/projects/{}",
+                project.slug.as_ref()
+            )))
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "edit_project.html")]
+struct EditProjectTemplate<'a> {
+    messages: Messages,
+    heading: &'a str,
+    title: &'a str,
+    brief: &'a str,
+    visible_to_all: bool,
+    action: &'a str,
+    csrf_token: &'a str,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EditProjectForm {
+    title: String,
+    brief: String,
+    visible_to_all: Option<String>,
+}
+
+impl EditProjectForm {
+    fn visibility(&self) -> ProjectVisibility {
+        if self.visible_to_all.is_some() {
+            ProjectVisibility::All
+        } else {
+            ProjectVisibility::Authenticated
+        }
+    }
+}
+
+#[tracing::instrument("Create project form", skip(messages, session))]
+pub async fn create_project_form(
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    render_template(EditProjectTemplate {
+        messages: messages.into(),
+        heading: "Create project",
+        title: "",
+        brief: "",
+        visible_to_all: true,
+        action: "/projects/create",
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[tracing::instrument("Create project", skip(pool, form))]
+pub async fn create_project(
+    pool: web::Data<Pool>,
+    form: web::Form<EditProjectForm>,
+    user_id: UserID,
+) -> Result<HttpResponse, InternalError<ProjectError>> {
+    let new_project = NewProject {
+        author_id: &user_id,
+        title: form.title.trim(),
+        brief: &form.brief,
+        visibility: form.visibility(),
+    };
+    let project = insert_new_project(&pool, &new_project)
+        .map_err(|e| redirect_with_error("/projects/create", e))?;
+    Ok(see_other(&format!(
+        "/projects/{}/view",
+        project.id.as_ref()
+    )))
+}
+
+/// Returns project if user is allowed to edit it
+fn get_editable_project(
+    pool: &Pool,
+    project_id: &ProjectID,
+    user_id: &UserID,
+) -> actix_web::Result<Project> {
+    let project = get_project_by_id(pool, project_id)
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("No project with such id"))?;
+    if !can_edit_project(pool, &project, user_id).map_err(e500)? {
+        return Err(ErrorForbidden("User is not allowed to edit project"));
+    }
+    Ok(project)
+}
+
+#[tracing::instrument("Edit project form", skip(pool, messages, session))]
+pub async fn edit_project_form(
+    pool: web::Data<Pool>,
+    path: web::Path<ProjectID>,
+    messages: IncomingFlashMessages,
+    user_id: UserID,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let project = get_editable_project(&pool, &path, &user_id)?;
+    render_template(EditProjectTemplate {
+        messages: messages.into(),
+        heading: "Edit project",
+        title: &project.title,
+        brief: &project.brief,
+        visible_to_all: project.visibility == ProjectVisibility::All,
+        action: &format!("/projects/{}/edit", project.id.as_ref()),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[tracing::instrument("Edit project", skip(pool, form))]
+pub async fn edit_project(
+    pool: web::Data<Pool>,
+    path: web::Path<ProjectID>,
+    form: web::Form<EditProjectForm>,
+    user_id: UserID,
+) -> actix_web::Result<HttpResponse> {
+    let project = get_editable_project(&pool, &path, &user_id)?;
+    let changeset = UpdateProject {
+        id: &project.id,
+        title: Some(form.title.trim()),
+        brief: Some(&form.brief),
+        visibility: Some(form.visibility()),
+    };
+    update_project(&pool, &changeset)
+        .map_err(|e| redirect_with_error(&format!("/projects/{}/edit", project.id.as_ref()), e))?;
+    Ok(see_other(&format!(
+        "/projects/{}/view",
+        project.id.as_ref()
+    )))
+}
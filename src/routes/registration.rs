@@ -1,6 +1,8 @@
-use crate::domain::users::{NewUser, PasswordError, UserName, UserPassword};
+use crate::config::PasswordHashingConfig;
+use crate::domain::users::{NewUser, PasswordError, UserEmail, UserName, UserPassword};
 use crate::middleware::{Messages, Session};
 use crate::services::{insert_new_user, UserError};
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::utils::{e500, see_other};
 use crate::utils::{redirect_with_error, render_template};
 use crate::Pool;
@@ -17,6 +19,7 @@ const REGISTRATION_FORM_SESSION_KEY: &str = "registration_form";
 #[derive(serde::Serialize, serde::Deserialize)]
 struct RegistrationCache {
     name: String,
+    email: String,
 }
 
 #[derive(Template)]
@@ -24,6 +27,8 @@ struct RegistrationCache {
 struct RegistrationTemplate<'a> {
     messages: Messages,
     name: Option<&'a str>,
+    email: Option<&'a str>,
+    csrf_token: &'a str,
 }
 
 #[tracing::instrument(skip(messages, session))]
@@ -36,10 +41,13 @@ pub async fn registration_form(
         .map_err(e500)?;
 
     let name = form_data.as_ref().map(|f| f.name.as_str());
+    let email = form_data.as_ref().map(|f| f.email.as_str());
 
     render_template(RegistrationTemplate {
         messages: messages.into(),
         name,
+        email,
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
     })
 }
 
@@ -49,6 +57,10 @@ pub enum RegistrationError {
     InvalidName(#[source] anyhow::Error),
     #[error("Name is already taken")]
     TakenName,
+    #[error("Invalid email")]
+    InvalidEmail(#[source] anyhow::Error),
+    #[error("Email is already taken")]
+    TakenEmail,
     #[error("Invalid password")]
     InvalidPassword(#[source] PasswordError),
     #[error("Passwords don't match")]
@@ -68,14 +80,16 @@ impl std::fmt::Debug for RegistrationError {
 #[derive(serde::Deserialize)]
 pub struct RegistrationFormData {
     name: String,
+    email: String,
     password: Secret<String>,
     repeat_password: Secret<String>,
 }
 
-#[tracing::instrument("Registration", skip(form, pool, session))]
+#[tracing::instrument("Registration", skip(form, pool, hashing, session))]
 pub async fn registration(
     form: web::Form<RegistrationFormData>,
     pool: web::Data<Pool>,
+    hashing: web::Data<PasswordHashingConfig>,
     session: Session,
 ) -> Result<HttpResponse, InternalError<RegistrationError>> {
     let registration_redirect = |e| {
@@ -83,6 +97,7 @@ pub async fn registration(
             REGISTRATION_FORM_SESSION_KEY,
             RegistrationCache {
                 name: form.0.name.clone(),
+                email: form.0.email.clone(),
             },
         ) {
             RegistrationError::UnexpectedError(anyhow::anyhow!(
@@ -104,12 +119,23 @@ pub async fn registration(
         name: UserName::parse(&form.0.name)
             .map_err(RegistrationError::InvalidName)
             .map_err(registration_redirect)?,
+        email: UserEmail::parse(form.0.email.clone())
+            .map_err(RegistrationError::InvalidEmail)
+            .map_err(registration_redirect)?,
         password: UserPassword::parse(form.0.password)
             .map_err(RegistrationError::InvalidPassword)
             .map_err(registration_redirect)?,
     };
 
-    match insert_new_user(&pool, &new_user) {
+    let pool = pool.get_ref().clone();
+    let hashing = hashing.get_ref().clone();
+    let insert_result =
+        spawn_blocking_with_tracing(move || insert_new_user(&pool, &new_user, &hashing))
+            .await
+            .map_err(|e| RegistrationError::UnexpectedError(anyhow::Error::new(e)))
+            .map_err(registration_redirect)?;
+
+    match insert_result {
         Ok(user) => {
             session.renew();
             session
@@ -120,6 +146,7 @@ pub async fn registration(
         }
         Err(e) => match e {
             UserError::TakenName => Err(registration_redirect(RegistrationError::TakenName)),
+            UserError::TakenEmail => Err(registration_redirect(RegistrationError::TakenEmail)),
             _ => Err(registration_redirect(RegistrationError::UnexpectedError(
                 e.into(),
             ))),
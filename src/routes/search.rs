@@ -0,0 +1,52 @@
+use crate::domain::search::{SearchQuery, SearchResults};
+use crate::domain::users::UserID;
+use crate::middleware::Messages;
+use crate::services::search;
+use crate::utils::{e500, render_template};
+use crate::Pool;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+
+#[derive(Template)]
+#[template(path = "search.html")]
+struct SearchTemplate<'a> {
+    messages: Messages,
+    query: &'a str,
+    error: Option<String>,
+    results: SearchResults,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SearchParams {
+    q: Option<String>,
+}
+
+#[tracing::instrument("Search", skip(pool, messages))]
+pub async fn search_page(
+    pool: web::Data<Pool>,
+    params: web::Query<SearchParams>,
+    current_user_id: Option<UserID>,
+    messages: IncomingFlashMessages,
+) -> actix_web::Result<HttpResponse> {
+    let query = params.q.as_deref().unwrap_or("").trim();
+    // Page without query only shows search form
+    let (results, error) = if query.is_empty() {
+        (SearchResults::default(), None)
+    } else {
+        match SearchQuery::parse(query) {
+            Ok(parsed) => (
+                search(&pool, &parsed, current_user_id.is_some()).map_err(e500)?,
+                None,
+            ),
+            Err(e) => (SearchResults::default(), Some(e.to_string())),
+        }
+    };
+
+    render_template(SearchTemplate {
+        messages: messages.into(),
+        query,
+        error,
+        results,
+    })
+}
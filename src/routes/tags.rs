@@ -0,0 +1,88 @@
+use crate::domain::blog_posts::BlogPost;
+use crate::domain::tags::TagName;
+use crate::middleware::Messages;
+use crate::routes::blog_posts::BLOG_POST_ORDERS;
+use crate::routes::internal::pagination::{PageQuery, Pagination};
+use crate::services::{get_blog_posts_by_tag, get_tag_counts};
+use crate::utils::{e500, render_template};
+use crate::Pool;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+
+struct TagCloudEntry {
+    name: TagName,
+    count: usize,
+    /// Font size in `em`, more popular tags are bigger
+    size: String,
+}
+
+#[derive(Template)]
+#[template(path = "tags.html")]
+struct TagsTemplate {
+    messages: Messages,
+    tags: Vec<TagCloudEntry>,
+}
+
+const MIN_TAG_SIZE: f64 = 0.9;
+const MAX_TAG_SIZE: f64 = 1.8;
+
+#[tracing::instrument("All tags", skip(pool, messages))]
+pub async fn all_tags(
+    pool: web::Data<Pool>,
+    messages: IncomingFlashMessages,
+) -> actix_web::Result<HttpResponse> {
+    let tags = get_tag_counts(&pool).map_err(e500)?;
+    let min = tags.iter().map(|(_, count)| *count).min().unwrap_or(0);
+    let max = tags.iter().map(|(_, count)| *count).max().unwrap_or(0);
+
+    render_template(TagsTemplate {
+        messages: messages.into(),
+        tags: tags
+            .into_iter()
+            .map(|(name, count)| {
+                let weight = if max > min {
+                    (count - min) as f64 / (max - min) as f64
+                } else {
+                    0.0
+                };
+                TagCloudEntry {
+                    name,
+                    count,
+                    size: format!(
+                        "{:.2}",
+                        MIN_TAG_SIZE + (MAX_TAG_SIZE - MIN_TAG_SIZE) * weight
+                    ),
+                }
+            })
+            .collect(),
+    })
+}
+
+#[derive(Template)]
+#[template(path = "tag.html")]
+struct TagTemplate {
+    messages: Messages,
+    tag: TagName,
+    blog_posts: Vec<BlogPost>,
+    pagination: Pagination,
+}
+
+#[tracing::instrument("Blog posts with tag", skip(pool, messages))]
+pub async fn tag(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    query: web::Query<PageQuery>,
+    messages: IncomingFlashMessages,
+) -> actix_web::Result<HttpResponse> {
+    let tag = TagName::parse(&path).map_err(|_| actix_web::error::ErrorNotFound("No such tag"))?;
+    let request = query.to_request(BLOG_POST_ORDERS)?;
+    let page = get_blog_posts_by_tag(&pool, &tag, &request).map_err(e500)?;
+
+    render_template(TagTemplate {
+        messages: messages.into(),
+        pagination: Pagination::new(&format!("/tags/{}", tag), &request, &page, BLOG_POST_ORDERS),
+        tag,
+        blog_posts: page.items,
+    })
+}
@@ -0,0 +1,165 @@
+use crate::domain::users::{RecoveryCode, TotpSecret, UserID};
+use crate::middleware::{Messages, Session};
+use crate::services::{disable_two_factor, enable_two_factor, get_user_by_id, use_recovery_code};
+use crate::utils::{e500, redirect_with_error, render_template, see_other};
+use crate::Pool;
+use actix_web::error::InternalError;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama::Template;
+use secrecy::{ExposeSecret, Secret};
+use std::fmt::Formatter;
+
+/// Name of the site shown in authenticator apps
+const TOTP_ISSUER: &str = "holodome";
+
+#[derive(Template)]
+#[template(path = "two_factor_enable.html")]
+struct EnableTwoFactorPage<'a> {
+    messages: Messages,
+    secret: &'a str,
+    otpauth_uri: &'a str,
+    csrf_token: &'a str,
+}
+
+#[tracing::instrument(skip(pool, messages, session))]
+pub async fn enable_two_factor_form(
+    pool: web::Data<Pool>,
+    user_id: UserID,
+    messages: IncomingFlashMessages,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let user = get_user_by_id(&pool, &user_id)
+        .map_err(e500)?
+        .ok_or_else(|| e500("Failed to get user"))?;
+    if user.totp_secret.is_some() {
+        return Ok(see_other("/account/settings"));
+    }
+
+    // Secret is kept in session until user proves that authenticator is set up
+    let secret = match session.get_totp_enrollment_secret().map_err(e500)? {
+        Some(secret) => secret,
+        None => {
+            let secret = TotpSecret::generate_random();
+            session
+                .insert_totp_enrollment_secret(&secret)
+                .map_err(e500)?;
+            secret
+        }
+    };
+
+    render_template(EnableTwoFactorPage {
+        messages: messages.into(),
+        secret: secret.as_ref().expose_secret(),
+        otpauth_uri: &secret.otpauth_uri(TOTP_ISSUER, user.name.as_ref()),
+        csrf_token: session.get_csrf_token().map_err(e500)?.expose_secret(),
+    })
+}
+
+#[derive(thiserror::Error)]
+pub enum TwoFactorSettingsError {
+    #[error("Two-factor authentication setup has expired, try again")]
+    NoPendingSecret,
+    #[error("Two-factor authentication is not enabled")]
+    NotEnabled,
+    #[error("Invalid code")]
+    InvalidCode,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for TwoFactorSettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct TwoFactorCodeForm {
+    code: Secret<String>,
+}
+
+#[derive(Template)]
+#[template(path = "two_factor_recovery_codes.html")]
+struct RecoveryCodesPage {
+    messages: Messages,
+    recovery_codes: Vec<String>,
+}
+
+#[tracing::instrument("Enable two-factor authentication", skip(form, pool, session))]
+pub async fn enable_two_factor_confirm(
+    form: web::Form<TwoFactorCodeForm>,
+    pool: web::Data<Pool>,
+    user_id: UserID,
+    session: Session,
+) -> actix_web::Result<HttpResponse> {
+    let redirect_to_enable = |e: TwoFactorSettingsError| -> actix_web::Error {
+        redirect_with_error("/account/two_factor/enable", e).into()
+    };
+    let secret = session
+        .get_totp_enrollment_secret()
+        .map_err(|e| redirect_to_enable(TwoFactorSettingsError::UnexpectedError(e)))?
+        .ok_or_else(|| redirect_to_enable(TwoFactorSettingsError::NoPendingSecret))?;
+    if !secret.verify(form.code.expose_secret()) {
+        return Err(redirect_to_enable(TwoFactorSettingsError::InvalidCode));
+    }
+
+    let recovery_codes = enable_two_factor(&pool, &user_id, &secret)
+        .map_err(|e| redirect_to_enable(TwoFactorSettingsError::UnexpectedError(e)))?;
+    session.remove_totp_enrollment_secret();
+
+    // Recovery codes are not stored in plain text, so this is the only time they are shown
+    render_template(RecoveryCodesPage {
+        messages: Messages::empty(),
+        recovery_codes: recovery_codes
+            .iter()
+            .map(|code| code.as_ref().expose_secret().clone())
+            .collect(),
+    })
+}
+
+#[tracing::instrument("Disable two-factor authentication", skip(form, pool))]
+pub async fn disable_two_factor_confirm(
+    form: web::Form<TwoFactorCodeForm>,
+    pool: web::Data<Pool>,
+    user_id: UserID,
+) -> Result<HttpResponse, InternalError<TwoFactorSettingsError>> {
+    let user = get_user_by_id(&pool, &user_id)
+        .map_err(|e| redirect_with_error_to_account(TwoFactorSettingsError::UnexpectedError(e)))?
+        .ok_or_else(|| {
+            redirect_with_error_to_account(TwoFactorSettingsError::UnexpectedError(
+                anyhow::anyhow!("Failed to get user by id"),
+            ))
+        })?;
+    let secret = user
+        .totp_secret
+        .ok_or_else(|| redirect_with_error_to_account(TwoFactorSettingsError::NotEnabled))?;
+
+    let code = form.code.expose_secret();
+    let is_valid = secret.verify(code)
+        || match RecoveryCode::parse(code) {
+            Ok(recovery_code) => {
+                use_recovery_code(&pool, &user_id, &recovery_code).map_err(|e| {
+                    redirect_with_error_to_account(TwoFactorSettingsError::UnexpectedError(e))
+                })?
+            }
+            Err(_) => false,
+        };
+    if !is_valid {
+        return Err(redirect_with_error_to_account(
+            TwoFactorSettingsError::InvalidCode,
+        ));
+    }
+
+    disable_two_factor(&pool, &user_id)
+        .map_err(|e| redirect_with_error_to_account(TwoFactorSettingsError::UnexpectedError(e)))?;
+
+    FlashMessage::info("Two-factor authentication has been disabled").send();
+    Ok(see_other("/account/settings"))
+}
+
+fn redirect_with_error_to_account<E: std::fmt::Display>(e: E) -> InternalError<E> {
+    redirect_with_error("/account/settings", e)
+}
@@ -1,11 +1,18 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostStatus};
+use crate::domain::comments::Comment;
+use crate::domain::projects::Project;
 use crate::domain::time::DateTime;
-use crate::domain::users::UserID;
+use crate::domain::users::{User, UserID, UserName};
 use crate::middleware::Messages;
+use crate::routes::blog_posts::BLOG_POST_ORDERS;
+use crate::routes::internal::pagination::{PageQuery, Pagination};
 use crate::services::{
-    get_blog_post_by_id, get_blog_posts_of_author, get_comments_of_author, get_user_by_id,
+    get_blog_post_by_id, get_blog_posts_of_author, get_comments_of_author, get_projects_of_editor,
+    get_user_by_id, get_user_by_name, PageRequest, SortOrder,
 };
 use crate::utils::{e500, render_template};
 use crate::Pool;
+use actix_web::error::ErrorNotFound;
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use askama::Template;
@@ -18,10 +25,11 @@ struct ProjectInfo<'a> {
 }
 
 struct BlogPostInfo<'a> {
-    id: &'a str,
+    slug: &'a str,
     title: &'a str,
     brief: &'a str,
     role: &'a str,
+    status: Option<String>,
 }
 
 struct CommentInfo {
@@ -34,43 +42,58 @@ struct CommentInfo {
 #[derive(Template)]
 #[template(path = "user.html")]
 struct UserPageTemplate<'a> {
+    id: &'a str,
     name: &'a str,
     projects: Vec<ProjectInfo<'a>>,
     blog_posts: Vec<BlogPostInfo<'a>>,
     comments: Vec<CommentInfo>,
+    more_blog_posts: bool,
+    more_comments: bool,
     messages: Messages,
     registered_when: &'a str,
     display_account_link: bool,
 }
 
-#[tracing::instrument("User page", skip(pool, messages))]
-pub async fn user_page(
-    pool: web::Data<Pool>,
-    path: web::Path<UserID>,
-    messages: IncomingFlashMessages,
-    current_user_id: Option<UserID>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = path.into_inner();
-    let user = get_user_by_id(&pool, &user_id)
-        .map_err(e500)?
-        .ok_or_else(|| e500("Failed to get user"))?;
+/// Number of blog posts and comments shown on user page, rest are on listing pages
+const PREVIEW_SIZE: i64 = 5;
+
+/// Orders in which comment listings can be sorted
+const COMMENT_ORDERS: &[SortOrder] = &[SortOrder::Newest, SortOrder::Oldest, SortOrder::Updated];
+
+fn project_infos<'a>(projects: &'a [Project], user_id: &UserID) -> Vec<ProjectInfo<'a>> {
+    projects
+        .iter()
+        .map(|p| ProjectInfo {
+            id: p.id.as_ref().as_str(),
+            title: p.title.as_str(),
+            brief: p.brief.as_str(),
+            role: if &p.author_id == user_id {
+                "Author"
+            } else {
+                "Editor"
+            },
+        })
+        .collect()
+}
 
-    let blog_posts = get_blog_posts_of_author(&pool, &user_id).map_err(e500)?;
-    let blog_post_infos = blog_posts
+fn blog_post_infos(blog_posts: &[BlogPost]) -> Vec<BlogPostInfo<'_>> {
+    blog_posts
         .iter()
         .map(|b| BlogPostInfo {
-            id: b.id.as_ref().as_str(),
+            slug: b.slug.as_ref().as_str(),
             title: b.title.as_str(),
             brief: b.brief.as_str(),
             role: "TODO",
+            status: (b.status != BlogPostStatus::Published).then(|| b.status.to_string()),
         })
-        .collect();
+        .collect()
+}
 
-    let comments = get_comments_of_author(&pool, &user_id).map_err(e500)?;
+fn comment_infos(pool: &Pool, comments: Vec<Comment>) -> actix_web::Result<Vec<CommentInfo>> {
     let mut comment_infos = Vec::new();
     let now = DateTime::now();
     for comment in comments {
-        let blog_post = get_blog_post_by_id(&pool, &comment.post_id)
+        let blog_post = get_blog_post_by_id(pool, &comment.post_id)
             .map_err(e500)?
             .ok_or_else(|| e500("Failed to get blog post"))?;
         comment_infos.push(CommentInfo {
@@ -80,14 +103,129 @@ pub async fn user_page(
             contents: comment.contents.clone(),
         });
     }
+    Ok(comment_infos)
+}
+
+/// Finds user by id or name, both of which can be used in user urls
+pub(crate) fn resolve_user(pool: &Pool, id_or_name: &str) -> actix_web::Result<User> {
+    let user_id = UserID::from(id_or_name.to_string());
+    if let Some(user) = get_user_by_id(pool, &user_id).map_err(e500)? {
+        return Ok(user);
+    }
+    let name = UserName::parse(id_or_name).map_err(|_| ErrorNotFound("No such user"))?;
+    get_user_by_name(pool, &name)
+        .map_err(e500)?
+        .ok_or_else(|| ErrorNotFound("No such user"))
+}
+
+#[tracing::instrument("User page", skip(pool, messages))]
+pub async fn user_page(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = resolve_user(&pool, &path)?;
+    let user_id = user.id.clone();
+
+    let is_own_page = current_user_id.as_ref() == Some(&user.id);
+    let preview = PageRequest::first(SortOrder::Newest, PREVIEW_SIZE);
+    // Author can see all of their posts, others only the published ones
+    let blog_posts =
+        get_blog_posts_of_author(&pool, &user_id, is_own_page, &preview).map_err(e500)?;
+    let comments = get_comments_of_author(&pool, &user_id, current_user_id.is_some(), &preview)
+        .map_err(e500)?;
+    let projects =
+        get_projects_of_editor(&pool, &user_id, current_user_id.is_some()).map_err(e500)?;
 
     render_template(UserPageTemplate {
+        id: user.id.as_ref().as_str(),
         name: user.name.as_ref(),
-        projects: vec![],
-        blog_posts: blog_post_infos,
-        comments: comment_infos,
+        projects: project_infos(&projects, &user_id),
+        blog_posts: blog_post_infos(&blog_posts.items),
+        more_blog_posts: blog_posts.next.is_some(),
+        more_comments: comments.next.is_some(),
+        comments: comment_infos(&pool, comments.items)?,
         messages: messages.into(),
         registered_when: user.created_at.ago().as_str(),
-        display_account_link: current_user_id.map(|it| it == user.id).unwrap_or(false),
+        display_account_link: is_own_page,
+    })
+}
+
+#[derive(Template)]
+#[template(path = "user_blog_posts.html")]
+struct UserBlogPostsTemplate<'a> {
+    id: &'a str,
+    name: &'a str,
+    blog_posts: Vec<BlogPostInfo<'a>>,
+    pagination: Pagination,
+    messages: Messages,
+}
+
+#[tracing::instrument("User blog posts", skip(pool, messages))]
+pub async fn user_blog_posts(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    query: web::Query<PageQuery>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = resolve_user(&pool, &path)?;
+    let user_id = user.id.clone();
+
+    let is_own_page = current_user_id.as_ref() == Some(&user.id);
+    let request = query.to_request(BLOG_POST_ORDERS)?;
+    let page = get_blog_posts_of_author(&pool, &user_id, is_own_page, &request).map_err(e500)?;
+
+    render_template(UserBlogPostsTemplate {
+        id: user.id.as_ref().as_str(),
+        name: user.name.as_ref(),
+        blog_posts: blog_post_infos(&page.items),
+        pagination: Pagination::new(
+            &format!("/users/{}/blog_posts", user.id.as_ref()),
+            &request,
+            &page,
+            BLOG_POST_ORDERS,
+        ),
+        messages: messages.into(),
+    })
+}
+
+#[derive(Template)]
+#[template(path = "user_comments.html")]
+struct UserCommentsTemplate<'a> {
+    id: &'a str,
+    name: &'a str,
+    comments: Vec<CommentInfo>,
+    pagination: Pagination,
+    messages: Messages,
+}
+
+#[tracing::instrument("User comments", skip(pool, messages))]
+pub async fn user_comments(
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    query: web::Query<PageQuery>,
+    messages: IncomingFlashMessages,
+    current_user_id: Option<UserID>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user = resolve_user(&pool, &path)?;
+    let user_id = user.id.clone();
+
+    let request = query.to_request(COMMENT_ORDERS)?;
+    let page = get_comments_of_author(&pool, &user_id, current_user_id.is_some(), &request)
+        .map_err(e500)?;
+
+    render_template(UserCommentsTemplate {
+        id: user.id.as_ref().as_str(),
+        name: user.name.as_ref(),
+        pagination: Pagination::new(
+            &format!("/users/{}/comments", user.id.as_ref()),
+            &request,
+            &page,
+            COMMENT_ORDERS,
+        ),
+        comments: comment_infos(&pool, page.items)?,
+        messages: messages.into(),
     })
 }
@@ -0,0 +1,819 @@
+//! Allowlist-based sanitization of HTML rendered from user markdown.
+//! Markdown may contain raw HTML, so everything that is not known to be harmless is removed:
+//! unknown tags are dropped with their contents kept, except for tags like `<script>`
+//! whose contents are dropped too, unknown attributes are dropped, and urls
+//! may only have allowed schemes.
+
+use crate::markdown::{FOOTNOTE_ID_PREFIX, HEADING_ID_PREFIX};
+use pulldown_cmark::escape::escape_html;
+
+/// Values allowed for attribute
+#[derive(Debug, PartialEq)]
+pub enum AttributeValue {
+    Any,
+    /// Relative url or url with one of schemes of policy
+    Url,
+    /// Comma separated urls with sizes, like in `srcset`. Each url is checked like `Url`.
+    UrlSet,
+    /// Classes, only ones with allowed prefixes are kept
+    Classes,
+    /// Id with one of prefixes of policy, so it can't clash with ids of page around it
+    Id,
+    OneOf(&'static [&'static str]),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SanitizePolicy {
+    /// Allowed tags with their allowed attributes
+    pub tags: &'static [(&'static str, &'static [(&'static str, AttributeValue)])],
+    pub url_schemes: &'static [&'static str],
+    /// Prefixes of allowed classes, so that content can't use classes of page styles
+    pub class_prefixes: &'static [&'static str],
+    /// Prefixes of allowed ids
+    pub id_prefixes: &'static [&'static str],
+    /// Value of `rel` attribute set on all links
+    pub link_rel: Option<&'static str>,
+}
+
+/// Tags without closing tag
+const VOID_TAGS: &[&str] = &["br", "hr", "img", "input", "source"];
+
+/// Tags that are kept only if they have attribute with given value,
+/// since they would be something else without it
+const REQUIRED_ATTRIBUTES: &[(&str, &str, &str)] = &[("input", "type", "checkbox")];
+
+/// Tags that are dropped together with their contents
+const DROPPED_WITH_CONTENTS: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "textarea", "title", "noscript", "template",
+    "svg", "math", "select", "xmp", "noembed", "noframes",
+];
+
+const TEXT_ALIGN: AttributeValue = AttributeValue::OneOf(&[
+    "text-align: left",
+    "text-align: center",
+    "text-align: right",
+]);
+
+/// Blog posts are written by users too, but they may use more of html
+pub const POST_POLICY: SanitizePolicy = SanitizePolicy {
+    tags: &[
+        (
+            "a",
+            &[
+                ("href", AttributeValue::Url),
+                ("title", AttributeValue::Any),
+                ("class", AttributeValue::Classes),
+            ],
+        ),
+        ("abbr", &[("title", AttributeValue::Any)]),
+        ("b", &[]),
+        ("blockquote", &[]),
+        ("br", &[]),
+        ("code", &[("class", AttributeValue::Classes)]),
+        ("dd", &[]),
+        ("del", &[]),
+        ("details", &[]),
+        (
+            "div",
+            &[
+                ("class", AttributeValue::Classes),
+                ("id", AttributeValue::Id),
+            ],
+        ),
+        ("dl", &[]),
+        ("dt", &[]),
+        ("em", &[]),
+        ("h1", &[("id", AttributeValue::Id)]),
+        ("h2", &[("id", AttributeValue::Id)]),
+        ("h3", &[("id", AttributeValue::Id)]),
+        ("h4", &[("id", AttributeValue::Id)]),
+        ("h5", &[("id", AttributeValue::Id)]),
+        ("h6", &[("id", AttributeValue::Id)]),
+        ("hr", &[]),
+        ("i", &[]),
+        (
+            "img",
+            &[
+                ("src", AttributeValue::Url),
+                ("srcset", AttributeValue::UrlSet),
+                ("sizes", AttributeValue::Any),
+                ("alt", AttributeValue::Any),
+                ("title", AttributeValue::Any),
+                ("loading", AttributeValue::OneOf(&["lazy", "eager"])),
+                (
+                    "decoding",
+                    AttributeValue::OneOf(&["async", "sync", "auto"]),
+                ),
+            ],
+        ),
+        (
+            "input",
+            &[
+                ("type", AttributeValue::OneOf(&["checkbox"])),
+                ("disabled", AttributeValue::Any),
+                ("checked", AttributeValue::Any),
+            ],
+        ),
+        ("kbd", &[]),
+        ("li", &[("id", AttributeValue::Id)]),
+        ("mark", &[]),
+        ("nav", &[("class", AttributeValue::Classes)]),
+        ("ol", &[("start", AttributeValue::Any)]),
+        ("p", &[]),
+        ("picture", &[]),
+        ("pre", &[("class", AttributeValue::Classes)]),
+        ("s", &[]),
+        (
+            "source",
+            &[
+                ("type", AttributeValue::OneOf(&["image/webp"])),
+                ("srcset", AttributeValue::UrlSet),
+                ("sizes", AttributeValue::Any),
+            ],
+        ),
+        (
+            "span",
+            &[
+                ("class", AttributeValue::Classes),
+                ("data-line", AttributeValue::Any),
+            ],
+        ),
+        ("strong", &[]),
+        ("sub", &[]),
+        ("summary", &[]),
+        ("sup", &[("class", AttributeValue::Classes)]),
+        ("table", &[]),
+        ("tbody", &[]),
+        ("td", &[("style", TEXT_ALIGN)]),
+        ("th", &[("style", TEXT_ALIGN)]),
+        ("thead", &[]),
+        ("tr", &[]),
+        ("ul", &[]),
+    ],
+    url_schemes: &["http", "https", "mailto"],
+    class_prefixes: &[
+        "hl-",
+        "language-",
+        "code-block",
+        "line",
+        "highlighted",
+        "footnote-",
+        "heading-anchor",
+        "table-of-contents",
+    ],
+    id_prefixes: &[HEADING_ID_PREFIX, FOOTNOTE_ID_PREFIX],
+    link_rel: None,
+};
+
+/// Comments don't get images, ids and classes except for ones of code highlighting,
+/// and their links are not endorsed
+pub const COMMENT_POLICY: SanitizePolicy = SanitizePolicy {
+    tags: &[
+        (
+            "a",
+            &[
+                ("href", AttributeValue::Url),
+                ("title", AttributeValue::Any),
+            ],
+        ),
+        ("b", &[]),
+        ("blockquote", &[]),
+        ("br", &[]),
+        ("code", &[("class", AttributeValue::Classes)]),
+        ("del", &[]),
+        ("em", &[]),
+        ("h1", &[]),
+        ("h2", &[]),
+        ("h3", &[]),
+        ("h4", &[]),
+        ("h5", &[]),
+        ("h6", &[]),
+        ("hr", &[]),
+        ("i", &[]),
+        (
+            "input",
+            &[
+                ("type", AttributeValue::OneOf(&["checkbox"])),
+                ("disabled", AttributeValue::Any),
+                ("checked", AttributeValue::Any),
+            ],
+        ),
+        ("kbd", &[]),
+        ("li", &[]),
+        ("ol", &[("start", AttributeValue::Any)]),
+        ("p", &[]),
+        ("pre", &[("class", AttributeValue::Classes)]),
+        ("s", &[]),
+        (
+            "span",
+            &[
+                ("class", AttributeValue::Classes),
+                ("data-line", AttributeValue::Any),
+            ],
+        ),
+        ("strong", &[]),
+        ("ul", &[]),
+    ],
+    url_schemes: &["http", "https", "mailto"],
+    class_prefixes: &["hl-", "language-", "code-block", "line", "highlighted"],
+    id_prefixes: &[],
+    link_rel: Some("nofollow ugc"),
+};
+
+impl SanitizePolicy {
+    fn allowed_attributes(&self, tag: &str) -> Option<&'static [(&'static str, AttributeValue)]> {
+        self.tags
+            .iter()
+            .find(|(name, _)| *name == tag)
+            .map(|(_, attributes)| *attributes)
+    }
+
+    fn is_allowed_url(&self, url: &str) -> bool {
+        // Browsers ignore whitespace and control characters in schemes
+        let url: String = url
+            .chars()
+            .filter(|c| !(c.is_ascii_whitespace() || c.is_control()))
+            .collect::<String>()
+            .to_lowercase();
+        match url.find([':', '/', '?', '#']) {
+            Some(i) if url[i..].starts_with(':') => self.url_schemes.contains(&&url[..i]),
+            _ => true,
+        }
+    }
+
+    fn allowed_classes(&self, classes: &str) -> String {
+        classes
+            .split_ascii_whitespace()
+            .filter(|class| {
+                self.class_prefixes
+                    .iter()
+                    .any(|prefix| class.starts_with(prefix))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Value of attribute that can be rendered, if attribute is allowed
+    fn sanitize_attribute(&self, tag: &str, name: &str, value: &str) -> Option<String> {
+        let (_, allowed) = self
+            .allowed_attributes(tag)?
+            .iter()
+            .find(|(allowed_name, _)| *allowed_name == name)?;
+        match allowed {
+            AttributeValue::Any => Some(value.to_string()),
+            AttributeValue::Url => self.is_allowed_url(value).then(|| value.to_string()),
+            AttributeValue::UrlSet => value
+                .split(',')
+                .all(|candidate| {
+                    let url = candidate
+                        .split_ascii_whitespace()
+                        .next()
+                        .unwrap_or_default();
+                    self.is_allowed_url(url)
+                })
+                .then(|| value.to_string()),
+            AttributeValue::Classes => {
+                Some(self.allowed_classes(value)).filter(|classes| !classes.is_empty())
+            }
+            AttributeValue::Id => self
+                .id_prefixes
+                .iter()
+                .any(|prefix| value.starts_with(prefix))
+                .then(|| value.to_string()),
+            AttributeValue::OneOf(values) => values
+                .contains(&value.to_lowercase().as_str())
+                .then(|| value.to_string()),
+        }
+    }
+}
+
+/// Tag parsed from html
+#[derive(Debug, PartialEq)]
+enum Tag {
+    Open {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    Close {
+        name: String,
+    },
+    /// Comment, doctype and such, which are dropped
+    Other,
+}
+
+pub fn sanitize_html(html: &str, policy: &SanitizePolicy) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut open_tags: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        let text_len = rest.find('<').unwrap_or(rest.len());
+        output.push_str(&rest[..text_len]);
+        i += text_len;
+        if i == html.len() {
+            break;
+        }
+
+        let (tag, len) = match parse_tag(&html[i..]) {
+            Some(parsed) => parsed,
+            None => {
+                output.push_str("&lt;");
+                i += 1;
+                continue;
+            }
+        };
+        i += len;
+        match tag {
+            Tag::Open { name, attributes } => {
+                if DROPPED_WITH_CONTENTS.contains(&name.as_str()) {
+                    i += contents_length(&html[i..], &name);
+                    continue;
+                }
+                if policy.allowed_attributes(&name).is_none()
+                    || !has_required_attributes(&name, &attributes)
+                {
+                    continue;
+                }
+                push_open_tag(&mut output, &name, &attributes, policy);
+                if !VOID_TAGS.contains(&name.as_str()) {
+                    open_tags.push(name);
+                }
+            }
+            Tag::Close { name } => {
+                if let Some(position) = open_tags.iter().rposition(|open| *open == name) {
+                    for open in open_tags.drain(position..).rev() {
+                        output.push_str(&format!("</{}>", open));
+                    }
+                }
+            }
+            Tag::Other => {}
+        }
+    }
+    for open in open_tags.into_iter().rev() {
+        output.push_str(&format!("</{}>", open));
+    }
+    output
+}
+
+fn has_required_attributes(name: &str, attributes: &[(String, String)]) -> bool {
+    REQUIRED_ATTRIBUTES
+        .iter()
+        .filter(|(tag, _, _)| *tag == name)
+        .all(|(_, attribute, required)| {
+            // Browsers use first of duplicated attributes, so does `push_open_tag`
+            attributes
+                .iter()
+                .find(|(a, _)| a == attribute)
+                .is_some_and(|(_, value)| value.eq_ignore_ascii_case(required))
+        })
+}
+
+fn push_open_tag(
+    output: &mut String,
+    name: &str,
+    attributes: &[(String, String)],
+    policy: &SanitizePolicy,
+) {
+    output.push('<');
+    output.push_str(name);
+    let mut seen = Vec::new();
+    for (attribute, value) in attributes {
+        if seen.contains(&attribute) {
+            continue;
+        }
+        seen.push(attribute);
+        if let Some(value) = policy.sanitize_attribute(name, attribute, value) {
+            output.push_str(&format!(" {}=\"", attribute));
+            escape_html(&mut *output, &value).expect("Writing to string can't fail");
+            output.push('"');
+        }
+    }
+    if let (Some(rel), "a") = (policy.link_rel, name) {
+        output.push_str(&format!(" rel=\"{}\"", rel));
+    }
+    if VOID_TAGS.contains(&name) {
+        output.push_str(" /");
+    }
+    output.push('>');
+}
+
+/// Attributes whose urls are resolved by `make_urls_absolute`
+const URL_ATTRIBUTES: &[&str] = &["href", "src"];
+const URL_SET_ATTRIBUTES: &[&str] = &["srcset"];
+
+/// Makes relative urls in sanitized html absolute, so that it can be shown outside of site,
+/// like in feed readers. Paths are resolved against base url of site, fragments like
+/// footnote links against url of page the html is from.
+pub fn make_urls_absolute(html: &str, base_url: &str, page_url: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        let text_len = rest.find('<').unwrap_or(rest.len());
+        output.push_str(&rest[..text_len]);
+        i += text_len;
+        if i == html.len() {
+            break;
+        }
+
+        let (tag, len) = match parse_tag(&html[i..]) {
+            Some(parsed) => parsed,
+            None => {
+                output.push_str("&lt;");
+                i += 1;
+                continue;
+            }
+        };
+        i += len;
+        match tag {
+            Tag::Open { name, attributes } => {
+                output.push('<');
+                output.push_str(&name);
+                for (attribute, value) in attributes {
+                    let value = if URL_ATTRIBUTES.contains(&attribute.as_str()) {
+                        absolute_url(&value, base_url, page_url)
+                    } else if URL_SET_ATTRIBUTES.contains(&attribute.as_str()) {
+                        value
+                            .split(',')
+                            .map(|candidate| {
+                                let candidate = candidate.trim();
+                                let (url, descriptor) =
+                                    candidate.split_once(' ').unwrap_or((candidate, ""));
+                                let url = absolute_url(url, base_url, page_url);
+                                format!("{} {}", url, descriptor).trim_end().to_string()
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    } else {
+                        value
+                    };
+                    output.push_str(&format!(" {}=\"", attribute));
+                    escape_html(&mut output, &value).expect("Writing to string can't fail");
+                    output.push('"');
+                }
+                if VOID_TAGS.contains(&name.as_str()) {
+                    output.push_str(" /");
+                }
+                output.push('>');
+            }
+            Tag::Close { name } => output.push_str(&format!("</{}>", name)),
+            Tag::Other => {}
+        }
+    }
+    output
+}
+
+fn absolute_url(url: &str, base_url: &str, page_url: &str) -> String {
+    let has_scheme = matches!(url.find([':', '/', '?', '#']), Some(i) if url[i..].starts_with(':'));
+    if url.is_empty() || has_scheme || url.starts_with("//") {
+        url.to_string()
+    } else if url.starts_with('/') {
+        format!("{}{}", base_url, url)
+    } else if url.starts_with('#') || url.starts_with('?') {
+        format!("{}{}", page_url, url)
+    } else {
+        let directory = page_url.rfind('/').map_or(page_url, |i| &page_url[..i + 1]);
+        format!("{}{}", directory, url)
+    }
+}
+
+/// Parses tag at start of html, returning it with its length.
+/// Returns `None` if `<` doesn't start tag and should be escaped.
+fn parse_tag(html: &str) -> Option<(Tag, usize)> {
+    let rest = &html[1..];
+    if let Some(comment) = rest.strip_prefix("!--") {
+        let len = comment.find("-->").map_or(html.len(), |i| 1 + 3 + i + 3);
+        return Some((Tag::Other, len));
+    }
+    if rest.starts_with('!') || rest.starts_with('?') {
+        let len = rest.find('>').map_or(html.len(), |i| 1 + i + 1);
+        return Some((Tag::Other, len));
+    }
+
+    let (is_close, name_start) = match rest.strip_prefix('/') {
+        Some(_) => (true, 2),
+        None => (false, 1),
+    };
+    let name_len = html[name_start..]
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(html.len() - name_start);
+    if name_len == 0 || !html[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = html[name_start..name_start + name_len].to_ascii_lowercase();
+
+    let (attributes, attributes_len) = parse_attributes(&html[name_start + name_len..])?;
+    let len = name_start + name_len + attributes_len;
+    let tag = if is_close {
+        Tag::Close { name }
+    } else {
+        Tag::Open { name, attributes }
+    };
+    Some((tag, len))
+}
+
+/// Parses attributes up to and including end of tag.
+/// Returns `None` if tag doesn't end.
+fn parse_attributes(html: &str) -> Option<(Vec<(String, String)>, usize)> {
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    loop {
+        let rest = &html[i..];
+        let c = rest.chars().next()?;
+        if c == '>' {
+            return Some((attributes, i + 1));
+        }
+        if c.is_ascii_whitespace() || c == '/' {
+            i += 1;
+            continue;
+        }
+
+        let name_len = rest
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len())
+            .max(c.len_utf8());
+        let name = rest[..name_len].to_ascii_lowercase();
+        i += name_len;
+
+        let after_name = html[i..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let value = match after_name.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start_matches(|c: char| c.is_ascii_whitespace());
+                i = html.len() - value.len();
+                match value.chars().next()? {
+                    quote @ ('"' | '\'') => {
+                        let end = value[1..].find(quote)?;
+                        i += end + 2;
+                        &value[1..end + 1]
+                    }
+                    _ => {
+                        let end = value
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(value.len());
+                        i += end;
+                        &value[..end]
+                    }
+                }
+            }
+            None => "",
+        };
+        attributes.push((name, decode_entities(value)));
+    }
+}
+
+/// Length of contents of dropped tag, including its closing tag
+fn contents_length(html: &str, name: &str) -> usize {
+    let closing = format!("</{}", name);
+    let lowercase = html.to_ascii_lowercase();
+    match lowercase.find(&closing) {
+        Some(i) => {
+            let after = i + closing.len();
+            html[after..]
+                .find('>')
+                .map_or(html.len(), |end| after + end + 1)
+        }
+        None => html.len(),
+    }
+}
+
+/// Decodes character references, so that urls like `javascript&#58;` can be checked
+fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+            .map_or(rest.len(), |end| end + 1);
+        match decode_entity(&rest[1..end]) {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end..];
+                rest = rest.strip_prefix(';').unwrap_or(rest);
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return Some(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+    Some(match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "colon" => ':',
+        "Tab" => '\t',
+        "NewLine" => '\n',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(html: &str) -> String {
+        sanitize_html(html, &POST_POLICY)
+    }
+
+    fn comment(html: &str) -> String {
+        sanitize_html(html, &COMMENT_POLICY)
+    }
+
+    #[test]
+    fn allowed_html_is_kept() {
+        let html = "<p>Some <em>text</em> &amp; <a href=\"https://example.com/?a=1&amp;b=2\">link</a></p>\n";
+        assert_eq!(post(html), html);
+    }
+
+    #[test]
+    fn scripts_are_removed_with_contents() {
+        assert_eq!(comment("<p>a<script>alert(1)</script>b</p>"), "<p>ab</p>");
+        assert_eq!(comment("<SCRIPT SRC=//evil.com/x.js></SCRIPT>"), "");
+        assert_eq!(comment("<style>body{}</style ><p>x</p>"), "<p>x</p>");
+        assert_eq!(comment("<script>never closed"), "");
+    }
+
+    #[test]
+    fn unknown_tags_are_removed_with_contents_kept() {
+        assert_eq!(comment("<form action=\"/x\"><b>a</b></form>"), "<b>a</b>");
+    }
+
+    #[test]
+    fn event_handlers_are_removed() {
+        assert_eq!(post("<img src=x onerror=alert(1)>"), "<img src=\"x\" />");
+        assert_eq!(
+            comment("<p onclick=\"alert(1)\" style=\"x\">a</p>"),
+            "<p>a</p>"
+        );
+        assert_eq!(
+            post("<a href=\"/\"onmouseover=alert(1)>a</a>"),
+            "<a href=\"/\">a</a>"
+        );
+    }
+
+    #[test]
+    fn javascript_urls_are_removed() {
+        for url in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            " javascript:alert(1)",
+            "java\tscript:alert(1)",
+            "javascript&#58;alert(1)",
+            "javascript&colon;alert(1)",
+            "&#x6A;avascript:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==",
+            "vbscript:msgbox(1)",
+        ] {
+            let html = format!("<a href=\"{}\">a</a>", url);
+            assert_eq!(post(&html), "<a>a</a>", "{}", url);
+        }
+    }
+
+    #[test]
+    fn relative_urls_are_kept() {
+        assert_eq!(
+            post("<a href=\"/posts/a:b\">a</a>"),
+            "<a href=\"/posts/a:b\">a</a>"
+        );
+        assert_eq!(post("<a href=\"#fn\">a</a>"), "<a href=\"#fn\">a</a>");
+    }
+
+    #[test]
+    fn attribute_values_are_escaped() {
+        assert_eq!(
+            post("<a title='\"><script>alert(1)</script>'>a</a>"),
+            "<a title=\"&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\">a</a>"
+        );
+    }
+
+    #[test]
+    fn comments_and_broken_tags_are_not_passed() {
+        assert_eq!(comment("<!-- <script>alert(1)</script> -->a"), "a");
+        assert_eq!(comment("a <b"), "a &lt;b");
+        assert_eq!(comment("1 < 2"), "1 &lt; 2");
+        assert_eq!(
+            comment("<img src=x onerror=alert(1)"),
+            "&lt;img src=x onerror=alert(1)"
+        );
+    }
+
+    #[test]
+    fn unclosed_tags_are_closed() {
+        assert_eq!(comment("<p><strong>a</p>"), "<p><strong>a</strong></p>");
+        assert_eq!(comment("<em>a"), "<em>a</em>");
+        assert_eq!(comment("a</div></p>"), "a");
+    }
+
+    #[test]
+    fn comment_policy_is_stricter() {
+        assert_eq!(comment("<img src=\"https://example.com/a.png\">"), "");
+        assert_eq!(comment("<h2 id=\"login\">a</h2>"), "<h2>a</h2>");
+        assert_eq!(
+            comment("<span class=\"ui modal hl-keyword\">fn</span>"),
+            "<span class=\"hl-keyword\">fn</span>"
+        );
+        assert_eq!(
+            post("<span class=\"ui label footnote-reference\">a</span>"),
+            "<span class=\"footnote-reference\">a</span>"
+        );
+        assert_eq!(post("<div class=\"ui modal\">a</div>"), "<div>a</div>");
+    }
+
+    #[test]
+    fn only_checkbox_inputs_are_kept() {
+        assert_eq!(
+            post("<input type=\"checkbox\" checked>"),
+            "<input type=\"checkbox\" checked=\"\" />"
+        );
+        assert_eq!(post("<input type=\"text\" value=\"x\">a"), "a");
+        assert_eq!(post("<input>a"), "a");
+        assert_eq!(post("<input type=\"password\" type=\"checkbox\">a"), "a");
+    }
+
+    #[test]
+    fn all_urls_of_srcset_are_checked() {
+        assert_eq!(
+            post("<img srcset=\"/a.png 480w, https://example.com/b.png 960w\">"),
+            "<img srcset=\"/a.png 480w, https://example.com/b.png 960w\" />"
+        );
+        assert_eq!(
+            post("<picture><source srcset=\"/a.png 1x, javascript:alert(1) 2x\"></picture>"),
+            "<picture><source /></picture>"
+        );
+    }
+
+    #[test]
+    fn only_prefixed_ids_are_kept() {
+        assert_eq!(
+            post("<div id=\"edit-comment-form\">a</div>"),
+            "<div>a</div>"
+        );
+        assert_eq!(
+            post("<h2 id=\"h-setup\">a</h2>"),
+            "<h2 id=\"h-setup\">a</h2>"
+        );
+        assert_eq!(post("<li id=\"fn-1\">a</li>"), "<li id=\"fn-1\">a</li>");
+    }
+
+    #[test]
+    fn comment_links_are_not_followed() {
+        assert_eq!(
+            comment("<a href=\"https://example.com\" rel=\"me\">a</a>"),
+            "<a href=\"https://example.com\" rel=\"nofollow ugc\">a</a>"
+        );
+    }
+
+    #[test]
+    fn table_alignment_is_kept() {
+        assert_eq!(
+            post("<th style=\"text-align: center\">a</th><td style=\"color: red\">b</td>"),
+            "<th style=\"text-align: center\">a</th><td>b</td>"
+        );
+    }
+
+    #[test]
+    fn relative_urls_are_made_absolute() {
+        let absolute = |html: &str| {
+            make_urls_absolute(
+                html,
+                "https://holodome.dev",
+                "https://holodome.dev/posts/post",
+            )
+        };
+        assert_eq!(
+            absolute("<p><img src=\"/media/a.png\" alt=\"a &amp; b\" /></p>"),
+            "<p><img src=\"https://holodome.dev/media/a.png\" alt=\"a &amp; b\" /></p>"
+        );
+        assert_eq!(
+            absolute("<a href=\"#fn-1\">1</a><a href=\"other\">o</a>"),
+            "<a href=\"https://holodome.dev/posts/post#fn-1\">1</a>\
+             <a href=\"https://holodome.dev/posts/other\">o</a>"
+        );
+        assert_eq!(
+            absolute("<a href=\"https://example.com/\">e</a><a href=\"mailto:a@b.c\">m</a>"),
+            "<a href=\"https://example.com/\">e</a><a href=\"mailto:a@b.c\">m</a>"
+        );
+        assert_eq!(
+            absolute("<source srcset=\"/a-480w.webp 480w, /a-960w.webp 960w\" />"),
+            "<source srcset=\"https://holodome.dev/a-480w.webp 480w, \
+             https://holodome.dev/a-960w.webp 960w\" />"
+        );
+    }
+}
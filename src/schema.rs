@@ -1,3 +1,43 @@
+table! {
+    blog_post_autosaves (user_id, slot) {
+        user_id -> Text,
+        slot -> Text,
+        title -> Text,
+        brief -> Text,
+        contents -> Text,
+        tags -> Text,
+        saved_at -> Text,
+        expires_at -> Text,
+    }
+}
+
+table! {
+    blog_post_revisions (post_id, number) {
+        post_id -> Text,
+        number -> Integer,
+        author_id -> Text,
+        title -> Text,
+        brief -> Text,
+        contents -> Text,
+        created_at -> Text,
+        tags -> Text,
+    }
+}
+
+table! {
+    blog_post_slug_history (slug) {
+        slug -> Text,
+        post_id -> Text,
+    }
+}
+
+table! {
+    blog_post_tag_junctions (post_id, tag_name) {
+        post_id -> Text,
+        tag_name -> Text,
+    }
+}
+
 table! {
     blog_posts (id) {
         id -> Text,
@@ -8,6 +48,12 @@ table! {
         created_at -> Text,
         updated_at -> Text,
         visibility -> Text,
+        comments_locked -> Bool,
+        status -> Text,
+        publish_at -> Nullable<Text>,
+        version -> Integer,
+        comment_count -> Integer,
+        slug -> Text,
     }
 }
 
@@ -27,6 +73,41 @@ table! {
         created_at -> Text,
         updated_at -> Text,
         is_deleted -> Bool,
+        is_hidden -> Bool,
+        hidden_reason -> Nullable<Text>,
+        hidden_by -> Nullable<Text>,
+        version -> Integer,
+    }
+}
+
+table! {
+    media (id) {
+        id -> Text,
+        owner_id -> Text,
+        file_name -> Text,
+        media_type -> Text,
+        size -> Integer,
+        created_at -> Text,
+        has_variants -> Bool,
+    }
+}
+
+table! {
+    password_reset_tokens (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        created_at -> Text,
+        expires_at -> Text,
+    }
+}
+
+table! {
+    pending_emails (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        email -> Text,
+        created_at -> Text,
+        expires_at -> Text,
     }
 }
 
@@ -44,6 +125,13 @@ table! {
     }
 }
 
+table! {
+    project_slug_history (slug) {
+        slug -> Text,
+        project_id -> Text,
+    }
+}
+
 table! {
     projects (id) {
         id -> Text,
@@ -51,6 +139,22 @@ table! {
         brief -> Text,
         author_id -> Text,
         visibility -> Text,
+        created_at -> Text,
+        updated_at -> Text,
+        slug -> Text,
+    }
+}
+
+table! {
+    recovery_codes (code_hash) {
+        code_hash -> Text,
+        user_id -> Text,
+    }
+}
+
+table! {
+    tags (name) {
+        name -> Text,
     }
 }
 
@@ -61,27 +165,51 @@ table! {
         email -> Text,
         created_at -> Text,
         password -> Text,
-        password_salt -> Text,
+        password_salt -> Nullable<Text>,
         is_banned -> Bool,
         role -> Text,
+        sessions_valid_after -> Nullable<Text>,
+        totp_secret -> Nullable<Text>,
+        totp_last_step -> Nullable<BigInt>,
     }
 }
 
+joinable!(blog_post_autosaves -> users (user_id));
+joinable!(blog_post_revisions -> blog_posts (post_id));
+joinable!(blog_post_revisions -> users (author_id));
+joinable!(blog_post_slug_history -> blog_posts (post_id));
+joinable!(blog_post_tag_junctions -> blog_posts (post_id));
+joinable!(blog_post_tag_junctions -> tags (tag_name));
 joinable!(blog_posts -> users (author_id));
 joinable!(comments -> blog_posts (post_id));
 joinable!(comments -> users (author_id));
+joinable!(media -> users (owner_id));
+joinable!(password_reset_tokens -> users (user_id));
+joinable!(pending_emails -> users (user_id));
 joinable!(project_blog_post_junctions -> blog_posts (post_id));
 joinable!(project_blog_post_junctions -> projects (project_id));
 joinable!(project_editor_junctions -> projects (project_id));
 joinable!(project_editor_junctions -> users (user_id));
+joinable!(project_slug_history -> projects (project_id));
 joinable!(projects -> users (author_id));
+joinable!(recovery_codes -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    blog_post_autosaves,
+    blog_post_revisions,
+    blog_post_slug_history,
+    blog_post_tag_junctions,
     blog_posts,
     check_if_migrated,
     comments,
+    media,
+    password_reset_tokens,
+    pending_emails,
     project_blog_post_junctions,
     project_editor_junctions,
+    project_slug_history,
     projects,
+    recovery_codes,
+    tags,
     users,
 );
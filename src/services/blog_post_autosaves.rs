@@ -0,0 +1,82 @@
+use crate::domain::blog_posts::{AutosaveSlot, BlogPostAutosave};
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::schema::blog_post_autosaves::dsl::*;
+use crate::Pool;
+use diesel::{replace_into, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+
+/// How long unsaved text of editor is kept
+pub const AUTOSAVE_LIFETIME_DAYS: i64 = 7;
+
+/// Text of blog post editor that is saved automatically
+pub struct AutosaveContents<'a> {
+    pub title: &'a str,
+    pub brief: &'a str,
+    pub contents: &'a str,
+    pub tags: &'a str,
+}
+
+/// Saves text of editor, replacing previous autosave of the same slot
+pub fn save_autosave(
+    pool: &Pool,
+    user: &UserID,
+    autosave_slot: AutosaveSlot,
+    text: &AutosaveContents,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    let time = DateTime::now();
+    let autosave = BlogPostAutosave {
+        user_id: user.clone(),
+        slot: autosave_slot,
+        title: text.title.to_string(),
+        brief: text.brief.to_string(),
+        contents: text.contents.to_string(),
+        tags: text.tags.to_string(),
+        saved_at: time.clone(),
+        expires_at: time.plus(chrono::Duration::days(AUTOSAVE_LIFETIME_DAYS)),
+    };
+    replace_into(blog_post_autosaves)
+        .values(&autosave)
+        .execute(&conn)?;
+    Ok(())
+}
+
+/// Returns autosave of slot unless it has expired
+pub fn get_autosave(
+    pool: &Pool,
+    user: &UserID,
+    autosave_slot: &AutosaveSlot,
+) -> Result<Option<BlogPostAutosave>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(blog_post_autosaves
+        .filter(user_id.eq(user))
+        .filter(slot.eq(autosave_slot))
+        .filter(expires_at.gt(DateTime::now()))
+        .first::<BlogPostAutosave>(&conn)
+        .optional()?)
+}
+
+/// Removes autosave, which is done when form is submitted or user discards it
+pub fn delete_autosave(
+    pool: &Pool,
+    user: &UserID,
+    autosave_slot: &AutosaveSlot,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    diesel::delete(
+        blog_post_autosaves
+            .filter(user_id.eq(user))
+            .filter(slot.eq(autosave_slot)),
+    )
+    .execute(&conn)?;
+    Ok(())
+}
+
+/// Removes expired autosaves, returns number of removed ones
+pub fn delete_expired_autosaves(pool: &Pool) -> Result<usize, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(
+        diesel::delete(blog_post_autosaves.filter(expires_at.le(DateTime::now())))
+            .execute(&conn)?,
+    )
+}
@@ -0,0 +1,105 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostID, BlogPostRevision, UpdateBlogPost};
+use crate::domain::tags::TagName;
+use crate::domain::time::DateTime;
+use crate::domain::users::{UserID, UserName};
+use crate::schema::blog_post_revisions::dsl::*;
+use crate::services::tags::load_blog_post_tags;
+use crate::services::{update_blog_post, BlogPostError};
+use crate::Pool;
+use diesel::result::Error;
+use diesel::{
+    insert_into, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl, RunQueryDsl,
+    SqliteConnection,
+};
+
+/// Saves current text and tags of blog post as new revision, unless they are the same
+/// as in latest one
+pub(crate) fn record_blog_post_revision(
+    conn: &SqliteConnection,
+    blog_post: &BlogPost,
+    editor_id: &UserID,
+) -> Result<(), Error> {
+    let post_tags = TagName::join(&load_blog_post_tags(conn, &blog_post.id)?);
+    let latest = blog_post_revisions
+        .filter(post_id.eq(&blog_post.id))
+        .order(number.desc())
+        .first::<BlogPostRevision>(conn)
+        .optional()?;
+    if let Some(latest) = latest.as_ref() {
+        if latest.title == blog_post.title
+            && latest.brief == blog_post.brief
+            && latest.contents == blog_post.contents
+            && latest.tags == post_tags
+        {
+            return Ok(());
+        }
+    }
+
+    let revision = BlogPostRevision {
+        post_id: blog_post.id.clone(),
+        number: latest.map(|it| it.number + 1).unwrap_or(1),
+        author_id: editor_id.clone(),
+        title: blog_post.title.clone(),
+        brief: blog_post.brief.clone(),
+        contents: blog_post.contents.clone(),
+        created_at: DateTime::now(),
+        tags: post_tags,
+    };
+    insert_into(blog_post_revisions)
+        .values(&revision)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Returns revisions of blog post together with names of their authors, newest first
+pub fn get_blog_post_revisions(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+) -> Result<Vec<(BlogPostRevision, UserName)>, anyhow::Error> {
+    use crate::schema::{blog_post_revisions, users};
+    let conn = pool.get()?;
+    Ok(blog_post_revisions::table
+        .filter(post_id.eq(blog_post_id))
+        .inner_join(users::table.on(users::id.eq(author_id)))
+        .order(number.desc())
+        .select((blog_post_revisions::all_columns, users::name))
+        .load::<(BlogPostRevision, UserName)>(&conn)?)
+}
+
+pub fn get_blog_post_revision(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+    revision_number: i32,
+) -> Result<Option<BlogPostRevision>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(blog_post_revisions
+        .filter(post_id.eq(blog_post_id))
+        .filter(number.eq(revision_number))
+        .first::<BlogPostRevision>(&conn)
+        .optional()?)
+}
+
+/// Brings text and tags of blog post back to ones from given revision. Previous revisions
+/// are kept, restored text becomes the newest revision.
+pub fn restore_blog_post_revision(
+    pool: &Pool,
+    revision: &BlogPostRevision,
+    editor_id: &UserID,
+) -> Result<(), BlogPostError> {
+    let revision_tags = TagName::parse_list(&revision.tags)?;
+    update_blog_post(
+        pool,
+        &UpdateBlogPost {
+            id: &revision.post_id,
+            title: Some(&revision.title),
+            brief: Some(&revision.brief),
+            contents: Some(&revision.contents),
+            visibility: None,
+            status: None,
+            publish_at: None,
+        },
+        Some(&revision_tags),
+        editor_id,
+        None,
+    )
+}
@@ -1,16 +1,31 @@
-use crate::domain::blog_posts::{BlogPost, BlogPostID, NewBlogPost, UpdateBlogPost};
+use crate::domain::blog_posts::{
+    BlogPost, BlogPostID, BlogPostStatus, NewBlogPost, UpdateBlogPost,
+};
+use crate::domain::slugs::Slug;
+use crate::domain::tags::TagName;
 use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 use crate::schema::blog_posts::dsl::*;
+use crate::schema::blog_posts::BoxedQuery;
+use crate::services::pagination::{paginate, time_key};
+use crate::services::slugs::{unique_blog_post_slug, update_blog_post_slug};
+use crate::services::tags::replace_blog_post_tags;
+use crate::services::{record_blog_post_revision, Cursor, Page, PageRequest, SortOrder};
 use crate::Pool;
 use diesel::result::{DatabaseErrorKind, Error};
-use diesel::{insert_into, update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::sqlite::Sqlite;
+use diesel::{
+    insert_into, update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+    SqliteConnection,
+};
 use std::fmt::Formatter;
 
 #[derive(thiserror::Error)]
 pub enum BlogPostError {
     #[error("Title is already taken")]
     TakenTitle,
+    #[error("Blog post has been changed by someone else")]
+    Conflict,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -53,7 +68,14 @@ pub fn insert_new_blog_post(
         .get()
         .map_err(|e| BlogPostError::UnexpectedError(e.into()))?;
     let time = DateTime::now();
-    let blog_post = BlogPost {
+    let publish_time = match new_blog_post.status {
+        BlogPostStatus::Published => new_blog_post
+            .publish_at
+            .clone()
+            .or_else(|| Some(time.clone())),
+        _ => new_blog_post.publish_at.clone(),
+    };
+    let mut blog_post = BlogPost {
         id: BlogPostID::generate_random(),
         title: new_blog_post.title.to_string(),
         brief: new_blog_post.brief.to_string(),
@@ -62,38 +84,166 @@ pub fn insert_new_blog_post(
         created_at: time.clone(),
         updated_at: time,
         visibility: new_blog_post.visibility.clone(),
+        comments_locked: false,
+        status: new_blog_post.status,
+        publish_at: publish_time,
+        version: 1,
+        comment_count: 0,
+        slug: Slug::from_title(new_blog_post.title),
     };
-    insert_into(blog_posts)
-        .values(&blog_post)
-        .execute(&conn)
-        .map_err(get_blog_post_error_error_from_database_error)?;
+    conn.transaction::<_, Error, _>(|| {
+        blog_post.slug = unique_blog_post_slug(&conn, &blog_post.title, None)?;
+        insert_into(blog_posts).values(&blog_post).execute(&conn)?;
+        replace_blog_post_tags(&conn, &blog_post.id, new_blog_post.tags)?;
+        record_blog_post_revision(&conn, &blog_post, new_blog_post.author_id)?;
+        Ok(())
+    })
+    .map_err(get_blog_post_error_error_from_database_error)?;
     Ok(blog_post)
 }
 
-pub fn update_blog_post(pool: &Pool, changeset: &UpdateBlogPost) -> Result<(), BlogPostError> {
+/// Updates blog post, replacing its tags if they are given. If text or tags of post change,
+/// new revision authored by editor is saved.
+/// When expected version is given, update is rejected with `BlogPostError::Conflict` if
+/// post has been changed since that version was read. Edits made by users always give it,
+/// only internal changes such as restoring revisions are applied unchecked.
+pub fn update_blog_post(
+    pool: &Pool,
+    changeset: &UpdateBlogPost,
+    new_tags: Option<&[TagName]>,
+    editor_id: &UserID,
+    expected_version: Option<i32>,
+) -> Result<(), BlogPostError> {
     let conn = pool
         .get()
         .map_err(|e| BlogPostError::UnexpectedError(e.into()))?;
-    update(blog_posts.filter(id.eq(&changeset.id)))
-        .set(changeset)
-        .execute(&conn)
+    let is_updated = conn
+        .transaction::<_, Error, _>(|| {
+            let target = blog_posts.filter(id.eq(&changeset.id));
+            let changes = (
+                changeset,
+                version.eq(version + 1),
+                updated_at.eq(DateTime::now()),
+            );
+            let updated = match expected_version {
+                Some(expected) => update(target.filter(version.eq(expected)))
+                    .set(changes)
+                    .execute(&conn)?,
+                None => update(target).set(changes).execute(&conn)?,
+            };
+            if updated == 0 {
+                return Ok(false);
+            }
+
+            if changeset.title.is_some() {
+                update_blog_post_slug(&conn, changeset.id)?;
+            }
+
+            if let Some(new_tags) = new_tags {
+                replace_blog_post_tags(&conn, changeset.id, new_tags)?;
+            }
+
+            let changes_revision = changeset.title.is_some()
+                || changeset.brief.is_some()
+                || changeset.contents.is_some()
+                || new_tags.is_some();
+            if changes_revision {
+                let blog_post = target.first::<BlogPost>(&conn)?;
+                record_blog_post_revision(&conn, &blog_post, editor_id)?;
+            }
+            Ok(true)
+        })
         .map_err(get_blog_post_error_error_from_database_error)?;
+    if !is_updated && expected_version.is_some() {
+        return Err(BlogPostError::Conflict);
+    }
     Ok(())
 }
 
+/// Returns page of blog posts of author. Unpublished posts are included only if asked for.
 pub fn get_blog_posts_of_author(
     pool: &Pool,
     author: &UserID,
-) -> Result<Vec<BlogPost>, anyhow::Error> {
+    include_unpublished: bool,
+    request: &PageRequest,
+) -> Result<Page<BlogPost>, anyhow::Error> {
     let conn = pool.get()?;
-    Ok(blog_posts
-        .filter(author_id.eq(author))
-        .load::<BlogPost>(&conn)?)
+    let mut query = blog_posts.filter(author_id.eq(author)).into_boxed();
+    if !include_unpublished {
+        query = query.filter(status.eq(BlogPostStatus::Published));
+    }
+    load_blog_post_page(&conn, query, request)
+}
+
+/// Returns page of published blog posts, that is ones that can be shown in public listings
+pub fn get_all_blog_posts(
+    pool: &Pool,
+    request: &PageRequest,
+) -> Result<Page<BlogPost>, anyhow::Error> {
+    let conn = pool.get()?;
+    let query = blog_posts
+        .filter(status.eq(BlogPostStatus::Published))
+        .into_boxed();
+    load_blog_post_page(&conn, query, request)
 }
 
-pub fn get_all_blog_posts(pool: &Pool) -> Result<Vec<BlogPost>, anyhow::Error> {
+pub(crate) fn load_blog_post_page(
+    conn: &SqliteConnection,
+    query: BoxedQuery<Sqlite>,
+    request: &PageRequest,
+) -> Result<Page<BlogPost>, anyhow::Error> {
+    let rows = match request.order {
+        SortOrder::Newest | SortOrder::Oldest => {
+            paginate!(query, request, created_at, id, time_key)
+        }
+        SortOrder::Updated => paginate!(query, request, updated_at, id, time_key),
+        SortOrder::Commented => {
+            paginate!(query, request, comment_count, id, Cursor::numeric_key)
+        }
+    }
+    .load::<BlogPost>(conn)?;
+    Ok(Page::from_rows(rows, request, |blog_post| {
+        match request.order {
+            SortOrder::Newest | SortOrder::Oldest => {
+                Cursor::new(&blog_post.created_at, &blog_post.id)
+            }
+            SortOrder::Updated => Cursor::new(&blog_post.updated_at, &blog_post.id),
+            SortOrder::Commented => Cursor::new(blog_post.comment_count, &blog_post.id),
+        }
+    }))
+}
+
+/// Publishes scheduled blog posts whose publication time has come.
+/// Returns number of published posts.
+pub fn publish_scheduled_blog_posts(pool: &Pool) -> Result<usize, anyhow::Error> {
     let conn = pool.get()?;
-    Ok(blog_posts.load::<BlogPost>(&conn)?)
+    // Single statement, so posts unscheduled in the meantime are left as they are
+    let now = DateTime::now();
+    let published = update(
+        blog_posts
+            .filter(status.eq(BlogPostStatus::Scheduled))
+            .filter(publish_at.le(&now)),
+    )
+    .set((
+        status.eq(BlogPostStatus::Published),
+        version.eq(version + 1),
+        updated_at.eq(&now),
+    ))
+    .execute(&conn)?;
+    Ok(published)
+}
+
+/// Locked blog posts don't accept new comments
+pub fn set_blog_post_comments_locked(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+    locked: bool,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(blog_posts.filter(id.eq(blog_post_id)))
+        .set(comments_locked.eq(locked))
+        .execute(&conn)?;
+    Ok(())
 }
 
 fn get_blog_post_error_error_from_database_error(e: Error) -> BlogPostError {
@@ -1,13 +1,16 @@
-use crate::domain::blog_posts::BlogPostID;
+use crate::domain::blog_posts::{BlogPostID, BlogPostStatus, BlogPostVisibility};
 use crate::domain::comments::{Comment, CommentID, CommentView, NewComment, UpdateComment};
 use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 use crate::schema::comments::dsl::*;
+use crate::services::pagination::{paginate, time_key};
+use crate::services::{Cursor, Page, PageRequest, SortOrder};
 use crate::Pool;
 use diesel::{
     insert_into, update, EqAll, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl,
     RunQueryDsl,
 };
+use std::fmt::Formatter;
 
 pub fn get_comment_by_id(
     pool: &Pool,
@@ -20,14 +23,41 @@ pub fn get_comment_by_id(
         .optional()?)
 }
 
+/// Returns page of comments of author that can be shown in public listings: ones that are
+/// not hidden or deleted, on published posts the viewer can see. Comments can't be sorted
+/// by comments, so they are sorted from newest in that case.
 pub fn get_comments_of_author(
     pool: &Pool,
     post_author_id: &UserID,
-) -> Result<Vec<Comment>, anyhow::Error> {
+    include_authenticated: bool,
+    request: &PageRequest,
+) -> Result<Page<Comment>, anyhow::Error> {
+    use crate::schema::blog_posts;
     let conn = pool.get()?;
-    Ok(comments
+    let mut visible_posts = blog_posts::table
+        .filter(blog_posts::status.eq(BlogPostStatus::Published))
+        .select(blog_posts::id)
+        .into_boxed();
+    if !include_authenticated {
+        visible_posts = visible_posts.filter(blog_posts::visibility.eq(BlogPostVisibility::All));
+    }
+    let query = comments
         .filter(author_id.eq(post_author_id))
-        .load::<Comment>(&conn)?)
+        .filter(is_hidden.eq(false))
+        .filter(is_deleted.eq(false))
+        .filter(post_id.eq_any(visible_posts))
+        .into_boxed();
+    let rows = match request.order {
+        SortOrder::Updated => paginate!(query, request, updated_at, id, time_key),
+        _ => paginate!(query, request, created_at, id, time_key),
+    }
+    .load::<Comment>(&conn)?;
+    Ok(Page::from_rows(rows, request, |comment| {
+        match request.order {
+            SortOrder::Updated => Cursor::new(&comment.updated_at, &comment.id),
+            _ => Cursor::new(&comment.created_at, &comment.id),
+        }
+    }))
 }
 
 pub fn get_comments_for_blog_post(
@@ -60,15 +90,54 @@ pub fn get_comment_views_for_blog_post(
             created_at,
             updated_at,
             is_deleted,
+            is_hidden,
+            hidden_reason,
+            version,
         ))
         .load::<CommentView>(&conn)?)
 }
 
-pub fn update_comment(pool: &Pool, changeset: &UpdateComment) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    update(comments.filter(id.eq(&changeset.id)))
-        .set(changeset)
-        .execute(&conn)?;
+#[derive(thiserror::Error)]
+pub enum CommentError {
+    #[error("Comment has been changed by someone else")]
+    Conflict,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for CommentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Updates comment. When expected version is given, update is rejected with
+/// `CommentError::Conflict` if comment has been changed since that version was read.
+/// Edits of contents always give it, deletion is applied unchecked.
+pub fn update_comment(
+    pool: &Pool,
+    changeset: &UpdateComment,
+    expected_version: Option<i32>,
+) -> Result<(), CommentError> {
+    let conn = pool.get().map_err(anyhow::Error::new)?;
+    let target = comments.filter(id.eq(&changeset.id));
+    let changes = (
+        changeset,
+        version.eq(version + 1),
+        updated_at.eq(DateTime::now()),
+    );
+    let updated = match expected_version {
+        Some(expected) => update(target.filter(version.eq(expected)))
+            .set(changes)
+            .execute(&conn),
+        None => update(target).set(changes).execute(&conn),
+    }
+    .map_err(anyhow::Error::new)?;
+    if updated == 0 && expected_version.is_some() {
+        return Err(CommentError::Conflict);
+    }
     Ok(())
 }
 
@@ -84,7 +153,41 @@ pub fn insert_new_comment(pool: &Pool, new_comment: &NewComment) -> Result<Comme
         created_at: time.clone(),
         updated_at: time,
         is_deleted: false,
+        is_hidden: false,
+        hidden_reason: None,
+        hidden_by: None,
+        version: 1,
     };
     insert_into(comments).values(&comment).execute(&conn)?;
     Ok(comment)
 }
+
+/// Hides comment from everyone except moderators. Comment stays in thread so replies to it are kept.
+pub fn hide_comment(
+    pool: &Pool,
+    comment_id: &CommentID,
+    moderator_id: &UserID,
+    reason: &str,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(comments.filter(id.eq(comment_id)))
+        .set((
+            is_hidden.eq(true),
+            hidden_reason.eq(Some(reason)),
+            hidden_by.eq(Some(moderator_id)),
+        ))
+        .execute(&conn)?;
+    Ok(())
+}
+
+pub fn restore_comment(pool: &Pool, comment_id: &CommentID) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(comments.filter(id.eq(comment_id)))
+        .set((
+            is_hidden.eq(false),
+            hidden_reason.eq(None::<String>),
+            hidden_by.eq(None::<UserID>),
+        ))
+        .execute(&conn)?;
+    Ok(())
+}
@@ -11,7 +11,7 @@ pub fn get_stored_credentials(
     let conn = pool.get()?;
     Ok(users
         .filter(name.eq(username.as_ref().as_str()))
-        .select((name, password, password_salt, id))
+        .select((name, password, password_salt, id, is_banned))
         .first::<StoredCredentials>(&conn)
         .optional()?)
 }
@@ -0,0 +1,47 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostStatus, BlogPostVisibility};
+use crate::domain::projects::ProjectID;
+use crate::domain::users::{UserID, UserName};
+use crate::schema::blog_posts::dsl::*;
+use crate::Pool;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+
+/// Number of latest blog posts in feed
+pub const FEED_SIZE: i64 = 20;
+
+/// Blog posts that feed is made of
+#[derive(Debug, Clone, Copy)]
+pub enum FeedSource<'a> {
+    All,
+    Author(&'a UserID),
+    Project(&'a ProjectID),
+}
+
+/// Returns latest published blog posts of source with names of their authors.
+/// Feeds are read anonymously, so only posts visible to everyone are included.
+pub fn get_feed_blog_posts(
+    pool: &Pool,
+    source: FeedSource,
+) -> Result<Vec<(BlogPost, UserName)>, anyhow::Error> {
+    use crate::schema::project_blog_post_junctions as junctions;
+    use crate::schema::users;
+    let conn = pool.get()?;
+    let mut query = blog_posts
+        .inner_join(users::table)
+        .filter(status.eq(BlogPostStatus::Published))
+        .filter(visibility.eq(BlogPostVisibility::All))
+        .select((crate::schema::blog_posts::all_columns, users::name))
+        .order((publish_at.desc(), id.desc()))
+        .limit(FEED_SIZE)
+        .into_boxed();
+    match source {
+        FeedSource::All => {}
+        FeedSource::Author(author) => query = query.filter(author_id.eq(author)),
+        FeedSource::Project(project) => {
+            let project_posts = junctions::table
+                .filter(junctions::project_id.eq(project))
+                .select(junctions::post_id);
+            query = query.filter(id.eq_any(project_posts));
+        }
+    }
+    Ok(query.load::<(BlogPost, UserName)>(&conn)?)
+}
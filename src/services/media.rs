@@ -0,0 +1,229 @@
+use crate::domain::media::{Media, MediaID, MediaType};
+use crate::domain::time::DateTime;
+use crate::domain::users::UserID;
+use crate::media::{
+    generate_variants, has_variants as type_has_variants, strip_metadata, variant_keys, MediaStore,
+};
+use crate::schema::media::dsl::*;
+use crate::services::pagination::{paginate, time_key};
+use crate::services::{Cursor, Page, PageRequest};
+use crate::Pool;
+use diesel::{
+    insert_into, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection,
+};
+use std::fmt::Formatter;
+
+#[derive(thiserror::Error)]
+pub enum MediaError {
+    #[error("No file was uploaded")]
+    NoFile,
+    #[error("File is empty")]
+    EmptyFile,
+    #[error("File is too large, maximum size is {0}")]
+    TooLarge(String),
+    #[error("Only PNG, JPEG, GIF and WebP images and PDF documents can be uploaded")]
+    UnsupportedType,
+    #[error("Image is damaged or too large")]
+    InvalidImage,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for MediaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Human-readable size, like `1.5 MB`
+pub fn format_file_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Checks uploaded file and saves it to store. Type of file is determined from its contents.
+/// Metadata, like location where photo was taken, is removed from images before saving.
+/// Images also get resized copies, see `media::generate_variants`.
+#[tracing::instrument(
+    "Save uploaded media",
+    skip(pool, store, data),
+    fields(size = data.len())
+)]
+pub fn save_media(
+    pool: &Pool,
+    store: &dyn MediaStore,
+    owner: &UserID,
+    name: &str,
+    data: &[u8],
+    max_size: usize,
+) -> Result<Media, MediaError> {
+    if data.is_empty() {
+        return Err(MediaError::EmptyFile);
+    }
+    if data.len() > max_size {
+        return Err(MediaError::TooLarge(format_file_size(max_size)));
+    }
+    let sniffed_type = MediaType::sniff(data).ok_or(MediaError::UnsupportedType)?;
+    let stripped = strip_metadata(sniffed_type, data);
+
+    let new_media = Media {
+        id: MediaID::generate_random(),
+        owner_id: owner.clone(),
+        file_name: clean_file_name(name, sniffed_type),
+        media_type: sniffed_type,
+        size: stripped.len() as i32,
+        created_at: DateTime::now(),
+        has_variants: type_has_variants(sniffed_type),
+    };
+    let mut files = vec![(new_media.storage_key(), stripped)];
+    if new_media.has_variants {
+        // Original is decoded, since it still has orientation of photo
+        let variants = generate_variants(&new_media.storage_key(), data).map_err(|e| {
+            tracing::warn!("Failed to make resized copies of image: {:?}", e);
+            MediaError::InvalidImage
+        })?;
+        files.extend(variants);
+    }
+
+    let saved = save_files(store, &files).and_then(|_| {
+        let conn = pool.get()?;
+        insert_into(media).values(&new_media).execute(&conn)?;
+        Ok(())
+    });
+    if let Err(e) = saved {
+        for (key, _) in &files {
+            if let Err(delete_error) = store.delete(key) {
+                tracing::error!("Failed to delete orphaned media: {:?}", delete_error);
+            }
+        }
+        return Err(e.into());
+    }
+    Ok(new_media)
+}
+
+fn save_files(store: &dyn MediaStore, files: &[(String, Vec<u8>)]) -> Result<(), anyhow::Error> {
+    for (key, data) in files {
+        store.save(key, data)?;
+    }
+    Ok(())
+}
+
+/// Makes resized copies of images that were uploaded before copies were made on upload.
+/// Images that can't be read are left without copies and tried again on next start.
+pub fn generate_missing_media_variants(
+    conn: &SqliteConnection,
+    store: &dyn MediaStore,
+) -> Result<(), anyhow::Error> {
+    let missing = media
+        .filter(has_variants.eq(false))
+        .load::<Media>(conn)?
+        .into_iter()
+        .filter(|item| type_has_variants(item.media_type));
+    for item in missing {
+        let key = item.storage_key();
+        let generated = store
+            .load(&key)
+            .and_then(|data| generate_variants(&key, &data))
+            .and_then(|variants| save_files(store, &variants));
+        match generated {
+            Ok(()) => {
+                diesel::update(media.filter(id.eq(&item.id)))
+                    .set(has_variants.eq(true))
+                    .execute(conn)?;
+            }
+            Err(e) => tracing::error!("Failed to make resized copies of {}: {:?}", key, e),
+        }
+    }
+    Ok(())
+}
+
+/// Removes directories and control characters from name of uploaded file
+fn clean_file_name(name: &str, media_type_: MediaType) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).take(255).collect();
+    let name = name.trim();
+    if name.is_empty() {
+        format!("file.{}", media_type_.extension())
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn get_media_by_id(pool: &Pool, media_id: &MediaID) -> Result<Option<Media>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(media
+        .filter(id.eq(media_id))
+        .first::<Media>(&conn)
+        .optional()?)
+}
+
+/// Returns page of files uploaded by user. Media can only be sorted by upload time.
+pub fn get_media_of_owner(
+    pool: &Pool,
+    owner: &UserID,
+    request: &PageRequest,
+) -> Result<Page<Media>, anyhow::Error> {
+    let conn = pool.get()?;
+    let query = media.filter(owner_id.eq(owner)).into_boxed();
+    let rows = paginate!(query, request, created_at, id, time_key).load::<Media>(&conn)?;
+    Ok(Page::from_rows(rows, request, |item| {
+        Cursor::new(&item.created_at, &item.id)
+    }))
+}
+
+/// Deletes file from database and store. Posts that reference it are left with broken links.
+#[tracing::instrument("Delete media", skip(pool, store, item), fields(id = %item.id))]
+pub fn delete_media(
+    pool: &Pool,
+    store: &dyn MediaStore,
+    item: &Media,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    diesel::delete(media.filter(id.eq(&item.id))).execute(&conn)?;
+    // Copies may be partly saved even if image doesn't have them all
+    if type_has_variants(item.media_type) {
+        for key in variant_keys(&item.storage_key()) {
+            store.delete(&key)?;
+        }
+    }
+    store.delete(&item.storage_key())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_sizes_are_formatted() {
+        assert_eq!(format_file_size(0), "0 B");
+        assert_eq!(format_file_size(1023), "1023 B");
+        assert_eq!(format_file_size(1536), "1.5 KB");
+        assert_eq!(format_file_size(10 * 1024 * 1024), "10.0 MB");
+    }
+
+    #[test]
+    fn file_names_are_cleaned() {
+        assert_eq!(clean_file_name("cat.png", MediaType::Png), "cat.png");
+        assert_eq!(
+            clean_file_name("C:\\Users\\me\\cat.png", MediaType::Png),
+            "cat.png"
+        );
+        assert_eq!(
+            clean_file_name("../../etc/passwd", MediaType::Pdf),
+            "passwd"
+        );
+        assert_eq!(clean_file_name("a\nb\0.gif", MediaType::Gif), "ab.gif");
+        assert_eq!(clean_file_name("dir/", MediaType::Jpeg), "file.jpg");
+    }
+}
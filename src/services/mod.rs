@@ -1,11 +1,35 @@
+mod blog_post_autosaves;
+mod blog_post_revisions;
 mod blog_posts;
 mod comments;
 mod credentials;
+mod feeds;
+mod media;
+mod pagination;
+mod password_resets;
+mod pending_emails;
+mod permissions;
 mod projects;
+mod search;
+mod slugs;
+mod tags;
+mod two_factor;
 mod users;
 
+pub use blog_post_autosaves::*;
+pub use blog_post_revisions::*;
 pub use blog_posts::*;
 pub use comments::*;
 pub use credentials::*;
+pub use feeds::*;
+pub use media::*;
+pub use pagination::*;
+pub use password_resets::*;
+pub use pending_emails::*;
+pub use permissions::*;
 pub use projects::*;
+pub use search::*;
+pub use slugs::*;
+pub use tags::*;
+pub use two_factor::*;
 pub use users::*;
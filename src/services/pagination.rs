@@ -0,0 +1,287 @@
+/// Order in which listings can be sorted
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Newest,
+    Oldest,
+    /// Recently updated first
+    Updated,
+    /// Most commented first
+    Commented,
+}
+
+impl SortOrder {
+    /// Value of order in query string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+            SortOrder::Updated => "updated",
+            SortOrder::Commented => "commented",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Newest => "Newest",
+            SortOrder::Oldest => "Oldest",
+            SortOrder::Updated => "Recently updated",
+            SortOrder::Commented => "Most commented",
+        }
+    }
+}
+
+/// Position in listing: sort key and id of row that is at the edge of page.
+/// Id is used to break ties between rows with the same key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub key: String,
+    pub id: String,
+}
+
+impl Cursor {
+    const SEPARATOR: char = '|';
+
+    pub fn new(key: impl ToString, id: impl ToString) -> Self {
+        Self {
+            key: key.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Cursor, anyhow::Error> {
+        let (key, id) = s
+            .rsplit_once(Self::SEPARATOR)
+            .ok_or_else(|| anyhow::anyhow!("Invalid page cursor"))?;
+        Ok(Self::new(key, id))
+    }
+
+    /// Key parsed as number, used for orders by count
+    pub(crate) fn numeric_key(&self) -> Result<i32, anyhow::Error> {
+        self.key
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid page cursor"))
+    }
+
+    /// Checks that key can be compared with keys of rows sorted in given order
+    pub fn check_key(&self, order: SortOrder) -> Result<(), anyhow::Error> {
+        match order {
+            SortOrder::Commented => self.numeric_key().map(|_| ()),
+            SortOrder::Newest | SortOrder::Oldest | SortOrder::Updated => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.key, Self::SEPARATOR, self.id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PagePosition {
+    /// Page starts right after row with cursor
+    After(Cursor),
+    /// Page ends right before row with cursor
+    Before(Cursor),
+}
+
+/// Direction in which rows are read from database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Scan {
+    Ascending,
+    Descending,
+}
+
+/// Page of listing that is requested. Pages are defined by position relative to rows
+/// of neighbour pages rather than by offset, so listings that change between requests
+/// don't skip or repeat rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    pub order: SortOrder,
+    pub size: i64,
+    /// First page is returned if not set
+    pub position: Option<PagePosition>,
+}
+
+impl PageRequest {
+    pub const DEFAULT_SIZE: i64 = 10;
+    pub const MAX_SIZE: i64 = 100;
+
+    pub fn first(order: SortOrder, size: i64) -> Self {
+        Self {
+            order,
+            size: size.clamp(1, Self::MAX_SIZE),
+            position: None,
+        }
+    }
+
+    pub fn cursor(&self) -> Option<&Cursor> {
+        match &self.position {
+            Some(PagePosition::After(cursor)) | Some(PagePosition::Before(cursor)) => Some(cursor),
+            None => None,
+        }
+    }
+
+    /// Direction of scan. Pages before cursor are read backwards.
+    pub(crate) fn scan(&self) -> Scan {
+        let ascending = matches!(self.order, SortOrder::Oldest);
+        let backwards = matches!(self.position, Some(PagePosition::Before(_)));
+        if ascending != backwards {
+            Scan::Ascending
+        } else {
+            Scan::Descending
+        }
+    }
+
+    /// One row more than fits on the page is read to know whether there are more rows
+    pub(crate) fn limit(&self) -> i64 {
+        self.size + 1
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::first(SortOrder::default(), Self::DEFAULT_SIZE)
+    }
+}
+
+/// Page of listing with cursors of neighbour pages, if they exist
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub prev: Option<Cursor>,
+    pub next: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    /// Makes page from rows read in direction of `request.scan()`
+    pub(crate) fn from_rows(
+        mut rows: Vec<T>,
+        request: &PageRequest,
+        cursor_of: impl Fn(&T) -> Cursor,
+    ) -> Self {
+        let has_more = rows.len() as i64 > request.size;
+        rows.truncate(request.size as usize);
+        let (has_prev, has_next) = match request.position {
+            None => (false, has_more),
+            Some(PagePosition::After(_)) => (true, has_more),
+            Some(PagePosition::Before(_)) => {
+                rows.reverse();
+                (has_more, true)
+            }
+        };
+        Self {
+            prev: rows.first().filter(|_| has_prev).map(&cursor_of),
+            next: rows.last().filter(|_| has_next).map(&cursor_of),
+            items: rows,
+        }
+    }
+}
+
+/// Orders boxed query by `$key` and `$id` columns in direction of `$request` and
+/// skips rows up to its cursor. `$cursor_key` is key of cursor converted to type of `$key`.
+macro_rules! paginate {
+    ($query:expr, $request:expr, $key:expr, $id:expr, $cursor_key:expr) => {{
+        use diesel::{BoolExpressionMethods as _, ExpressionMethods as _, QueryDsl as _};
+        let request: &$crate::services::PageRequest = $request;
+        let query = match request.scan() {
+            $crate::services::pagination::Scan::Ascending => $query.order(($key.asc(), $id.asc())),
+            $crate::services::pagination::Scan::Descending => {
+                $query.order(($key.desc(), $id.desc()))
+            }
+        };
+        match (request.cursor(), request.scan()) {
+            (None, _) => query,
+            (Some(cursor), $crate::services::pagination::Scan::Ascending) => {
+                let key = $cursor_key(cursor)?;
+                query.filter(
+                    $key.gt(key.clone())
+                        .or($key.eq(key).and($id.gt(cursor.id.clone()))),
+                )
+            }
+            (Some(cursor), $crate::services::pagination::Scan::Descending) => {
+                let key = $cursor_key(cursor)?;
+                query.filter(
+                    $key.lt(key.clone())
+                        .or($key.eq(key).and($id.lt(cursor.id.clone()))),
+                )
+            }
+        }
+        .limit(request.limit())
+    }};
+}
+
+pub(crate) use paginate;
+
+/// Cursor key of rows ordered by time, which is stored as text in order-preserving format
+pub(crate) fn time_key(cursor: &Cursor) -> Result<String, anyhow::Error> {
+    Ok(cursor.key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(order: SortOrder, position: Option<PagePosition>) -> PageRequest {
+        PageRequest {
+            order,
+            size: 2,
+            position,
+        }
+    }
+
+    fn cursor_of(n: &i32) -> Cursor {
+        Cursor::new(n, n)
+    }
+
+    #[test]
+    fn cursor_round_trips_through_string() {
+        let cursor = Cursor::new("2022-05-01 12:00:00 UTC", "some-id");
+        assert_eq!(Cursor::parse(&cursor.to_string()).unwrap(), cursor);
+        assert!(Cursor::parse("no separator").is_err());
+    }
+
+    #[test]
+    fn backwards_pages_are_read_in_reverse_order() {
+        let before = Some(PagePosition::Before(Cursor::new(1, 1)));
+        assert_eq!(request(SortOrder::Newest, None).scan(), Scan::Descending);
+        assert_eq!(request(SortOrder::Oldest, None).scan(), Scan::Ascending);
+        assert_eq!(
+            request(SortOrder::Newest, before.clone()).scan(),
+            Scan::Ascending
+        );
+        assert_eq!(request(SortOrder::Oldest, before).scan(), Scan::Descending);
+    }
+
+    #[test]
+    fn first_page_has_only_next_cursor() {
+        let page = Page::from_rows(vec![1, 2, 3], &request(SortOrder::Newest, None), cursor_of);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.prev, None);
+        assert_eq!(page.next, Some(cursor_of(&2)));
+    }
+
+    #[test]
+    fn last_page_has_only_prev_cursor() {
+        let after = Some(PagePosition::After(cursor_of(&2)));
+        let page = Page::from_rows(vec![3], &request(SortOrder::Newest, after), cursor_of);
+        assert_eq!(page.items, vec![3]);
+        assert_eq!(page.prev, Some(cursor_of(&3)));
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn page_before_cursor_is_reversed() {
+        let before = Some(PagePosition::Before(cursor_of(&5)));
+        let page = Page::from_rows(
+            vec![4, 3, 2],
+            &request(SortOrder::Oldest, before),
+            cursor_of,
+        );
+        assert_eq!(page.items, vec![3, 4]);
+        assert_eq!(page.prev, Some(cursor_of(&3)));
+        assert_eq!(page.next, Some(cursor_of(&4)));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::domain::time::DateTime;
+use crate::domain::users::{HashedUserPassword, PasswordResetToken, UserID, UserToken};
+use crate::schema::password_reset_tokens::dsl::*;
+use crate::Pool;
+use diesel::result::Error;
+use diesel::{
+    insert_into, update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use std::fmt::Formatter;
+
+/// How long password reset link stays valid
+pub const PASSWORD_RESET_LIFETIME_HOURS: i64 = 1;
+
+#[derive(thiserror::Error)]
+pub enum PasswordResetError {
+    #[error("Password reset link is invalid or has already been used")]
+    InvalidToken,
+    #[error("Password reset link has expired")]
+    ExpiredToken,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for PasswordResetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+impl From<Error> for PasswordResetError {
+    fn from(e: Error) -> Self {
+        PasswordResetError::UnexpectedError(e.into())
+    }
+}
+
+/// Stores password reset request of user, replacing previous unused ones.
+/// Returns token that should be sent to user.
+pub fn insert_password_reset_token(pool: &Pool, user: &UserID) -> Result<UserToken, anyhow::Error> {
+    let conn = pool.get()?;
+    let token = UserToken::generate_random();
+    let time = DateTime::now();
+    let reset_token = PasswordResetToken {
+        token_hash: token.hashed(),
+        user_id: user.clone(),
+        created_at: time.clone(),
+        expires_at: time.plus(chrono::Duration::hours(PASSWORD_RESET_LIFETIME_HOURS)),
+    };
+    conn.transaction::<_, Error, _>(|| {
+        diesel::delete(password_reset_tokens.filter(user_id.eq(user))).execute(&conn)?;
+        insert_into(password_reset_tokens)
+            .values(&reset_token)
+            .execute(&conn)?;
+        Ok(())
+    })?;
+    Ok(token)
+}
+
+/// Checks that token can be used to reset password.
+pub fn check_password_reset_token(
+    pool: &Pool,
+    token: &UserToken,
+) -> Result<UserID, PasswordResetError> {
+    let conn = pool
+        .get()
+        .map_err(|e| PasswordResetError::UnexpectedError(e.into()))?;
+    let reset_token = password_reset_tokens
+        .filter(token_hash.eq(token.hashed()))
+        .first::<PasswordResetToken>(&conn)
+        .optional()?
+        .ok_or(PasswordResetError::InvalidToken)?;
+    if reset_token.expires_at.is_past() {
+        return Err(PasswordResetError::ExpiredToken);
+    }
+    Ok(reset_token.user_id)
+}
+
+/// Consumes token and sets new password of user it was issued to.
+/// All sessions of user created before the reset are revoked.
+pub fn reset_password(
+    pool: &Pool,
+    token: &UserToken,
+    new_password: &HashedUserPassword,
+) -> Result<UserID, PasswordResetError> {
+    use crate::schema::users;
+
+    let conn = pool
+        .get()
+        .map_err(|e| PasswordResetError::UnexpectedError(e.into()))?;
+    conn.transaction(|| {
+        let reset_token = password_reset_tokens
+            .filter(token_hash.eq(token.hashed()))
+            .first::<PasswordResetToken>(&conn)
+            .optional()?
+            .ok_or(PasswordResetError::InvalidToken)?;
+        if reset_token.expires_at.is_past() {
+            return Err(PasswordResetError::ExpiredToken);
+        }
+
+        diesel::delete(password_reset_tokens.filter(user_id.eq(&reset_token.user_id)))
+            .execute(&conn)?;
+        update(users::table.filter(users::id.eq(&reset_token.user_id)))
+            .set((
+                users::password.eq(new_password),
+                users::password_salt.eq(None::<String>),
+                users::sessions_valid_after.eq(Some(DateTime::now())),
+            ))
+            .execute(&conn)?;
+        Ok(reset_token.user_id)
+    })
+}
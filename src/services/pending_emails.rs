@@ -0,0 +1,109 @@
+use crate::domain::time::DateTime;
+use crate::domain::users::{PendingEmail, UserEmail, UserID, UserToken};
+use crate::schema::pending_emails::dsl::*;
+use crate::Pool;
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel::{
+    insert_into, update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use std::fmt::Formatter;
+
+/// How long email confirmation link stays valid
+pub const EMAIL_CONFIRMATION_LIFETIME_HOURS: i64 = 24;
+
+#[derive(thiserror::Error)]
+pub enum ConfirmEmailError {
+    #[error("Confirmation link is invalid or has already been used")]
+    InvalidToken,
+    #[error("Confirmation link has expired")]
+    ExpiredToken,
+    #[error("Email is already taken")]
+    TakenEmail,
+    #[error("Something went wrong")]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ConfirmEmailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::error_chain_fmt;
+
+        error_chain_fmt(self, f)
+    }
+}
+
+impl From<Error> for ConfirmEmailError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref data)
+                if data.message().contains("email") =>
+            {
+                ConfirmEmailError::TakenEmail
+            }
+            _ => ConfirmEmailError::UnexpectedError(e.into()),
+        }
+    }
+}
+
+/// Stores request to change email of user, replacing previous unconfirmed ones.
+/// Returns token that should be sent to the new address.
+pub fn insert_pending_email(
+    pool: &Pool,
+    user: &UserID,
+    new_email: &UserEmail,
+) -> Result<UserToken, anyhow::Error> {
+    let conn = pool.get()?;
+    let token = UserToken::generate_random();
+    let time = DateTime::now();
+    let pending_email = PendingEmail {
+        token_hash: token.hashed(),
+        user_id: user.clone(),
+        email: new_email.clone(),
+        created_at: time.clone(),
+        expires_at: time.plus(chrono::Duration::hours(EMAIL_CONFIRMATION_LIFETIME_HOURS)),
+    };
+    conn.transaction::<_, Error, _>(|| {
+        diesel::delete(pending_emails.filter(user_id.eq(user))).execute(&conn)?;
+        insert_into(pending_emails)
+            .values(&pending_email)
+            .execute(&conn)?;
+        Ok(())
+    })?;
+    Ok(token)
+}
+
+pub fn get_pending_email_of_user(
+    pool: &Pool,
+    user: &UserID,
+) -> Result<Option<PendingEmail>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(pending_emails
+        .filter(user_id.eq(user))
+        .first::<PendingEmail>(&conn)
+        .optional()?)
+}
+
+/// Consumes token and changes email of user it was issued to.
+pub fn confirm_pending_email(pool: &Pool, token: &UserToken) -> Result<UserID, ConfirmEmailError> {
+    use crate::schema::users;
+
+    let conn = pool
+        .get()
+        .map_err(|e| ConfirmEmailError::UnexpectedError(e.into()))?;
+    conn.transaction(|| {
+        let pending_email = pending_emails
+            .filter(token_hash.eq(token.hashed()))
+            .first::<PendingEmail>(&conn)
+            .optional()?
+            .ok_or(ConfirmEmailError::InvalidToken)?;
+        if pending_email.expires_at.is_past() {
+            return Err(ConfirmEmailError::ExpiredToken);
+        }
+
+        diesel::delete(pending_emails.filter(token_hash.eq(&pending_email.token_hash)))
+            .execute(&conn)?;
+        update(users::table.filter(users::id.eq(&pending_email.user_id)))
+            .set(users::email.eq(&pending_email.email))
+            .execute(&conn)?;
+        Ok(pending_email.user_id)
+    })
+}
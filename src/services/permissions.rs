@@ -0,0 +1,97 @@
+use crate::domain::blog_posts::BlogPost;
+use crate::domain::projects::{Project, ProjectVisibility};
+use crate::domain::users::{UserID, UserRole};
+use crate::services::get_user_by_id;
+use crate::Pool;
+use diesel::{ExpressionMethods, JoinOnDsl, QueryDsl, RunQueryDsl};
+
+/// Checks if user is allowed to edit blog post. Editing is allowed to author of the post,
+/// editors of projects the post belongs to and administrators.
+pub fn can_edit_blog_post(
+    pool: &Pool,
+    blog_post: &BlogPost,
+    user: &UserID,
+) -> Result<bool, anyhow::Error> {
+    use crate::schema::{project_blog_post_junctions, project_editor_junctions};
+
+    if &blog_post.author_id == user {
+        return Ok(true);
+    }
+
+    let is_admin = get_user_by_id(pool, user)?
+        .map(|u| u.role == UserRole::Admin)
+        .unwrap_or(false);
+    if is_admin {
+        return Ok(true);
+    }
+
+    let conn = pool.get()?;
+    let editor_projects: i64 =
+        project_blog_post_junctions::table
+            .inner_join(project_editor_junctions::table.on(
+                project_editor_junctions::project_id.eq(project_blog_post_junctions::project_id),
+            ))
+            .filter(project_blog_post_junctions::post_id.eq(&blog_post.id))
+            .filter(project_editor_junctions::user_id.eq(user))
+            .count()
+            .get_result(&conn)?;
+    Ok(editor_projects != 0)
+}
+
+/// Checks if user is allowed to view blog post. Drafts and scheduled posts can only be seen
+/// by those who can edit them.
+pub fn can_view_blog_post(
+    pool: &Pool,
+    blog_post: &BlogPost,
+    user: Option<&UserID>,
+) -> Result<bool, anyhow::Error> {
+    if blog_post.status.is_public() {
+        return Ok(true);
+    }
+    match user {
+        Some(user) => can_edit_blog_post(pool, blog_post, user),
+        None => Ok(false),
+    }
+}
+
+/// Checks if user is allowed to edit project. Editing is allowed to editors of the project,
+/// which include its author, and administrators.
+pub fn can_edit_project(
+    pool: &Pool,
+    project: &Project,
+    user: &UserID,
+) -> Result<bool, anyhow::Error> {
+    use crate::schema::project_editor_junctions;
+
+    if &project.author_id == user {
+        return Ok(true);
+    }
+
+    let is_admin = get_user_by_id(pool, user)?
+        .map(|u| u.role == UserRole::Admin)
+        .unwrap_or(false);
+    if is_admin {
+        return Ok(true);
+    }
+
+    let conn = pool.get()?;
+    let editors: i64 = project_editor_junctions::table
+        .filter(project_editor_junctions::project_id.eq(&project.id))
+        .filter(project_editor_junctions::user_id.eq(user))
+        .count()
+        .get_result(&conn)?;
+    Ok(editors != 0)
+}
+
+/// Checks if project can be seen by visitor. Some projects are shown only to logged in users.
+pub fn can_view_project(project: &Project, user: Option<&UserID>) -> bool {
+    project.visibility == ProjectVisibility::All || user.is_some()
+}
+
+/// Checks if user is allowed to hide comments and lock comment sections.
+/// Moderation is allowed to moderators and administrators.
+pub fn can_moderate_comments(pool: &Pool, user: &UserID) -> Result<bool, anyhow::Error> {
+    Ok(get_user_by_id(pool, user)?
+        .map(|u| matches!(u.role, UserRole::Admin | UserRole::Moderator))
+        .unwrap_or(false))
+}
@@ -1,12 +1,19 @@
 use crate::domain::blog_posts::BlogPostID;
-use crate::domain::projects::{NewProject, Project, ProjectID, UpdateProject};
+use crate::domain::projects::{NewProject, Project, ProjectID, ProjectVisibility, UpdateProject};
+use crate::domain::slugs::Slug;
+use crate::domain::time::DateTime;
 use crate::domain::users::UserID;
 use crate::schema::projects::dsl::*;
+use crate::schema::projects::BoxedQuery;
+use crate::services::pagination::{paginate, time_key};
+use crate::services::slugs::{unique_project_slug, update_project_slug};
+use crate::services::{Cursor, Page, PageRequest, SortOrder};
 use crate::Pool;
-use diesel::result::DatabaseErrorKind;
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel::sqlite::Sqlite;
 use diesel::{
-    insert_into, update, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
-    RunQueryDsl,
+    insert_into, update, BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension,
+    QueryDsl, RunQueryDsl, SqliteConnection,
 };
 use std::fmt::Formatter;
 
@@ -14,6 +21,8 @@ use std::fmt::Formatter;
 pub enum ProjectError {
     #[error("Title is already taken")]
     TakenTitle,
+    #[error("Title can't be empty")]
+    EmptyTitle,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -45,44 +54,120 @@ pub fn get_project_by_title(pool: &Pool, t: &str) -> Result<Option<Project>, any
         .optional()?)
 }
 
-pub fn get_all_projects(pool: &Pool) -> Result<Vec<Project>, anyhow::Error> {
+/// Returns page of projects. Projects can't be sorted by comments,
+/// so they are sorted from newest in that case.
+pub fn get_all_projects(
+    pool: &Pool,
+    request: &PageRequest,
+) -> Result<Page<Project>, anyhow::Error> {
+    let conn = pool.get()?;
+    load_project_page(&conn, projects.into_boxed(), request)
+}
+
+/// Returns page of projects for listing. Projects visible only to authenticated users
+/// are included if visitor is logged in.
+pub fn get_listed_projects(
+    pool: &Pool,
+    include_authenticated: bool,
+    request: &PageRequest,
+) -> Result<Page<Project>, anyhow::Error> {
     let conn = pool.get()?;
-    Ok(projects.load::<Project>(&conn)?)
+    let mut query = projects.into_boxed();
+    if !include_authenticated {
+        query = query.filter(visibility.eq(ProjectVisibility::All));
+    }
+    load_project_page(&conn, query, request)
+}
+
+fn load_project_page(
+    conn: &SqliteConnection,
+    query: BoxedQuery<Sqlite>,
+    request: &PageRequest,
+) -> Result<Page<Project>, anyhow::Error> {
+    let rows = match request.order {
+        SortOrder::Updated => paginate!(query, request, updated_at, id, time_key),
+        _ => paginate!(query, request, created_at, id, time_key),
+    }
+    .load::<Project>(conn)?;
+    Ok(Page::from_rows(rows, request, |project| {
+        match request.order {
+            SortOrder::Updated => Cursor::new(&project.updated_at, &project.id),
+            _ => Cursor::new(&project.created_at, &project.id),
+        }
+    }))
 }
 
 pub fn update_project(pool: &Pool, changeset: &UpdateProject) -> Result<(), ProjectError> {
+    if changeset.title.is_some_and(|t| t.trim().is_empty()) {
+        return Err(ProjectError::EmptyTitle);
+    }
     let conn = pool
         .get()
         .map_err(|e| ProjectError::UnexpectedError(e.into()))?;
-    update(projects.filter(id.eq(&changeset.id)))
-        .set(changeset)
-        .execute(&conn)
-        .map_err(get_project_error_from_database_error)?;
+    conn.transaction::<_, Error, _>(|| {
+        update(projects.filter(id.eq(&changeset.id)))
+            .set((changeset, updated_at.eq(DateTime::now())))
+            .execute(&conn)?;
+        if changeset.title.is_some() {
+            update_project_slug(&conn, changeset.id)?;
+        }
+        Ok(())
+    })
+    .map_err(get_project_error_from_database_error)?;
     Ok(())
 }
 
 pub fn insert_new_project(pool: &Pool, new_project: &NewProject) -> Result<Project, ProjectError> {
+    if new_project.title.trim().is_empty() {
+        return Err(ProjectError::EmptyTitle);
+    }
     let project = {
         let conn = pool
             .get()
             .map_err(|e| ProjectError::UnexpectedError(e.into()))?;
-        let project = Project {
+        let time = DateTime::now();
+        let mut project = Project {
             id: ProjectID::generate_random(),
             title: new_project.title.to_string(),
             brief: new_project.brief.to_string(),
             author_id: new_project.author_id.clone(),
             visibility: new_project.visibility.clone(),
+            created_at: time.clone(),
+            updated_at: time,
+            slug: Slug::from_title(new_project.title),
         };
-        insert_into(projects)
-            .values(&project)
-            .execute(&conn)
-            .map_err(get_project_error_from_database_error)?;
+        conn.transaction::<_, Error, _>(|| {
+            project.slug = unique_project_slug(&conn, &project.title, None)?;
+            insert_into(projects).values(&project).execute(&conn)?;
+            Ok(())
+        })
+        .map_err(get_project_error_from_database_error)?;
         project
     };
     add_project_editor(pool, &project.id, &project.author_id)?;
     Ok(project)
 }
 
+/// Returns projects user is editor of, from newest. Authors are editors of their projects.
+pub fn get_projects_of_editor(
+    pool: &Pool,
+    user: &UserID,
+    include_authenticated: bool,
+) -> Result<Vec<Project>, anyhow::Error> {
+    use crate::schema::project_editor_junctions;
+    let conn = pool.get()?;
+    let edited = project_editor_junctions::table
+        .filter(project_editor_junctions::user_id.eq(user))
+        .select(project_editor_junctions::project_id);
+    let mut query = projects.filter(id.eq_any(edited)).into_boxed();
+    if !include_authenticated {
+        query = query.filter(visibility.eq(ProjectVisibility::All));
+    }
+    Ok(query
+        .order((created_at.desc(), id.desc()))
+        .load::<Project>(&conn)?)
+}
+
 pub fn get_project_editor_ids(
     pool: &Pool,
     project_id_: &ProjectID,
@@ -0,0 +1,115 @@
+use crate::domain::search::{
+    BlogPostSearchHit, CommentSearchHit, ProjectSearchHit, SearchQuery, SearchResults,
+};
+use crate::Pool;
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::{sql_query, RunQueryDsl};
+
+/// Maximum number of results of each kind
+pub const SEARCH_RESULTS_LIMIT: i64 = 20;
+
+// Matches in snippets are marked with control characters, which can't be confused
+// with text of posts, and replaced with html tags after text is escaped
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+const BLOG_POSTS_QUERY: &str = "
+    select b.id as id, b.title as title,
+        snippet(blog_posts_fts, -1, char(2), char(3), '…', 24) as snippet
+    from blog_posts_fts
+    join blog_posts b on b.id = blog_posts_fts.id
+    where blog_posts_fts match ?
+        and b.status = 'published'
+        and (? or b.visibility = 'all')
+    order by blog_posts_fts.rank
+    limit ?";
+
+const COMMENTS_QUERY: &str = "
+    select c.id as id, c.post_id as post_id, b.title as post_title,
+        snippet(comments_fts, 1, char(2), char(3), '…', 24) as snippet
+    from comments_fts
+    join comments c on c.id = comments_fts.id
+    join blog_posts b on b.id = c.post_id
+    where comments_fts match ?
+        and not c.is_deleted
+        and not c.is_hidden
+        and b.status = 'published'
+        and (? or b.visibility = 'all')
+    order by comments_fts.rank
+    limit ?";
+
+const PROJECTS_QUERY: &str = "
+    select p.id as id, p.title as title,
+        snippet(projects_fts, -1, char(2), char(3), '…', 24) as snippet
+    from projects_fts
+    join projects p on p.id = projects_fts.id
+    where projects_fts match ?
+        and (? or p.visibility = 'all')
+    order by projects_fts.rank
+    limit ?";
+
+/// Searches published blog posts, comments on them and projects, best matches first.
+/// Posts and projects visible only to authenticated users are not searched otherwise.
+/// Deleted and hidden comments are never found.
+pub fn search(
+    pool: &Pool,
+    query: &SearchQuery,
+    is_authenticated: bool,
+) -> Result<SearchResults, anyhow::Error> {
+    let conn = pool.get()?;
+    let fts_match = query.to_fts_match();
+    let run = |sql: &'static str| {
+        sql_query(sql)
+            .bind::<Text, _>(fts_match.clone())
+            .bind::<Bool, _>(is_authenticated)
+            .bind::<BigInt, _>(SEARCH_RESULTS_LIMIT)
+    };
+
+    let mut results = SearchResults {
+        blog_posts: run(BLOG_POSTS_QUERY).load::<BlogPostSearchHit>(&conn)?,
+        comments: run(COMMENTS_QUERY).load::<CommentSearchHit>(&conn)?,
+        projects: run(PROJECTS_QUERY).load::<ProjectSearchHit>(&conn)?,
+    };
+    for hit in results.blog_posts.iter_mut() {
+        hit.snippet = highlight_snippet(&hit.snippet);
+    }
+    for hit in results.comments.iter_mut() {
+        hit.snippet = highlight_snippet(&hit.snippet);
+    }
+    for hit in results.projects.iter_mut() {
+        hit.snippet = highlight_snippet(&hit.snippet);
+    }
+    Ok(results)
+}
+
+/// Escapes snippet returned by database and wraps marked matches in `<mark>` tags
+fn highlight_snippet(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#x27;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_are_highlighted_and_text_is_escaped() {
+        let snippet = "<b>bold</b> \u{2}word\u{3} & more";
+        assert_eq!(
+            highlight_snippet(snippet),
+            "&lt;b&gt;bold&lt;/b&gt; <mark>word</mark> &amp; more"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostID};
+use crate::domain::projects::{Project, ProjectID};
+use crate::domain::slugs::Slug;
+use crate::Pool;
+use diesel::result::Error;
+use diesel::{
+    delete, insert_or_ignore_into, update, Connection, ExpressionMethods, OptionalExtension,
+    QueryDsl, RunQueryDsl, SqliteConnection,
+};
+
+/// Result of looking up blog post or project by slug
+#[derive(Debug)]
+pub enum SlugMatch<T> {
+    /// Slug is the current one
+    Current(T),
+    /// Slug belonged to item before it was renamed, item has to be redirected to
+    Previous(T),
+}
+
+/// Returns first of slug made from title and its suffixed variants that is not taken
+fn first_free_slug(
+    title: &str,
+    mut is_taken: impl FnMut(&Slug) -> Result<bool, Error>,
+) -> Result<Slug, Error> {
+    let base = Slug::from_title(title);
+    if !is_taken(&base)? {
+        return Ok(base);
+    }
+    let mut n = 2;
+    loop {
+        let slug = base.with_suffix(n);
+        if !is_taken(&slug)? {
+            return Ok(slug);
+        }
+        n += 1;
+    }
+}
+
+/// Makes slug for blog post with title. Slugs of other posts, current or previous, are taken.
+pub(crate) fn unique_blog_post_slug(
+    conn: &SqliteConnection,
+    title: &str,
+    post: Option<&BlogPostID>,
+) -> Result<Slug, Error> {
+    use crate::schema::blog_post_slug_history as history;
+    use crate::schema::blog_posts;
+    first_free_slug(title, |slug| {
+        let current_owner = blog_posts::table
+            .filter(blog_posts::slug.eq(slug))
+            .select(blog_posts::id)
+            .first::<BlogPostID>(conn)
+            .optional()?;
+        let previous_owner = history::table
+            .filter(history::slug.eq(slug))
+            .select(history::post_id)
+            .first::<BlogPostID>(conn)
+            .optional()?;
+        Ok(current_owner
+            .into_iter()
+            .chain(previous_owner)
+            .any(|owner| Some(&owner) != post))
+    })
+}
+
+/// Gives blog post new slug if its title has changed, keeping old one in history
+pub(crate) fn update_blog_post_slug(
+    conn: &SqliteConnection,
+    post_id: &BlogPostID,
+) -> Result<(), Error> {
+    use crate::schema::blog_post_slug_history as history;
+    use crate::schema::blog_posts;
+    let (old_slug, title) = blog_posts::table
+        .filter(blog_posts::id.eq(post_id))
+        .select((blog_posts::slug, blog_posts::title))
+        .first::<(Slug, String)>(conn)?;
+    let new_slug = unique_blog_post_slug(conn, &title, Some(post_id))?;
+    if new_slug == old_slug {
+        return Ok(());
+    }
+
+    insert_or_ignore_into(history::table)
+        .values((history::slug.eq(&old_slug), history::post_id.eq(post_id)))
+        .execute(conn)?;
+    // Post may get one of its previous slugs back
+    delete(history::table.filter(history::slug.eq(&new_slug))).execute(conn)?;
+    update(blog_posts::table.filter(blog_posts::id.eq(post_id)))
+        .set(blog_posts::slug.eq(&new_slug))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_blog_post_by_slug(
+    pool: &Pool,
+    blog_post_slug: &Slug,
+) -> Result<Option<SlugMatch<BlogPost>>, anyhow::Error> {
+    use crate::schema::blog_post_slug_history as history;
+    use crate::schema::blog_posts;
+    let conn = pool.get()?;
+    if let Some(blog_post) = blog_posts::table
+        .filter(blog_posts::slug.eq(blog_post_slug))
+        .first::<BlogPost>(&conn)
+        .optional()?
+    {
+        return Ok(Some(SlugMatch::Current(blog_post)));
+    }
+    Ok(blog_posts::table
+        .filter(
+            blog_posts::id.eq_any(
+                history::table
+                    .filter(history::slug.eq(blog_post_slug))
+                    .select(history::post_id),
+            ),
+        )
+        .first::<BlogPost>(&conn)
+        .optional()?
+        .map(SlugMatch::Previous))
+}
+
+/// Paths of project pages that would be shadowed by project with such slug
+const RESERVED_PROJECT_SLUGS: [&str; 2] = ["all", "create"];
+
+/// Makes slug for project with title. Slugs of other projects, current or previous, are taken.
+pub(crate) fn unique_project_slug(
+    conn: &SqliteConnection,
+    title: &str,
+    project: Option<&ProjectID>,
+) -> Result<Slug, Error> {
+    use crate::schema::project_slug_history as history;
+    use crate::schema::projects;
+    first_free_slug(title, |slug| {
+        if RESERVED_PROJECT_SLUGS.contains(&slug.as_ref().as_str()) {
+            return Ok(true);
+        }
+        let current_owner = projects::table
+            .filter(projects::slug.eq(slug))
+            .select(projects::id)
+            .first::<ProjectID>(conn)
+            .optional()?;
+        let previous_owner = history::table
+            .filter(history::slug.eq(slug))
+            .select(history::project_id)
+            .first::<ProjectID>(conn)
+            .optional()?;
+        Ok(current_owner
+            .into_iter()
+            .chain(previous_owner)
+            .any(|owner| Some(&owner) != project))
+    })
+}
+
+/// Gives project new slug if its title has changed, keeping old one in history
+pub(crate) fn update_project_slug(
+    conn: &SqliteConnection,
+    project_id: &ProjectID,
+) -> Result<(), Error> {
+    use crate::schema::project_slug_history as history;
+    use crate::schema::projects;
+    let (old_slug, title) = projects::table
+        .filter(projects::id.eq(project_id))
+        .select((projects::slug, projects::title))
+        .first::<(Slug, String)>(conn)?;
+    let new_slug = unique_project_slug(conn, &title, Some(project_id))?;
+    if new_slug == old_slug {
+        return Ok(());
+    }
+
+    insert_or_ignore_into(history::table)
+        .values((
+            history::slug.eq(&old_slug),
+            history::project_id.eq(project_id),
+        ))
+        .execute(conn)?;
+    delete(history::table.filter(history::slug.eq(&new_slug))).execute(conn)?;
+    update(projects::table.filter(projects::id.eq(project_id)))
+        .set(projects::slug.eq(&new_slug))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_project_by_slug(
+    pool: &Pool,
+    project_slug: &Slug,
+) -> Result<Option<SlugMatch<Project>>, anyhow::Error> {
+    use crate::schema::project_slug_history as history;
+    use crate::schema::projects;
+    let conn = pool.get()?;
+    if let Some(project) = projects::table
+        .filter(projects::slug.eq(project_slug))
+        .first::<Project>(&conn)
+        .optional()?
+    {
+        return Ok(Some(SlugMatch::Current(project)));
+    }
+    Ok(projects::table
+        .filter(
+            projects::id.eq_any(
+                history::table
+                    .filter(history::slug.eq(project_slug))
+                    .select(history::project_id),
+            ),
+        )
+        .first::<Project>(&conn)
+        .optional()?
+        .map(SlugMatch::Previous))
+}
+
+/// Replaces placeholder slugs, which are ids of blog posts and projects created
+/// before slugs were introduced, with slugs made from titles
+pub fn generate_missing_slugs(conn: &SqliteConnection) -> Result<(), anyhow::Error> {
+    use crate::schema::{blog_posts, projects};
+    conn.transaction::<_, Error, _>(|| {
+        let post_ids = blog_posts::table
+            .filter(blog_posts::slug.eq(blog_posts::id))
+            .select(blog_posts::id)
+            .load::<BlogPostID>(conn)?;
+        for post_id in post_ids {
+            update_blog_post_slug(conn, &post_id)?;
+        }
+        let project_ids = projects::table
+            .filter(projects::slug.eq(projects::id))
+            .select(projects::id)
+            .load::<ProjectID>(conn)?;
+        for project_id in project_ids {
+            update_project_slug(conn, &project_id)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
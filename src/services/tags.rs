@@ -0,0 +1,89 @@
+use crate::domain::blog_posts::{BlogPost, BlogPostID, BlogPostStatus};
+use crate::domain::tags::TagName;
+use crate::schema::blog_post_tag_junctions::dsl::*;
+use crate::services::{load_blog_post_page, Page, PageRequest};
+use crate::Pool;
+use diesel::dsl::not;
+use diesel::result::Error;
+use diesel::{
+    delete, insert_into, insert_or_ignore_into, ExpressionMethods, QueryDsl, RunQueryDsl,
+    SqliteConnection,
+};
+use std::collections::BTreeMap;
+
+/// Returns tags of blog post in alphabetical order
+pub fn get_blog_post_tags(
+    pool: &Pool,
+    blog_post_id: &BlogPostID,
+) -> Result<Vec<TagName>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(load_blog_post_tags(&conn, blog_post_id)?)
+}
+
+pub(crate) fn load_blog_post_tags(
+    conn: &SqliteConnection,
+    blog_post_id: &BlogPostID,
+) -> Result<Vec<TagName>, Error> {
+    blog_post_tag_junctions
+        .filter(post_id.eq(blog_post_id))
+        .select(tag_name)
+        .order(tag_name.asc())
+        .load::<TagName>(conn)
+}
+
+/// Replaces tags of blog post. Tags that are no longer used by any post are removed.
+/// Should be called in transaction that changes the post, see `update_blog_post`.
+pub(crate) fn replace_blog_post_tags(
+    conn: &SqliteConnection,
+    blog_post_id: &BlogPostID,
+    new_tags: &[TagName],
+) -> Result<(), Error> {
+    use crate::schema::tags::dsl::{name, tags};
+    delete(blog_post_tag_junctions.filter(post_id.eq(blog_post_id))).execute(conn)?;
+    for tag in new_tags {
+        insert_or_ignore_into(tags)
+            .values(name.eq(tag))
+            .execute(conn)?;
+        insert_into(blog_post_tag_junctions)
+            .values((post_id.eq(blog_post_id), tag_name.eq(tag)))
+            .execute(conn)?;
+    }
+    delete(tags.filter(not(name.eq_any(blog_post_tag_junctions.select(tag_name)))))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Returns page of published blog posts with given tag
+pub fn get_blog_posts_by_tag(
+    pool: &Pool,
+    tag: &TagName,
+    request: &PageRequest,
+) -> Result<Page<BlogPost>, anyhow::Error> {
+    use crate::schema::blog_posts::dsl::{blog_posts, id, status};
+    let conn = pool.get()?;
+    let tagged = blog_post_tag_junctions
+        .filter(tag_name.eq(tag))
+        .select(post_id);
+    let query = blog_posts
+        .filter(id.eq_any(tagged))
+        .filter(status.eq(BlogPostStatus::Published))
+        .into_boxed();
+    load_blog_post_page(&conn, query, request)
+}
+
+/// Returns tags used by published blog posts together with number of such posts,
+/// in alphabetical order
+pub fn get_tag_counts(pool: &Pool) -> Result<Vec<(TagName, usize)>, anyhow::Error> {
+    use crate::schema::blog_posts::dsl::{blog_posts, status};
+    let conn = pool.get()?;
+    let mut counts = BTreeMap::<TagName, usize>::new();
+    for tag in blog_posts
+        .inner_join(blog_post_tag_junctions)
+        .filter(status.eq(BlogPostStatus::Published))
+        .select(tag_name)
+        .load::<TagName>(&conn)?
+    {
+        *counts.entry(tag).or_default() += 1;
+    }
+    Ok(counts.into_iter().collect())
+}
@@ -0,0 +1,101 @@
+use crate::domain::users::{RecoveryCode, TotpSecret, UserID, RECOVERY_CODE_COUNT};
+use crate::schema::recovery_codes::dsl::*;
+use crate::Pool;
+use diesel::result::Error;
+use diesel::{
+    insert_into, update, BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl,
+    RunQueryDsl,
+};
+
+/// Enables two-factor authentication for user, replacing previous secret if any.
+/// Returns freshly generated recovery codes that should be shown to user once.
+pub fn enable_two_factor(
+    pool: &Pool,
+    user: &UserID,
+    secret: &TotpSecret,
+) -> Result<Vec<RecoveryCode>, anyhow::Error> {
+    use crate::schema::users;
+
+    let conn = pool.get()?;
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::generate_random())
+        .collect();
+    conn.transaction::<_, Error, _>(|| {
+        update(users::table.filter(users::id.eq(user)))
+            .set((
+                users::totp_secret.eq(Some(secret)),
+                users::totp_last_step.eq(None::<i64>),
+            ))
+            .execute(&conn)?;
+        diesel::delete(recovery_codes.filter(user_id.eq(user))).execute(&conn)?;
+        for code in codes.iter() {
+            insert_into(recovery_codes)
+                .values((code_hash.eq(code.hashed()), user_id.eq(user)))
+                .execute(&conn)?;
+        }
+        Ok(())
+    })?;
+    Ok(codes)
+}
+
+/// Disables two-factor authentication for user, deleting unused recovery codes.
+pub fn disable_two_factor(pool: &Pool, user: &UserID) -> Result<(), anyhow::Error> {
+    use crate::schema::users;
+
+    let conn = pool.get()?;
+    conn.transaction::<_, Error, _>(|| {
+        update(users::table.filter(users::id.eq(user)))
+            .set((
+                users::totp_secret.eq(None::<TotpSecret>),
+                users::totp_last_step.eq(None::<i64>),
+            ))
+            .execute(&conn)?;
+        diesel::delete(recovery_codes.filter(user_id.eq(user))).execute(&conn)?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Records that TOTP code of given time step has been accepted for user.
+/// Returns false if code of this or later step has already been accepted,
+/// so that intercepted codes can't be replayed.
+pub fn use_totp_step(pool: &Pool, user: &UserID, step: u64) -> Result<bool, anyhow::Error> {
+    use crate::schema::users;
+
+    let conn = pool.get()?;
+    let step = step as i64;
+    let updated = update(
+        users::table.filter(users::id.eq(user)).filter(
+            users::totp_last_step
+                .is_null()
+                .or(users::totp_last_step.lt(step)),
+        ),
+    )
+    .set(users::totp_last_step.eq(Some(step)))
+    .execute(&conn)?;
+    Ok(updated != 0)
+}
+
+/// Consumes recovery code of user. Returns false if there is no such unused code.
+pub fn use_recovery_code(
+    pool: &Pool,
+    user: &UserID,
+    code: &RecoveryCode,
+) -> Result<bool, anyhow::Error> {
+    let conn = pool.get()?;
+    let deleted = diesel::delete(
+        recovery_codes
+            .filter(user_id.eq(user))
+            .filter(code_hash.eq(code.hashed())),
+    )
+    .execute(&conn)?;
+    Ok(deleted != 0)
+}
+
+pub fn count_recovery_codes(pool: &Pool, user: &UserID) -> Result<i64, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(recovery_codes
+        .filter(user_id.eq(user))
+        .count()
+        .get_result(&conn)?)
+}
@@ -1,15 +1,21 @@
+use crate::config::PasswordHashingConfig;
 use crate::domain::time::DateTime;
 use crate::domain::users::{
     Credentials, HashedUserPassword, NewUser, UpdateUser, User, UserEmail, UserID, UserName,
-    UserPasswordSalt, UserRole,
+    UserPassword, UserRole,
 };
 use crate::schema::users::dsl::*;
 use crate::services::get_stored_credentials;
 use crate::Pool;
 use diesel::result::{DatabaseErrorKind, Error};
-use diesel::{insert_into, update, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::{
+    insert_into, update, EscapeExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
+    RunQueryDsl, TextExpressionMethods,
+};
 use std::fmt::Formatter;
-use uuid::Uuid;
+
+/// Number of users shown on one page of admin panel
+pub const USERS_PER_PAGE: i64 = 20;
 
 pub fn get_user_by_id(pool: &Pool, user_id: &UserID) -> Result<Option<User>, anyhow::Error> {
     let conn = pool.get()?;
@@ -27,6 +33,70 @@ pub fn get_user_by_name(pool: &Pool, user_name: &UserName) -> Result<Option<User
         .optional()?)
 }
 
+pub fn get_user_by_email(
+    pool: &Pool,
+    user_email: &UserEmail,
+) -> Result<Option<User>, anyhow::Error> {
+    let conn = pool.get()?;
+    Ok(users
+        .filter(email.eq(user_email))
+        .first::<User>(&conn)
+        .optional()?)
+}
+
+/// Gets users ordered by name whose names contain given text.
+/// Pages are numbered from 1. Returns users on page and total number of matching users.
+pub fn search_users(
+    pool: &Pool,
+    name_contains: Option<&str>,
+    page: i64,
+) -> Result<(Vec<User>, i64), anyhow::Error> {
+    let conn = pool.get()?;
+    let pattern = name_contains.map(|s| {
+        format!(
+            "%{}%",
+            s.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+
+    let mut count_query = users.count().into_boxed();
+    let mut page_query = users.into_boxed();
+    if let Some(pattern) = &pattern {
+        count_query = count_query.filter(name.like(pattern).escape('\\'));
+        page_query = page_query.filter(name.like(pattern).escape('\\'));
+    }
+    let total = count_query.get_result::<i64>(&conn)?;
+    let found = page_query
+        .order(name.asc())
+        .limit(USERS_PER_PAGE)
+        .offset((page.max(1) - 1) * USERS_PER_PAGE)
+        .load::<User>(&conn)?;
+    Ok((found, total))
+}
+
+/// Checks that session of user started at given time has not been revoked.
+/// Sessions that do not store login time are only valid if user never revoked any.
+/// Sessions of banned users are never valid.
+pub fn is_session_valid(
+    pool: &Pool,
+    user_id: &UserID,
+    logged_in_at: Option<&DateTime>,
+) -> Result<bool, anyhow::Error> {
+    let user = match get_user_by_id(pool, user_id)? {
+        Some(user) => user,
+        None => return Ok(false),
+    };
+    if user.is_banned {
+        return Ok(false);
+    }
+    Ok(match user.sessions_valid_after {
+        Some(valid_after) => logged_in_at.is_some_and(|t| *t > valid_after),
+        None => true,
+    })
+}
+
 #[derive(thiserror::Error)]
 pub enum UserError {
     #[error("Name is already taken")]
@@ -45,22 +115,28 @@ impl std::fmt::Debug for UserError {
     }
 }
 
-pub fn insert_new_user(pool: &Pool, new_user: &NewUser) -> Result<User, UserError> {
+pub fn insert_new_user(
+    pool: &Pool,
+    new_user: &NewUser,
+    hashing: &PasswordHashingConfig,
+) -> Result<User, UserError> {
     let conn = pool
         .get()
         .map_err(|e| UserError::UnexpectedError(e.into()))?;
-    let salt = UserPasswordSalt::generate_random();
-    let hashed_password = HashedUserPassword::parse(&new_user.password, &salt);
+    let hashed_password = HashedUserPassword::parse(&new_user.password, hashing)?;
 
     let user = User {
         id: UserID::generate_random(),
         name: new_user.name.clone(),
-        email: UserEmail::parse(format!("{}@email.com", Uuid::new_v4())).expect("Oh no"), // TODO
+        email: new_user.email.clone(),
         password: hashed_password,
-        password_salt: salt,
+        password_salt: None,
         created_at: DateTime::now(),
         is_banned: false,
         role: UserRole::User,
+        sessions_valid_after: None,
+        totp_secret: None,
+        totp_last_step: None,
     };
 
     insert_into(users)
@@ -82,6 +158,35 @@ pub fn update_user(pool: &Pool, changeset: &UpdateUser) -> Result<(), UserError>
     Ok(())
 }
 
+/// Makes all sessions of user started before now invalid.
+pub fn revoke_user_sessions(pool: &Pool, user_id: &UserID) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(users.filter(id.eq(user_id)))
+        .set(sessions_valid_after.eq(Some(DateTime::now())))
+        .execute(&conn)?;
+    Ok(())
+}
+
+/// Bans user, logging them out everywhere.
+pub fn ban_user(pool: &Pool, user_id: &UserID) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(users.filter(id.eq(user_id)))
+        .set((
+            is_banned.eq(true),
+            sessions_valid_after.eq(Some(DateTime::now())),
+        ))
+        .execute(&conn)?;
+    Ok(())
+}
+
+pub fn unban_user(pool: &Pool, user_id: &UserID) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    update(users.filter(id.eq(user_id)))
+        .set(is_banned.eq(false))
+        .execute(&conn)?;
+    Ok(())
+}
+
 fn get_user_error_from_database_error(e: Error) -> UserError {
     match e {
         Error::DatabaseError(DatabaseErrorKind::UniqueViolation, ref data) => {
@@ -102,24 +207,68 @@ fn get_user_error_from_database_error(e: Error) -> UserError {
 pub enum AuthError {
     #[error("Invalid credentials")]
     InvalidCredentials(#[source] anyhow::Error),
+    #[error("User is banned")]
+    Banned,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
-pub fn validate_credentials(credentials: Credentials, pool: &Pool) -> Result<UserID, AuthError> {
-    if let Some(stored) = get_stored_credentials(credentials.name, pool)? {
-        let hashed_password = HashedUserPassword::parse(&credentials.password, &stored.salt);
-        if hashed_password == stored.password {
-            Ok(stored.user_id)
-        } else {
-            Err(AuthError::InvalidCredentials(anyhow::anyhow!(
-                "Passwords don't match"
-            )))
+/// Checks credentials against stored ones. If stored hash uses legacy scheme or outdated
+/// parameters, it is replaced with a fresh Argon2id one.
+#[tracing::instrument(name = "Validate credentials", skip(credentials, pool, hashing))]
+pub fn validate_credentials(
+    credentials: Credentials,
+    pool: &Pool,
+    hashing: &PasswordHashingConfig,
+) -> Result<UserID, AuthError> {
+    let stored = match get_stored_credentials(credentials.name, pool)? {
+        Some(stored) => stored,
+        None => {
+            HashedUserPassword::verify_dummy(&credentials.password, hashing);
+            return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+                "No user with such name found"
+            )));
         }
-    } else {
-        Err(AuthError::InvalidCredentials(anyhow::anyhow!(
-            "No user with such name found"
-        )))
+    };
+
+    if !stored
+        .password
+        .verify(&credentials.password, stored.salt.as_ref())?
+    {
+        return Err(AuthError::InvalidCredentials(anyhow::anyhow!(
+            "Passwords don't match"
+        )));
+    }
+
+    // Checked only after password, so that ban status is not revealed to strangers
+    if stored.is_banned {
+        return Err(AuthError::Banned);
+    }
+
+    if stored.password.needs_rehash(hashing) {
+        rehash_password(pool, &stored.user_id, &credentials.password, hashing)?;
     }
+
+    Ok(stored.user_id)
+}
+
+#[tracing::instrument(name = "Rehash password", skip(pool, user_password, hashing))]
+fn rehash_password(
+    pool: &Pool,
+    user_id: &UserID,
+    user_password: &UserPassword,
+    hashing: &PasswordHashingConfig,
+) -> Result<(), anyhow::Error> {
+    let hashed_password = HashedUserPassword::parse(user_password, hashing)?;
+    let changeset = UpdateUser {
+        id: user_id,
+        name: None,
+        email: None,
+        password: Some(&hashed_password),
+        password_salt: Some(None),
+        is_banned: None,
+        role: None,
+    };
+    update_user(pool, &changeset)?;
+    Ok(())
 }
@@ -1,25 +1,49 @@
-use crate::config::Config;
-use crate::routes::error_handlers::{internal_error_handler, not_found_handler};
+use crate::config::{Config, MediaStorageConfig};
+use crate::domain::users::HashedUserPassword;
+use crate::login_throttle::{attempt_store_from_config, LoginThrottle};
+use crate::mail::{mailer_from_config, Mailer};
+use crate::media::{media_store_from_config, MediaStore, MEDIA_PATH};
+use crate::middleware::require_csrf_token;
+use crate::routes::error_handlers::{forbidden_handler, internal_error_handler, not_found_handler};
+use crate::services::{
+    delete_expired_autosaves, generate_missing_media_variants, generate_missing_slugs,
+    publish_scheduled_blog_posts,
+};
+use crate::telemetry::spawn_blocking_with_tracing;
 use crate::Pool;
 use actix_session::storage::RedisSessionStore;
 use actix_session::SessionMiddleware;
 use actix_web::dev::Server;
-use actix_web::middleware::ErrorHandlers;
+use actix_web::middleware::{DefaultHeaders, ErrorHandlers};
 use actix_web::{http, web, App, HttpServer};
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_flash_messages::FlashMessagesFramework;
+use actix_web_lab::middleware::from_fn;
 use diesel::r2d2::ConnectionManager;
 use diesel::RunQueryDsl;
-use secrecy::{ExposeSecret, Secret};
+use futures_util::future::{join, select, Either};
+use secrecy::ExposeSecret;
 use std::net::TcpListener;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing_actix_web::TracingLogger;
 
+/// Space taken by fields and headers of multipart form besides uploaded file
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+/// How often expired autosaves of blog post editors are removed
+const AUTOSAVE_CLEANUP_PERIOD: Duration = Duration::from_secs(60 * 60);
+
 pub struct Application {
     pub port: u16,
     pub server: Server,
+    pool: Pool,
+    publish_interval: Duration,
 }
 
+/// Public URL of the site, used to build links that are sent to users.
+#[derive(Debug, Clone)]
+pub struct ApplicationBaseUrl(pub String);
+
 pub fn get_connection_pool(uri: &str) -> Pool {
     let pool: Pool = Pool::builder()
         .connection_timeout(Duration::new(10, 0))
@@ -29,62 +53,126 @@ pub fn get_connection_pool(uri: &str) -> Pool {
 }
 
 impl Application {
-    pub async fn build_with_pool(config: Config, pool: Pool) -> Result<Self, anyhow::Error> {
+    pub async fn build_with_pool(
+        config: Config,
+        pool: Pool,
+        mailer: Arc<dyn Mailer>,
+    ) -> Result<Self, anyhow::Error> {
         // Check if database migrated
         let conn = pool.get()?;
         crate::schema::check_if_migrated::dsl::check_if_migrated
             .load::<(i32,)>(&conn)
             .map_err(|_| anyhow::anyhow!("Database is not migrated"))?;
+        generate_missing_slugs(&conn)?;
+        let media_store = media_store_from_config(&config.media)?;
+        generate_missing_media_variants(&conn, &*media_store)?;
+        drop(conn);
+        HashedUserPassword::prepare_dummy(&config.app.password_hashing)?;
 
         let address = format!("{}:{}", config.app.host, config.app.port);
         tracing::info!("Starting server on {:?}", &address);
         let listener = TcpListener::bind(address)?;
 
         let port = listener.local_addr().unwrap().port();
-        let server = run(
-            listener,
-            pool.clone(),
-            config.app.hmac_secret,
-            config.redis_uri,
-            config.app.workers,
-        )
-        .await?;
+        let publish_interval = Duration::from_secs(config.app.publish_interval_seconds);
+        let server = run(listener, pool.clone(), config, mailer, media_store).await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            pool,
+            publish_interval,
+        })
     }
 
     pub async fn build(config: Config) -> Result<Self, anyhow::Error> {
         let pool = get_connection_pool(&config.database_uri);
-        Self::build_with_pool(config, pool).await
+        let mailer = mailer_from_config(&config.email)?;
+        Self::build_with_pool(config, pool, mailer).await
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// Runs server together with background publishing of scheduled blog posts
+    /// and cleanup of expired autosaves
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let publisher = run_periodically(
+            self.pool.clone(),
+            self.publish_interval,
+            "Publish scheduled blog posts",
+            publish_scheduled_blog_posts,
+        );
+        let cleaner = run_periodically(
+            self.pool,
+            AUTOSAVE_CLEANUP_PERIOD,
+            "Delete expired autosaves",
+            delete_expired_autosaves,
+        );
+        let background = join(publisher, cleaner);
+        futures_util::pin_mut!(background);
+        match select(self.server, background).await {
+            Either::Left((result, _)) => result,
+            Either::Right((_, _)) => unreachable!("Background tasks never stop"),
+        }
+    }
+}
+
+/// Runs blocking job in background every period. Job returns number of items it has
+/// processed, which is logged together with errors under given name.
+async fn run_periodically<F>(pool: Pool, period: Duration, name: &'static str, job: F)
+where
+    F: Fn(&Pool) -> Result<usize, anyhow::Error> + Copy + Send + 'static,
+{
+    let mut interval = actix_web::rt::time::interval(period);
+    loop {
+        interval.tick().await;
+        let pool = pool.clone();
+        let result = spawn_blocking_with_tracing(move || job(&pool))
+            .await
+            .map_err(anyhow::Error::new)
+            .and_then(|r| r);
+        match result {
+            Ok(0) => {}
+            Ok(processed) => tracing::info!("{}: processed {} items", name, processed),
+            Err(e) => tracing::error!("{}: failed: {:?}", name, e),
+        }
     }
 }
 
 async fn run(
     listener: TcpListener,
     pool: Pool,
-    hmac_secret: Secret<String>,
-    redis_uri: Secret<String>,
-    workers: Option<usize>,
+    config: Config,
+    mailer: Arc<dyn Mailer>,
+    media_store: Arc<dyn MediaStore>,
 ) -> Result<Server, anyhow::Error> {
-    let workers = workers.unwrap_or_else(num_cpus::get_physical);
+    let workers = config.app.workers.unwrap_or_else(num_cpus::get_physical);
     tracing::info!("Workers: {:?}", &workers);
-    let secret_key = actix_web::cookie::Key::from(hmac_secret.expose_secret().as_bytes());
+    let attempt_store = attempt_store_from_config(&config.app.login_throttle, &config.redis_uri)?;
+    let login_throttle =
+        web::Data::new(LoginThrottle::new(attempt_store, config.app.login_throttle));
+    let password_hashing = config.app.password_hashing;
+    let media_dir = match &config.media.storage {
+        MediaStorageConfig::Local { path } => path.clone(),
+    };
+    // Multipart forms carry uploaded file together with other fields
+    let upload_payload_config =
+        web::PayloadConfig::new(config.media.max_upload_size + MULTIPART_OVERHEAD);
+    let media_config = config.media;
+    let base_url = ApplicationBaseUrl(config.app.base_url);
+    let secret_key =
+        actix_web::cookie::Key::from(config.app.hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
-    let redis_uri = format!("redis://{}", redis_uri.expose_secret());
+    let redis_uri = format!("redis://{}", config.redis_uri.expose_secret());
     let redis_store = RedisSessionStore::new(redis_uri)
         .await
         .expect("Failed to connect to redis");
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(require_csrf_token))
             .wrap(TracingLogger::default())
             .wrap(message_framework.clone())
             .wrap(
@@ -98,11 +186,24 @@ async fn run(
                         http::StatusCode::INTERNAL_SERVER_ERROR,
                         internal_error_handler,
                     )
-                    .handler(http::StatusCode::NOT_FOUND, not_found_handler),
+                    .handler(http::StatusCode::NOT_FOUND, not_found_handler)
+                    .handler(http::StatusCode::FORBIDDEN, forbidden_handler),
             )
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(password_hashing.clone()))
+            .app_data(web::Data::new(base_url.clone()))
+            .app_data(web::Data::from(mailer.clone()))
+            .app_data(login_throttle.clone())
+            .app_data(web::Data::from(media_store.clone()))
+            .app_data(web::Data::new(media_config.clone()))
             .service(actix_files::Files::new("/static", "./static").show_files_listing())
-            .configure(crate::routes::configure)
+            .service(
+                web::scope(MEDIA_PATH)
+                    // Uploads are checked to be images or documents, browsers must not guess otherwise
+                    .wrap(DefaultHeaders::new().add(("X-Content-Type-Options", "nosniff")))
+                    .service(actix_files::Files::new("", &media_dir)),
+            )
+            .configure(|cfg| crate::routes::configure(cfg, upload_payload_config.clone()))
     })
     .workers(workers)
     .listen(listener)
@@ -52,6 +52,13 @@ pub fn see_other(location: &str) -> HttpResponse {
         .finish()
 }
 
+/// Redirects to address resource has been moved to, e.g. after renaming
+pub fn moved_permanently(location: &str) -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
 pub fn redirect_with_error<E>(route: &str, e: E) -> InternalError<E>
 where
     E: std::fmt::Display,
@@ -46,6 +46,8 @@ $(document).ready(() => {
         paragraph.hide();
 
         $( "#edit-comment-form-contents" ).val(paragraph.text().trim());
+        form.find( ".markdown-editor-tabs .item[data-tab=write]" ).click();
+        $( "#edit-comment-form-version" ).val($( "#comment-" + comment_id ).data("version"));
         $( "#comment-contents-" + comment_id ).after(form);
     })
     ;
@@ -55,9 +57,35 @@ $(document).ready(() => {
 
         let comment_id = e.target.id.replace("delete-comment-", "");
 
-        let current_path = window.location.pathname;
-        window.location.href = window.location.origin + current_path.replace("/view", "/comments/")
-            + comment_id + "/delete";
+        let current_path = window.location.pathname.replace("/view", "");
+        let form = $( "#delete-comment-form" );
+        form.attr("action", current_path + "/comments/" + comment_id + "/delete");
+        form.submit();
+    })
+    ;
+
+    $( ".hide-comment-button" ).click(e => {
+        e.preventDefault();
+
+        let comment_id = e.target.id.replace("hide-comment-", "");
+
+        let current_path = window.location.pathname.replace("/view", "");
+        let form = $( "#hide-comment-form" );
+        form.attr("action", current_path + "/comments/" + comment_id + "/hide");
+        form.show();
+        $( "#comment-contents-" + comment_id ).after(form);
+    })
+    ;
+
+    $( ".restore-comment-button" ).click(e => {
+        e.preventDefault();
+
+        let comment_id = e.target.id.replace("restore-comment-", "");
+
+        let current_path = window.location.pathname.replace("/view", "");
+        let form = $( "#restore-comment-form" );
+        form.attr("action", current_path + "/comments/" + comment_id + "/restore");
+        form.submit();
     })
     ;
 });
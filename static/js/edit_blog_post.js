@@ -0,0 +1,100 @@
+// Inserts text at cursor position of textarea
+insert_at_cursor = (textarea, text) => {
+    let start = textarea.selectionStart;
+    let end = textarea.selectionEnd;
+    let value = textarea.value;
+    textarea.value = value.substring(0, start) + text + value.substring(end);
+    textarea.selectionStart = textarea.selectionEnd = start + text.length;
+    textarea.focus();
+}
+
+// How often changed text of editor is saved, in milliseconds
+const AUTOSAVE_INTERVAL = 5000;
+
+// Fields of editor that are autosaved
+autosave_fields = () => {
+    return {
+        title: $( "#title_input" ).val(),
+        brief: $( "#brief_input" ).val(),
+        contents: $( "#blog_post_contents_textarea" ).val(),
+        tags: $( "#tags_input" ).val(),
+    };
+}
+
+// Periodically sends text of editor to server if it has changed, so it isn't lost
+// if page is closed before form is submitted
+start_autosave = form => {
+    let status = $( "#autosave_status" );
+    let last_saved = JSON.stringify(autosave_fields());
+    let submitted = false;
+    form.submit(() => { submitted = true; });
+
+    setInterval(() => {
+        let fields = autosave_fields();
+        let current = JSON.stringify(fields);
+        if (submitted || current === last_saved) {
+            return;
+        }
+        fetch(form.data("autosave"), {
+            method: "POST",
+            headers: {
+                "X-CSRF-Token": $( "input[name=csrf_token]" ).first().val(),
+            },
+            body: new URLSearchParams(fields),
+        })
+            .then(response => {
+                if (!response.ok || response.redirected) {
+                    throw new Error(response.statusText);
+                }
+                last_saved = current;
+                status.text("Draft saved at " + new Date().toLocaleTimeString());
+            })
+            .catch(() => status.text("Failed to save draft"));
+    }, AUTOSAVE_INTERVAL);
+}
+
+$(document).ready(() => {
+    start_autosave($( "#edit_blog_post_form" ));
+
+    $( "#autosave_restore_button" ).click(e => {
+        e.preventDefault();
+        $( "#title_input" ).val($( "#autosave_title" ).val());
+        $( "#brief_input" ).val($( "#autosave_brief" ).val());
+        $( "#blog_post_contents_textarea" ).val($( "#autosave_contents" ).val());
+        $( "#tags_input" ).val($( "#autosave_tags" ).val());
+        $( "#autosave_banner" ).hide();
+    });
+
+    $( "#media_upload_button" ).click(e => {
+        e.preventDefault();
+        let input = $( "#media_upload_input" )[0];
+        let status = $( "#media_upload_status" );
+        if (input.files.length === 0) {
+            status.text("Choose file first");
+            return;
+        }
+
+        let data = new FormData();
+        data.append("file", input.files[0]);
+        status.text("Uploading...");
+        fetch("/account/media/upload", {
+            method: "POST",
+            headers: {
+                "Accept": "application/json",
+                "X-CSRF-Token": $( "input[name=csrf_token]" ).val(),
+            },
+            body: data,
+        })
+            .then(response => response.json())
+            .then(result => {
+                if (result.error !== undefined) {
+                    status.text(result.error);
+                    return;
+                }
+                insert_at_cursor($( "#blog_post_contents_textarea" )[0], result.markdown);
+                input.value = "";
+                status.text("");
+            })
+            .catch(() => status.text("Failed to upload file"));
+    });
+});
@@ -0,0 +1,46 @@
+// Renders markdown with server, the same way as it is rendered after saving
+render_markdown_preview = (contents, context, target) => {
+    target.html('<div class="ui active centered inline loader"></div>');
+    fetch("/preview", {
+        method: "POST",
+        headers: {
+            "X-CSRF-Token": $( "input[name=csrf_token]" ).first().val(),
+        },
+        body: new URLSearchParams({ contents: contents, context: context }),
+    })
+        .then(response => {
+            // Visitors that are not logged in are redirected to login page
+            if (!response.ok || response.redirected) {
+                throw new Error(response.statusText);
+            }
+            return response.text();
+        })
+        .then(html => target.html(html))
+        .catch(() => target.text("Failed to render preview"));
+}
+
+// Switches between textarea of editor and its preview with "Write" and "Preview" tabs.
+// Editor is element with class "markdown-editor" and markdown context in "data-context".
+$(document).ready(() => {
+    $( ".markdown-editor" ).each((_, element) => {
+        let editor = $(element);
+        let textarea = editor.find("textarea");
+        let preview = editor.find(".markdown-preview");
+        let tabs = editor.find(".markdown-editor-tabs .item");
+
+        tabs.click(e => {
+            e.preventDefault();
+            let tab = $(e.currentTarget);
+            tabs.removeClass("active");
+            tab.addClass("active");
+            if (tab.data("tab") === "preview") {
+                textarea.hide();
+                preview.show();
+                render_markdown_preview(textarea.val(), editor.data("context"), preview);
+            } else {
+                preview.hide();
+                textarea.show();
+            }
+        });
+    });
+});
@@ -1,13 +1,15 @@
 use crate::api::assert_is_redirect_to_resource;
-use crate::common::TestApp;
+use crate::common::{extract_csrf_token, TestApp};
 
 #[tokio::test]
 async fn account_shows_correct_user_name() {
     let app = TestApp::spawn().await;
     let register_body = serde_json::json!({
         "name": "SuperValidName",
+        "email": "supervalidname@email.com",
         "password": "!1Aapass",
-        "repeat_password": "!1Aapass"
+        "repeat_password": "!1Aapass",
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
 
     let response = app.post_registration(&register_body).await;
@@ -0,0 +1,173 @@
+use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
+use crate::common::{extract_csrf_token, TestApp, TestUser};
+use holosite::domain::users::{UserID, UserRole};
+use holosite::services::{ban_user, get_user_by_id};
+use secrecy::ExposeSecret;
+
+async fn post_admin_action(app: &TestApp, user_id: &UserID, action: &str) -> reqwest::Response {
+    // Admin panel has no forms when the only listed user is the current one
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post(
+        &format!("/admin/users/{}/{}", user_id.as_ref(), action),
+        &serde_json::json!({ "csrf_token": csrf }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_access_admin_panel() {
+    let app = TestApp::spawn().await;
+    let response = app.get_page("/admin/users").await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn regular_users_cant_access_admin_panel() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let response = app.get_page("/admin/users").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    let response = app
+        .post(
+            &format!("/admin/users/{}/promote", user_id.as_ref()),
+            &serde_json::json!({ "csrf_token": csrf }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::User);
+}
+
+#[tokio::test]
+async fn admin_can_search_users() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    let other_user = TestUser::generate();
+    other_user.register_internally(app.pool());
+
+    let html = app.get_page_html("/admin/users").await;
+    assert!(html.contains(user.name.as_ref()));
+    assert!(html.contains(other_user.name.as_ref()));
+
+    let html = app
+        .get_page_html(&format!("/admin/users?query={}", user.name.as_ref()))
+        .await;
+    assert!(html.contains(user.name.as_ref()));
+    assert!(!html.contains(other_user.name.as_ref()));
+}
+
+#[tokio::test]
+async fn banned_user_cant_log_in() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+
+    let response = post_admin_action(&app, &user_id, "ban").await;
+    assert_is_redirect_to_resource(&response, "/admin/users");
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert!(stored.is_banned);
+
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+    let response = app
+        .post_login(&serde_json::json!({
+            "name": user.name.as_ref(),
+            "password": user.password.as_ref().expose_secret(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+    let html = app.get_login_page_html().await;
+    assert!(html.contains("Your account has been banned"));
+}
+
+#[tokio::test]
+async fn open_session_of_banned_user_is_rejected() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    assert_resp_ok(&app.get_page("/account/settings").await);
+
+    ban_user(app.pool(), &user_id).unwrap();
+
+    let response = app.get_page("/account/settings").await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn admin_can_promote_and_demote_users() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+
+    post_admin_action(&app, &user_id, "promote").await;
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::Admin);
+
+    post_admin_action(&app, &user_id, "demote").await;
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::User);
+}
+
+#[tokio::test]
+async fn admin_can_make_user_moderator() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+
+    post_admin_action(&app, &user_id, "make_moderator").await;
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::Moderator);
+}
+
+#[tokio::test]
+async fn admin_cant_change_own_account() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    let admin_id = admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+
+    let response = post_admin_action(&app, &admin_id, "demote").await;
+    assert_is_redirect_to_resource(&response, "/admin/users");
+    let html = app.get_page_html("/admin/users").await;
+    assert!(html.contains("change your own account"));
+
+    let stored = get_user_by_id(app.pool(), &admin_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::Admin);
+}
+
+#[tokio::test]
+async fn admin_can_force_logout_of_user() {
+    let app = TestApp::spawn().await;
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+
+    let response = post_admin_action(&app, &user_id, "logout").await;
+    assert_is_redirect_to_resource(&response, "/admin/users");
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert!(stored.sessions_valid_after.is_some());
+}
@@ -0,0 +1,132 @@
+use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
+use crate::common::{extract_csrf_token, TestApp, TestBlogPost, TestUser};
+use holosite::domain::blog_posts::AutosaveSlot;
+use holosite::services::get_autosave;
+
+fn autosave_json(csrf: &str, contents: &str) -> serde_json::Value {
+    serde_json::json!({
+        "csrf_token": csrf,
+        "title": "Unsaved title",
+        "brief": "Unsaved brief",
+        "contents": contents,
+        "tags": "",
+    })
+}
+
+#[tokio::test]
+async fn autosave_of_new_post_is_offered_on_editor() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+
+    let response = app
+        .post(
+            "/blog_posts/autosave",
+            &autosave_json(&csrf, "Unsaved <b>text</b>"),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let autosave = get_autosave(app.pool(), &user_id, &AutosaveSlot::NewBlogPost).unwrap();
+    assert_eq!(autosave.unwrap().contents, "Unsaved <b>text</b>");
+
+    let html = app.get_create_blog_post_page_html().await;
+    assert!(html.contains("You have unsaved draft"));
+    assert!(html.contains("Unsaved &lt;b&gt;text&lt;/b&gt;"));
+}
+
+#[tokio::test]
+async fn autosave_is_removed_when_post_is_created() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+    app.post("/blog_posts/autosave", &autosave_json(&csrf, "Unsaved"))
+        .await;
+
+    app.post_create_blog_post(&TestBlogPost::generate().to_json(&csrf))
+        .await;
+
+    let autosave = get_autosave(app.pool(), &user_id, &AutosaveSlot::NewBlogPost).unwrap();
+    assert!(autosave.is_none());
+    let html = app.get_create_blog_post_page_html().await;
+    assert!(!html.contains("You have unsaved draft"));
+}
+
+#[tokio::test]
+async fn autosave_can_be_discarded() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let post_id = TestBlogPost::generate().register_internally(app.pool(), &user_id);
+    let csrf = extract_csrf_token(&app.get_edit_blog_post_page_html(post_id.as_ref()).await);
+    let autosave_path = format!("/blog_posts/{}/autosave", post_id.as_ref());
+    let edit_path = format!("/blog_posts/{}/edit", post_id.as_ref());
+
+    let response = app
+        .post(&autosave_path, &autosave_json(&csrf, "Unsaved"))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let html = app.get_edit_blog_post_page_html(post_id.as_ref()).await;
+    assert!(html.contains("You have unsaved draft"));
+
+    let response = app
+        .post(
+            &format!("{}/discard", autosave_path),
+            &serde_json::json!({ "csrf_token": csrf }),
+        )
+        .await;
+    assert_is_redirect_to_resource(&response, &edit_path);
+    let html = app.get_edit_blog_post_page_html(post_id.as_ref()).await;
+    assert!(html.contains("Unsaved draft has been discarded"));
+    assert!(!html.contains("You have unsaved draft"));
+}
+
+#[tokio::test]
+async fn only_editors_can_autosave_post() {
+    let app = TestApp::spawn().await;
+    let author_id = TestUser::generate().register_internally(app.pool());
+    let post_id = TestBlogPost::generate().register_internally(app.pool(), &author_id);
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+
+    let response = app
+        .post(
+            &format!("/blog_posts/{}/autosave", post_id.as_ref()),
+            &autosave_json(&csrf, "Unsaved"),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn autosave_requires_login() {
+    let app = TestApp::spawn().await;
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+
+    let response = app
+        .post("/blog_posts/autosave", &autosave_json(&csrf, "Unsaved"))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn editor_without_autosave_has_no_banner() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let response = app.get_create_blog_post_page().await;
+    assert_resp_ok(&response);
+    assert!(!response
+        .text()
+        .await
+        .unwrap()
+        .contains("You have unsaved draft"));
+}
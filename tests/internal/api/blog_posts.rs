@@ -1,6 +1,12 @@
 use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
-use crate::common::{extract_csrf_token, TestApp, TestBlogPost, TestComment, TestUser};
-use holosite::domain::blog_posts::BlogPostVisibility;
+use crate::common::{
+    extract_csrf_token, TestApp, TestBlogPost, TestComment, TestProject, TestUser,
+};
+use holosite::domain::blog_posts::{BlogPostStatus, BlogPostVisibility, UpdateBlogPost};
+use holosite::domain::time::DateTime;
+use holosite::services::{
+    add_project_blog_post, add_project_editor, get_blog_post_by_id, update_blog_post,
+};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_see_create_blog_post_page() {
@@ -106,7 +112,7 @@ async fn edit_blog_post_works() {
     );
 
     let updated = TestBlogPost::generate();
-    app.post_edit_blog_post(&updated.to_json(&csrf), &blog_post_id)
+    app.post_edit_blog_post(&updated.to_edit_json(&csrf, 1), &blog_post_id)
         .await;
 
     let response = app
@@ -185,9 +191,10 @@ async fn can_change_blog_post_visibility() {
         &app.get_edit_blog_post_page_html(blog_post_id.as_ref().as_str())
             .await,
     );
-    app.post_edit_blog_post(&blog_post.to_json(&csrf), &blog_post_id)
+    app.post_edit_blog_post(&blog_post.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
         .await;
-    app.post_logout().await;
 
     let html = app
         .get_view_blog_post_page_html(blog_post_id.as_ref())
@@ -221,7 +228,486 @@ async fn blogpost_contents_are_rendered_from_markdown() {
         .get_view_blog_post_page_html(blog_post_id.as_ref().as_str())
         .await;
     assert!(html.contains(&blog_post.title));
-    assert!(html.contains("<h1>This is title</h1>"));
+    assert!(html.contains("<h1 id=\"h-this-is-title\">This is title"));
     assert!(html.contains("<em>Hello world</em>"));
     assert!(html.contains("<p><code>inline code</code></p>"));
 }
+
+#[tokio::test]
+async fn xss_payloads_in_blog_posts_are_not_rendered() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.title = "<script>alert('title')</script>".to_string();
+    blog_post.brief = "<img src=x onerror=alert('brief')>".to_string();
+    blog_post.contents = r#"
+<script>alert('contents')</script>
+
+<div onmouseover="alert(1)">[link](javascript:alert(1))</div>
+
+<a href="JaVaScRiPt:alert(1)">link</a> <a href="https://example.com">safe link</a>
+    "#
+    .to_string();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref().as_str())
+        .await;
+    assert!(!html.contains("<script>alert"));
+    assert!(!html.contains("<img"));
+    assert!(!html.contains("onmouseover"));
+    assert!(!html.to_lowercase().contains("href=\"javascript:"));
+    assert!(html.contains("&lt;script&gt;alert"));
+    assert!(html.contains("<a href=\"https://example.com\">safe link</a>"));
+}
+
+#[tokio::test]
+async fn other_users_cant_edit_blog_post() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+
+    let other_user = TestUser::generate();
+    other_user.register_internally(app.pool());
+    other_user.login(&app).await;
+
+    let response = app.get_edit_blog_post_page(blog_post_id.as_ref()).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let updated = TestBlogPost::generate();
+    let response = app
+        .post_edit_blog_post(&updated.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, blog_post.title);
+}
+
+#[tokio::test]
+async fn project_editor_can_edit_blog_post_of_project() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+    let project_id = TestProject::generate().register_internally(app.pool(), &author_id);
+    add_project_blog_post(app.pool(), &project_id, &blog_post_id).unwrap();
+
+    let editor = TestUser::generate();
+    let editor_id = editor.register_internally(app.pool());
+    editor.login(&app).await;
+
+    // Not yet an editor of the project
+    let response = app.get_edit_blog_post_page(blog_post_id.as_ref()).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    add_project_editor(app.pool(), &project_id, &editor_id).unwrap();
+    let response = app.get_edit_blog_post_page(blog_post_id.as_ref()).await;
+    assert_resp_ok(&response);
+
+    let csrf = extract_csrf_token(
+        &app.get_edit_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let updated = TestBlogPost::generate();
+    let response = app
+        .post_edit_blog_post(&updated.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, updated.title);
+}
+
+#[tokio::test]
+async fn admin_can_edit_any_blog_post() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+
+    let admin = TestUser::generate();
+    admin.register_admin_internally(app.pool());
+    admin.login(&app).await;
+
+    let response = app.get_edit_blog_post_page(blog_post_id.as_ref()).await;
+    assert_resp_ok(&response);
+
+    let csrf = extract_csrf_token(
+        &app.get_edit_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let updated = TestBlogPost::generate();
+    app.post_edit_blog_post(&updated.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, updated.title);
+}
+
+#[tokio::test]
+async fn draft_is_visible_only_to_its_editors() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None);
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+
+    let response = app.get_view_blog_post_page(blog_post_id.as_ref()).await;
+    assert_eq!(response.status().as_u16(), 404);
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(!html.contains(&blog_post.title));
+
+    let other_user = TestUser::generate();
+    other_user.register_internally(app.pool());
+    other_user.login(&app).await;
+    let response = app.get_view_blog_post_page(blog_post_id.as_ref()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    author.login(&app).await;
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(html.contains(&blog_post.contents));
+    assert!(html.contains("Draft"));
+}
+
+#[tokio::test]
+async fn cant_comment_on_draft_of_other_user() {
+    let app = TestApp::spawn().await;
+    let author_id = TestUser::generate().register_internally(app.pool());
+    let blog_post_id = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(app.pool(), &author_id);
+
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    let comment = TestComment::generate();
+    app.post_create_comment(
+        &serde_json::json!({ "csrf_token": csrf, "contents": &comment.contents }),
+        &blog_post_id,
+    )
+    .await;
+
+    let comments =
+        holosite::services::get_comments_for_blog_post(app.pool(), &blog_post_id).unwrap();
+    assert!(comments.is_empty());
+}
+
+#[tokio::test]
+async fn blog_post_can_be_created_as_draft_and_published_later() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+
+    let blog_post = TestBlogPost::generate();
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+    let mut body = blog_post.to_json(&csrf);
+    body["status"] = "draft".into();
+    let response = app.post_create_blog_post(&body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(!html.contains(&blog_post.title));
+
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let blog_post_id = location
+        .trim_start_matches("/blog_posts/")
+        .trim_end_matches("/view");
+    let mut body = blog_post.to_edit_json(&csrf, 1);
+    body["status"] = "published".into();
+    app.post(&format!("/blog_posts/{}/edit", blog_post_id), &body)
+        .await;
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(html.contains(&blog_post.title));
+}
+
+#[tokio::test]
+async fn scheduled_blog_post_requires_publication_time() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+
+    let blog_post = TestBlogPost::generate();
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+    let mut body = blog_post.to_json(&csrf);
+    body["status"] = "scheduled".into();
+    let response = app.post_create_blog_post(&body).await;
+    assert_is_redirect_to_resource(&response, "/blog_posts/create");
+
+    let html = app.get_create_blog_post_page_html().await;
+    assert!(html.contains("Publication time must be set"));
+}
+
+#[tokio::test]
+async fn scheduled_blog_post_is_published_in_background() {
+    let app = TestApp::spawn().await;
+    let author_id = TestUser::generate().register_internally(app.pool());
+    let blog_post = TestBlogPost::generate_with_status(
+        BlogPostStatus::Scheduled,
+        Some(DateTime::now().plus(chrono::Duration::seconds(1))),
+    );
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(!html.contains(&blog_post.title));
+
+    // Publishing is checked every second in tests
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.status, BlogPostStatus::Published);
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(html.contains(&blog_post.title));
+}
+
+#[tokio::test]
+async fn history_shows_diff_and_restores_revision() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.contents = "first line\nsecond line".to_string();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+
+    let csrf = extract_csrf_token(
+        &app.get_edit_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let mut edited = blog_post.to_edit_json(&csrf, 1);
+    edited["contents"] = "first line\nchanged line".into();
+    app.post_edit_blog_post(&edited, &blog_post_id).await;
+
+    let html = app
+        .get_page_html(&format!("/blog_posts/{}/history", blog_post_id.as_ref()))
+        .await;
+    assert!(html.contains(&test_user.name.as_ref().to_string()));
+    assert!(html.contains("/revisions/1/restore"));
+
+    let html = app
+        .get_page_html(&format!(
+            "/blog_posts/{}/diff?from=1&to=2",
+            blog_post_id.as_ref()
+        ))
+        .await;
+    assert!(html.contains("second line"));
+    assert!(html.contains("changed line"));
+
+    let csrf = extract_csrf_token(
+        &app.get_page_html(&format!("/blog_posts/{}/history", blog_post_id.as_ref()))
+            .await,
+    );
+    let response = app
+        .post(
+            &format!("/blog_posts/{}/revisions/1/restore", blog_post_id.as_ref()),
+            &serde_json::json!({ "csrf_token": csrf }),
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/history", blog_post_id.as_ref()),
+    );
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.contents, blog_post.contents);
+}
+
+#[tokio::test]
+async fn other_users_cant_see_history_of_blog_post() {
+    let app = TestApp::spawn().await;
+    let author_id = TestUser::generate().register_internally(app.pool());
+    let blog_post_id = TestBlogPost::generate().register_internally(app.pool(), &author_id);
+
+    let other_user = TestUser::generate();
+    other_user.register_internally(app.pool());
+    other_user.login(&app).await;
+
+    let response = app
+        .get_page(&format!("/blog_posts/{}/history", blog_post_id.as_ref()))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn stale_blog_post_edit_shows_conflict() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+
+    let html = app
+        .get_edit_blog_post_page_html(blog_post_id.as_ref().as_str())
+        .await;
+    assert!(html.contains(r#"name="version" value="1""#));
+    let csrf = extract_csrf_token(&html);
+
+    let first = TestBlogPost::generate();
+    let response = app
+        .post_edit_blog_post(&first.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+
+    let second = TestBlogPost::generate();
+    let response = app
+        .post_edit_blog_post(&second.to_edit_json(&csrf, 1), &blog_post_id)
+        .await;
+    assert_eq!(response.status(), 409);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("has been changed by someone else"));
+    assert!(html.contains(&first.contents));
+    assert!(html.contains(&second.contents));
+    assert!(html.contains(r#"name="version" value="2""#));
+
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref().as_str())
+        .await;
+    assert!(html.contains(&first.title));
+}
+
+#[tokio::test]
+async fn blog_post_edit_without_version_is_rejected() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let csrf = extract_csrf_token(
+        &app.get_edit_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+
+    let response = app
+        .post_edit_blog_post(&TestBlogPost::generate().to_json(&csrf), &blog_post_id)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let stored = get_blog_post_by_id(app.pool(), &blog_post_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, blog_post.title);
+}
+
+#[tokio::test]
+async fn all_blog_posts_page_is_paginated() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let first = TestBlogPost::generate();
+    first.register_internally(app.pool(), &user_id);
+    let second = TestBlogPost::generate();
+    second.register_internally(app.pool(), &user_id);
+
+    let html = app
+        .get_page_html("/blog_posts/all?sort=oldest&size=1")
+        .await;
+    assert!(html.contains(&first.title));
+    assert!(!html.contains(&second.title));
+    assert!(html.contains("Next"));
+    assert!(html.contains("Most commented"));
+}
+
+#[tokio::test]
+async fn all_blog_posts_page_rejects_invalid_cursor() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_page("/blog_posts/all?after=invalid").await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .get_page("/blog_posts/all?sort=commented&after=abc%7Cx")
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn blog_post_can_be_viewed_by_slug() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.title = "Shareable title".to_string();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let html = app.get_page_html("/posts/shareable-title").await;
+    assert!(html.contains(&blog_post.contents));
+
+    let html = app.get_all_blog_posts_page_html().await;
+    assert!(html.contains("/posts/shareable-title"));
+}
+
+#[tokio::test]
+async fn previous_slug_of_blog_post_redirects_permanently() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.title = "Old title".to_string();
+    let post_id = blog_post.register_internally(app.pool(), &user_id);
+    test_user.login(&app).await;
+
+    let csrf = extract_csrf_token(&app.get_edit_blog_post_page_html(post_id.as_ref()).await);
+    blog_post.title = "New title".to_string();
+    app.post(
+        &format!("/blog_posts/{}/edit", post_id.as_ref()),
+        &blog_post.to_edit_json(&csrf, 1),
+    )
+    .await;
+
+    let response = app.get_page("/posts/old-title").await;
+    assert_eq!(response.status().as_u16(), 301);
+    assert_eq!(response.headers()["Location"], "/posts/new-title");
+}
+
+#[tokio::test]
+async fn previous_slug_of_unpublished_blog_post_is_not_found() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None);
+    blog_post.title = "Old draft title".to_string();
+    let post_id = blog_post.register_internally(app.pool(), &user_id);
+    let changeset = UpdateBlogPost {
+        id: &post_id,
+        title: Some("New draft title"),
+        brief: None,
+        contents: None,
+        visibility: None,
+        status: None,
+        publish_at: None,
+    };
+    update_blog_post(app.pool(), &changeset, None, &user_id, None).unwrap();
+
+    let response = app.get_page("/posts/old-draft-title").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
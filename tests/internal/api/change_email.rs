@@ -0,0 +1,124 @@
+use crate::api::assert_is_redirect_to_resource;
+use crate::common::{extract_csrf_token, TestApp, TestUser};
+use holosite::services::get_user_by_id;
+use regex::Regex;
+
+fn extract_confirmation_link(body: &str) -> String {
+    let reg = Regex::new(r#"https?://[^/]+(/confirm_email\?token=[A-Za-z0-9]+)"#).unwrap();
+    reg.captures(body)
+        .map(|it| it[1].to_string())
+        .expect("Failed to get confirmation link")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_email() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let account = app.get_account_settings_page_html().await;
+    let csrf = extract_csrf_token(&account);
+
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    // Token of new anonymous session
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "csrf_token": csrf,
+            "new_email": "new@email.com",
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn new_email_must_be_valid() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let account = app.get_account_settings_page_html().await;
+    let csrf = extract_csrf_token(&account);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "csrf_token": csrf,
+            "new_email": "not an email",
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+
+    let html = app.get_account_settings_page_html().await;
+    assert!(html.contains("Invalid email"));
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn cant_change_to_taken_email() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let other_user = TestUser::generate();
+    other_user.register_internally(app.pool());
+
+    let account = app.get_account_settings_page_html().await;
+    let csrf = extract_csrf_token(&account);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "csrf_token": csrf,
+            "new_email": other_user.email.as_ref(),
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+
+    let html = app.get_account_settings_page_html().await;
+    assert!(html.contains("Email is already taken"));
+}
+
+#[tokio::test]
+async fn email_is_changed_only_after_confirmation() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let account = app.get_account_settings_page_html().await;
+    let csrf = extract_csrf_token(&account);
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "csrf_token": csrf,
+            "new_email": "new@email.com",
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+
+    let emails = app.sent_emails();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to.as_ref(), "new@email.com");
+
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.email, user.email);
+
+    let link = extract_confirmation_link(&emails[0].body);
+    let response = app.get_page(&link).await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+
+    let html = app.get_account_settings_page_html().await;
+    assert!(html.contains("Your email has been changed"));
+    assert!(html.contains("new@email.com"));
+
+    // Token is single-use
+    let response = app.get_page(&link).await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+    let html = app.get_account_settings_page_html().await;
+    assert!(html.contains("Confirmation link is invalid or has already been used"));
+}
@@ -12,7 +12,10 @@ async fn you_must_be_logged_in_to_change_password() {
     let account = app.get_account_settings_page_html().await;
     let csrf = extract_csrf_token(&account);
 
-    app.post_logout().await;
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    // Token of new anonymous session
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
 
     let response = app
         .post_change_name(&serde_json::json!({
@@ -88,7 +91,9 @@ async fn change_name_works() {
         .await;
     assert_is_redirect_to_resource(&response, "/account/settings");
 
-    let response = app.post_logout().await;
+    let response = app
+        .post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
     assert_is_redirect_to_resource(&response, "/login");
 
     let html = app.get_login_page_html().await;
@@ -97,7 +102,8 @@ async fn change_name_works() {
     let response = app
         .post_login(&serde_json::json!({
             "name": "NewName",
-            "password": user.password.as_ref().expose_secret()
+            "password": user.password.as_ref().expose_secret(),
+            "csrf_token": extract_csrf_token(&html),
         }))
         .await;
     assert_is_redirect_to_resource(&response, "/blog_posts/all");
@@ -13,7 +13,10 @@ async fn you_must_be_logged_in_to_change_password() {
     let account = app.get_account_settings_page_html().await;
     let csrf = extract_csrf_token(&account);
 
-    app.post_logout().await;
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    // Token of new anonymous session
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
 
     let new_password = Uuid::new_v4().to_string();
     let response = app
@@ -132,7 +135,9 @@ async fn change_password_works() {
     let html = app.get_account_settings_page_html().await;
     assert!(html.contains("Your password has been changed"));
 
-    let response = app.post_logout().await;
+    let response = app
+        .post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
     assert_is_redirect_to_resource(&response, "/login");
 
     let html = app.get_login_page_html().await;
@@ -141,7 +146,8 @@ async fn change_password_works() {
     let response = app
         .post_login(&serde_json::json!({
             "name": user.name.as_ref(),
-            "password": &new_password
+            "password": &new_password,
+            "csrf_token": extract_csrf_token(&html),
         }))
         .await;
     assert_is_redirect_to_resource(&response, "/blog_posts/all");
@@ -1,5 +1,6 @@
 use crate::api::assert_is_redirect_to_resource;
 use crate::common::{extract_csrf_token, TestApp, TestBlogPost, TestComment, TestUser};
+use holosite::services::{get_comment_by_id, hide_comment, set_blog_post_comments_locked};
 
 #[tokio::test]
 async fn create_comment_works() {
@@ -58,7 +59,8 @@ async fn edit_comment_works() {
             &serde_json::json!({
                 "csrf_token": csrf,
                 "contents": "New contents",
-                "is_deleted": false
+                "is_deleted": false,
+                "version": 1
             }),
             &blog_post_id,
             &comment_id,
@@ -88,7 +90,9 @@ async fn cant_edit_others_comment() {
     let test_comment = TestComment::generate();
     let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
 
-    app.post_logout().await;
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
 
     let other_user = TestUser::generate();
     other_user.register_internally(app.pool());
@@ -103,6 +107,7 @@ async fn cant_edit_others_comment() {
         &serde_json::json!({
             "csrf_token": csrf,
             "contents": "New contents",
+            "version": 1,
         }),
         &blog_post_id,
         &comment_id,
@@ -113,7 +118,8 @@ async fn cant_edit_others_comment() {
         .get_view_blog_post_page_html(blog_post_id.as_ref())
         .await;
     assert!(!post_html.contains("New contents"));
-    assert!(post_html.contains("Can't change others comment"));
+    // Flash messages are escaped like any other field
+    assert!(post_html.contains("Can&#x27;t change others comment"));
     assert!(post_html.contains(&test_comment.contents));
 }
 
@@ -128,7 +134,17 @@ async fn delete_comment_works() {
     let test_comment = TestComment::generate();
     let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
 
-    let response = app.post_delete_comment(&blog_post_id, &comment_id).await;
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let response = app
+        .post_delete_comment(
+            &serde_json::json!({ "csrf_token": csrf }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
     assert_is_redirect_to_resource(
         &response,
         &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
@@ -227,7 +243,8 @@ async fn delete_comment_in_middle_of_response_tree_works() {
             &serde_json::json!({
                 "csrf_token": csrf,
                 "contents": "New contents",
-                "is_deleted": true
+                "is_deleted": true,
+                "version": 1
             }),
             &blog_post_id,
             &comment_id,
@@ -282,3 +299,356 @@ async fn markdown_comments_rendering_works() {
     assert!(post_html
         .contains("This is <em>very</em> <strong>good</strong> <code>markdown</code> render"));
 }
+
+#[tokio::test]
+async fn xss_payloads_in_comments_are_not_rendered() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let payloads = [
+        "<script>alert(1)</script>",
+        "<img src=x onerror=alert(1)>",
+        "[link](javascript:alert(1))",
+        "<a href=\"jav&#x61;script:alert(1)\">link</a>",
+        "<svg onload=alert(1)>",
+        "<p style=\"x\" onclick=\"alert(1)\">text</p>",
+        "<iframe src=\"https://example.com\"></iframe>",
+    ];
+    for payload in payloads {
+        let mut test_comment = TestComment::generate();
+        test_comment.contents = payload.to_string();
+        test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
+    }
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(!post_html.contains("<script>alert"));
+    assert!(!post_html.contains("<img"));
+    assert!(!post_html.contains("<svg"));
+    assert!(!post_html.contains("<iframe"));
+    // Source of comment is kept escaped for editing, attributes must not get into markup
+    assert!(!post_html.contains("onclick=\""));
+    assert!(!post_html.contains("href=\"javascript:"));
+    assert!(!post_html.contains("href=\"jav"));
+    assert!(post_html.contains("<p>text</p>"));
+}
+
+#[tokio::test]
+async fn comment_links_are_nofollow() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let mut test_comment = TestComment::generate();
+    test_comment.contents = "[link](https://example.com)".to_string();
+    test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("<a href=\"https://example.com\" rel=\"nofollow ugc\">link</a>"));
+}
+
+#[tokio::test]
+async fn moderator_can_hide_comment_and_replies_are_kept() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &author_id);
+    let response_comment = TestComment::generate();
+    response_comment.register_response_internally(
+        app.pool(),
+        &blog_post_id,
+        &author_id,
+        &comment_id,
+    );
+
+    let moderator = TestUser::generate();
+    moderator.register_moderator_internally(app.pool());
+    moderator.login(&app).await;
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let response = app
+        .post_hide_comment(
+            &serde_json::json!({ "csrf_token": csrf, "reason": "Offtopic" }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("Removed by moderator"));
+    assert!(post_html.contains("Offtopic"));
+    assert!(!post_html.contains(&test_comment.contents));
+    assert!(post_html.contains(&response_comment.contents));
+}
+
+#[tokio::test]
+async fn regular_user_cant_hide_comment() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    app.post_hide_comment(
+        &serde_json::json!({ "csrf_token": csrf, "reason": "Offtopic" }),
+        &blog_post_id,
+        &comment_id,
+    )
+    .await;
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("You are not allowed to moderate comments"));
+    assert!(post_html.contains(&test_comment.contents));
+    let stored = get_comment_by_id(app.pool(), &comment_id).unwrap().unwrap();
+    assert!(!stored.is_hidden);
+}
+
+#[tokio::test]
+async fn moderator_can_restore_comment() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &author_id);
+
+    let moderator = TestUser::generate();
+    let moderator_id = moderator.register_moderator_internally(app.pool());
+    moderator.login(&app).await;
+    hide_comment(app.pool(), &comment_id, &moderator_id, "Spam").unwrap();
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let response = app
+        .post_restore_comment(
+            &serde_json::json!({ "csrf_token": csrf }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains(&test_comment.contents));
+}
+
+#[tokio::test]
+async fn author_cant_edit_hidden_comment() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    author.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &author_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &author_id);
+    let moderator_id = TestUser::generate().register_moderator_internally(app.pool());
+    hide_comment(app.pool(), &comment_id, &moderator_id, "Spam").unwrap();
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    app.post_edit_comment(
+        &serde_json::json!({ "csrf_token": csrf, "contents": "New contents", "version": 1 }),
+        &blog_post_id,
+        &comment_id,
+    )
+    .await;
+
+    let stored = get_comment_by_id(app.pool(), &comment_id).unwrap().unwrap();
+    assert_eq!(stored.contents, test_comment.contents);
+}
+
+#[tokio::test]
+async fn cant_comment_on_locked_blog_post() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    set_blog_post_comments_locked(app.pool(), &blog_post_id, true).unwrap();
+    let test_comment = TestComment::generate();
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let response = app
+        .post_create_comment(
+            &serde_json::json!({
+                "csrf_token": csrf,
+                "contents": &test_comment.contents
+            }),
+            &blog_post_id,
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("Comments on this blog post are locked"));
+    assert!(!post_html.contains(&test_comment.contents));
+}
+
+#[tokio::test]
+async fn moderator_can_lock_and_unlock_comments() {
+    let app = TestApp::spawn().await;
+    let moderator = TestUser::generate();
+    let moderator_id = moderator.register_moderator_internally(app.pool());
+    moderator.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &moderator_id);
+
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+    let response = app
+        .post(
+            &format!("/blog_posts/{}/lock_comments", blog_post_id.as_ref()),
+            &serde_json::json!({ "csrf_token": csrf }),
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("Unlock comments"));
+
+    app.post(
+        &format!("/blog_posts/{}/unlock_comments", blog_post_id.as_ref()),
+        &serde_json::json!({ "csrf_token": csrf }),
+    )
+    .await;
+    let post_html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(post_html.contains("Lock comments"));
+    assert!(!post_html.contains("Unlock comments"));
+}
+
+#[tokio::test]
+async fn stale_comment_edit_shows_conflict() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
+
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref().as_str())
+        .await;
+    assert!(html.contains(r#"data-version="1""#));
+    let csrf = extract_csrf_token(&html);
+
+    let response = app
+        .post_edit_comment(
+            &serde_json::json!({
+                "csrf_token": csrf,
+                "contents": "First contents",
+                "version": 1
+            }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/view", blog_post_id.as_ref()),
+    );
+
+    let response = app
+        .post_edit_comment(
+            &serde_json::json!({
+                "csrf_token": csrf,
+                "contents": "Second contents",
+                "version": 1
+            }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
+    assert_eq!(response.status(), 409);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("First contents"));
+    assert!(html.contains("Second contents"));
+
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref().as_str())
+        .await;
+    assert!(html.contains("First contents"));
+    assert!(!html.contains("Second contents"));
+}
+
+#[tokio::test]
+async fn comment_edit_without_version_is_rejected() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let blog_post_id = TestBlogPost::generate().register_internally(app.pool(), &user_id);
+    let test_comment = TestComment::generate();
+    let comment_id = test_comment.register_internally(app.pool(), &blog_post_id, &user_id);
+    let csrf = extract_csrf_token(
+        &app.get_view_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+
+    let response = app
+        .post_edit_comment(
+            &serde_json::json!({ "csrf_token": csrf, "contents": "New contents" }),
+            &blog_post_id,
+            &comment_id,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let stored = get_comment_by_id(app.pool(), &comment_id).unwrap().unwrap();
+    assert_eq!(stored.contents, test_comment.contents);
+}
@@ -0,0 +1,101 @@
+use crate::api::assert_is_redirect_to_resource;
+use crate::common::{extract_csrf_token, TestApp, TestUser};
+use holosite::services::get_user_by_id;
+use secrecy::ExposeSecret;
+
+#[tokio::test]
+async fn form_without_csrf_token_is_rejected() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    app.get_account_settings_page_html().await;
+
+    let response = app
+        .post_change_name(&serde_json::json!({ "new_name": "NewName" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_change_name(&serde_json::json!({
+            "new_name": "NewName",
+            "csrf_token": "invalid",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.name, user.name);
+}
+
+#[tokio::test]
+async fn login_without_csrf_token_is_rejected() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "name": user.name.as_ref(),
+            "password": user.password.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_page("/account/settings").await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn csrf_token_can_be_passed_in_header() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+
+    let response = app
+        .post_with_csrf_header(
+            "/account/change_name",
+            &serde_json::json!({ "new_name": "NewName" }),
+            &csrf,
+        )
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/settings");
+
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.name.as_ref(), "NewName");
+}
+
+#[tokio::test]
+async fn csrf_token_is_rotated_on_login() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+
+    let anonymous_csrf = extract_csrf_token(&app.get_login_page_html().await);
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    assert_ne!(anonymous_csrf, csrf);
+
+    let response = app
+        .post_change_name(&serde_json::json!({
+            "new_name": "NewName",
+            "csrf_token": anonymous_csrf,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn logout_is_not_possible_with_get() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+
+    app.get_page("/logout").await;
+
+    let response = app.get_page("/account/settings").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,72 @@
+use crate::common::{get_test_config, TestApp, TestBlogPost, TestUser};
+
+#[tokio::test]
+async fn blog_feeds_are_served_in_all_formats() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    for (path, content_type) in [
+        ("/feed.atom", "application/atom+xml"),
+        ("/feed.rss", "application/rss+xml"),
+        ("/feed.json", "application/feed+json"),
+    ] {
+        let response = app.get_page(path).await;
+        assert_eq!(response.status().as_u16(), 200);
+        let header = response.headers()["content-type"].to_str().unwrap();
+        assert!(header.starts_with(content_type));
+        assert!(response.text().await.unwrap().contains(&blog_post.title));
+    }
+}
+
+#[tokio::test]
+async fn feed_contents_have_absolute_urls() {
+    let app = TestApp::spawn().await;
+    let base_url = get_test_config().app.base_url;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.title = "Feed urls".to_string();
+    blog_post.contents = "[About](/about) and note[^1]\n\n[^1]: Note".to_string();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let feed: serde_json::Value = serde_json::from_str(&app.get_page_html("/feed.json").await)
+        .expect("Feed is not valid JSON");
+    let html = feed["items"][0]["content_html"].as_str().unwrap();
+    assert!(html.contains(&format!("href=\"{}/about\"", base_url)));
+    assert!(html.contains(&format!("href=\"{}/posts/feed-urls#fn-1\"", base_url)));
+    assert!(!html.contains("href=\"/"));
+    assert!(!html.contains("href=\"#"));
+}
+
+#[tokio::test]
+async fn feed_does_not_contain_posts_for_authenticated_users() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let blog_post = TestBlogPost::generate_authenticated();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let xml = app.get_page_html("/feed.atom").await;
+    assert!(!xml.contains(&blog_post.title));
+}
+
+#[tokio::test]
+async fn user_feed_works() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let xml = app
+        .get_page_html(&format!("/users/{}/feed.atom", user_id.as_ref()))
+        .await;
+    assert!(xml.contains(&blog_post.title));
+}
+
+#[tokio::test]
+async fn unknown_feed_format_is_not_found() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_page("/feed.xml").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
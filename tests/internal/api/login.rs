@@ -1,12 +1,15 @@
 use crate::api::assert_is_redirect_to_resource;
-use crate::common::{TestApp, TestUser};
+use crate::common::{extract_csrf_token, get_test_config, TestApp, TestUser};
 use secrecy::ExposeSecret;
 use uuid::Uuid;
 
 #[tokio::test]
 async fn logout_returns_redirect_to_login_when_not_logged_in() {
     let app = TestApp::spawn().await;
-    let response = app.post_logout().await;
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+    let response = app
+        .post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
     assert_is_redirect_to_resource(&response, "/login")
 }
 
@@ -16,7 +19,8 @@ async fn error_flash_message_is_set_on_failure() {
 
     let login_body = serde_json::json!({
         "name": "SuperValidName",
-        "password": "!1Aapass"
+        "password": "!1Aapass",
+        "csrf_token": extract_csrf_token(&app.get_login_page_html().await),
     });
     let response = app.post_login(&login_body).await;
 
@@ -37,7 +41,8 @@ async fn error_message_invalid_name_is_set_on_login() {
 
     let login_body = serde_json::json!({
         "name": "",
-        "password": "1"
+        "password": "1",
+        "csrf_token": extract_csrf_token(&app.get_login_page_html().await),
     });
     let response = app.post_login(&login_body).await;
 
@@ -58,7 +63,8 @@ async fn error_message_invalid_password_is_set_on_login() {
 
     let login_body = serde_json::json!({
         "name": "Hello",
-        "password": ""
+        "password": "",
+        "csrf_token": extract_csrf_token(&app.get_login_page_html().await),
     });
     let response = app.post_login(&login_body).await;
 
@@ -78,8 +84,10 @@ async fn registration_works() {
     let app = TestApp::spawn().await;
     let register_body = serde_json::json!({
         "name": "SuperValidName",
+        "email": "supervalidname@email.com",
         "password": "!1Aapass",
-        "repeat_password": "!1Aapass"
+        "repeat_password": "!1Aapass",
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
 
     let response = app.post_registration(&register_body).await;
@@ -91,15 +99,20 @@ async fn registration_logout_login_works() {
     let app = TestApp::spawn().await;
     let register_body = serde_json::json!({
         "name": "SuperValidName",
+        "email": "supervalidname@email.com",
         "password": "!1Aapass",
-        "repeat_password": "!1Aapass"
+        "repeat_password": "!1Aapass",
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
 
     let response = app.post_registration(&register_body).await;
     assert_is_redirect_to_resource(&response, "/blog_posts/all");
     // Now we are logged
 
-    let response = app.post_logout().await;
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    let response = app
+        .post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
     assert_is_redirect_to_resource(&response, "/login");
     let login_page = app.get_login_page_html().await;
     assert!(login_page.contains("You have successfully logged out"));
@@ -107,7 +120,8 @@ async fn registration_logout_login_works() {
 
     let login_body = serde_json::json!({
         "name":  "SuperValidName",
-        "password": "!1Aapass"
+        "password": "!1Aapass",
+        "csrf_token": extract_csrf_token(&login_page),
     });
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to_resource(&response, "/blog_posts/all");
@@ -119,8 +133,10 @@ async fn registration_with_invalid_password_and_not_equal_repeat_is_password_err
 
     let login_body = serde_json::json!({
         "name": "ValidName",
+        "email": "validname@email.com",
         "password": "aaaa",
-        "repeat_password": "aaaa"
+        "repeat_password": "aaaa",
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
     let response = app.post_registration(&login_body).await;
 
@@ -141,8 +157,10 @@ async fn you_cant_create_user_with_taken_name() {
 
     let register_body = serde_json::json!({
         "name": test_user.name.as_ref(),
+        "email": TestUser::generate().email.as_ref(),
         "password": test_user.password.as_ref().expose_secret(),
-        "repeat_password": test_user.password.as_ref().expose_secret()
+        "repeat_password": test_user.password.as_ref().expose_secret(),
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
 
     let response = app.post_registration(&register_body).await;
@@ -181,7 +199,8 @@ async fn user_name_persists_between_sequential_login_attempts() {
     let name = Uuid::new_v4().to_string();
     let login_body = serde_json::json!({
         "name": &name,
-        "password": ""
+        "password": "",
+        "csrf_token": extract_csrf_token(&app.get_login_page_html().await),
     });
     let response = app.post_login(&login_body).await;
 
@@ -201,8 +220,10 @@ async fn user_name_persists_between_sequential_registration_attempts() {
     let name = Uuid::new_v4().to_string();
     let login_body = serde_json::json!({
         "name": &name,
+        "email": "",
         "password": "",
-        "repeat_password": ""
+        "repeat_password": "",
+        "csrf_token": extract_csrf_token(&app.get_registration_page_html().await),
     });
 
     let response = app.post_registration(&login_body).await;
@@ -214,3 +235,37 @@ async fn user_name_persists_between_sequential_registration_attempts() {
     let html_page = app.get_registration_page_html().await;
     assert!(!html_page.contains(&name));
 }
+
+#[tokio::test]
+async fn login_is_locked_out_after_too_many_failures() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    let attempts = get_test_config().app.login_throttle.attempts_per_name;
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+
+    for _ in 0..attempts {
+        let response = app
+            .post_login(&serde_json::json!({
+                "name": user.name.as_ref(),
+                "password": "!1Aawrong",
+                "csrf_token": csrf,
+            }))
+            .await;
+        assert_is_redirect_to_resource(&response, "/login");
+    }
+    let html_page = app.get_login_page_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+
+    // Even correct password is rejected during lockout
+    let response = app
+        .post_login(&serde_json::json!({
+            "name": user.name.as_ref(),
+            "password": user.password.as_ref().expose_secret(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+    let html_page = app.get_login_page_html().await;
+    assert!(html_page.contains("Too many failed login attempts"));
+}
@@ -0,0 +1,164 @@
+use crate::api::assert_is_redirect_to_resource;
+use crate::common::{extract_csrf_token, test_png, TestApp, TestUser};
+use holosite::services::{get_media_of_owner, PageRequest};
+
+#[tokio::test]
+async fn media_library_requires_login() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_page("/account/media").await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn uploaded_file_is_served_and_listed_in_library() {
+    let app = TestApp::spawn().await;
+    let png = test_png(8, 8);
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+
+    let response = app.post_media_upload(&csrf, "cat.png", &png, false).await;
+    assert_is_redirect_to_resource(&response, "/account/media");
+
+    let html = app.get_page_html("/account/media").await;
+    assert!(html.contains("cat.png has been uploaded"));
+    let page = get_media_of_owner(app.pool(), &user_id, &PageRequest::default()).unwrap();
+    assert_eq!(page.items.len(), 1);
+    let url = format!("/media/{}", page.items[0].storage_key());
+    assert!(html.contains(&format!("![cat.png]({})", url)));
+
+    let response = app.get_page(&url).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), png);
+
+    let response = app
+        .get_page(&format!("/media/{}-480w.webp", page.items[0].id.as_ref()))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "image/webp");
+}
+
+#[tokio::test]
+async fn editor_gets_markdown_of_uploaded_file() {
+    let app = TestApp::spawn().await;
+    let png = test_png(8, 8);
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+
+    let response = app.post_media_upload(&csrf, "cat.png", &png, true).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let json: serde_json::Value = response.json().await.unwrap();
+    let url = json["url"].as_str().unwrap();
+    assert!(url.starts_with("/media/") && url.ends_with(".png"));
+    assert_eq!(json["markdown"], format!("![cat.png]({})", url));
+}
+
+#[tokio::test]
+async fn unsupported_files_are_rejected() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+
+    let response = app
+        .post_media_upload(&csrf, "x.png", b"<svg onload=alert(1)>", true)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert!(json["error"].as_str().unwrap().contains("can be uploaded"));
+
+    let response = app.post_media_upload(&csrf, "x.png", b"", false).await;
+    assert_is_redirect_to_resource(&response, "/account/media");
+    assert!(app
+        .get_page_html("/account/media")
+        .await
+        .contains("File is empty"));
+
+    let page = get_media_of_owner(app.pool(), &user_id, &PageRequest::default()).unwrap();
+    assert!(page.items.is_empty());
+}
+
+#[tokio::test]
+async fn upload_without_csrf_token_is_rejected() {
+    let app = TestApp::spawn().await;
+    let png = test_png(8, 8);
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    app.get_page_html("/account/media").await;
+
+    let response = app
+        .post_media_upload("invalid", "cat.png", &png, false)
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn upload_without_session_is_rejected() {
+    let app = TestApp::spawn().await;
+    let png = test_png(8, 8);
+
+    let response = app.post_media_upload("token", "cat.png", &png, false).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn files_larger_than_default_payload_limit_can_be_uploaded() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+    let data = test_png(512, 512);
+    assert!(data.len() > 256 * 1024);
+
+    let response = app.post_media_upload(&csrf, "large.png", &data, true).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page = get_media_of_owner(app.pool(), &user_id, &PageRequest::default()).unwrap();
+    assert_eq!(page.items.len(), 1);
+}
+
+#[tokio::test]
+async fn only_owner_can_delete_media() {
+    let app = TestApp::spawn().await;
+    let png = test_png(8, 8);
+    let owner = TestUser::generate();
+    let owner_id = owner.register_internally(app.pool());
+    owner.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+    app.post_media_upload(&csrf, "cat.png", &png, false).await;
+    let media = get_media_of_owner(app.pool(), &owner_id, &PageRequest::default())
+        .unwrap()
+        .items
+        .remove(0);
+    let delete_path = format!("/account/media/{}/delete", media.id.as_ref());
+
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    let other = TestUser::generate();
+    other.register_internally(app.pool());
+    other.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+    let response = app
+        .post(&delete_path, &serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    owner.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_page_html("/account/media").await);
+    let response = app
+        .post(&delete_path, &serde_json::json!({ "csrf_token": csrf }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/media");
+    let page = get_media_of_owner(app.pool(), &owner_id, &PageRequest::default()).unwrap();
+    assert!(page.items.is_empty());
+}
@@ -1,11 +1,23 @@
 mod account;
+mod admin;
+mod blog_post_autosaves;
 mod blog_posts;
+mod change_email;
 mod change_name;
 mod change_password;
 mod comments;
+mod csrf;
+mod feeds;
 mod health_check;
 mod home;
 mod login;
+mod media;
+mod password_reset;
+mod preview;
+mod projects;
+mod search;
+mod tags;
+mod two_factor;
 mod users;
 
 fn strip_from_query_params(s: &str) -> &str {
@@ -0,0 +1,139 @@
+use crate::api::assert_is_redirect_to_resource;
+use crate::common::{extract_csrf_token, get_test_config, TestApp, TestUser};
+use holosite::domain::users::{HashedUserPassword, UserPassword};
+use holosite::services::{insert_password_reset_token, reset_password};
+use regex::Regex;
+use secrecy::{ExposeSecret, Secret};
+
+fn extract_reset_token(body: &str) -> String {
+    let reg = Regex::new(r#"https?://[^/]+/password_reset/confirm\?token=([A-Za-z0-9]+)"#).unwrap();
+    reg.captures(body)
+        .map(|it| it[1].to_string())
+        .expect("Failed to get password reset link")
+}
+
+#[tokio::test]
+async fn reset_request_for_unknown_email_looks_the_same() {
+    let app = TestApp::spawn().await;
+    let csrf = extract_csrf_token(&app.get_page_html("/password_reset").await);
+
+    let response = app
+        .post_password_reset(&serde_json::json!({
+            "email": "unknown@email.com",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let html = app.get_login_page_html().await;
+    assert!(html.contains("If account with such email exists"));
+    assert!(app.sent_emails().is_empty());
+}
+
+#[tokio::test]
+async fn new_password_must_be_valid() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    let csrf = extract_csrf_token(&app.get_page_html("/password_reset").await);
+
+    app.post_password_reset(&serde_json::json!({
+        "email": user.email.as_ref(),
+        "csrf_token": csrf,
+    }))
+    .await;
+    let token = extract_reset_token(&app.sent_emails()[0].body);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "new_password": "short",
+            "repeat_new_password": "short",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/password_reset/confirm");
+
+    let html = app
+        .get_page_html(&format!("/password_reset/confirm?token={}", token))
+        .await;
+    assert!(html.contains("New password is invalid"));
+}
+
+#[tokio::test]
+async fn password_reset_works() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    let csrf = extract_csrf_token(&app.get_page_html("/password_reset").await);
+
+    let response = app
+        .post_password_reset(&serde_json::json!({
+            "email": user.email.as_ref(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let emails = app.sent_emails();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].to, user.email);
+    let token = extract_reset_token(&emails[0].body);
+
+    let response = app
+        .post_password_reset_confirm(&serde_json::json!({
+            "token": token,
+            "new_password": "!1Bbnewpass",
+            "repeat_new_password": "!1Bbnewpass",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+    let html = app.get_login_page_html().await;
+    assert!(html.contains("Your password has been reset"));
+
+    // Old password no longer works
+    let response = app
+        .post_login(&serde_json::json!({
+            "name": user.name.as_ref(),
+            "password": user.password.as_ref().expose_secret(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "name": user.name.as_ref(),
+            "password": "!1Bbnewpass",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/blog_posts/all");
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+
+    // Token is single-use
+    let response = app
+        .get_page(&format!("/password_reset/confirm?token={}", token))
+        .await;
+    assert_is_redirect_to_resource(&response, "/password_reset");
+}
+
+#[tokio::test]
+async fn password_reset_logs_out_existing_sessions() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let token = insert_password_reset_token(app.pool(), &user_id).unwrap();
+    let password = UserPassword::parse(Secret::new("!1Bbnewpass".to_string())).unwrap();
+    let password =
+        HashedUserPassword::parse(&password, &get_test_config().app.password_hashing).unwrap();
+    reset_password(app.pool(), &token, &password).unwrap();
+
+    let response = app.get_page("/account/settings").await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
@@ -0,0 +1,90 @@
+use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
+use crate::common::{extract_csrf_token, TestApp, TestUser};
+
+const MARKDOWN: &str = "| a | b |\n|---|---|\n| 1 | 2 |\n\n<script>alert(1)</script>";
+
+async fn logged_in_app() -> (TestApp, String) {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+    (app, csrf)
+}
+
+#[tokio::test]
+async fn post_preview_is_rendered_like_post() {
+    let (app, csrf) = logged_in_app().await;
+
+    let response = app
+        .post_preview(&serde_json::json!({
+            "contents": MARKDOWN,
+            "context": "post",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_resp_ok(&response);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<table>"), "{}", html);
+    assert!(!html.contains("<script"), "{}", html);
+}
+
+#[tokio::test]
+async fn comment_preview_is_rendered_like_comment() {
+    let (app, csrf) = logged_in_app().await;
+
+    let response = app
+        .post_preview(&serde_json::json!({
+            "contents": MARKDOWN,
+            "context": "comment",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_resp_ok(&response);
+    let html = response.text().await.unwrap();
+    assert!(!html.contains("<table>"), "{}", html);
+    assert!(!html.contains("<script"), "{}", html);
+}
+
+#[tokio::test]
+async fn unknown_context_is_rejected() {
+    let (app, csrf) = logged_in_app().await;
+
+    let response = app
+        .post_preview(&serde_json::json!({
+            "contents": "a",
+            "context": "email",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn preview_requires_csrf_token() {
+    let (app, _) = logged_in_app().await;
+
+    let response = app
+        .post_preview(&serde_json::json!({ "contents": "a", "context": "post" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn preview_requires_login() {
+    let app = TestApp::spawn().await;
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+
+    let response = app
+        .post_preview(&serde_json::json!({
+            "contents": "a",
+            "context": "post",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
@@ -0,0 +1,214 @@
+use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
+use crate::common::{extract_csrf_token, TestApp, TestBlogPost, TestProject, TestUser};
+use holosite::domain::blog_posts::BlogPostStatus;
+use holosite::domain::projects::UpdateProject;
+use holosite::services::{
+    add_project_blog_post, add_project_editor, get_project_by_id, get_project_by_title,
+    update_project,
+};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_create_project() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_create_project_page().await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let response = app.get_create_project_page().await;
+    assert_resp_ok(&response);
+}
+
+#[tokio::test]
+async fn created_project_is_listed_and_viewed() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+    user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_project_page_html().await);
+
+    let test_project = TestProject::generate();
+    let response = app.post_create_project(&test_project.to_json(&csrf)).await;
+    let project = get_project_by_title(app.pool(), &test_project.title)
+        .unwrap()
+        .unwrap();
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/projects/{}/view", project.id.as_ref()),
+    );
+
+    let html = app.get_all_projects_page_html().await;
+    assert!(html.contains(&test_project.title));
+    assert!(html.contains(&format!("/projects/{}", project.slug.as_ref())));
+
+    let html = app.get_view_project_page_html(project.id.as_ref()).await;
+    assert!(html.contains(&test_project.title));
+    assert!(html.contains(&test_project.brief));
+    assert!(html.contains(user.name.as_ref()));
+}
+
+#[tokio::test]
+async fn project_with_taken_title_is_not_created() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+    let test_project = TestProject::generate();
+    test_project.register_internally(app.pool(), &user_id);
+    let csrf = extract_csrf_token(&app.get_create_project_page_html().await);
+
+    let response = app.post_create_project(&test_project.to_json(&csrf)).await;
+    assert_is_redirect_to_resource(&response, "/projects/create");
+    let html = app.get_create_project_page_html().await;
+    assert!(html.contains("Title is already taken"));
+}
+
+#[tokio::test]
+async fn project_page_is_shown_at_slug() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let test_project = TestProject::generate();
+    let project_id = test_project.register_internally(app.pool(), &user_id);
+    let project = get_project_by_id(app.pool(), &project_id).unwrap().unwrap();
+
+    let html = app
+        .get_page_html(&format!("/projects/{}", project.slug.as_ref()))
+        .await;
+    assert!(html.contains(&test_project.brief));
+}
+
+#[tokio::test]
+async fn previous_slug_of_hidden_project_is_not_found() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let test_project = TestProject::generate_authenticated();
+    let project_id = test_project.register_internally(app.pool(), &user_id);
+    let old_slug = get_project_by_id(app.pool(), &project_id)
+        .unwrap()
+        .unwrap()
+        .slug;
+    let new_title = "Renamed project".to_string();
+    update_project(
+        app.pool(),
+        &UpdateProject {
+            id: &project_id,
+            title: Some(&new_title),
+            brief: None,
+            visibility: None,
+        },
+    )
+    .unwrap();
+
+    let response = app
+        .get_page(&format!("/projects/{}", old_slug.as_ref()))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn only_editors_can_edit_project() {
+    let app = TestApp::spawn().await;
+    let author = TestUser::generate();
+    let author_id = author.register_internally(app.pool());
+    let project_id = TestProject::generate().register_internally(app.pool(), &author_id);
+    let editor = TestUser::generate();
+    let editor_id = editor.register_internally(app.pool());
+    add_project_editor(app.pool(), &project_id, &editor_id).unwrap();
+    let stranger = TestUser::generate();
+    stranger.register_internally(app.pool());
+
+    stranger.login(&app).await;
+    let response = app.get_edit_project_page(project_id.as_ref()).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let csrf = extract_csrf_token(&app.get_create_project_page_html().await);
+    let response = app
+        .post_edit_project(&TestProject::generate().to_json(&csrf), &project_id)
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+
+    editor.login(&app).await;
+    let html = app.get_edit_project_page_html(project_id.as_ref()).await;
+    let csrf = extract_csrf_token(&html);
+    let new_project = TestProject::generate();
+    let response = app
+        .post_edit_project(&new_project.to_json(&csrf), &project_id)
+        .await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/projects/{}/view", project_id.as_ref()),
+    );
+    let project = get_project_by_id(app.pool(), &project_id).unwrap().unwrap();
+    assert_eq!(project.title, new_project.title);
+    assert_eq!(project.brief, new_project.brief);
+}
+
+#[tokio::test]
+async fn authenticated_projects_are_hidden_from_anonymous_visitors() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let test_project = TestProject::generate_authenticated();
+    let project_id = test_project.register_internally(app.pool(), &user_id);
+
+    let html = app.get_all_projects_page_html().await;
+    assert!(!html.contains(&test_project.title));
+    let html = app.get_view_project_page_html(project_id.as_ref()).await;
+    assert!(html.contains("You have to be authenticated to view this project"));
+    assert!(!html.contains(&test_project.brief));
+    let html = app.get_user_page_html(user_id.as_ref()).await;
+    assert!(!html.contains(&test_project.title));
+
+    user.login(&app).await;
+    let html = app.get_all_projects_page_html().await;
+    assert!(html.contains(&test_project.title));
+    let html = app.get_view_project_page_html(project_id.as_ref()).await;
+    assert!(html.contains(&test_project.brief));
+}
+
+#[tokio::test]
+async fn project_page_lists_visible_blog_posts() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let project_id = TestProject::generate().register_internally(app.pool(), &user_id);
+    let published = TestBlogPost::generate();
+    let draft = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None);
+    for blog_post in [&published, &draft] {
+        let post_id = blog_post.register_internally(app.pool(), &user_id);
+        add_project_blog_post(app.pool(), &project_id, &post_id).unwrap();
+    }
+
+    let html = app.get_view_project_page_html(project_id.as_ref()).await;
+    assert!(html.contains(&published.title));
+    assert!(!html.contains(&draft.title));
+}
+
+#[tokio::test]
+async fn user_page_lists_projects_of_user() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let other_user_id = TestUser::generate().register_internally(app.pool());
+    let own_project = TestProject::generate();
+    own_project.register_internally(app.pool(), &user_id);
+    let edited_project = TestProject::generate();
+    let edited_project_id = edited_project.register_internally(app.pool(), &other_user_id);
+    add_project_editor(app.pool(), &edited_project_id, &user_id).unwrap();
+    let other_project = TestProject::generate();
+    other_project.register_internally(app.pool(), &other_user_id);
+
+    let html = app.get_user_page_html(user_id.as_ref()).await;
+    assert!(html.contains(&own_project.title));
+    assert!(html.contains(&edited_project.title));
+    assert!(!html.contains(&other_project.title));
+}
+
+#[tokio::test]
+async fn unknown_project_is_not_found() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_view_project_page("nothing").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,50 @@
+use crate::api::assert_resp_ok;
+use crate::common::{TestApp, TestBlogPost, TestUser};
+use holosite::domain::blog_posts::BlogPostVisibility;
+
+#[tokio::test]
+async fn search_page_shows_matching_blog_posts() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.contents = "Something about <i>durians</i>".to_string();
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let response = app.get_page("/search").await;
+    assert_resp_ok(&response);
+
+    let html = app.get_page_html("/search?q=durians").await;
+    assert!(html.contains(&blog_post.title));
+    assert!(html.contains("<mark>durians</mark>"));
+    assert!(!html.contains("<i>durians</i>"));
+
+    let html = app.get_page_html("/search?q=rambutans").await;
+    assert!(html.contains("Nothing has been found"));
+}
+
+#[tokio::test]
+async fn protected_blog_posts_are_found_only_by_logged_in_users() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.contents = "lychee".to_string();
+    blog_post.visibility = BlogPostVisibility::Authenticated;
+    blog_post.register_internally(app.pool(), &user_id);
+
+    let html = app.get_page_html("/search?q=lychee").await;
+    assert!(!html.contains(&blog_post.title));
+
+    test_user.login(&app).await;
+    let html = app.get_page_html("/search?q=lychee").await;
+    assert!(html.contains(&blog_post.title));
+}
+
+#[tokio::test]
+async fn too_long_query_is_reported() {
+    let app = TestApp::spawn().await;
+    let html = app
+        .get_page_html(&format!("/search?q={}", "a".repeat(300)))
+        .await;
+    assert!(html.contains("Search query is too long"));
+}
@@ -0,0 +1,73 @@
+use crate::api::{assert_is_redirect_to_resource, assert_resp_ok};
+use crate::common::{extract_csrf_token, TestApp, TestBlogPost, TestUser};
+use holosite::services::get_blog_post_by_title;
+
+#[tokio::test]
+async fn blog_post_can_be_created_with_tags_and_found_by_them() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let csrf = extract_csrf_token(&app.get_create_blog_post_page_html().await);
+
+    let blog_post = TestBlogPost::generate();
+    let mut body = blog_post.to_json(&csrf);
+    body["tags"] = serde_json::json!("Rust, web development");
+    app.post_create_blog_post(&body).await;
+    let blog_post_id = get_blog_post_by_title(app.pool(), &blog_post.title)
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let html = app
+        .get_view_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(html.contains(r#"href="/tags/rust""#));
+    assert!(html.contains(r#"href="/tags/web-development""#));
+
+    let html = app
+        .get_edit_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(html.contains(r#"value="rust, web-development""#));
+
+    let html = app.get_page_html("/tags/RUST").await;
+    assert!(html.contains(&blog_post.title));
+
+    let html = app.get_page_html("/tags").await;
+    assert!(html.contains("web-development"));
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let app = TestApp::spawn().await;
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(app.pool());
+    test_user.login(&app).await;
+    let blog_post_id = TestBlogPost::generate().register_internally(app.pool(), &user_id);
+    let csrf = extract_csrf_token(
+        &app.get_edit_blog_post_page_html(blog_post_id.as_ref())
+            .await,
+    );
+
+    let mut body = TestBlogPost::generate().to_edit_json(&csrf, 1);
+    body["tags"] = serde_json::json!("c++");
+    let response = app.post_edit_blog_post(&body, &blog_post_id).await;
+    assert_is_redirect_to_resource(
+        &response,
+        &format!("/blog_posts/{}/edit", blog_post_id.as_ref()),
+    );
+    let html = app
+        .get_edit_blog_post_page_html(blog_post_id.as_ref())
+        .await;
+    assert!(html.contains("can contain only letters"));
+}
+
+#[tokio::test]
+async fn invalid_tag_page_is_not_found() {
+    let app = TestApp::spawn().await;
+    let response = app.get_page("/tags/a.b").await;
+    assert_eq!(response.status(), 404);
+
+    let response = app.get_page("/tags/nothing").await;
+    assert_resp_ok(&response);
+}
@@ -0,0 +1,229 @@
+use crate::api::assert_is_redirect_to_resource;
+use crate::common::{extract_csrf_token, TestApp, TestUser};
+use holosite::domain::users::TotpSecret;
+use holosite::services::{enable_two_factor, get_user_by_id};
+use regex::Regex;
+use secrecy::ExposeSecret;
+
+fn extract_totp_secret(html: &str) -> TotpSecret {
+    let reg = Regex::new(r#"Secret: <code>([A-Z2-7]+)</code>"#).unwrap();
+    let secret = reg
+        .captures(html)
+        .map(|it| it[1].to_string())
+        .expect("Failed to get TOTP secret");
+    TotpSecret::parse(secret).unwrap()
+}
+
+async fn post_password(app: &TestApp, user: &TestUser) -> reqwest::Response {
+    let csrf = extract_csrf_token(&app.get_login_page_html().await);
+    app.post_login(&serde_json::json!({
+        "name": user.name.as_ref(),
+        "password": user.password.as_ref().expose_secret(),
+        "csrf_token": csrf,
+    }))
+    .await
+}
+
+async fn get_two_factor_csrf_token(app: &TestApp) -> String {
+    extract_csrf_token(&app.get_page_html("/login/two_factor").await)
+}
+
+#[tokio::test]
+async fn enabling_two_factor_requires_valid_code() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let html = app.get_page_html("/account/two_factor/enable").await;
+    let csrf = extract_csrf_token(&html);
+
+    let response = app
+        .post_enable_two_factor(&serde_json::json!({
+            "code": "000000",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/account/two_factor/enable");
+
+    let html = app.get_page_html("/account/two_factor/enable").await;
+    assert!(html.contains("Invalid code"));
+    let user = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert!(user.totp_secret.is_none());
+}
+
+#[tokio::test]
+async fn enabling_two_factor_shows_recovery_codes() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    user.login(&app).await;
+
+    let html = app.get_page_html("/account/two_factor/enable").await;
+    let csrf = extract_csrf_token(&html);
+    let secret = extract_totp_secret(&html);
+
+    let response = app
+        .post_enable_two_factor(&serde_json::json!({
+            "code": secret.current_code(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Save your recovery codes"));
+
+    let stored = get_user_by_id(app.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(stored.totp_secret, Some(secret));
+}
+
+#[tokio::test]
+async fn login_requires_code_when_two_factor_is_enabled() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let secret = TotpSecret::generate_random();
+    enable_two_factor(app.pool(), &user_id, &secret).unwrap();
+
+    let response = post_password(&app, &user).await;
+    assert_is_redirect_to_resource(&response, "/login/two_factor");
+    let csrf = get_two_factor_csrf_token(&app).await;
+
+    // Password alone does not log user in
+    let response = app.get_page("/account/settings").await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let response = app
+        .post_two_factor_login(&serde_json::json!({
+            "code": secret.current_code(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/blog_posts/all");
+
+    let response = app.get_page("/account/settings").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn recovery_code_can_be_used_once_to_log_in() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let codes = enable_two_factor(app.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+    let code = codes[0].as_ref().expose_secret();
+
+    post_password(&app, &user).await;
+    let csrf = get_two_factor_csrf_token(&app).await;
+    let response = app
+        .post_two_factor_login(&serde_json::json!({ "code": code, "csrf_token": csrf }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/blog_posts/all");
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+
+    post_password(&app, &user).await;
+    let csrf = get_two_factor_csrf_token(&app).await;
+    let response = app
+        .post_two_factor_login(&serde_json::json!({ "code": code, "csrf_token": csrf }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login/two_factor");
+}
+
+#[tokio::test]
+async fn too_many_invalid_codes_require_password_again() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let secret = TotpSecret::generate_random();
+    enable_two_factor(app.pool(), &user_id, &secret).unwrap();
+
+    post_password(&app, &user).await;
+    let csrf = get_two_factor_csrf_token(&app).await;
+    for _ in 0..4 {
+        let response = app
+            .post_two_factor_login(&serde_json::json!({
+                "code": "invalid",
+                "csrf_token": csrf,
+            }))
+            .await;
+        assert_is_redirect_to_resource(&response, "/login/two_factor");
+    }
+    let response = app
+        .post_two_factor_login(&serde_json::json!({
+            "code": "invalid",
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+
+    let response = app
+        .post_two_factor_login(&serde_json::json!({
+            "code": secret.current_code(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login");
+}
+
+#[tokio::test]
+async fn code_cant_be_used_twice() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let secret = TotpSecret::generate_random();
+    enable_two_factor(app.pool(), &user_id, &secret).unwrap();
+    let code = secret.current_code();
+
+    post_password(&app, &user).await;
+    let csrf = get_two_factor_csrf_token(&app).await;
+    let response = app
+        .post_two_factor_login(&serde_json::json!({ "code": code, "csrf_token": csrf }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/blog_posts/all");
+    let csrf = extract_csrf_token(&app.get_account_settings_page_html().await);
+    app.post_logout(&serde_json::json!({ "csrf_token": csrf }))
+        .await;
+
+    post_password(&app, &user).await;
+    let csrf = get_two_factor_csrf_token(&app).await;
+    let response = app
+        .post_two_factor_login(&serde_json::json!({ "code": code, "csrf_token": csrf }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login/two_factor");
+}
+
+#[tokio::test]
+async fn invalid_codes_are_counted_across_password_logins() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    let user_id = user.register_internally(app.pool());
+    let secret = TotpSecret::generate_random();
+    enable_two_factor(app.pool(), &user_id, &secret).unwrap();
+
+    for _ in 0..2 {
+        post_password(&app, &user).await;
+        let csrf = get_two_factor_csrf_token(&app).await;
+        for _ in 0..3 {
+            app.post_two_factor_login(&serde_json::json!({
+                "code": "invalid",
+                "csrf_token": csrf,
+            }))
+            .await;
+        }
+    }
+
+    let response = post_password(&app, &user).await;
+    assert_is_redirect_to_resource(&response, "/login/two_factor");
+    let csrf = get_two_factor_csrf_token(&app).await;
+    let response = app
+        .post_two_factor_login(&serde_json::json!({
+            "code": secret.current_code(),
+            "csrf_token": csrf,
+        }))
+        .await;
+    assert_is_redirect_to_resource(&response, "/login/two_factor");
+    let html = app.get_page_html("/login/two_factor").await;
+    assert!(html.contains("Too many invalid codes, try again in"));
+}
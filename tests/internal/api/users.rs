@@ -1,4 +1,6 @@
 use crate::common::{TestApp, TestBlogPost, TestComment, TestUser};
+use holosite::domain::blog_posts::BlogPostStatus;
+use holosite::services::hide_comment;
 
 #[tokio::test]
 async fn test_user_page_works() {
@@ -37,3 +39,76 @@ async fn test_user_page_displays_comment() {
     let html = app.get_user_page_html(user_id.as_ref()).await;
     assert!(html.contains(&test_comment.contents))
 }
+
+#[tokio::test]
+async fn user_blog_posts_and_comments_are_listed_on_separate_pages() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(app.pool(), &user_id);
+    let comment = TestComment::generate();
+    comment.register_internally(app.pool(), &blog_post_id, &user_id);
+
+    let html = app
+        .get_page_html(&format!("/users/{}/blog_posts", user_id.as_ref()))
+        .await;
+    assert!(html.contains(&blog_post.title));
+
+    let html = app
+        .get_page_html(&format!("/users/{}/comments", user_id.as_ref()))
+        .await;
+    assert!(html.contains(&comment.contents));
+}
+
+#[tokio::test]
+async fn user_page_can_be_found_by_name() {
+    let app = TestApp::spawn().await;
+    let user = TestUser::generate();
+    user.register_internally(app.pool());
+
+    let html = app.get_user_page_html(user.name.as_ref()).await;
+    assert!(html.contains(user.name.as_ref()));
+}
+
+#[tokio::test]
+async fn hidden_comments_are_not_shown_on_user_pages() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let moderator_id = TestUser::generate().register_internally(app.pool());
+    let blog_post_id = TestBlogPost::generate().register_internally(app.pool(), &user_id);
+    let comment = TestComment::generate();
+    let comment_id = comment.register_internally(app.pool(), &blog_post_id, &user_id);
+    hide_comment(app.pool(), &comment_id, &moderator_id, "Spam").unwrap();
+
+    let html = app.get_user_page_html(user_id.as_ref()).await;
+    assert!(!html.contains(&comment.contents));
+    let html = app
+        .get_page_html(&format!("/users/{}/comments", user_id.as_ref()))
+        .await;
+    assert!(!html.contains(&comment.contents));
+}
+
+#[tokio::test]
+async fn comments_on_posts_hidden_from_visitor_are_not_listed() {
+    let app = TestApp::spawn().await;
+    let user_id = TestUser::generate().register_internally(app.pool());
+    let draft = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None);
+    let draft_id = draft.register_internally(app.pool(), &user_id);
+    let authenticated = TestBlogPost::generate_authenticated();
+    let authenticated_id = authenticated.register_internally(app.pool(), &user_id);
+    let on_draft = TestComment::generate();
+    on_draft.register_internally(app.pool(), &draft_id, &user_id);
+    let on_authenticated = TestComment::generate();
+    on_authenticated.register_internally(app.pool(), &authenticated_id, &user_id);
+
+    let html = app.get_user_page_html(user_id.as_ref()).await;
+    assert!(!html.contains(&on_draft.contents));
+    assert!(!html.contains(&on_authenticated.contents));
+    let html = app
+        .get_page_html(&format!("/users/{}/comments", user_id.as_ref()))
+        .await;
+    assert!(!html.contains(&on_draft.contents));
+    assert!(!html.contains(&on_authenticated.contents));
+    assert!(!html.contains(&draft.title));
+    assert!(!html.contains(&authenticated.title));
+}
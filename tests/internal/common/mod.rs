@@ -4,7 +4,7 @@ mod test_comment;
 mod test_project;
 mod test_user;
 
-use holosite::config::Config;
+use holosite::config::{AttemptStorageConfig, Config, MediaStorageConfig};
 use once_cell::sync::Lazy;
 use regex::Regex;
 pub use test_app::*;
@@ -45,10 +45,50 @@ pub(crate) fn get_test_config() -> Config {
     c.database_uri = ":memory:".to_string();
     c.app.port = 0;
     c.app.workers = Some(1);
+    // Every test client connects from the same address, so counters must not be shared
+    c.app.login_throttle.storage = AttemptStorageConfig::Memory;
+    c.app.publish_interval_seconds = 1;
+    c.media.storage = MediaStorageConfig::Local {
+        path: test_media_dir(),
+    };
 
     c
 }
 
+/// Separate directory for uploads of each test
+pub(crate) fn test_media_dir() -> String {
+    std::env::temp_dir()
+        .join(format!("holosite-test-media-{}", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// PNG image with random pixels, so that it can't be compressed
+pub fn test_png(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_fn(width, height, |_, _| image::Rgb(rand::random()));
+    let mut data = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+    data
+}
+
+/// JPEG image without metadata
+pub fn test_jpeg(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([40, 120, 200]));
+    let mut data = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Jpeg,
+        )
+        .unwrap();
+    data
+}
+
 embed_migrations!();
 
 pub struct TestDB {
@@ -3,14 +3,17 @@ use crate::common::{get_test_config, init_tracing, TestDB};
 use holosite::domain::blog_posts::BlogPostID;
 use holosite::domain::comments::CommentID;
 use holosite::domain::projects::ProjectID;
+use holosite::mail::{Email, InMemoryMailer};
 use holosite::startup::Application;
 use holosite::Pool;
 use reqwest::Response;
+use std::sync::Arc;
 
 pub struct TestApp {
     address: String,
     db: TestDB,
     api_client: reqwest::Client,
+    mailer: Arc<InMemoryMailer>,
 }
 
 impl TestApp {
@@ -18,7 +21,8 @@ impl TestApp {
         init_tracing();
         let config = get_test_config();
         let db = TestDB::new(&config.database_uri);
-        let app = Application::build_with_pool(config.clone(), db.pool().clone())
+        let mailer = Arc::new(InMemoryMailer::new());
+        let app = Application::build_with_pool(config.clone(), db.pool().clone(), mailer.clone())
             .await
             .expect("Failed to build application");
 
@@ -36,6 +40,7 @@ impl TestApp {
             address,
             db,
             api_client: client,
+            mailer,
         }
     }
 
@@ -43,12 +48,12 @@ impl TestApp {
         self.db.pool()
     }
 
-    pub async fn post_logout(&self) -> Response {
-        self.api_client
-            .get(format!("{}/logout", &self.address))
-            .send()
-            .await
-            .expect("Failed to execute request")
+    pub fn sent_emails(&self) -> Vec<Email> {
+        self.mailer.sent()
+    }
+
+    pub async fn post_logout(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/logout", body).await
     }
 
     pub async fn get_health_check(&self) -> Response {
@@ -93,6 +98,26 @@ impl TestApp {
         self.post("/account/change_name", body).await
     }
 
+    pub async fn post_change_email(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/account/change_email", body).await
+    }
+
+    pub async fn post_two_factor_login(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/login/two_factor", body).await
+    }
+
+    pub async fn post_enable_two_factor(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/account/two_factor/enable", body).await
+    }
+
+    pub async fn post_password_reset(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/password_reset", body).await
+    }
+
+    pub async fn post_password_reset_confirm(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/password_reset/confirm", body).await
+    }
+
     pub async fn post_create_blog_post(&self, body: &impl serde::Serialize) -> Response {
         self.post("/blog_posts/create", body).await
     }
@@ -138,22 +163,60 @@ impl TestApp {
 
     pub async fn post_delete_comment(
         &self,
+        body: &impl serde::Serialize,
         post_id: &BlogPostID,
         comment_id: &CommentID,
     ) -> Response {
-        self.api_client
-            .get(
-                format!(
-                    "{}/blog_posts/{}/comments/{}/delete",
-                    &self.address,
-                    post_id.as_ref(),
-                    comment_id.as_ref()
-                )
-                .as_str(),
+        self.post(
+            format!(
+                "/blog_posts/{}/comments/{}/delete",
+                post_id.as_ref(),
+                comment_id.as_ref()
             )
-            .send()
-            .await
-            .expect("Failed to execute request")
+            .as_str(),
+            body,
+        )
+        .await
+    }
+
+    pub async fn post_hide_comment(
+        &self,
+        body: &impl serde::Serialize,
+        post_id: &BlogPostID,
+        comment_id: &CommentID,
+    ) -> Response {
+        self.post(
+            format!(
+                "/blog_posts/{}/comments/{}/hide",
+                post_id.as_ref(),
+                comment_id.as_ref()
+            )
+            .as_str(),
+            body,
+        )
+        .await
+    }
+
+    pub async fn post_restore_comment(
+        &self,
+        body: &impl serde::Serialize,
+        post_id: &BlogPostID,
+        comment_id: &CommentID,
+    ) -> Response {
+        self.post(
+            format!(
+                "/blog_posts/{}/comments/{}/restore",
+                post_id.as_ref(),
+                comment_id.as_ref()
+            )
+            .as_str(),
+            body,
+        )
+        .await
+    }
+
+    pub async fn post_preview(&self, body: &impl serde::Serialize) -> Response {
+        self.post("/preview", body).await
     }
 
     pub async fn post_create_project(&self, body: &impl serde::Serialize) -> Response {
@@ -230,13 +293,21 @@ impl TestApp {
         self.get_page_html("/projects/all").await
     }
 
+    pub async fn get_create_project_page(&self) -> Response {
+        self.get_page("/projects/create").await
+    }
+
+    pub async fn get_create_project_page_html(&self) -> String {
+        self.get_page_html("/projects/create").await
+    }
+
     pub async fn get_edit_project_page(&self, id: &str) -> Response {
-        self.get_page(format!("/blog_posts/{}/edit", id).as_str())
+        self.get_page(format!("/projects/{}/edit", id).as_str())
             .await
     }
 
     pub async fn get_edit_project_page_html(&self, id: &str) -> String {
-        self.get_page_html(format!("/blog_posts/{}/edit", id).as_str())
+        self.get_page_html(format!("/projects/{}/edit", id).as_str())
             .await
     }
 
@@ -268,6 +339,61 @@ impl TestApp {
             .expect("Failed to execute request")
     }
 
+    /// Sends form with CSRF token passed in header instead of form field
+    pub async fn post_with_csrf_header<Body>(
+        &self,
+        rel_addr: &str,
+        body: &Body,
+        csrf: &str,
+    ) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}{}", &self.address, rel_addr))
+            .header("X-CSRF-Token", csrf)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// Uploads file as multipart form, like browser does.
+    /// Scripts of editor ask for JSON, forms of media library are redirected.
+    pub async fn post_media_upload(
+        &self,
+        csrf: &str,
+        file_name: &str,
+        data: &[u8],
+        accept_json: bool,
+    ) -> Response {
+        const BOUNDARY: &str = "----test-boundary";
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"csrf_token\"\r\n\r\n{csrf}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            b = BOUNDARY,
+            csrf = csrf,
+            name = file_name
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        let mut request = self
+            .api_client
+            .post(format!("{}/account/media/upload", &self.address))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(body);
+        if accept_json {
+            request = request.header("Accept", "application/json");
+        }
+        request.send().await.expect("Failed to execute request")
+    }
+
     pub async fn get_page_html(&self, rel_address: &str) -> String {
         let response = self.get_page(rel_address).await;
         assert_resp_ok(&response);
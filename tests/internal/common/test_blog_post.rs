@@ -1,4 +1,5 @@
-use holosite::domain::blog_posts::{BlogPostID, BlogPostVisibility, NewBlogPost};
+use holosite::domain::blog_posts::{BlogPostID, BlogPostStatus, BlogPostVisibility, NewBlogPost};
+use holosite::domain::time::DateTime;
 use holosite::domain::users::UserID;
 use holosite::services::insert_new_blog_post;
 use holosite::Pool;
@@ -9,6 +10,8 @@ pub struct TestBlogPost {
     pub brief: String,
     pub contents: String,
     pub visibility: BlogPostVisibility,
+    pub status: BlogPostStatus,
+    pub publish_at: Option<DateTime>,
 }
 
 impl TestBlogPost {
@@ -18,9 +21,18 @@ impl TestBlogPost {
             brief: Uuid::new_v4().to_string(),
             contents: Uuid::new_v4().to_string(),
             visibility: BlogPostVisibility::All,
+            status: BlogPostStatus::Published,
+            publish_at: None,
         }
     }
 
+    pub fn generate_with_status(status: BlogPostStatus, publish_at: Option<DateTime>) -> Self {
+        let mut result = Self::generate();
+        result.status = status;
+        result.publish_at = publish_at;
+        result
+    }
+
     pub fn generate_authenticated() -> Self {
         let mut result = Self::generate();
         result.visibility = BlogPostVisibility::Authenticated;
@@ -46,6 +58,13 @@ impl TestBlogPost {
         }
     }
 
+    /// Body of form editing post, which was filled from given version of it
+    pub fn to_edit_json(&self, csrf: &str, version: i32) -> serde_json::Value {
+        let mut body = self.to_json(csrf);
+        body["version"] = serde_json::json!(version);
+        body
+    }
+
     pub fn register_internally(&self, pool: &Pool, author_id: &UserID) -> BlogPostID {
         let new_blog_post = NewBlogPost {
             title: self.title.as_str(),
@@ -53,6 +72,9 @@ impl TestBlogPost {
             contents: self.contents.as_str(),
             author_id,
             visibility: self.visibility.clone(),
+            status: self.status,
+            publish_at: self.publish_at.clone(),
+            tags: &[],
         };
         insert_new_blog_post(pool, &new_blog_post)
             .expect("Failed to insert blog post")
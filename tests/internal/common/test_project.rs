@@ -7,6 +7,7 @@ use uuid::Uuid;
 pub struct TestProject {
     pub title: String,
     pub brief: String,
+    pub visibility: ProjectVisibility,
 }
 
 impl TestProject {
@@ -14,14 +15,31 @@ impl TestProject {
         Self {
             title: Uuid::new_v4().to_string(),
             brief: Uuid::new_v4().to_string(),
+            visibility: ProjectVisibility::All,
         }
     }
 
-    pub fn to_json(&self) -> serde_json::Value {
-        serde_json::json!({
-            "title": self.title.clone(),
-            "brief": self.brief.clone(),
-        })
+    pub fn generate_authenticated() -> Self {
+        let mut result = Self::generate();
+        result.visibility = ProjectVisibility::Authenticated;
+        result
+    }
+
+    pub fn to_json(&self, csrf: &str) -> serde_json::Value {
+        if self.visibility == ProjectVisibility::All {
+            serde_json::json!({
+                "csrf_token": csrf,
+                "title": self.title.clone(),
+                "brief": self.brief.clone(),
+                "visible_to_all": "on",
+            })
+        } else {
+            serde_json::json!({
+                "csrf_token": csrf,
+                "title": self.title.clone(),
+                "brief": self.brief.clone(),
+            })
+        }
     }
 
     pub fn register_internally(&self, pool: &Pool, author_id: &UserID) -> ProjectID {
@@ -29,10 +47,10 @@ impl TestProject {
             title: self.title.as_str(),
             brief: self.brief.as_str(),
             author_id,
-            visibility: ProjectVisibility::All,
+            visibility: self.visibility.clone(),
         };
         insert_new_project(pool, &new_project)
-            .expect("Failed to insert blog post")
+            .expect("Failed to insert project")
             .id
     }
 }
@@ -1,13 +1,18 @@
 use crate::api::assert_is_redirect_to_resource;
 use crate::common::test_app::TestApp;
-use holosite::domain::users::{NewUser, UserID, UserName, UserPassword};
-use holosite::services::insert_new_user;
+use crate::common::{extract_csrf_token, get_test_config};
+use holosite::domain::users::{
+    NewUser, UpdateUser, UserEmail, UserID, UserName, UserPassword, UserRole,
+};
+use holosite::services::{insert_new_user, update_user};
 use holosite::Pool;
 use secrecy::ExposeSecret;
 use secrecy::Secret;
+use uuid::Uuid;
 
 pub struct TestUser {
     pub name: UserName,
+    pub email: UserEmail,
     pub password: UserPassword,
 }
 
@@ -15,6 +20,7 @@ impl TestUser {
     pub fn generate() -> Self {
         Self {
             name: UserName::generate_random(),
+            email: UserEmail::parse(format!("{}@email.com", Uuid::new_v4())).expect("OOps"),
             password: UserPassword::parse(Secret::new("!1Aapass".to_string())).expect("OOps"),
         }
     }
@@ -22,18 +28,47 @@ impl TestUser {
     pub fn register_internally(&self, pool: &Pool) -> UserID {
         let new_user = NewUser {
             name: self.name.clone(),
+            email: self.email.clone(),
             password: self.password.clone(),
         };
-        insert_new_user(pool, &new_user)
+        insert_new_user(pool, &new_user, &get_test_config().app.password_hashing)
             .expect("Failed to insert new user")
             .id
     }
 
+    pub fn register_admin_internally(&self, pool: &Pool) -> UserID {
+        self.register_with_role_internally(pool, UserRole::Admin)
+    }
+
+    pub fn register_moderator_internally(&self, pool: &Pool) -> UserID {
+        self.register_with_role_internally(pool, UserRole::Moderator)
+    }
+
+    fn register_with_role_internally(&self, pool: &Pool, role: UserRole) -> UserID {
+        let id = self.register_internally(pool);
+        update_user(
+            pool,
+            &UpdateUser {
+                id: &id,
+                name: None,
+                email: None,
+                password: None,
+                password_salt: None,
+                is_banned: None,
+                role: Some(role),
+            },
+        )
+        .expect("Failed to change user role");
+        id
+    }
+
     pub async fn login(&self, app: &TestApp) {
+        let csrf = extract_csrf_token(&app.get_login_page_html().await);
         let response = app
             .post_login(&serde_json::json!({
                 "name": self.name.as_ref(),
-                "password": self.password.as_ref().expose_secret()
+                "password": self.password.as_ref().expose_secret(),
+                "csrf_token": csrf,
             }))
             .await;
         assert_is_redirect_to_resource(&response, "/blog_posts/all");
@@ -0,0 +1,96 @@
+use crate::common::{TestBlogPost, TestDB, TestUser};
+use claim::{assert_none, assert_some};
+use holosite::domain::blog_posts::AutosaveSlot;
+use holosite::services::{
+    delete_autosave, delete_expired_autosaves, get_autosave, save_autosave, AutosaveContents,
+};
+
+fn text(contents: &str) -> AutosaveContents<'_> {
+    AutosaveContents {
+        title: "Title",
+        brief: "Brief",
+        contents,
+        tags: "rust, web",
+    }
+}
+
+#[test]
+fn autosave_is_stored_and_replaced() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    save_autosave(
+        db.pool(),
+        &user_id,
+        AutosaveSlot::NewBlogPost,
+        &text("First"),
+    )
+    .unwrap();
+    save_autosave(
+        db.pool(),
+        &user_id,
+        AutosaveSlot::NewBlogPost,
+        &text("Second"),
+    )
+    .unwrap();
+
+    let autosave = get_autosave(db.pool(), &user_id, &AutosaveSlot::NewBlogPost)
+        .unwrap()
+        .unwrap();
+    assert_eq!(autosave.slot, AutosaveSlot::NewBlogPost);
+    assert_eq!(autosave.title, "Title");
+    assert_eq!(autosave.brief, "Brief");
+    assert_eq!(autosave.contents, "Second");
+    assert_eq!(autosave.tags, "rust, web");
+}
+
+#[test]
+fn slots_are_separate_for_users_and_posts() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let post_slot = AutosaveSlot::BlogPost(post_id);
+
+    save_autosave(db.pool(), &user_id, AutosaveSlot::NewBlogPost, &text("New")).unwrap();
+    save_autosave(db.pool(), &user_id, post_slot.clone(), &text("Edited")).unwrap();
+
+    let autosave = get_autosave(db.pool(), &user_id, &post_slot)
+        .unwrap()
+        .unwrap();
+    assert_eq!(autosave.slot, post_slot);
+    assert_eq!(autosave.contents, "Edited");
+    assert_none!(get_autosave(db.pool(), &other_user_id, &AutosaveSlot::NewBlogPost).unwrap());
+
+    delete_autosave(db.pool(), &user_id, &post_slot).unwrap();
+    assert_none!(get_autosave(db.pool(), &user_id, &post_slot).unwrap());
+    assert_some!(get_autosave(db.pool(), &user_id, &AutosaveSlot::NewBlogPost).unwrap());
+}
+
+#[test]
+fn expired_autosaves_are_not_returned_and_cleaned_up() {
+    use diesel::RunQueryDsl;
+
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_user_id = TestUser::generate().register_internally(db.pool());
+    save_autosave(db.pool(), &user_id, AutosaveSlot::NewBlogPost, &text("Old")).unwrap();
+    save_autosave(
+        db.pool(),
+        &other_user_id,
+        AutosaveSlot::NewBlogPost,
+        &text("New"),
+    )
+    .unwrap();
+    diesel::sql_query(format!(
+        "update blog_post_autosaves set expires_at = '2000-01-01 00:00:00 UTC' where user_id = '{}'",
+        user_id.as_ref()
+    ))
+    .execute(&db.pool().get().unwrap())
+    .unwrap();
+
+    assert_none!(get_autosave(db.pool(), &user_id, &AutosaveSlot::NewBlogPost).unwrap());
+    assert_eq!(delete_expired_autosaves(db.pool()).unwrap(), 1);
+    assert_eq!(delete_expired_autosaves(db.pool()).unwrap(), 0);
+    assert_some!(get_autosave(db.pool(), &other_user_id, &AutosaveSlot::NewBlogPost).unwrap());
+}
@@ -0,0 +1,116 @@
+use crate::common::{TestBlogPost, TestDB, TestUser};
+use claim::{assert_none, assert_ok, assert_some};
+use holosite::domain::blog_posts::{BlogPostID, UpdateBlogPost};
+use holosite::domain::users::UserID;
+use holosite::services::{
+    get_blog_post_by_id, get_blog_post_revision, get_blog_post_revisions,
+    restore_blog_post_revision, update_blog_post,
+};
+use holosite::Pool;
+
+fn change_contents(pool: &Pool, post_id: &BlogPostID, editor_id: &UserID, contents: &str) {
+    update_blog_post(
+        pool,
+        &UpdateBlogPost {
+            id: post_id,
+            title: None,
+            brief: None,
+            contents: Some(contents),
+            visibility: None,
+            status: None,
+            publish_at: None,
+        },
+        None,
+        editor_id,
+        None,
+    )
+    .expect("Failed to update blog post");
+}
+
+#[test]
+fn new_blog_post_has_first_revision() {
+    let db = TestDB::spawn();
+    let user = TestUser::generate();
+    let user_id = user.register_internally(db.pool());
+    let test_post = TestBlogPost::generate();
+    let post_id = test_post.register_internally(db.pool(), &user_id);
+
+    let revisions = get_blog_post_revisions(db.pool(), &post_id).unwrap();
+    assert_eq!(revisions.len(), 1);
+    let (revision, author_name) = &revisions[0];
+    assert_eq!(revision.number, 1);
+    assert_eq!(revision.author_id, user_id);
+    assert_eq!(author_name, &user.name);
+    assert_eq!(revision.title, test_post.title);
+    assert_eq!(revision.contents, test_post.contents);
+}
+
+#[test]
+fn every_edit_creates_revision_with_its_editor() {
+    let db = TestDB::spawn();
+    let author_id = TestUser::generate().register_internally(db.pool());
+    let editor_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &author_id);
+
+    change_contents(db.pool(), &post_id, &author_id, "Second");
+    change_contents(db.pool(), &post_id, &editor_id, "Third");
+
+    let revisions = get_blog_post_revisions(db.pool(), &post_id).unwrap();
+    let numbers: Vec<i32> = revisions.iter().map(|(r, _)| r.number).collect();
+    assert_eq!(numbers, vec![3, 2, 1]);
+    assert_eq!(revisions[0].0.contents, "Third");
+    assert_eq!(revisions[0].0.author_id, editor_id);
+    assert_eq!(revisions[1].0.contents, "Second");
+    assert_eq!(revisions[1].0.author_id, author_id);
+}
+
+#[test]
+fn saving_unchanged_text_does_not_create_revision() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let test_post = TestBlogPost::generate();
+    let post_id = test_post.register_internally(db.pool(), &user_id);
+
+    change_contents(db.pool(), &post_id, &user_id, &test_post.contents);
+
+    let revisions = get_blog_post_revisions(db.pool(), &post_id).unwrap();
+    assert_eq!(revisions.len(), 1);
+}
+
+#[test]
+fn get_blog_post_revision_works() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    change_contents(db.pool(), &post_id, &user_id, "Second");
+
+    let res = get_blog_post_revision(db.pool(), &post_id, 2);
+    assert_ok!(&res);
+    let res = res.unwrap();
+    assert_some!(&res);
+    assert_eq!(res.unwrap().contents, "Second");
+
+    assert_none!(get_blog_post_revision(db.pool(), &post_id, 3).unwrap());
+}
+
+#[test]
+fn restore_creates_new_revision_from_old_one() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let test_post = TestBlogPost::generate();
+    let post_id = test_post.register_internally(db.pool(), &user_id);
+    change_contents(db.pool(), &post_id, &user_id, "Accidental edit");
+
+    let first = get_blog_post_revision(db.pool(), &post_id, 1)
+        .unwrap()
+        .unwrap();
+    assert_ok!(restore_blog_post_revision(db.pool(), &first, &user_id));
+
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(post.contents, test_post.contents);
+    let revisions = get_blog_post_revisions(db.pool(), &post_id).unwrap();
+    assert_eq!(revisions.len(), 3);
+    assert_eq!(revisions[0].0.number, 3);
+    assert_eq!(revisions[0].0.contents, test_post.contents);
+    assert_eq!(revisions[1].0.contents, "Accidental edit");
+}
@@ -1,9 +1,13 @@
 use crate::common::{TestBlogPost, TestDB, TestUser};
 use claim::{assert_err, assert_ok, assert_some};
-use holosite::domain::blog_posts::{BlogPostID, BlogPostVisibility, NewBlogPost, UpdateBlogPost};
+use holosite::domain::blog_posts::{
+    BlogPostID, BlogPostStatus, BlogPostVisibility, NewBlogPost, UpdateBlogPost,
+};
+use holosite::domain::time::DateTime;
 use holosite::services::{
     get_all_blog_posts, get_blog_post_by_id, get_blog_post_by_title, insert_new_blog_post,
-    update_blog_post, BlogPostError,
+    publish_scheduled_blog_posts, set_blog_post_comments_locked, update_blog_post, BlogPostError,
+    PageRequest, SortOrder,
 };
 
 #[test]
@@ -21,6 +25,9 @@ fn add_new_blog_post_works() {
             contents: &test_post.contents,
             author_id: &user_id,
             visibility: BlogPostVisibility::All,
+            status: BlogPostStatus::Published,
+            publish_at: None,
+            tags: &[],
         },
     );
     assert_ok!(&res);
@@ -80,8 +87,10 @@ fn update_blog_post_title_works() {
         brief: None,
         contents: None,
         visibility: None,
+        status: None,
+        publish_at: None,
     };
-    let res = update_blog_post(db.pool(), &changeset);
+    let res = update_blog_post(db.pool(), &changeset, None, &user_id, None);
     assert_ok!(res);
 
     let res = get_blog_post_by_id(db.pool(), &post_id);
@@ -113,7 +122,12 @@ fn cant_change_blog_post_title_to_taken_title() {
             brief: None,
             contents: None,
             visibility: None,
+            status: None,
+            publish_at: None,
         },
+        None,
+        &user_id,
+        None,
     );
     assert_err!(&res);
     let res = res.unwrap_err();
@@ -137,8 +151,10 @@ fn change_blog_post_brief_works() {
         brief: Some("New Brief"),
         contents: None,
         visibility: None,
+        status: None,
+        publish_at: None,
     };
-    let res = update_blog_post(db.pool(), &changeset);
+    let res = update_blog_post(db.pool(), &changeset, None, &user_id, None);
     assert_ok!(res);
 
     let res = get_blog_post_by_id(db.pool(), &post_id);
@@ -165,8 +181,10 @@ fn change_blog_post_contents_works() {
         brief: None,
         contents: Some("New contents"),
         visibility: None,
+        status: None,
+        publish_at: None,
     };
-    let res = update_blog_post(db.pool(), &changeset);
+    let res = update_blog_post(db.pool(), &changeset, None, &user_id, None);
     assert_ok!(res);
 
     let res = get_blog_post_by_id(db.pool(), &post_id);
@@ -195,6 +213,9 @@ fn cant_add_new_blog_post_with_taken_title() {
             contents: &test_post.contents,
             author_id: &user_id,
             visibility: BlogPostVisibility::All,
+            status: BlogPostStatus::Published,
+            publish_at: None,
+            tags: &[],
         },
     );
     assert_err!(&res);
@@ -217,11 +238,163 @@ fn get_all_blog_posts_works() {
         })
         .collect();
 
-    let res = get_all_blog_posts(db.pool());
+    let res = get_all_blog_posts(
+        db.pool(),
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    );
     assert_ok!(&res);
-    let res = res.unwrap();
+    let res = res.unwrap().items;
     assert_eq!(res.len(), post_ids.len());
     for i in 0..res.len() {
         assert_eq!(res[i].id, post_ids[i]);
     }
 }
+
+#[test]
+fn lock_comments_works() {
+    let db = TestDB::spawn();
+    let test_user = TestUser::generate();
+    let user_id = test_user.register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert!(!post.comments_locked);
+
+    assert_ok!(set_blog_post_comments_locked(db.pool(), &post_id, true));
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert!(post.comments_locked);
+
+    assert_ok!(set_blog_post_comments_locked(db.pool(), &post_id, false));
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert!(!post.comments_locked);
+}
+
+#[test]
+fn published_blog_post_gets_publication_time() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(post.status, BlogPostStatus::Published);
+    assert_some!(post.publish_at);
+}
+
+#[test]
+fn get_all_blog_posts_returns_only_published() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let published_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_with_status(
+        BlogPostStatus::Scheduled,
+        Some(DateTime::now().plus(chrono::Duration::days(1))),
+    )
+    .register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_with_status(BlogPostStatus::Archived, None)
+        .register_internally(db.pool(), &user_id);
+
+    let res = get_all_blog_posts(db.pool(), &PageRequest::default())
+        .unwrap()
+        .items;
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].id, published_id);
+}
+
+#[test]
+fn publish_scheduled_blog_posts_publishes_only_due_posts() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let due_id = TestBlogPost::generate_with_status(
+        BlogPostStatus::Scheduled,
+        Some(DateTime::now().plus(chrono::Duration::minutes(-1))),
+    )
+    .register_internally(db.pool(), &user_id);
+    let future_id = TestBlogPost::generate_with_status(
+        BlogPostStatus::Scheduled,
+        Some(DateTime::now().plus(chrono::Duration::days(1))),
+    )
+    .register_internally(db.pool(), &user_id);
+    let draft_id = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+    let before = get_blog_post_by_id(db.pool(), &due_id).unwrap().unwrap();
+
+    let published = publish_scheduled_blog_posts(db.pool());
+    assert_ok!(&published);
+    assert_eq!(published.unwrap(), 1);
+
+    let status_of = |post_id| {
+        get_blog_post_by_id(db.pool(), post_id)
+            .unwrap()
+            .unwrap()
+            .status
+    };
+    assert_eq!(status_of(&due_id), BlogPostStatus::Published);
+    assert_eq!(status_of(&future_id), BlogPostStatus::Scheduled);
+    assert_eq!(status_of(&draft_id), BlogPostStatus::Draft);
+    let after = get_blog_post_by_id(db.pool(), &due_id).unwrap().unwrap();
+    assert_eq!(after.version, before.version + 1);
+    assert!(after.updated_at.as_ref() >= before.updated_at.as_ref());
+}
+
+#[test]
+fn update_blog_post_increments_version_and_updated_at() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let before = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(before.version, 1);
+
+    let changeset = UpdateBlogPost {
+        id: &post_id,
+        title: None,
+        brief: Some("New brief"),
+        contents: None,
+        visibility: None,
+        status: None,
+        publish_at: None,
+    };
+    assert_ok!(update_blog_post(
+        db.pool(),
+        &changeset,
+        None,
+        &user_id,
+        Some(1)
+    ));
+
+    let after = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(after.version, 2);
+    assert_ne!(after.updated_at, before.updated_at);
+}
+
+#[test]
+fn update_blog_post_with_stale_version_is_rejected() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    let first = UpdateBlogPost {
+        id: &post_id,
+        title: None,
+        brief: None,
+        contents: Some("First editor contents"),
+        visibility: None,
+        status: None,
+        publish_at: None,
+    };
+    assert_ok!(update_blog_post(db.pool(), &first, None, &user_id, Some(1)));
+
+    let second = UpdateBlogPost {
+        contents: Some("Second editor contents"),
+        ..first
+    };
+    let res = update_blog_post(db.pool(), &second, None, &user_id, Some(1));
+    match res {
+        Err(BlogPostError::Conflict) => {}
+        _ => panic!("Expected conflict: got {:?}", res),
+    };
+
+    let post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(post.contents, "First editor contents");
+    assert_eq!(post.version, 2);
+}
@@ -1,10 +1,12 @@
 use crate::common::{TestBlogPost, TestComment, TestDB, TestUser};
 use claim::{assert_ok, assert_some};
+use holosite::domain::blog_posts::BlogPostStatus;
 use holosite::domain::comments::{CommentID, NewComment, UpdateComment};
 use holosite::domain::users::UserName;
 use holosite::services::{
     get_comment_by_id, get_comment_views_for_blog_post, get_comments_for_blog_post,
-    get_comments_of_author, insert_new_comment, update_comment,
+    get_comments_of_author, hide_comment, insert_new_comment, restore_comment, update_comment,
+    CommentError, PageRequest, SortOrder,
 };
 
 #[test]
@@ -58,9 +60,14 @@ fn get_comment_by_author_works() {
     let comment = TestComment::generate();
     comment.register_internally(db.pool(), &blog_post_id, &user_id);
 
-    let res = get_comments_of_author(db.pool(), &user_id);
+    let res = get_comments_of_author(
+        db.pool(),
+        &user_id,
+        false,
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    );
     assert_ok!(&res);
-    let res = res.unwrap();
+    let res = res.unwrap().items;
     assert_eq!(res.len(), 1);
     assert_eq!(res[0].contents, comment.contents);
 }
@@ -80,15 +87,84 @@ fn get_comment_by_author_returns_all_comments() {
         })
         .collect();
 
-    let res = get_comments_of_author(db.pool(), &user_id);
+    let res = get_comments_of_author(
+        db.pool(),
+        &user_id,
+        false,
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    );
     assert_ok!(&res);
-    let res = res.unwrap();
+    let res = res.unwrap().items;
     assert_eq!(res.len(), comment_ids.len());
     for i in 0..res.len() {
         assert_eq!(res[i].id, comment_ids[i]);
     }
 }
 
+#[test]
+fn hidden_and_deleted_comments_of_author_are_not_listed() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let moderator_id = TestUser::generate().register_internally(db.pool());
+    let blog_post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    let visible = TestComment::generate().register_internally(db.pool(), &blog_post_id, &user_id);
+    let hidden = TestComment::generate().register_internally(db.pool(), &blog_post_id, &user_id);
+    hide_comment(db.pool(), &hidden, &moderator_id, "Spam").unwrap();
+    let deleted = TestComment::generate().register_internally(db.pool(), &blog_post_id, &user_id);
+    let changeset = UpdateComment {
+        id: &deleted,
+        contents: None,
+        is_deleted: Some(true),
+    };
+    update_comment(db.pool(), &changeset, None).unwrap();
+
+    let listed: Vec<CommentID> = get_comments_of_author(
+        db.pool(),
+        &user_id,
+        false,
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    )
+    .unwrap()
+    .items
+    .into_iter()
+    .map(|comment| comment.id)
+    .collect();
+    assert_eq!(listed, vec![visible]);
+}
+
+#[test]
+fn comments_of_author_are_listed_only_on_posts_visible_to_viewer() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let published = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let draft = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+    let authenticated =
+        TestBlogPost::generate_authenticated().register_internally(db.pool(), &user_id);
+
+    let visible = TestComment::generate().register_internally(db.pool(), &published, &user_id);
+    TestComment::generate().register_internally(db.pool(), &draft, &user_id);
+    let for_authenticated =
+        TestComment::generate().register_internally(db.pool(), &authenticated, &user_id);
+
+    let listed = |include_authenticated| {
+        get_comments_of_author(
+            db.pool(),
+            &user_id,
+            include_authenticated,
+            &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+        )
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|comment| comment.id)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(listed(false), vec![visible.clone()]);
+    assert_eq!(listed(true), vec![visible, for_authenticated]);
+}
+
 #[test]
 fn get_comment_by_blog_post_works() {
     let db = TestDB::spawn();
@@ -171,7 +247,7 @@ fn update_comment_contents_works() {
         contents: Some("New contents"),
         is_deleted: None,
     };
-    let res = update_comment(db.pool(), &changeset);
+    let res = update_comment(db.pool(), &changeset, None);
     assert_ok!(&res);
 
     let res = get_comment_by_id(db.pool(), &comment_id);
@@ -208,3 +284,80 @@ fn test_get_comment_view_works() {
         assert_eq!(res[i].author_name, user_names[i]);
     }
 }
+
+#[test]
+fn hide_comment_works() {
+    let db = TestDB::spawn();
+    let user = TestUser::generate();
+    let user_id = user.register_internally(db.pool());
+    let moderator = TestUser::generate();
+    let moderator_id = moderator.register_moderator_internally(db.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(db.pool(), &user_id);
+    let comment = TestComment::generate();
+    let comment_id = comment.register_internally(db.pool(), &blog_post_id, &user_id);
+
+    assert_ok!(hide_comment(db.pool(), &comment_id, &moderator_id, "Spam"));
+
+    let stored = get_comment_by_id(db.pool(), &comment_id).unwrap().unwrap();
+    assert!(stored.is_hidden);
+    assert_eq!(stored.hidden_reason.as_deref(), Some("Spam"));
+    assert_eq!(stored.hidden_by, Some(moderator_id));
+    // Contents are kept so that comment can be restored
+    assert_eq!(stored.contents, comment.contents);
+
+    let views = get_comment_views_for_blog_post(db.pool(), &blog_post_id).unwrap();
+    assert!(views[0].is_hidden);
+    assert_eq!(views[0].hidden_reason.as_deref(), Some("Spam"));
+}
+
+#[test]
+fn restore_comment_works() {
+    let db = TestDB::spawn();
+    let user = TestUser::generate();
+    let user_id = user.register_internally(db.pool());
+    let moderator = TestUser::generate();
+    let moderator_id = moderator.register_moderator_internally(db.pool());
+    let blog_post = TestBlogPost::generate();
+    let blog_post_id = blog_post.register_internally(db.pool(), &user_id);
+    let comment = TestComment::generate();
+    let comment_id = comment.register_internally(db.pool(), &blog_post_id, &user_id);
+    hide_comment(db.pool(), &comment_id, &moderator_id, "Spam").unwrap();
+
+    assert_ok!(restore_comment(db.pool(), &comment_id));
+
+    let stored = get_comment_by_id(db.pool(), &comment_id).unwrap().unwrap();
+    assert!(!stored.is_hidden);
+    assert_eq!(stored.hidden_reason, None);
+    assert_eq!(stored.hidden_by, None);
+}
+
+#[test]
+fn update_comment_with_stale_version_is_rejected() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let blog_post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let comment_id =
+        TestComment::generate().register_internally(db.pool(), &blog_post_id, &user_id);
+
+    let first = UpdateComment {
+        id: &comment_id,
+        contents: Some("First contents"),
+        is_deleted: None,
+    };
+    assert_ok!(update_comment(db.pool(), &first, Some(1)));
+    let second = UpdateComment {
+        id: &comment_id,
+        contents: Some("Second contents"),
+        is_deleted: None,
+    };
+    let res = update_comment(db.pool(), &second, Some(1));
+    match res {
+        Err(CommentError::Conflict) => {}
+        _ => panic!("Expected conflict: got {:?}", res),
+    };
+
+    let comment = get_comment_by_id(db.pool(), &comment_id).unwrap().unwrap();
+    assert_eq!(comment.contents, "First contents");
+    assert_eq!(comment.version, 2);
+}
@@ -0,0 +1,70 @@
+use crate::common::{TestBlogPost, TestDB, TestProject, TestUser};
+use holosite::domain::blog_posts::{BlogPostID, BlogPostStatus};
+use holosite::services::{add_project_blog_post, get_feed_blog_posts, FeedSource, FEED_SIZE};
+use holosite::Pool;
+
+fn feed_ids(pool: &Pool, source: FeedSource) -> Vec<BlogPostID> {
+    get_feed_blog_posts(pool, source)
+        .unwrap()
+        .into_iter()
+        .map(|(blog_post, _)| blog_post.id)
+        .collect()
+}
+
+#[test]
+fn feed_contains_only_public_published_posts() {
+    let db = TestDB::spawn();
+    let user = TestUser::generate();
+    let user_id = user.register_internally(db.pool());
+    let public_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_authenticated().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+
+    let res = get_feed_blog_posts(db.pool(), FeedSource::All).unwrap();
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].0.id, public_id);
+    assert_eq!(res[0].1, user.name);
+}
+
+#[test]
+fn feed_has_latest_posts_first_and_is_limited() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_ids: Vec<BlogPostID> = (0..FEED_SIZE + 1)
+        .map(|_| TestBlogPost::generate().register_internally(db.pool(), &user_id))
+        .collect();
+
+    let ids = feed_ids(db.pool(), FeedSource::All);
+    assert_eq!(ids.len() as i64, FEED_SIZE);
+    assert_eq!(ids[0], post_ids[post_ids.len() - 1]);
+}
+
+#[test]
+fn author_feed_contains_only_their_posts() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate().register_internally(db.pool(), &other_id);
+
+    assert_eq!(
+        feed_ids(db.pool(), FeedSource::Author(&user_id)),
+        vec![post_id]
+    );
+}
+
+#[test]
+fn project_feed_contains_only_its_posts() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let project_id = TestProject::generate().register_internally(db.pool(), &user_id);
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    add_project_blog_post(db.pool(), &project_id, &post_id).unwrap();
+
+    assert_eq!(
+        feed_ids(db.pool(), FeedSource::Project(&project_id)),
+        vec![post_id]
+    );
+}
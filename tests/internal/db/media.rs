@@ -0,0 +1,198 @@
+use crate::common::{test_jpeg, test_media_dir, test_png, TestDB, TestUser};
+use claim::{assert_none, assert_ok, assert_some};
+use holosite::domain::media::MediaType;
+use holosite::media::{variant_keys, LocalMediaStore, MediaStore};
+use holosite::services::{
+    delete_media, generate_missing_media_variants, get_media_by_id, get_media_of_owner, save_media,
+    MediaError, PageRequest, SortOrder,
+};
+use std::path::Path;
+
+const MAX_SIZE: usize = 64 * 1024;
+
+fn store() -> (LocalMediaStore, String) {
+    let dir = test_media_dir();
+    (LocalMediaStore::new(&dir, "/media").unwrap(), dir)
+}
+
+#[test]
+fn uploaded_media_is_stored() {
+    let db = TestDB::spawn();
+    let (store, dir) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let png = test_png(8, 8);
+
+    let saved = save_media(db.pool(), &store, &user_id, "cat.png", &png, MAX_SIZE).unwrap();
+    assert_eq!(saved.media_type, MediaType::Png);
+    assert_eq!(saved.file_name, "cat.png");
+    assert_eq!(saved.size, png.len() as i32);
+    assert_eq!(
+        std::fs::read(format!("{}/{}", dir, saved.storage_key())).unwrap(),
+        png
+    );
+    assert!(saved.has_variants);
+    for key in variant_keys(&saved.storage_key()) {
+        assert!(Path::new(&format!("{}/{}", dir, key)).exists(), "{}", key);
+    }
+    assert_eq!(
+        store.url(&saved.storage_key()),
+        format!("/media/{}.png", saved.id.as_ref())
+    );
+
+    let stored = get_media_by_id(db.pool(), &saved.id).unwrap();
+    assert_eq!(stored, Some(saved));
+}
+
+#[test]
+fn type_is_sniffed_from_contents_not_name() {
+    let db = TestDB::spawn();
+    let (store, _) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let png = test_png(8, 8);
+    let saved = save_media(db.pool(), &store, &user_id, "page.html", &png, MAX_SIZE).unwrap();
+    assert_eq!(saved.media_type, MediaType::Png);
+    assert!(saved.storage_key().ends_with(".png"));
+
+    let res = save_media(
+        db.pool(),
+        &store,
+        &user_id,
+        "cat.png",
+        b"<svg onload=alert(1)>",
+        MAX_SIZE,
+    );
+    assert!(matches!(res, Err(MediaError::UnsupportedType)));
+}
+
+#[test]
+fn exif_is_removed_from_uploaded_photos() {
+    let db = TestDB::spawn();
+    let (store, dir) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let jpeg = test_jpeg(8, 8);
+    let exif = b"\xff\xe1\0\x0fExif\0\0GPS 51N";
+    let photo = [&jpeg[..2], exif, &jpeg[2..]].concat();
+    let saved = save_media(db.pool(), &store, &user_id, "me.jpg", &photo, MAX_SIZE).unwrap();
+
+    let stored = std::fs::read(format!("{}/{}", dir, saved.storage_key())).unwrap();
+    assert_eq!(stored, jpeg);
+    assert_eq!(saved.size, stored.len() as i32);
+}
+
+#[test]
+fn empty_and_large_files_are_rejected() {
+    let db = TestDB::spawn();
+    let (store, _) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let res = save_media(db.pool(), &store, &user_id, "a.png", b"", MAX_SIZE);
+    assert!(matches!(res, Err(MediaError::EmptyFile)));
+
+    let large = test_png(256, 256);
+    assert!(large.len() > MAX_SIZE);
+    let res = save_media(db.pool(), &store, &user_id, "a.png", &large, MAX_SIZE);
+    assert!(matches!(res, Err(MediaError::TooLarge(_))));
+
+    let damaged = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    let res = save_media(db.pool(), &store, &user_id, "a.png", damaged, MAX_SIZE);
+    assert!(matches!(res, Err(MediaError::InvalidImage)));
+
+    let page = get_media_of_owner(db.pool(), &user_id, &PageRequest::default()).unwrap();
+    assert!(page.items.is_empty());
+}
+
+#[test]
+fn media_library_contains_only_own_files() {
+    let db = TestDB::spawn();
+    let (store, _) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_id = TestUser::generate().register_internally(db.pool());
+    let png = test_png(8, 8);
+
+    let first = save_media(db.pool(), &store, &user_id, "1.png", &png, MAX_SIZE).unwrap();
+    let second = save_media(db.pool(), &store, &user_id, "2.png", &png, MAX_SIZE).unwrap();
+    assert_ok!(save_media(
+        db.pool(),
+        &store,
+        &other_id,
+        "3.png",
+        &png,
+        MAX_SIZE
+    ));
+
+    let page = get_media_of_owner(
+        db.pool(),
+        &user_id,
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    )
+    .unwrap();
+    assert_eq!(page.items, vec![first, second]);
+}
+
+#[test]
+fn deleted_media_is_removed_from_store() {
+    let db = TestDB::spawn();
+    let (store, dir) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let png = test_png(8, 8);
+    let saved = save_media(db.pool(), &store, &user_id, "cat.png", &png, MAX_SIZE).unwrap();
+    assert_some!(get_media_by_id(db.pool(), &saved.id).unwrap());
+
+    delete_media(db.pool(), &store, &saved).unwrap();
+    assert_none!(get_media_by_id(db.pool(), &saved.id).unwrap());
+    assert!(!Path::new(&format!("{}/{}", dir, saved.storage_key())).exists());
+    for key in variant_keys(&saved.storage_key()) {
+        assert!(!Path::new(&format!("{}/{}", dir, key)).exists(), "{}", key);
+    }
+}
+
+#[test]
+fn images_uploaded_without_variants_get_them() {
+    let db = TestDB::spawn();
+    let (store, dir) = store();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let saved = save_media(
+        db.pool(),
+        &store,
+        &user_id,
+        "cat.png",
+        &test_png(8, 8),
+        MAX_SIZE,
+    )
+    .unwrap();
+    let gif = save_media(db.pool(), &store, &user_id, "a.gif", b"GIF89a", MAX_SIZE).unwrap();
+    assert!(!gif.has_variants);
+    let conn = db.pool().get().unwrap();
+    {
+        use diesel::{ExpressionMethods, RunQueryDsl};
+        use holosite::schema::media::dsl::{has_variants, media};
+
+        diesel::update(media)
+            .set(has_variants.eq(false))
+            .execute(&conn)
+            .unwrap();
+    }
+    for key in variant_keys(&saved.storage_key()) {
+        store.delete(&key).unwrap();
+    }
+
+    generate_missing_media_variants(&conn, &store).unwrap();
+    drop(conn);
+    assert!(
+        get_media_by_id(db.pool(), &saved.id)
+            .unwrap()
+            .unwrap()
+            .has_variants
+    );
+    assert!(
+        !get_media_by_id(db.pool(), &gif.id)
+            .unwrap()
+            .unwrap()
+            .has_variants
+    );
+    for key in variant_keys(&saved.storage_key()) {
+        assert!(Path::new(&format!("{}/{}", dir, key)).exists(), "{}", key);
+    }
+}
@@ -1,4 +1,15 @@
+mod blog_post_autosaves;
+mod blog_post_revisions;
 mod blog_posts;
 mod comments;
+mod feeds;
+mod media;
+mod pagination;
+mod password_resets;
+mod pending_emails;
 mod projects;
+mod search;
+mod slugs;
+mod tags;
+mod two_factor;
 mod users;
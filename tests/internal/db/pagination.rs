@@ -0,0 +1,139 @@
+use crate::common::{TestBlogPost, TestComment, TestDB, TestUser};
+use holosite::domain::blog_posts::{BlogPostID, BlogPostStatus, UpdateBlogPost};
+use holosite::domain::users::UserID;
+use holosite::services::{
+    get_all_blog_posts, get_blog_post_by_id, get_blog_posts_of_author, update_blog_post,
+    PagePosition, PageRequest, SortOrder,
+};
+use holosite::Pool;
+
+fn insert_blog_posts(pool: &Pool, user_id: &UserID, count: usize) -> Vec<BlogPostID> {
+    (0..count)
+        .map(|_| TestBlogPost::generate().register_internally(pool, user_id))
+        .collect()
+}
+
+fn ids(request: &PageRequest, pool: &Pool) -> Vec<BlogPostID> {
+    get_all_blog_posts(pool, request)
+        .unwrap()
+        .items
+        .into_iter()
+        .map(|blog_post| blog_post.id)
+        .collect()
+}
+
+#[test]
+fn pages_are_walked_forward_and_backward() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_ids = insert_blog_posts(db.pool(), &user_id, 5);
+
+    let first_request = PageRequest::first(SortOrder::Oldest, 2);
+    let first = get_all_blog_posts(db.pool(), &first_request).unwrap();
+    assert!(first.prev.is_none());
+    assert_eq!(ids(&first_request, db.pool()), post_ids[0..2]);
+
+    let second_request = PageRequest {
+        position: Some(PagePosition::After(first.next.unwrap())),
+        ..first_request.clone()
+    };
+    let second = get_all_blog_posts(db.pool(), &second_request).unwrap();
+    assert_eq!(ids(&second_request, db.pool()), post_ids[2..4]);
+
+    let last_request = PageRequest {
+        position: Some(PagePosition::After(second.next.unwrap())),
+        ..first_request.clone()
+    };
+    let last = get_all_blog_posts(db.pool(), &last_request).unwrap();
+    assert_eq!(ids(&last_request, db.pool()), post_ids[4..]);
+    assert!(last.next.is_none());
+
+    let back_request = PageRequest {
+        position: Some(PagePosition::Before(last.prev.unwrap())),
+        ..first_request.clone()
+    };
+    let back = get_all_blog_posts(db.pool(), &back_request).unwrap();
+    assert_eq!(ids(&back_request, db.pool()), post_ids[2..4]);
+    assert!(back.prev.is_some());
+    assert!(back.next.is_some());
+}
+
+#[test]
+fn newest_blog_posts_go_first() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let mut post_ids = insert_blog_posts(db.pool(), &user_id, 3);
+    post_ids.reverse();
+
+    assert_eq!(ids(&PageRequest::default(), db.pool()), post_ids);
+}
+
+#[test]
+fn recently_updated_blog_posts_go_first() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_ids = insert_blog_posts(db.pool(), &user_id, 3);
+    update_blog_post(
+        db.pool(),
+        &UpdateBlogPost {
+            id: &post_ids[0],
+            title: None,
+            brief: Some("Updated brief"),
+            contents: None,
+            visibility: None,
+            status: None,
+            publish_at: None,
+        },
+        None,
+        &user_id,
+        None,
+    )
+    .unwrap();
+
+    let request = PageRequest::first(SortOrder::Updated, 1);
+    assert_eq!(ids(&request, db.pool()), post_ids[0..1]);
+}
+
+#[test]
+fn most_commented_blog_posts_go_first() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_ids = insert_blog_posts(db.pool(), &user_id, 3);
+    for _ in 0..2 {
+        TestComment::generate().register_internally(db.pool(), &post_ids[1], &user_id);
+    }
+    TestComment::generate().register_internally(db.pool(), &post_ids[2], &user_id);
+
+    let post = get_blog_post_by_id(db.pool(), &post_ids[1])
+        .unwrap()
+        .unwrap();
+    assert_eq!(post.comment_count, 2);
+
+    let request = PageRequest::first(SortOrder::Commented, 2);
+    let page = get_all_blog_posts(db.pool(), &request).unwrap();
+    assert_eq!(
+        ids(&request, db.pool()),
+        vec![post_ids[1].clone(), post_ids[2].clone()]
+    );
+
+    let request = PageRequest {
+        position: Some(PagePosition::After(page.next.unwrap())),
+        ..request
+    };
+    assert_eq!(ids(&request, db.pool()), post_ids[0..1]);
+}
+
+#[test]
+fn unpublished_blog_posts_of_author_are_listed_only_if_asked_for() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+
+    let request = PageRequest::default();
+    let public = get_blog_posts_of_author(db.pool(), &user_id, false, &request).unwrap();
+    assert_eq!(public.items.len(), 1);
+    let all = get_blog_posts_of_author(db.pool(), &user_id, true, &request).unwrap();
+    assert_eq!(all.items.len(), 2);
+}
@@ -0,0 +1,86 @@
+use crate::common::{get_test_config, TestDB, TestUser};
+use claim::{assert_ok, assert_some};
+use holosite::domain::time::DateTime;
+use holosite::domain::users::{HashedUserPassword, UserPassword, UserToken};
+use holosite::services::{
+    check_password_reset_token, get_user_by_id, insert_password_reset_token, is_session_valid,
+    reset_password, PasswordResetError,
+};
+use secrecy::Secret;
+
+fn new_password() -> HashedUserPassword {
+    let password = UserPassword::parse(Secret::new("!1Bbnewpass".to_string())).unwrap();
+    HashedUserPassword::parse(&password, &get_test_config().app.password_hashing).unwrap()
+}
+
+#[test]
+fn inserted_token_can_be_checked() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let token = insert_password_reset_token(db.pool(), &user_id).unwrap();
+    let res = check_password_reset_token(db.pool(), &token);
+    assert_ok!(&res);
+    assert_eq!(res.unwrap(), user_id);
+}
+
+#[test]
+fn new_request_replaces_previous_one() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let first_token = insert_password_reset_token(db.pool(), &user_id).unwrap();
+    let second_token = insert_password_reset_token(db.pool(), &user_id).unwrap();
+
+    match check_password_reset_token(db.pool(), &first_token) {
+        Err(PasswordResetError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+    assert_ok!(check_password_reset_token(db.pool(), &second_token));
+}
+
+#[test]
+fn reset_password_changes_password_and_consumes_token() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let token = insert_password_reset_token(db.pool(), &user_id).unwrap();
+    let password = new_password();
+
+    let res = reset_password(db.pool(), &token, &password);
+    assert_ok!(&res);
+    assert_eq!(res.unwrap(), user_id);
+
+    let user = get_user_by_id(db.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(user.password, password);
+    assert_some!(user.sessions_valid_after);
+
+    match reset_password(db.pool(), &token, &new_password()) {
+        Err(PasswordResetError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn unknown_token_is_rejected() {
+    let db = TestDB::spawn();
+    match reset_password(db.pool(), &UserToken::generate_random(), &new_password()) {
+        Err(PasswordResetError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn reset_password_revokes_earlier_sessions() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let logged_in_at = DateTime::now();
+    assert!(is_session_valid(db.pool(), &user_id, Some(&logged_in_at)).unwrap());
+    assert!(is_session_valid(db.pool(), &user_id, None).unwrap());
+
+    let token = insert_password_reset_token(db.pool(), &user_id).unwrap();
+    reset_password(db.pool(), &token, &new_password()).unwrap();
+
+    assert!(!is_session_valid(db.pool(), &user_id, Some(&logged_in_at)).unwrap());
+    assert!(!is_session_valid(db.pool(), &user_id, None).unwrap());
+    assert!(is_session_valid(db.pool(), &user_id, Some(&DateTime::now())).unwrap());
+}
@@ -0,0 +1,89 @@
+use crate::common::{TestDB, TestUser};
+use claim::{assert_none, assert_ok, assert_some};
+use holosite::domain::users::{UserEmail, UserToken};
+use holosite::services::{
+    confirm_pending_email, get_pending_email_of_user, get_user_by_id, insert_pending_email,
+    ConfirmEmailError,
+};
+
+fn new_email() -> UserEmail {
+    TestUser::generate().email
+}
+
+#[test]
+fn insert_pending_email_works() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let email = new_email();
+
+    let res = insert_pending_email(db.pool(), &user_id, &email);
+    assert_ok!(&res);
+
+    let pending = get_pending_email_of_user(db.pool(), &user_id).unwrap();
+    assert_some!(&pending);
+    assert_eq!(pending.unwrap().email, email);
+}
+
+#[test]
+fn new_request_replaces_previous_one() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let first_token = insert_pending_email(db.pool(), &user_id, &new_email()).unwrap();
+    let second_email = new_email();
+    insert_pending_email(db.pool(), &user_id, &second_email).unwrap();
+
+    let pending = get_pending_email_of_user(db.pool(), &user_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.email, second_email);
+
+    match confirm_pending_email(db.pool(), &first_token) {
+        Err(ConfirmEmailError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn confirm_pending_email_changes_email_and_consumes_token() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let email = new_email();
+    let token = insert_pending_email(db.pool(), &user_id, &email).unwrap();
+
+    let res = confirm_pending_email(db.pool(), &token);
+    assert_ok!(&res);
+    assert_eq!(res.unwrap(), user_id);
+
+    let user = get_user_by_id(db.pool(), &user_id).unwrap().unwrap();
+    assert_eq!(user.email, email);
+    assert_none!(get_pending_email_of_user(db.pool(), &user_id).unwrap());
+
+    match confirm_pending_email(db.pool(), &token) {
+        Err(ConfirmEmailError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn unknown_token_is_rejected() {
+    let db = TestDB::spawn();
+    match confirm_pending_email(db.pool(), &UserToken::generate_random()) {
+        Err(ConfirmEmailError::InvalidToken) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn confirming_taken_email_is_error() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_user = TestUser::generate();
+    other_user.register_internally(db.pool());
+
+    let token = insert_pending_email(db.pool(), &user_id, &other_user.email).unwrap();
+    match confirm_pending_email(db.pool(), &token) {
+        Err(ConfirmEmailError::TakenEmail) => {}
+        res => panic!("Incorrect result: got {:?}", res),
+    };
+}
@@ -2,9 +2,10 @@ use crate::common::{TestBlogPost, TestDB, TestProject, TestUser};
 use claim::{assert_err, assert_ok, assert_some};
 use holosite::domain::projects::{NewProject, ProjectID, ProjectVisibility, UpdateProject};
 use holosite::services::{
-    add_project_blog_post, add_project_editor, get_all_projects, get_project_blog_post_ids,
-    get_project_by_id, get_project_by_title, get_project_editor_ids, insert_new_project,
-    remove_project_editor, update_project, ProjectError,
+    add_project_blog_post, add_project_editor, can_edit_project, get_all_projects,
+    get_listed_projects, get_project_blog_post_ids, get_project_by_id, get_project_by_title,
+    get_project_editor_ids, get_projects_of_editor, insert_new_project, remove_project_editor,
+    update_project, PageRequest, ProjectError, SortOrder,
 };
 
 #[test]
@@ -180,9 +181,12 @@ fn get_all_projects_works() {
         })
         .collect();
 
-    let res = get_all_projects(db.pool());
+    let res = get_all_projects(
+        db.pool(),
+        &PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE),
+    );
     assert_ok!(&res);
-    let res = res.unwrap();
+    let res = res.unwrap().items;
     assert_eq!(res.len(), post_ids.len());
     for i in 0..res.len() {
         assert_eq!(res[i].id, post_ids[i]);
@@ -236,3 +240,90 @@ fn test_project_blog_posts_workflow() {
     assert_eq!(blog_posts.len(), 1);
     assert_eq!(blog_posts[0], blog_post_id);
 }
+
+#[test]
+fn cant_add_new_project_with_empty_title() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+
+    let res = insert_new_project(
+        db.pool(),
+        &NewProject {
+            title: "",
+            brief: "Brief",
+            author_id: &user_id,
+            visibility: ProjectVisibility::All,
+        },
+    );
+    assert_err!(&res);
+    let res = res.unwrap_err();
+    match res {
+        ProjectError::EmptyTitle => {}
+        _ => panic!("Incorrect error type: got {:?}", res),
+    };
+}
+
+#[test]
+fn authenticated_projects_are_listed_only_to_authenticated_users() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let public_id = TestProject::generate().register_internally(db.pool(), &user_id);
+    let authenticated_id =
+        TestProject::generate_authenticated().register_internally(db.pool(), &user_id);
+    let request = PageRequest::first(SortOrder::Oldest, PageRequest::MAX_SIZE);
+
+    let res = get_listed_projects(db.pool(), false, &request)
+        .unwrap()
+        .items;
+    assert_eq!(
+        res.iter().map(|p| p.id.clone()).collect::<Vec<_>>(),
+        vec![public_id.clone()]
+    );
+
+    let res = get_listed_projects(db.pool(), true, &request)
+        .unwrap()
+        .items;
+    assert_eq!(
+        res.iter().map(|p| p.id.clone()).collect::<Vec<_>>(),
+        vec![public_id, authenticated_id]
+    );
+}
+
+#[test]
+fn projects_of_editor_include_authored_and_edited_projects() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_user_id = TestUser::generate().register_internally(db.pool());
+    let authored_id = TestProject::generate().register_internally(db.pool(), &user_id);
+    let edited_id =
+        TestProject::generate_authenticated().register_internally(db.pool(), &other_user_id);
+    add_project_editor(db.pool(), &edited_id, &user_id).unwrap();
+    TestProject::generate().register_internally(db.pool(), &other_user_id);
+
+    let res = get_projects_of_editor(db.pool(), &user_id, true).unwrap();
+    assert_eq!(
+        res.iter().map(|p| p.id.clone()).collect::<Vec<_>>(),
+        vec![edited_id, authored_id.clone()]
+    );
+
+    let res = get_projects_of_editor(db.pool(), &user_id, false).unwrap();
+    assert_eq!(
+        res.iter().map(|p| p.id.clone()).collect::<Vec<_>>(),
+        vec![authored_id]
+    );
+}
+
+#[test]
+fn only_author_and_editors_can_edit_project() {
+    let db = TestDB::spawn();
+    let author_id = TestUser::generate().register_internally(db.pool());
+    let editor_id = TestUser::generate().register_internally(db.pool());
+    let stranger_id = TestUser::generate().register_internally(db.pool());
+    let project_id = TestProject::generate().register_internally(db.pool(), &author_id);
+    add_project_editor(db.pool(), &project_id, &editor_id).unwrap();
+    let project = get_project_by_id(db.pool(), &project_id).unwrap().unwrap();
+
+    assert!(can_edit_project(db.pool(), &project, &author_id).unwrap());
+    assert!(can_edit_project(db.pool(), &project, &editor_id).unwrap());
+    assert!(!can_edit_project(db.pool(), &project, &stranger_id).unwrap());
+}
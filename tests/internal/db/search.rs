@@ -0,0 +1,159 @@
+use crate::common::{TestBlogPost, TestComment, TestDB, TestProject, TestUser};
+use holosite::domain::blog_posts::{BlogPostStatus, BlogPostVisibility, UpdateBlogPost};
+use holosite::domain::comments::UpdateComment;
+use holosite::domain::search::SearchQuery;
+use holosite::services::{hide_comment, search, update_blog_post, update_comment};
+
+fn query(s: &str) -> SearchQuery {
+    SearchQuery::parse(s).expect("Failed to parse query")
+}
+
+fn generate_post_with_contents(contents: &str) -> TestBlogPost {
+    let mut post = TestBlogPost::generate();
+    post.contents = contents.to_string();
+    post
+}
+
+#[test]
+fn blog_posts_are_found_with_highlighted_snippet() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = generate_post_with_contents("Bananas are <b>yellow</b> fruits")
+        .register_internally(db.pool(), &user_id);
+    generate_post_with_contents("Apples are red").register_internally(db.pool(), &user_id);
+
+    let results = search(db.pool(), &query("banana bananas"), false).unwrap();
+    assert!(results.blog_posts.is_empty());
+
+    let results = search(db.pool(), &query("BANANAS"), false).unwrap();
+    assert_eq!(results.blog_posts.len(), 1);
+    assert_eq!(results.blog_posts[0].id, post_id);
+    let snippet = &results.blog_posts[0].snippet;
+    assert!(snippet.contains("<mark>Bananas</mark>"));
+    assert!(snippet.contains("&lt;b&gt;yellow&lt;/b&gt;"));
+}
+
+#[test]
+fn better_matches_are_ranked_first() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let weak_id = generate_post_with_contents(&format!("kiwi {}", "filler ".repeat(50)))
+        .register_internally(db.pool(), &user_id);
+    let strong_id =
+        generate_post_with_contents("kiwi kiwi kiwi").register_internally(db.pool(), &user_id);
+
+    let results = search(db.pool(), &query("kiwi"), false).unwrap();
+    let ids: Vec<_> = results
+        .blog_posts
+        .iter()
+        .map(|hit| hit.id.clone())
+        .collect();
+    assert_eq!(ids, vec![strong_id, weak_id]);
+}
+
+#[test]
+fn search_index_follows_updates() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = generate_post_with_contents("old words").register_internally(db.pool(), &user_id);
+    update_blog_post(
+        db.pool(),
+        &UpdateBlogPost {
+            id: &post_id,
+            title: None,
+            brief: None,
+            contents: Some("new words"),
+            visibility: None,
+            status: None,
+            publish_at: None,
+        },
+        None,
+        &user_id,
+        None,
+    )
+    .unwrap();
+
+    assert!(search(db.pool(), &query("old"), false)
+        .unwrap()
+        .blog_posts
+        .is_empty());
+    assert_eq!(
+        search(db.pool(), &query("new"), false)
+            .unwrap()
+            .blog_posts
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn authenticated_only_and_unpublished_posts_are_respected() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let mut protected = generate_post_with_contents("mango");
+    protected.visibility = BlogPostVisibility::Authenticated;
+    protected.register_internally(db.pool(), &user_id);
+    let mut draft = generate_post_with_contents("mango");
+    draft.status = BlogPostStatus::Draft;
+    draft.register_internally(db.pool(), &user_id);
+
+    assert!(search(db.pool(), &query("mango"), false)
+        .unwrap()
+        .blog_posts
+        .is_empty());
+    assert_eq!(
+        search(db.pool(), &query("mango"), true)
+            .unwrap()
+            .blog_posts
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn deleted_and_hidden_comments_are_not_found() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let mut comment = TestComment::generate();
+    comment.contents = "papaya".to_string();
+    let visible_id = comment.register_internally(db.pool(), &post_id, &user_id);
+    let deleted_id = comment.register_internally(db.pool(), &post_id, &user_id);
+    let hidden_id = comment.register_internally(db.pool(), &post_id, &user_id);
+    update_comment(
+        db.pool(),
+        &UpdateComment {
+            id: &deleted_id,
+            contents: None,
+            is_deleted: Some(true),
+        },
+        None,
+    )
+    .unwrap();
+    hide_comment(db.pool(), &hidden_id, &user_id, "spam").unwrap();
+
+    let results = search(db.pool(), &query("papaya"), false).unwrap();
+    assert_eq!(results.comments.len(), 1);
+    assert_eq!(results.comments[0].id, visible_id);
+    assert_eq!(results.comments[0].post_id, post_id);
+}
+
+#[test]
+fn projects_are_found() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let mut project = TestProject::generate();
+    project.brief = "Growing pineapples".to_string();
+    let project_id = project.register_internally(db.pool(), &user_id);
+
+    let results = search(db.pool(), &query("pineapples"), false).unwrap();
+    assert_eq!(results.projects.len(), 1);
+    assert_eq!(results.projects[0].id, project_id);
+}
+
+#[test]
+fn query_with_fts_syntax_does_not_fail() {
+    let db = TestDB::spawn();
+    let res = search(db.pool(), &query(r#"a" OR NEAR(b* "#), false);
+    assert!(res.is_ok());
+}
@@ -0,0 +1,176 @@
+use crate::common::{TestBlogPost, TestDB, TestProject, TestUser};
+use holosite::domain::blog_posts::{BlogPostID, UpdateBlogPost};
+use holosite::domain::projects::UpdateProject;
+use holosite::domain::slugs::Slug;
+use holosite::domain::users::UserID;
+use holosite::services::{
+    generate_missing_slugs, get_blog_post_by_id, get_blog_post_by_slug, get_project_by_id,
+    get_project_by_slug, update_blog_post, update_project, SlugMatch,
+};
+use holosite::Pool;
+
+fn slug(s: &str) -> Slug {
+    Slug::parse(s).unwrap()
+}
+
+fn insert_blog_post(pool: &Pool, user_id: &UserID, title: &str) -> BlogPostID {
+    let mut blog_post = TestBlogPost::generate();
+    blog_post.title = title.to_string();
+    blog_post.register_internally(pool, user_id)
+}
+
+fn rename_blog_post(pool: &Pool, user_id: &UserID, post_id: &BlogPostID, title: &str) {
+    let changeset = UpdateBlogPost {
+        id: post_id,
+        title: Some(title),
+        brief: None,
+        contents: None,
+        visibility: None,
+        status: None,
+        publish_at: None,
+    };
+    update_blog_post(pool, &changeset, None, user_id, None).unwrap();
+}
+
+#[test]
+fn blog_post_gets_slug_from_title() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = insert_blog_post(db.pool(), &user_id, "Hello, World!");
+
+    let blog_post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(blog_post.slug, slug("hello-world"));
+    match get_blog_post_by_slug(db.pool(), &blog_post.slug).unwrap() {
+        Some(SlugMatch::Current(found)) => assert_eq!(found.id, post_id),
+        res => panic!("Blog post is not found by slug: {:?}", res),
+    }
+}
+
+#[test]
+fn colliding_slugs_get_suffixes() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    insert_blog_post(db.pool(), &user_id, "Hello, World!");
+    let second_id = insert_blog_post(db.pool(), &user_id, "Hello world");
+    let third_id = insert_blog_post(db.pool(), &user_id, "hello -- world");
+
+    let second = get_blog_post_by_id(db.pool(), &second_id).unwrap().unwrap();
+    assert_eq!(second.slug, slug("hello-world-2"));
+    let third = get_blog_post_by_id(db.pool(), &third_id).unwrap().unwrap();
+    assert_eq!(third.slug, slug("hello-world-3"));
+}
+
+#[test]
+fn renamed_blog_post_is_found_by_previous_slug() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = insert_blog_post(db.pool(), &user_id, "First title");
+
+    rename_blog_post(db.pool(), &user_id, &post_id, "Second title");
+
+    let blog_post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(blog_post.slug, slug("second-title"));
+    match get_blog_post_by_slug(db.pool(), &slug("first-title")).unwrap() {
+        Some(SlugMatch::Previous(found)) => assert_eq!(found.id, post_id),
+        res => panic!("Blog post is not found by previous slug: {:?}", res),
+    }
+}
+
+#[test]
+fn previous_slugs_are_not_given_to_other_blog_posts() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = insert_blog_post(db.pool(), &user_id, "Title");
+    rename_blog_post(db.pool(), &user_id, &post_id, "Other title");
+
+    let other_id = insert_blog_post(db.pool(), &user_id, "title");
+    let other = get_blog_post_by_id(db.pool(), &other_id).unwrap().unwrap();
+    assert_eq!(other.slug, slug("title-2"));
+}
+
+#[test]
+fn blog_post_can_get_its_previous_slug_back() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = insert_blog_post(db.pool(), &user_id, "Title");
+    rename_blog_post(db.pool(), &user_id, &post_id, "Other title");
+    rename_blog_post(db.pool(), &user_id, &post_id, "Title");
+
+    match get_blog_post_by_slug(db.pool(), &slug("title")).unwrap() {
+        Some(SlugMatch::Current(found)) => assert_eq!(found.id, post_id),
+        res => panic!("Blog post has not got its slug back: {:?}", res),
+    }
+    assert!(matches!(
+        get_blog_post_by_slug(db.pool(), &slug("other-title")).unwrap(),
+        Some(SlugMatch::Previous(_))
+    ));
+}
+
+#[test]
+fn renamed_project_is_found_by_previous_slug() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let mut project = TestProject::generate();
+    project.title = "My project".to_string();
+    let project_id = project.register_internally(db.pool(), &user_id);
+
+    update_project(
+        db.pool(),
+        &UpdateProject {
+            id: &project_id,
+            title: Some("Our project"),
+            brief: None,
+            visibility: None,
+        },
+    )
+    .unwrap();
+
+    let project = get_project_by_id(db.pool(), &project_id).unwrap().unwrap();
+    assert_eq!(project.slug, slug("our-project"));
+    match get_project_by_slug(db.pool(), &slug("my-project")).unwrap() {
+        Some(SlugMatch::Previous(found)) => assert_eq!(found.id, project_id),
+        res => panic!("Project is not found by previous slug: {:?}", res),
+    }
+}
+
+#[test]
+fn project_slugs_dont_shadow_project_pages() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let mut project = TestProject::generate();
+    project.title = "All".to_string();
+    let project_id = project.register_internally(db.pool(), &user_id);
+
+    let project = get_project_by_id(db.pool(), &project_id).unwrap().unwrap();
+    assert_eq!(project.slug, slug("all-2"));
+}
+
+#[test]
+fn unknown_slug_is_not_found() {
+    let db = TestDB::spawn();
+
+    assert!(get_blog_post_by_slug(db.pool(), &slug("nothing"))
+        .unwrap()
+        .is_none());
+    assert!(get_project_by_slug(db.pool(), &slug("nothing"))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn placeholder_slugs_are_replaced() {
+    use diesel::RunQueryDsl;
+
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = insert_blog_post(db.pool(), &user_id, "Old post");
+    // Posts that existed before slugs were introduced have ids as slugs
+    diesel::sql_query("update blog_posts set slug = id")
+        .execute(&db.pool().get().unwrap())
+        .unwrap();
+
+    generate_missing_slugs(&db.pool().get().unwrap()).unwrap();
+
+    let blog_post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(blog_post.slug, slug("old-post"));
+}
@@ -0,0 +1,143 @@
+use crate::common::{TestBlogPost, TestDB, TestUser};
+use claim::assert_ok;
+use holosite::domain::blog_posts::{BlogPostID, BlogPostStatus, UpdateBlogPost};
+use holosite::domain::tags::TagName;
+use holosite::domain::users::UserID;
+use holosite::services::{
+    get_blog_post_by_id, get_blog_post_revision, get_blog_post_revisions, get_blog_post_tags,
+    get_blog_posts_by_tag, get_tag_counts, restore_blog_post_revision, update_blog_post,
+    BlogPostError, PageRequest,
+};
+use holosite::Pool;
+
+fn tags(list: &str) -> Vec<TagName> {
+    TagName::parse_list(list).expect("Failed to parse tags")
+}
+
+fn set_blog_post_tags(
+    pool: &Pool,
+    post_id: &BlogPostID,
+    editor_id: &UserID,
+    new_tags: &[TagName],
+) -> Result<(), BlogPostError> {
+    let changeset = UpdateBlogPost {
+        id: post_id,
+        title: None,
+        brief: None,
+        contents: None,
+        visibility: None,
+        status: None,
+        publish_at: None,
+    };
+    update_blog_post(pool, &changeset, Some(new_tags), editor_id, None)
+}
+
+#[test]
+fn set_blog_post_tags_works() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    assert_ok!(set_blog_post_tags(
+        db.pool(),
+        &post_id,
+        &user_id,
+        &tags("web, Rust")
+    ));
+    let res = get_blog_post_tags(db.pool(), &post_id);
+    assert_ok!(&res);
+    assert_eq!(res.unwrap(), tags("rust, web"));
+}
+
+#[test]
+fn setting_tags_replaces_old_ones() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    set_blog_post_tags(db.pool(), &post_id, &user_id, &tags("rust, web")).unwrap();
+    set_blog_post_tags(db.pool(), &post_id, &user_id, &tags("web, diesel")).unwrap();
+
+    assert_eq!(
+        get_blog_post_tags(db.pool(), &post_id).unwrap(),
+        tags("diesel, web")
+    );
+    let counts = get_tag_counts(db.pool()).unwrap();
+    assert!(counts.iter().all(|(tag, _)| tag.as_ref() != "rust"));
+}
+
+#[test]
+fn blog_posts_can_be_found_by_tag() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let tagged_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    let other_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+    set_blog_post_tags(db.pool(), &tagged_id, &user_id, &tags("rust")).unwrap();
+    set_blog_post_tags(db.pool(), &other_id, &user_id, &tags("web")).unwrap();
+
+    let res = get_blog_posts_by_tag(
+        db.pool(),
+        &TagName::parse("rust").unwrap(),
+        &PageRequest::default(),
+    );
+    assert_ok!(&res);
+    let res = res.unwrap().items;
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].id, tagged_id);
+}
+
+#[test]
+fn drafts_are_not_listed_by_tag_and_not_counted() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let draft_id = TestBlogPost::generate_with_status(BlogPostStatus::Draft, None)
+        .register_internally(db.pool(), &user_id);
+    set_blog_post_tags(db.pool(), &draft_id, &user_id, &tags("secret")).unwrap();
+
+    let tag = TagName::parse("secret").unwrap();
+    assert!(
+        get_blog_posts_by_tag(db.pool(), &tag, &PageRequest::default())
+            .unwrap()
+            .items
+            .is_empty()
+    );
+    assert!(get_tag_counts(db.pool()).unwrap().is_empty());
+}
+
+#[test]
+fn tag_counts_are_calculated() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    for list in ["rust, web", "rust", "diesel"] {
+        let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+        set_blog_post_tags(db.pool(), &post_id, &user_id, &tags(list)).unwrap();
+    }
+
+    let counts = get_tag_counts(db.pool()).unwrap();
+    let counts: Vec<(&str, usize)> = counts
+        .iter()
+        .map(|(tag, count)| (tag.as_ref().as_str(), *count))
+        .collect();
+    assert_eq!(counts, vec![("diesel", 1), ("rust", 2), ("web", 1)]);
+}
+
+#[test]
+fn changing_tags_is_recorded_in_revision() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let post_id = TestBlogPost::generate().register_internally(db.pool(), &user_id);
+
+    set_blog_post_tags(db.pool(), &post_id, &user_id, &tags("rust")).unwrap();
+
+    let blog_post = get_blog_post_by_id(db.pool(), &post_id).unwrap().unwrap();
+    assert_eq!(blog_post.version, 2);
+    let revisions = get_blog_post_revisions(db.pool(), &post_id).unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0].0.tags, "rust");
+
+    let first = get_blog_post_revision(db.pool(), &post_id, 1)
+        .unwrap()
+        .unwrap();
+    restore_blog_post_revision(db.pool(), &first, &user_id).unwrap();
+    assert!(get_blog_post_tags(db.pool(), &post_id).unwrap().is_empty());
+}
@@ -0,0 +1,85 @@
+use crate::common::{TestDB, TestUser};
+use claim::{assert_none, assert_ok, assert_some_eq};
+use holosite::domain::users::{RecoveryCode, TotpSecret, RECOVERY_CODE_COUNT};
+use holosite::services::{
+    count_recovery_codes, disable_two_factor, enable_two_factor, get_user_by_id, use_recovery_code,
+    use_totp_step,
+};
+
+#[test]
+fn enable_two_factor_stores_secret_and_recovery_codes() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let secret = TotpSecret::generate_random();
+
+    let res = enable_two_factor(db.pool(), &user_id, &secret);
+    assert_ok!(&res);
+    assert_eq!(res.unwrap().len(), RECOVERY_CODE_COUNT);
+
+    let user = get_user_by_id(db.pool(), &user_id).unwrap().unwrap();
+    assert_some_eq!(user.totp_secret, secret);
+    assert_eq!(
+        count_recovery_codes(db.pool(), &user_id).unwrap(),
+        RECOVERY_CODE_COUNT as i64
+    );
+}
+
+#[test]
+fn recovery_code_is_single_use() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let codes = enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+
+    assert!(use_recovery_code(db.pool(), &user_id, &codes[0]).unwrap());
+    assert!(!use_recovery_code(db.pool(), &user_id, &codes[0]).unwrap());
+    assert_eq!(
+        count_recovery_codes(db.pool(), &user_id).unwrap(),
+        RECOVERY_CODE_COUNT as i64 - 1
+    );
+}
+
+#[test]
+fn recovery_code_of_other_user_is_rejected() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let other_user_id = TestUser::generate().register_internally(db.pool());
+    let codes = enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+
+    assert!(!use_recovery_code(db.pool(), &other_user_id, &codes[0]).unwrap());
+    assert!(!use_recovery_code(db.pool(), &user_id, &RecoveryCode::generate_random()).unwrap());
+}
+
+#[test]
+fn reenabling_replaces_recovery_codes() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let old_codes = enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+    enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+
+    assert!(!use_recovery_code(db.pool(), &user_id, &old_codes[0]).unwrap());
+}
+
+#[test]
+fn disable_two_factor_removes_secret_and_codes() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+
+    assert_ok!(disable_two_factor(db.pool(), &user_id));
+
+    let user = get_user_by_id(db.pool(), &user_id).unwrap().unwrap();
+    assert_none!(user.totp_secret);
+    assert_eq!(count_recovery_codes(db.pool(), &user_id).unwrap(), 0);
+}
+
+#[test]
+fn totp_step_is_accepted_once() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    enable_two_factor(db.pool(), &user_id, &TotpSecret::generate_random()).unwrap();
+
+    assert!(use_totp_step(db.pool(), &user_id, 10).unwrap());
+    assert!(!use_totp_step(db.pool(), &user_id, 10).unwrap());
+    assert!(!use_totp_step(db.pool(), &user_id, 9).unwrap());
+    assert!(use_totp_step(db.pool(), &user_id, 11).unwrap());
+}
@@ -1,8 +1,14 @@
-use crate::common::{TestDB, TestUser};
-use claim::{assert_err, assert_ok, assert_some};
-use holosite::domain::users::{HashedUserPassword, NewUser, UpdateUser, UserName, UserPassword};
+use crate::common::{get_test_config, TestDB, TestUser};
+use claim::{assert_err, assert_none, assert_ok, assert_some};
+use holosite::domain::time::DateTime;
+use holosite::domain::users::{
+    Credentials, HashedUserPassword, NewUser, UpdateUser, UserName, UserPassword, UserPasswordSalt,
+    UserRole,
+};
 use holosite::services::{
-    get_user_by_id, get_user_by_name, insert_new_user, update_user, UserError,
+    ban_user, can_moderate_comments, get_user_by_id, get_user_by_name, insert_new_user,
+    is_session_valid, revoke_user_sessions, search_users, unban_user, update_user,
+    validate_credentials, AuthError, UserError, USERS_PER_PAGE,
 };
 use secrecy::Secret;
 
@@ -14,8 +20,10 @@ fn test_add_new_user_works() {
         db.pool(),
         &NewUser {
             name: test_user.name.clone(),
+            email: test_user.email.clone(),
             password: test_user.password.clone(),
         },
+        &get_test_config().app.password_hashing,
     );
     assert_ok!(&res);
     let res = res.unwrap();
@@ -64,7 +72,9 @@ fn update_user_name_works() {
         name: Some(&new_name),
         email: None,
         password: None,
+        password_salt: None,
         is_banned: None,
+        role: None,
     };
     let res = update_user(db.pool(), &changeset);
     assert_ok!(res);
@@ -82,7 +92,8 @@ fn update_user_password_works() {
 
     let initial = get_user_by_id(db.pool(), &id).unwrap().unwrap();
     let new_password = UserPassword::parse(Secret::new("!1Aaaaaa".to_string())).unwrap();
-    let hashed_password = HashedUserPassword::parse(&new_password, &initial.password_salt);
+    let hashed_password =
+        HashedUserPassword::parse(&new_password, &get_test_config().app.password_hashing).unwrap();
 
     let changeset = UpdateUser {
         id: &id,
@@ -90,7 +101,9 @@ fn update_user_password_works() {
         name: None,
         email: None,
         password: Some(&hashed_password),
+        password_salt: None,
         is_banned: None,
+        role: None,
     };
     let res = update_user(db.pool(), &changeset);
     assert_ok!(res);
@@ -111,8 +124,10 @@ fn cant_create_user_with_same_name_and_get_correct_error_kind() {
         db.pool(),
         &NewUser {
             name: test_user.name.clone(),
+            email: TestUser::generate().email,
             password: test_user.password,
         },
+        &get_test_config().app.password_hashing,
     );
     assert_err!(&res);
     let res = res.unwrap_err();
@@ -137,7 +152,9 @@ fn cant_update_user_with_same_name_and_get_correct_error_kind() {
             name: Some(&other_user.name),
             email: None,
             password: None,
+            password_salt: None,
             is_banned: None,
+            role: None,
         },
     );
     assert_err!(&res);
@@ -147,3 +164,193 @@ fn cant_update_user_with_same_name_and_get_correct_error_kind() {
         _ => panic!("Incorrect error type: got {:?}", res),
     };
 }
+
+#[test]
+fn validate_credentials_works() {
+    let db = TestDB::spawn();
+    let test_user = TestUser::generate();
+    let id = test_user.register_internally(db.pool());
+
+    let credentials = Credentials {
+        name: test_user.name.clone(),
+        password: test_user.password.clone(),
+    };
+    let res = validate_credentials(
+        credentials,
+        db.pool(),
+        &get_test_config().app.password_hashing,
+    );
+    assert_ok!(&res);
+    assert_eq!(res.unwrap(), id);
+}
+
+#[test]
+fn validate_credentials_rejects_wrong_password() {
+    let db = TestDB::spawn();
+    let test_user = TestUser::generate();
+    test_user.register_internally(db.pool());
+
+    let credentials = Credentials {
+        name: test_user.name.clone(),
+        password: UserPassword::parse(Secret::new("!1Aaaaaa".to_string())).unwrap(),
+    };
+    let res = validate_credentials(
+        credentials,
+        db.pool(),
+        &get_test_config().app.password_hashing,
+    );
+    match res {
+        Err(AuthError::InvalidCredentials(_)) => {}
+        _ => panic!("Incorrect result: got {:?}", res),
+    };
+}
+
+#[test]
+fn legacy_password_is_rehashed_on_successful_validation() {
+    let db = TestDB::spawn();
+    let test_user = TestUser::generate();
+    let id = test_user.register_internally(db.pool());
+
+    let salt = UserPasswordSalt::generate_random();
+    let legacy_password = HashedUserPassword::parse_legacy(&test_user.password, &salt);
+    update_user(
+        db.pool(),
+        &UpdateUser {
+            id: &id,
+            name: None,
+            email: None,
+            password: Some(&legacy_password),
+            password_salt: Some(Some(&salt)),
+            is_banned: None,
+            role: None,
+        },
+    )
+    .unwrap();
+
+    let credentials = Credentials {
+        name: test_user.name.clone(),
+        password: test_user.password.clone(),
+    };
+    let res = validate_credentials(
+        credentials,
+        db.pool(),
+        &get_test_config().app.password_hashing,
+    );
+    assert_ok!(&res);
+
+    let user = get_user_by_id(db.pool(), &id).unwrap().unwrap();
+    assert!(!user.password.is_legacy());
+    assert_none!(&user.password_salt);
+    assert!(user.password.verify(&test_user.password, None).unwrap());
+}
+
+#[test]
+fn banned_user_cant_log_in() {
+    let db = TestDB::spawn();
+    let test_user = TestUser::generate();
+    let id = test_user.register_internally(db.pool());
+    ban_user(db.pool(), &id).unwrap();
+
+    let credentials = Credentials {
+        name: test_user.name.clone(),
+        password: test_user.password.clone(),
+    };
+    let res = validate_credentials(
+        credentials.clone(),
+        db.pool(),
+        &get_test_config().app.password_hashing,
+    );
+    assert!(matches!(res, Err(AuthError::Banned)));
+
+    unban_user(db.pool(), &id).unwrap();
+    let res = validate_credentials(
+        credentials,
+        db.pool(),
+        &get_test_config().app.password_hashing,
+    );
+    assert_ok!(&res);
+}
+
+#[test]
+fn sessions_of_banned_user_are_invalid() {
+    let db = TestDB::spawn();
+    let id = TestUser::generate().register_internally(db.pool());
+    let logged_in_at = DateTime::now();
+    assert!(is_session_valid(db.pool(), &id, Some(&logged_in_at)).unwrap());
+
+    ban_user(db.pool(), &id).unwrap();
+    assert!(!is_session_valid(db.pool(), &id, Some(&logged_in_at)).unwrap());
+
+    // Unbanning does not restore sessions that were open during ban
+    unban_user(db.pool(), &id).unwrap();
+    assert!(!is_session_valid(db.pool(), &id, Some(&logged_in_at)).unwrap());
+    assert!(is_session_valid(db.pool(), &id, Some(&DateTime::now())).unwrap());
+}
+
+#[test]
+fn revoke_user_sessions_invalidates_existing_sessions() {
+    let db = TestDB::spawn();
+    let id = TestUser::generate().register_internally(db.pool());
+    let logged_in_at = DateTime::now();
+
+    revoke_user_sessions(db.pool(), &id).unwrap();
+    assert!(!is_session_valid(db.pool(), &id, Some(&logged_in_at)).unwrap());
+    assert!(is_session_valid(db.pool(), &id, Some(&DateTime::now())).unwrap());
+}
+
+#[test]
+fn search_users_filters_by_name() {
+    let db = TestDB::spawn();
+    let user = TestUser::generate();
+    user.register_internally(db.pool());
+    TestUser::generate().register_internally(db.pool());
+
+    let (found, total) = search_users(db.pool(), Some(user.name.as_ref()), 1).unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].name, user.name);
+
+    // Wildcards are matched literally
+    let (found, total) = search_users(db.pool(), Some("%"), 1).unwrap();
+    assert_eq!(total, 0);
+    assert!(found.is_empty());
+}
+
+#[test]
+fn search_users_is_paginated() {
+    let db = TestDB::spawn();
+    let count = USERS_PER_PAGE + 5;
+    for _ in 0..count {
+        TestUser::generate().register_internally(db.pool());
+    }
+
+    let (first_page, total) = search_users(db.pool(), None, 1).unwrap();
+    assert_eq!(total, count);
+    assert_eq!(first_page.len() as i64, USERS_PER_PAGE);
+
+    let (second_page, _) = search_users(db.pool(), None, 2).unwrap();
+    assert_eq!(second_page.len(), 5);
+    assert!(first_page.last().unwrap().name.as_ref() < second_page[0].name.as_ref());
+}
+
+#[test]
+fn moderator_role_is_stored() {
+    let db = TestDB::spawn();
+    let moderator = TestUser::generate();
+    let moderator_id = moderator.register_moderator_internally(db.pool());
+
+    let stored = get_user_by_id(db.pool(), &moderator_id).unwrap().unwrap();
+    assert_eq!(stored.role, UserRole::Moderator);
+}
+
+#[test]
+fn only_moderators_and_admins_can_moderate_comments() {
+    let db = TestDB::spawn();
+    let user_id = TestUser::generate().register_internally(db.pool());
+    let moderator_id = TestUser::generate().register_moderator_internally(db.pool());
+    let admin_id = TestUser::generate().register_admin_internally(db.pool());
+
+    assert!(!can_moderate_comments(db.pool(), &user_id).unwrap());
+    assert!(can_moderate_comments(db.pool(), &moderator_id).unwrap());
+    assert!(can_moderate_comments(db.pool(), &admin_id).unwrap());
+}